@@ -1,13 +1,17 @@
 use clap::Parser;
 use futures_util::FutureExt;
-use once_cell::sync::Lazy;
+use garde::Validate;
 use pf_api::{
-  configure::env::get_env_source,
+  configure::{args::Command, env::get_env_source, StorageBackendKind},
   constant::ENV_PREFIX,
   error::result::ApiResult,
-  server::{worker::GarbageCollectorTask, ApiServer},
-  util::{self, tracing::INIT_SUBSCRIBER},
+  server::{
+    worker::{GarbageCollectorTask, WebhookTask},
+    ApiServer, ApiState,
+  },
+  service, util,
 };
+use pf_sdk::dto::request::CreateAccountRequest;
 
 #[tokio::main]
 async fn main() -> ApiResult {
@@ -17,19 +21,70 @@ async fn main() -> ApiResult {
   let config = pf_api::configure::ApiConfig::read(args.settings, get_env_source(ENV_PREFIX))?;
   // Validate settings
   config.validate()?;
-  // Force initialization of subscriber
-  Lazy::force(&INIT_SUBSCRIBER);
+  // Initialize subscriber, the guard flushes the log file on exit
+  let _guard = util::tracing::init_subscriber(&config.log)?;
+  // Check the database against the stored files and exit
+  if let Some(Command::Fsck { repair }) = args.command {
+    if config.storage.backend == StorageBackendKind::Memory {
+      return Err(anyhow::anyhow!("The memory storage backend keeps no files to check.").into());
+    }
+    let state = ApiState::new(config)?;
+    service::migrate::legacy_files(&state).await?;
+    let report = service::fsck::check(&state, repair).await?;
+    state.db.flush().await?;
+    println!("{report}");
+    return Ok(());
+  }
+  // Create the admin account and exit
+  if let Some(Command::CreateAdmin { username }) = args.command {
+    let mut password = String::new();
+    std::io::stdin().read_line(&mut password)?;
+    let req = CreateAccountRequest {
+      username,
+      password: password.trim_end_matches(['\r', '\n']).to_string(),
+    };
+    req.validate(&())?;
+    let state = ApiState::new(config)?;
+    let account = service::account::create_first_admin(&state, &req).await?;
+    println!("The admin account {} is created.", account.username);
+    return Ok(());
+  }
   // Create base directory if it doesn't exist
   tokio::fs::create_dir_all(&config.fs.base_dir).await?;
   // Initialize API server
   let server = ApiServer::new(config).await?;
+  // Resumable uploads are written to the local disk before they reach the storage backend
+  service::tus::check_upload_dir(&server.state).await?;
+  // Move the files stored by an older release into the blobs
+  service::migrate::legacy_files(&server.state).await?;
+  // Reconcile the database with the stored files before serving
+  if server.state.config.load().fsck.on_startup {
+    service::fsck::on_startup(&server.state).await?;
+  }
   // Create garbage collector task
   let gc_task = GarbageCollectorTask::new(server.state.clone());
-  // Start HTTP server and garbage collector task concurrently
+  // Create webhook delivery task
+  let webhook_task = WebhookTask::new(server.state.clone());
+  let state = server.state.clone();
+  // Start HTTP server and background tasks concurrently, they stop on SIGTERM or Ctrl-C
   util::task::join_all(vec![
     ("web server", true, server.run().boxed()),
     ("garbage collector", true, gc_task.run().boxed()),
+    ("webhook", true, webhook_task.run().boxed()),
+    (
+      "reload signal",
+      true,
+      util::task::reload_signal(state.clone()).boxed(),
+    ),
+    (
+      "shutdown signal",
+      true,
+      util::task::shutdown_signal(state.shutdown.clone()).boxed(),
+    ),
   ])
   .await?;
+  // Persist the database before exiting
+  state.db.flush().await?;
+  tracing::info!("The server stopped.");
   Ok(())
 }
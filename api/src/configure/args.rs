@@ -1,8 +1,26 @@
 use std::path::PathBuf;
 
+use super::RepairMode;
+
 #[derive(clap::Parser, Debug, Default)]
 #[command(author, version, about, long_about = None)]
 pub struct Args {
   #[arg(short, long)]
   pub settings: Option<PathBuf>,
+  #[command(subcommand)]
+  pub command: Option<Command>,
+}
+
+#[derive(clap::Subcommand, Debug)]
+pub enum Command {
+  /// Check the database against the stored files while the server is stopped.
+  Fsck {
+    #[arg(long, value_enum, default_value_t = RepairMode::None)]
+    repair: RepairMode,
+  },
+  /// Create the admin account while the server is stopped, the password is read from stdin.
+  CreateAdmin {
+    #[arg(long)]
+    username: String,
+  },
 }
@@ -1,25 +1,71 @@
-use crate::{configure::ServerConfig, error::result::ApiResult};
+use std::sync::Arc;
+
+use crate::{
+  configure::{ApiConfig, ServerConfig},
+  error::result::ApiResult,
+  router::REQUEST_ID_HEADER,
+};
 use anyhow::anyhow;
-use axum::http::HeaderValue;
+use arc_swap::ArcSwap;
+use axum::http::{HeaderName, HeaderValue};
+use pf_sdk::dto::{
+  tus::{
+    TUS_EXTENSION_HEADER, TUS_MAX_SIZE, TUS_RESUMABLE, TUS_VERSION_HEADER, UPLOAD_FILE_EXPIRES,
+    UPLOAD_FILE_URL, UPLOAD_LENGTH, UPLOAD_METADATA, UPLOAD_OFFSET,
+  },
+  API_KEY_HEADER,
+};
+
+pub fn allowed_origins(config: &ServerConfig) -> ApiResult<Vec<HeaderValue>> {
+  let mut origins = vec![HeaderValue::from_str(&config.domain_name)
+    .map_err(|err| anyhow!("Invalid domain url, Error: {err}"))?];
+  if let Some(ref public_addr) = config.get_public_addr() {
+    origins.push(
+      HeaderValue::from_str(public_addr)
+        .map_err(|err| anyhow!("Invalid public_addr, Error: {err}"))?,
+    );
+  }
+  Ok(origins)
+}
 
-pub fn cors_layer(config: &ServerConfig) -> ApiResult<tower_http::cors::CorsLayer> {
-  let domain = HeaderValue::from_str(&config.domain_name)
-    .map_err(|err| anyhow!("Invalid domain url, Error: {err}"))?;
-  let allow_origin = if let Some(ref public_addr) = config.get_public_addr() {
-    let public_addr = HeaderValue::from_str(public_addr)
-      .map_err(|err| anyhow!("Invalid public_addr, Error: {err}"))?;
-    tower_http::cors::AllowOrigin::list(vec![public_addr, domain])
-  } else {
-    tower_http::cors::AllowOrigin::exact(domain)
-  };
+/// The origins are checked against the current settings, so a reload applies to the next
+/// request.
+pub fn cors_layer(config: Arc<ArcSwap<ApiConfig>>) -> ApiResult<tower_http::cors::CorsLayer> {
+  allowed_origins(&config.load().server)?;
+  let allow_origin = tower_http::cors::AllowOrigin::predicate(move |origin, _| {
+    allowed_origins(&config.load().server).is_ok_and(|origins| origins.contains(origin))
+  });
   Ok(
     tower_http::cors::CorsLayer::new()
       .allow_methods([
         hyper::Method::GET,
         hyper::Method::POST,
         hyper::Method::DELETE,
+        hyper::Method::HEAD,
+        hyper::Method::PATCH,
+        hyper::Method::OPTIONS,
       ])
       .allow_origin(allow_origin)
-      .allow_headers([hyper::header::CONTENT_TYPE, hyper::header::AUTHORIZATION]),
+      .allow_headers([
+        hyper::header::CONTENT_TYPE,
+        hyper::header::AUTHORIZATION,
+        HeaderName::from_static(API_KEY_HEADER),
+        HeaderName::from_static(TUS_RESUMABLE),
+        HeaderName::from_static(UPLOAD_LENGTH),
+        HeaderName::from_static(UPLOAD_OFFSET),
+        HeaderName::from_static(UPLOAD_METADATA),
+      ])
+      .expose_headers([
+        hyper::header::LOCATION,
+        HeaderName::from_static(REQUEST_ID_HEADER),
+        HeaderName::from_static(TUS_RESUMABLE),
+        HeaderName::from_static(TUS_VERSION_HEADER),
+        HeaderName::from_static(TUS_EXTENSION_HEADER),
+        HeaderName::from_static(TUS_MAX_SIZE),
+        HeaderName::from_static(UPLOAD_LENGTH),
+        HeaderName::from_static(UPLOAD_OFFSET),
+        HeaderName::from_static(UPLOAD_FILE_URL),
+        HeaderName::from_static(UPLOAD_FILE_EXPIRES),
+      ]),
   )
 }
@@ -7,7 +7,7 @@ use once_cell::sync::Lazy;
 use pf_sdk::util::dir::get_cargo_project_root;
 use serde::Deserialize;
 use std::{
-  net::{AddrParseError, SocketAddr},
+  net::{AddrParseError, IpAddr, SocketAddr},
   path::PathBuf,
 };
 
@@ -17,6 +17,8 @@ pub mod args;
 pub mod cors;
 pub mod env;
 
+const S3_MIN_PART_BYTES_SIZE: usize = 5 * 1024 * 1024;
+
 pub static CONFIG: Lazy<ApiConfig> =
   Lazy::new(|| ApiConfig::read(None, get_env_source(ENV_PREFIX)).unwrap());
 
@@ -25,10 +27,24 @@ pub struct ApiConfig {
   pub server: ServerConfig,
   pub fs: FileSystemConfig,
   pub db: DatabaseConfig,
+  pub storage: StorageConfig,
+  pub rate_limit: RateLimitConfig,
+  pub quota: QuotaConfig,
+  pub webhook: WebhookConfig,
+  pub audit: AuditConfig,
+  pub log: LogConfig,
+  pub fsck: FsckConfig,
   pub max_upload_bytes_size: usize,
   pub default_code_length: usize,
   pub default_expire_secs: u64,
+  pub pending_upload_timeout_secs: u64,
   pub allow_manual_deletion: bool,
+  pub allow_registration: bool,
+  pub require_api_key: bool,
+  pub admin_token: Option<String>,
+  /// The settings file it was read from, read again when the settings are reloaded.
+  #[serde(skip)]
+  pub settings: Option<PathBuf>,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -40,9 +56,10 @@ pub struct ServerConfig {
   pub port: u16,
   file_tls_key_path: Option<String>,
   file_tls_cert_path: Option<String>,
+  pub shutdown_timeout_secs: u64,
 }
 
-#[derive(Debug, Deserialize, Clone, strum::Display, Copy)]
+#[derive(Debug, Deserialize, Clone, strum::Display, Copy, PartialEq, Eq)]
 pub enum UrlSchema {
   #[serde(rename = "http")]
   #[strum(serialize = "http")]
@@ -52,16 +69,156 @@ pub enum UrlSchema {
   Https,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, PartialEq)]
 pub struct DatabaseConfig {
   pub path_dir: PathBuf,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, PartialEq)]
 pub struct FileSystemConfig {
   pub base_dir: PathBuf,
 }
 
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct StorageConfig {
+  pub backend: StorageBackendKind,
+  pub s3: Option<S3Config>,
+}
+
+#[derive(Debug, Deserialize, Clone, strum::Display, Copy, PartialEq, Eq)]
+pub enum StorageBackendKind {
+  #[serde(rename = "local")]
+  #[strum(serialize = "local")]
+  Local,
+  #[serde(rename = "memory")]
+  #[strum(serialize = "memory")]
+  Memory,
+  #[serde(rename = "s3")]
+  #[strum(serialize = "s3")]
+  S3,
+}
+
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct S3Config {
+  pub endpoint: String,
+  pub region: String,
+  pub bucket: String,
+  pub access_key: String,
+  pub secret_key: String,
+  pub part_bytes_size: usize,
+}
+
+/// Every quota is unlimited when it is not set.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct QuotaConfig {
+  pub user_max_bytes: Option<u64>,
+  pub user_max_files: Option<usize>,
+  pub max_bytes: Option<u64>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct RateLimitConfig {
+  pub enable: bool,
+  #[serde(default)]
+  pub trusted_proxies: Vec<IpAddr>,
+  pub upload: RateLimitRule,
+  pub download: RateLimitRule,
+  pub not_found: RateLimitRule,
+}
+
+/// A token bucket holding `burst` tokens and refilled with `per_minute` tokens.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+pub struct RateLimitRule {
+  pub burst: u32,
+  pub per_minute: u32,
+}
+
+impl RateLimitRule {
+  pub fn is_unlimited(&self) -> bool {
+    self.burst == 0 || self.per_minute == 0
+  }
+}
+
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct WebhookConfig {
+  #[serde(default)]
+  pub urls: Vec<String>,
+  pub secret: Option<String>,
+  pub max_attempts: u32,
+  pub retry_delay_ms: u64,
+  pub timeout_secs: u64,
+  pub queue_size: usize,
+  pub concurrency: usize,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct AuditConfig {
+  pub enable: bool,
+  pub max_file_bytes: u64,
+  pub max_files: usize,
+  pub database: bool,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct FsckConfig {
+  /// Checks the database against the stored files before the server starts.
+  pub on_startup: bool,
+  pub repair: RepairMode,
+}
+
+/// What to do with the problems a consistency check finds.
+#[derive(
+  Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default, strum::Display, clap::ValueEnum,
+)]
+pub enum RepairMode {
+  /// Only report them.
+  #[default]
+  #[serde(rename = "none")]
+  #[strum(serialize = "none")]
+  None,
+  /// Delete the meta data without files and the files without meta data.
+  #[serde(rename = "delete")]
+  #[strum(serialize = "delete")]
+  Delete,
+  /// Like delete, but give the files without meta data a new record instead.
+  #[serde(rename = "register")]
+  #[strum(serialize = "register")]
+  Register,
+}
+
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct LogConfig {
+  pub format: LogFormat,
+  /// Filter directives like `info,pf_api=debug`, the RUST_LOG variable overrides them.
+  pub level: String,
+  /// Writes to this file instead of the standard output.
+  pub file: Option<PathBuf>,
+}
+
+#[derive(Debug, Deserialize, Clone, strum::Display, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+  #[serde(rename = "text")]
+  #[strum(serialize = "text")]
+  Text,
+  #[serde(rename = "pretty")]
+  #[strum(serialize = "pretty")]
+  Pretty,
+  #[serde(rename = "json")]
+  #[strum(serialize = "json")]
+  Json,
+}
+
+impl FileSystemConfig {
+  /// Keeps the unfinished resumable uploads on the local disk with every storage backend.
+  pub fn get_upload_dir(&self) -> PathBuf {
+    self.base_dir.join(".uploads")
+  }
+
+  pub fn get_audit_dir(&self) -> PathBuf {
+    self.base_dir.join(".audit")
+  }
+}
+
 impl ServerConfig {
   pub fn get_http_addr(&self) -> String {
     format!("{}://{}:{}", self.schema, self.host, self.port)
@@ -103,14 +260,16 @@ impl ApiConfig {
     file_src: Option<PathBuf>,
     env_src: Environment,
   ) -> Result<Self, config::ConfigError> {
-    config::Config::builder()
+    let mut config: Self = config::Config::builder()
       .add_source(config::File::from(
-        get_basic_settings_path(file_src)
+        get_basic_settings_path(file_src.clone())
           .map_err(|e| config::ConfigError::Message(e.to_string()))?,
       ))
       .add_source(env_src)
       .build()?
-      .try_deserialize()
+      .try_deserialize()?;
+    config.settings = file_src;
+    Ok(config)
   }
 
   pub fn validate(&self) -> ApiResult {
@@ -141,9 +300,75 @@ impl ApiConfig {
         "The port number is invalid.".to_string(),
       )));
     }
+    if self.storage.backend == StorageBackendKind::S3 {
+      let Some(s3) = self.storage.s3.as_ref() else {
+        return Err(ApiError::ConfigError(config::ConfigError::Message(
+          "The [storage.s3] section should be configured for the s3 backend.".to_string(),
+        )));
+      };
+      if s3.part_bytes_size < S3_MIN_PART_BYTES_SIZE {
+        return Err(ApiError::ConfigError(config::ConfigError::Message(
+          "The s3 part_bytes_size should be at least 5MB.".to_string(),
+        )));
+      }
+    }
+    for url in &self.webhook.urls {
+      if !url.starts_with("http://") && !url.starts_with("https://") {
+        return Err(ApiError::ConfigError(config::ConfigError::Message(
+          format!("The webhook url {url} should start with 'http://' or 'https://'."),
+        )));
+      }
+    }
+    if self.pending_upload_timeout_secs == 0 {
+      return Err(ApiError::ConfigError(config::ConfigError::Message(
+        "The pending_upload_timeout_secs should be greater than 0.".to_string(),
+      )));
+    }
+    if self.webhook.max_attempts == 0
+      || self.webhook.queue_size == 0
+      || self.webhook.concurrency == 0
+    {
+      return Err(ApiError::ConfigError(config::ConfigError::Message(
+        "The webhook max_attempts, queue_size and concurrency should be greater than 0."
+          .to_string(),
+      )));
+    }
+    if let Err(err) = tracing_subscriber::EnvFilter::try_new(&self.log.level) {
+      return Err(ApiError::ConfigError(config::ConfigError::Message(
+        format!("The log level {} is invalid, Error: {err}", self.log.level),
+      )));
+    }
+    if let Some(file) = self.log.file.as_ref() {
+      if file.file_name().is_none() {
+        return Err(ApiError::ConfigError(config::ConfigError::Message(
+          format!("The log file {} should name a file.", file.display()),
+        )));
+      }
+    }
+    cors::allowed_origins(&self.server)?;
     // TODO
     Ok(())
   }
+
+  /// The first changed setting that is only read at startup, so a reload can not apply it.
+  pub fn restart_required(&self, other: &ApiConfig) -> Option<&'static str> {
+    [
+      ("server.schema", self.server.schema != other.server.schema),
+      ("server.host", self.server.host != other.server.host),
+      ("server.port", self.server.port != other.server.port),
+      (
+        "server.shutdown_timeout_secs",
+        self.server.shutdown_timeout_secs != other.server.shutdown_timeout_secs,
+      ),
+      ("fs", self.fs != other.fs),
+      ("db", self.db != other.db),
+      ("storage", self.storage != other.storage),
+      ("webhook", self.webhook != other.webhook),
+      ("log", self.log != other.log),
+    ]
+    .into_iter()
+    .find_map(|(setting, changed)| changed.then_some(setting))
+  }
 }
 
 fn get_basic_settings_path(file_src: Option<PathBuf>) -> std::io::Result<PathBuf> {
@@ -1 +1,8 @@
 pub const ENV_PREFIX: &str = "PF";
+
+// Codes that would be shadowed by other routes.
+pub const RESERVED_CODES: [&str; 9] = [
+  "info", "tus", "upload", "healthz", "accounts", "files", "admin", "metrics", "quota",
+];
+
+pub const DEFAULT_PAGE_SIZE: usize = 20;
@@ -0,0 +1,50 @@
+use crate::{
+  error::{result::ApiResult, ApiError},
+  util::secret::SecretHash,
+};
+use chrono::{DateTime, Utc};
+use pf_sdk::dto::response::AccountResponse;
+use serde::{Deserialize, Serialize};
+use sled::IVec;
+
+#[derive(Debug, Clone, Serialize, Deserialize, fake::Dummy)]
+pub struct Account {
+  pub username: String,
+  pub password: SecretHash,
+  pub is_admin: bool,
+  pub created_at: DateTime<Utc>,
+}
+
+impl TryFrom<&IVec> for Account {
+  type Error = ApiError;
+
+  fn try_from(value: &IVec) -> ApiResult<Self> {
+    Ok(bincode::deserialize::<Self>(value)?)
+  }
+}
+
+impl TryFrom<IVec> for Account {
+  type Error = ApiError;
+
+  fn try_from(value: IVec) -> ApiResult<Self> {
+    Self::try_from(&value)
+  }
+}
+
+impl TryFrom<&Account> for IVec {
+  type Error = ApiError;
+
+  fn try_from(value: &Account) -> ApiResult<IVec> {
+    Ok(IVec::from(bincode::serialize(value)?))
+  }
+}
+
+impl From<&Account> for AccountResponse {
+  fn from(value: &Account) -> Self {
+    AccountResponse {
+      username: value.username.clone(),
+      is_admin: value.is_admin,
+      created_at: value.created_at,
+    }
+  }
+}
@@ -0,0 +1,52 @@
+use crate::error::{result::ApiResult, ApiError};
+use chrono::{DateTime, Utc};
+use pf_sdk::dto::{request::ApiKeyScope, response::ApiKeyResponse};
+use serde::{Deserialize, Serialize};
+use sled::IVec;
+
+#[derive(Debug, Clone, Serialize, Deserialize, fake::Dummy)]
+pub struct ApiKey {
+  pub id: String,
+  pub username: String,
+  pub name: String,
+  pub scopes: Vec<ApiKeyScope>,
+  // Keys are random enough that a plain SHA-256 digest is safe to store.
+  pub key_hash: String,
+  pub created_at: DateTime<Utc>,
+}
+
+impl TryFrom<&IVec> for ApiKey {
+  type Error = ApiError;
+
+  fn try_from(value: &IVec) -> ApiResult<Self> {
+    Ok(bincode::deserialize::<Self>(value)?)
+  }
+}
+
+impl TryFrom<IVec> for ApiKey {
+  type Error = ApiError;
+
+  fn try_from(value: IVec) -> ApiResult<Self> {
+    Self::try_from(&value)
+  }
+}
+
+impl TryFrom<&ApiKey> for IVec {
+  type Error = ApiError;
+
+  fn try_from(value: &ApiKey) -> ApiResult<IVec> {
+    Ok(IVec::from(bincode::serialize(value)?))
+  }
+}
+
+impl From<&ApiKey> for ApiKeyResponse {
+  fn from(value: &ApiKey) -> Self {
+    ApiKeyResponse {
+      id: value.id.clone(),
+      name: value.name.clone(),
+      scopes: value.scopes.clone(),
+      created_at: value.created_at,
+      key: None,
+    }
+  }
+}
@@ -0,0 +1,21 @@
+use super::file_path::FilePath;
+
+/// Blobs are stored once under their digest, random codes never start with a dot.
+pub const BLOB_CODE: &str = ".blobs";
+
+/// Uploads are written here and only moved to their blob once they are complete.
+pub const STAGING_CODE: &str = ".staging";
+
+pub fn blob_path(digest: &str) -> FilePath {
+  FilePath {
+    code: BLOB_CODE.to_string(),
+    file_name: digest.to_string(),
+  }
+}
+
+pub fn staging_path(file_path: &FilePath) -> FilePath {
+  FilePath {
+    code: format!("{STAGING_CODE}/{}", file_path.code),
+    file_name: file_path.file_name.clone(),
+  }
+}
@@ -4,7 +4,9 @@ use serde::{Deserialize, Serialize};
 use sled::IVec;
 use std::path::PathBuf;
 
-#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord, fake::Dummy)]
+#[derive(
+  Clone, Serialize, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, fake::Dummy,
+)]
 pub struct FilePath {
   pub code: String,
   pub file_name: String,
@@ -0,0 +1,40 @@
+use crate::{
+  error::{result::ApiResult, ApiError},
+  util::secret::SecretHash,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sled::IVec;
+
+#[derive(Debug, Clone, Serialize, Deserialize, fake::Dummy)]
+pub struct Manifest {
+  pub file_names: Vec<String>,
+  pub created_at: DateTime<Utc>,
+  pub expire_date_time: DateTime<Utc>,
+  pub secret: Option<SecretHash>,
+  pub owner: Option<String>,
+}
+
+impl TryFrom<&IVec> for Manifest {
+  type Error = ApiError;
+
+  fn try_from(value: &IVec) -> ApiResult<Self> {
+    Ok(bincode::deserialize::<Self>(value)?)
+  }
+}
+
+impl TryFrom<IVec> for Manifest {
+  type Error = ApiError;
+
+  fn try_from(value: IVec) -> ApiResult<Self> {
+    Self::try_from(&value)
+  }
+}
+
+impl TryFrom<&Manifest> for IVec {
+  type Error = ApiError;
+
+  fn try_from(value: &Manifest) -> ApiResult<IVec> {
+    Ok(IVec::from(bincode::serialize(value)?))
+  }
+}
@@ -1,4 +1,8 @@
 use crate::{
+  database::{
+    blob::{blob_path, staging_path},
+    file_path::FilePath,
+  },
   error::{result::ApiResult, ApiError},
   util::secret::SecretHash,
 };
@@ -7,6 +11,10 @@ use pf_sdk::dto::response::MetaDataFileResponse;
 use serde::{Deserialize, Serialize};
 use sled::IVec;
 
+/// Version of the record format, the database stores it so older records are rewritten
+/// before they are read.
+pub const FORMAT_VERSION: u32 = 1;
+
 #[derive(Debug, Clone, Serialize, Deserialize, fake::Dummy)]
 pub struct MetaDataFile {
   pub created_at: DateTime<Utc>,
@@ -15,6 +23,66 @@ pub struct MetaDataFile {
   pub manual_deletion: bool,
   pub max_download: Option<u32>,
   pub count_downloads: u32,
+  pub etag: String,
+  pub size: u64,
+  /// Hex SHA-256 of the content, empty until the upload is stored.
+  pub digest: String,
+  pub owner: Option<String>,
+  pub state: UploadState,
+}
+
+/// Only complete files are served, the others are removed by the garbage collector.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, fake::Dummy)]
+pub enum UploadState {
+  /// The content is still being received.
+  Pending,
+  Complete,
+  /// Storing the content failed and the file is about to be removed.
+  Failed,
+}
+
+/// The record before the format was versioned, the content was stored at the file path.
+#[derive(Deserialize)]
+struct LegacyMetaDataFile {
+  created_at: DateTime<Utc>,
+  expire_date_time: DateTime<Utc>,
+  secret: Option<SecretHash>,
+  manual_deletion: bool,
+  max_download: Option<u32>,
+  count_downloads: u32,
+}
+
+impl MetaDataFile {
+  /// Where the content of `file_path` lives in the storage backend. A complete file without
+  /// a digest is a legacy file that is still stored at its own path.
+  pub fn blob_path(&self, file_path: &FilePath) -> FilePath {
+    if !self.digest.is_empty() {
+      blob_path(&self.digest)
+    } else if self.state == UploadState::Complete {
+      file_path.clone()
+    } else {
+      staging_path(file_path)
+    }
+  }
+
+  /// Reads a record written before the format was versioned. The size and digest stay
+  /// unknown until the content is moved into the blobs.
+  pub fn from_legacy(value: &[u8]) -> ApiResult<Self> {
+    let legacy = bincode::deserialize::<LegacyMetaDataFile>(value)?;
+    Ok(Self {
+      created_at: legacy.created_at,
+      expire_date_time: legacy.expire_date_time,
+      secret: legacy.secret,
+      manual_deletion: legacy.manual_deletion,
+      max_download: legacy.max_download,
+      count_downloads: legacy.count_downloads,
+      etag: cuid2::create_id(),
+      size: 0,
+      digest: String::new(),
+      owner: None,
+      state: UploadState::Complete,
+    })
+  }
 }
 
 impl TryFrom<&[u8]> for MetaDataFile {
@@ -66,6 +134,9 @@ impl From<&MetaDataFile> for MetaDataFileResponse {
       allow_manual_deletion: value.manual_deletion,
       max_download: value.max_download,
       count_downloads: value.count_downloads,
+      owner: value.owner.clone(),
+      size: value.size,
+      sha256: (!value.digest.is_empty()).then(|| value.digest.clone()),
     }
   }
 }
@@ -1,42 +1,126 @@
 use crate::{
   configure::DatabaseConfig,
   error::{result::ApiResult, ApiError},
-  util::path::get_fs_path,
+  storage::StorageBackend,
 };
 use chrono::{DateTime, Utc};
-use sled::IVec;
+use pf_sdk::dto::response::AuditEvent;
+use sled::{IVec, Transactional};
+use std::collections::BTreeSet;
 use std::sync::Arc;
-use std::sync::RwLock;
+use std::sync::{Mutex, RwLock};
 use std::time::Duration;
-use std::{collections::BTreeSet, path::Path};
 use tokio::sync::Notify;
 
+use self::account::Account;
+use self::api_key::ApiKey;
+use self::blob::blob_path;
 use self::file_path::FilePath;
-use self::meta_data_file::MetaDataFile;
+use self::manifest::Manifest;
+use self::meta_data_file::{MetaDataFile, FORMAT_VERSION};
+use self::upload::Upload;
+use self::usage::{Reservation, Usage, UsageCounter, UsageIndex};
 
+pub mod account;
+pub mod api_key;
+pub mod blob;
 pub mod file_path;
+pub mod manifest;
 pub mod meta_data_file;
+pub mod upload;
+pub mod usage;
 
 pub type Expires = Arc<RwLock<BTreeSet<(DateTime<Utc>, FilePath)>>>;
 
+const FORMAT_VERSION_KEY: &str = "format_version";
+const FIRST_ADMIN_KEY: &str = "first_admin";
+
 #[derive(Clone)]
 pub struct Database {
   inner: sled::Db,
+  uploads: sled::Tree,
+  manifests: sled::Tree,
+  accounts: sled::Tree,
+  bootstrap: sled::Tree,
+  api_keys: sled::Tree,
+  blobs: sled::Tree,
+  audit: sled::Tree,
+  blob_lock: Arc<tokio::sync::Mutex<()>>,
   expires: Expires,
+  usage: UsageCounter,
   notify: Arc<Notify>,
 }
 
 impl Database {
   pub fn new(config: &DatabaseConfig) -> ApiResult<Self> {
     let db = sled::open(&config.path_dir)?;
+    Self::migrate(&db)?;
     let expires = Self::load_expires(&db)?;
+    let uploads = db.open_tree("uploads")?;
+    let manifests = db.open_tree("manifests")?;
+    let accounts = db.open_tree("accounts")?;
+    let bootstrap = db.open_tree("bootstrap")?;
+    let api_keys = db.open_tree("api_keys")?;
+    let blobs = db.open_tree("blobs")?;
+    let audit = db.open_tree("audit")?;
+    let usage = Self::load_usage(&db, &uploads)?;
     Ok(Self {
       inner: db,
+      uploads,
+      manifests,
+      accounts,
+      bootstrap,
+      api_keys,
+      blobs,
+      audit,
+      blob_lock: Default::default(),
       expires: Arc::new(RwLock::new(expires)),
+      usage: Arc::new(Mutex::new(usage)),
       notify: Default::default(),
     })
   }
 
+  /// Rewrites the records of an older format in one transaction, a database without a
+  /// format version and with records was written before the format was versioned.
+  fn migrate(db: &sled::Db) -> ApiResult {
+    let schema = db.open_tree("schema")?;
+    let version = match schema.get(FORMAT_VERSION_KEY)? {
+      Some(value) => u32::from_be_bytes(value.as_ref().try_into().map_err(|_| {
+        ApiError::DatabaseError(sled::Error::Unsupported(
+          "The format version of the database is invalid.".to_string(),
+        ))
+      })?),
+      None if db.is_empty() => FORMAT_VERSION,
+      None => 0,
+    };
+    if version > FORMAT_VERSION {
+      return Err(ApiError::DatabaseError(sled::Error::Unsupported(format!(
+        "The database format version {version} is newer than {FORMAT_VERSION}."
+      ))));
+    }
+    let mut records = vec![];
+    if version < FORMAT_VERSION {
+      for kv in db.iter() {
+        let (key, val) = kv?;
+        records.push((key, IVec::try_from(MetaDataFile::from_legacy(&val)?)?));
+      }
+    }
+    (&**db, &schema).transaction(|(inner, schema)| {
+      for (key, val) in &records {
+        inner.insert(key, val)?;
+      }
+      schema.insert(FORMAT_VERSION_KEY, &FORMAT_VERSION.to_be_bytes())?;
+      Ok(())
+    })?;
+    if !records.is_empty() {
+      tracing::info!(
+        "{} records are migrated to the format version {FORMAT_VERSION}.",
+        records.len()
+      );
+    }
+    Ok(())
+  }
+
   fn load_expires(db: &sled::Db) -> ApiResult<BTreeSet<(DateTime<Utc>, FilePath)>> {
     let mut expires = BTreeSet::new();
     for kv in db.iter() {
@@ -48,6 +132,57 @@ impl Database {
     Ok(expires)
   }
 
+  /// Counts the files and the running resumable uploads of every owner.
+  fn load_usage(db: &sled::Db, uploads: &sled::Tree) -> ApiResult<UsageIndex> {
+    let mut usage = UsageIndex::default();
+    for kv in db.iter() {
+      let (_, val) = kv?;
+      usage.add_file(&MetaDataFile::try_from(val)?);
+    }
+    for kv in uploads.iter() {
+      let (_, val) = kv?;
+      let upload = Upload::try_from(val)?;
+      usage.add(upload.owner.as_deref(), Usage::from(&upload));
+    }
+    Ok(usage)
+  }
+
+  pub fn usage(&self, owner: Option<&str>) -> ApiResult<Usage> {
+    let guard = self
+      .usage
+      .lock()
+      .map_err(|err| ApiError::LockError(err.to_string()))?;
+    Ok(guard.get(owner))
+  }
+
+  pub fn stored_bytes(&self) -> ApiResult<u64> {
+    let guard = self
+      .usage
+      .lock()
+      .map_err(|err| ApiError::LockError(err.to_string()))?;
+    Ok(guard.stored_bytes())
+  }
+
+  /// Holds `usage` for the owner when `check` accepts the total usage and the usage of
+  /// the owner, so concurrent uploads cannot all pass the same check.
+  pub fn reserve(
+    &self,
+    owner: Option<String>,
+    usage: Usage,
+    check: impl FnOnce(Usage, Option<Usage>) -> ApiResult,
+  ) -> ApiResult<Reservation> {
+    let mut reservation = Reservation::new(self.usage.clone(), owner);
+    reservation.grow(usage, check)?;
+    Ok(reservation)
+  }
+
+  fn track_usage(&self, f: impl FnOnce(&mut UsageIndex)) {
+    match self.usage.lock() {
+      Ok(mut guard) => f(&mut guard),
+      Err(err) => tracing::error!("Failed to acquire usage lock, Error: {err}"),
+    }
+  }
+
   pub fn fetch(&self, file_path: &FilePath) -> ApiResult<Option<MetaDataFile>> {
     self
       .inner
@@ -57,14 +192,20 @@ impl Database {
   }
 
   pub fn update(&self, file_path: &FilePath, old: MetaDataFile, new: MetaDataFile) -> ApiResult {
-    let old = IVec::try_from(old)?;
-    let new = IVec::try_from(new)?;
     let file_path = IVec::try_from(file_path)?;
-    let result = self
-      .inner
-      .compare_and_swap(&file_path, Some(old), Some(new))?;
+    let result = self.inner.compare_and_swap(
+      &file_path,
+      Some(IVec::try_from(&old)?),
+      Some(IVec::try_from(&new)?),
+    )?;
     match result {
-      Ok(_) => Ok(()),
+      Ok(_) => {
+        self.track_usage(|usage| {
+          usage.sub_file(&old);
+          usage.add_file(&new);
+        });
+        Ok(())
+      }
       Err(err) if err.current.is_some() => {
         tracing::warn!("Compare and swap failed, Error: {err}");
         Err(ApiError::BadRequestError(
@@ -80,26 +221,96 @@ impl Database {
     }
   }
 
+  pub fn increment_downloads(&self, file_path: &FilePath) -> ApiResult {
+    self.modify(file_path, |meta| meta.count_downloads += 1)?;
+    Ok(())
+  }
+
+  /// Applies `f` to the stored meta data, retrying when another request changed it first.
+  pub fn modify(
+    &self,
+    file_path: &FilePath,
+    f: impl Fn(&mut MetaDataFile),
+  ) -> ApiResult<Option<MetaDataFile>> {
+    loop {
+      let Some(meta) = self.fetch(file_path)? else {
+        return Ok(None);
+      };
+      let mut updated_meta = meta.clone();
+      f(&mut updated_meta);
+      let old_expire = meta.expire_date_time;
+      match self.update(file_path, meta, updated_meta.clone()) {
+        Err(ApiError::BadRequestError(_)) => continue,
+        Err(err) => return Err(err),
+        Ok(()) => {
+          if old_expire != updated_meta.expire_date_time {
+            self.reschedule(file_path, old_expire, updated_meta.expire_date_time)?;
+          }
+          return Ok(Some(updated_meta));
+        }
+      }
+    }
+  }
+
+  fn reschedule(
+    &self,
+    file_path: &FilePath,
+    old_expire: DateTime<Utc>,
+    new_expire: DateTime<Utc>,
+  ) -> ApiResult {
+    let mut guard = self
+      .expires
+      .write()
+      .map_err(|err| ApiError::LockError(err.to_string()))?;
+    guard.remove(&(old_expire, file_path.clone()));
+    guard.insert((new_expire, file_path.clone()));
+    drop(guard);
+    self.notify_gc();
+    Ok(())
+  }
+
+  pub fn fetch_all(&self) -> ApiResult<Vec<(FilePath, MetaDataFile)>> {
+    let mut files = vec![];
+    for kv in self.inner.iter() {
+      let (key, val) = kv?;
+      files.push((FilePath::try_from(&key)?, MetaDataFile::try_from(val)?));
+    }
+    files.sort_by(|(a_path, a), (b_path, b)| {
+      b.created_at
+        .cmp(&a.created_at)
+        .then_with(|| a_path.cmp(b_path))
+    });
+    Ok(files)
+  }
+
+  /// Scans every file, newest first, there is no index by owner.
+  pub fn fetch_owned(&self, owner: &str) -> ApiResult<Vec<(FilePath, MetaDataFile)>> {
+    let mut files = self.fetch_all()?;
+    files.retain(|(_, meta)| meta.owner.as_deref() == Some(owner));
+    Ok(files)
+  }
+
   pub fn exist(&self, path: &FilePath) -> ApiResult<bool> {
     Ok(self.inner.contains_key(IVec::try_from(path)?)?)
   }
 
   pub async fn store(&self, path: FilePath, meta: MetaDataFile) -> ApiResult {
     let expire_date_time = meta.expire_date_time;
-    let meta = IVec::try_from(&meta)?;
     let key = IVec::try_from(&path)?;
-    let result = self
-      .inner
-      .compare_and_swap(&key, Option::<IVec>::None, Some(meta))?;
+    let result =
+      self
+        .inner
+        .compare_and_swap(&key, Option::<IVec>::None, Some(IVec::try_from(&meta)?))?;
     match result {
       Ok(_) => {
+        self.track_usage(|index| index.add_file(&meta));
         let expire = (expire_date_time, path);
         match self.expires.write() {
           Ok(mut guard) => {
             let is_gc_notify = guard
               .iter()
               .next()
-              .map_or(true, |(first_expire, _)| *first_expire > expire_date_time);
+              .is_none_or(|(first_expire, _)| *first_expire > expire_date_time);
             guard.insert(expire.clone());
             drop(guard);
             if is_gc_notify {
@@ -108,6 +319,7 @@ impl Database {
           }
           Err(err) => {
             self.inner.remove(&key)?;
+            self.track_usage(|index| index.sub_file(&meta));
             return Err(ApiError::LockError(err.to_string()));
           }
         }
@@ -135,6 +347,8 @@ impl Database {
       .map(MetaDataFile::try_from)
       .transpose()?;
     if let Some(meta) = &meta {
+      self.track_usage(|usage| usage.sub_file(meta));
+      self.remove_from_manifest(&path)?;
       match self.expires.write() {
         Ok(mut guard) => {
           guard.remove(&(meta.expire_date_time, path));
@@ -147,7 +361,19 @@ impl Database {
     Ok(meta)
   }
 
-  pub async fn purge(&self, base_dir: &Path) -> ApiResult<Option<Duration>> {
+  pub fn next_expire(&self) -> ApiResult<Option<DateTime<Utc>>> {
+    let guard = self
+      .expires
+      .read()
+      .map_err(|err| ApiError::LockError(err.to_string()))?;
+    Ok(guard.iter().next().map(|(expire, _)| *expire))
+  }
+
+  /// Removes the expired files and returns them with the time until the next expiration.
+  pub async fn purge(
+    &self,
+    storage: &dyn StorageBackend,
+  ) -> ApiResult<(Vec<(FilePath, MetaDataFile)>, Option<Duration>)> {
     let mut paths_should_delete = vec![];
     let mut wakeup_next_time = None;
     match self.expires.write() {
@@ -169,18 +395,298 @@ impl Database {
         return Err(ApiError::LockError(err.to_string()));
       }
     }
-    self.remove_file(base_dir, paths_should_delete).await?;
-    Ok(wakeup_next_time)
+    let removed = self.remove_file(storage, paths_should_delete).await?;
+    Ok((removed, wakeup_next_time))
   }
 
-  pub async fn remove_file(&self, base_dir: &Path, paths: Vec<FilePath>) -> ApiResult {
+  /// Returns the files that existed before they were removed.
+  pub async fn remove_file(
+    &self,
+    storage: &dyn StorageBackend,
+    paths: Vec<FilePath>,
+  ) -> ApiResult<Vec<(FilePath, MetaDataFile)>> {
+    let mut removed = Vec::with_capacity(paths.len());
     for file_path in paths {
-      self.inner.remove(&IVec::try_from(&file_path)?)?;
-      tokio::fs::remove_file(get_fs_path(base_dir, &file_path)).await?;
+      let Some(meta) = self.delete(file_path.clone()).await? else {
+        continue;
+      };
+      if !meta.digest.is_empty() {
+        self.release_blob(storage, &meta.digest).await?;
+      } else if storage.exists(&meta.blob_path(&file_path)).await? {
+        // The upload failed or is still streaming, or a legacy file was not moved yet,
+        // so there is no blob.
+        storage.delete(&meta.blob_path(&file_path)).await?;
+      }
+      removed.push((file_path, meta));
+    }
+    Ok(removed)
+  }
+
+  pub fn fetch_blob_refs(&self, digest: &str) -> ApiResult<u64> {
+    Ok(self.blobs.get(digest)?.map(decode_refs).unwrap_or(0))
+  }
+
+  pub fn fetch_blobs(&self) -> ApiResult<Vec<(String, u64)>> {
+    let mut blobs = vec![];
+    for kv in self.blobs.iter() {
+      let (key, val) = kv?;
+      blobs.push((std::str::from_utf8(&key)?.to_string(), decode_refs(val)));
+    }
+    Ok(blobs)
+  }
+
+  /// Overwrites the reference count, zero drops the blob record.
+  pub async fn set_blob_refs(&self, digest: &str, refs: u64) -> ApiResult {
+    let _guard = self.blob_lock.lock().await;
+    if refs == 0 {
+      self.blobs.remove(digest)?;
+    } else {
+      self.blobs.insert(digest, &refs.to_be_bytes())?;
+    }
+    Ok(())
+  }
+
+  /// Adds a reference to the blob, the staged file becomes the blob when it is new. An
+  /// unreferenced blob left by a failed move is removed, so storing the content again starts
+  /// over.
+  pub async fn store_blob(
+    &self,
+    storage: &dyn StorageBackend,
+    staged: &FilePath,
+    digest: &str,
+  ) -> ApiResult {
+    let _guard = self.blob_lock.lock().await;
+    let refs = self.fetch_blob_refs(digest)?;
+    if refs == 0 {
+      let blob = blob_path(digest);
+      if let Err(err) = storage.rename(staged, &blob).await {
+        if storage.exists(&blob).await.unwrap_or(true) {
+          if let Err(e) = storage.delete(&blob).await {
+            tracing::warn!("Failed to remove the unreferenced blob {digest}: {e}");
+          }
+        }
+        return Err(err);
+      }
+    } else {
+      storage.delete(staged).await?;
+    }
+    self.blobs.insert(digest, &(refs + 1).to_be_bytes())?;
+    Ok(())
+  }
+
+  /// Drops a reference to the blob and deletes it with the last one.
+  pub async fn release_blob(&self, storage: &dyn StorageBackend, digest: &str) -> ApiResult {
+    let _guard = self.blob_lock.lock().await;
+    match self.fetch_blob_refs(digest)? {
+      0 => tracing::warn!("The blob {digest} has no references."),
+      1 => {
+        self.blobs.remove(digest)?;
+        storage.delete(&blob_path(digest)).await?;
+      }
+      refs => {
+        self.blobs.insert(digest, &(refs - 1).to_be_bytes())?;
+      }
+    }
+    Ok(())
+  }
+
+  pub fn code_exist(&self, code: &str) -> ApiResult<bool> {
+    if self.manifests.contains_key(code)? {
+      return Ok(true);
+    }
+    // A bincode encoded code is a length prefixed string, so the prefix only
+    // matches file paths with exactly this code.
+    let prefix = bincode::serialize(code)?;
+    Ok(self.inner.scan_prefix(prefix).next().transpose()?.is_some())
+  }
+
+  pub fn fetch_manifest(&self, code: &str) -> ApiResult<Option<Manifest>> {
+    self
+      .manifests
+      .get(code)?
+      .map(Manifest::try_from)
+      .transpose()
+  }
+
+  pub fn store_manifest(&self, code: &str, manifest: &Manifest) -> ApiResult {
+    let result = self.manifests.compare_and_swap(
+      code,
+      Option::<IVec>::None,
+      Some(IVec::try_from(manifest)?),
+    )?;
+    match result {
+      Ok(_) => Ok(()),
+      Err(_) => Err(ApiError::ResourceExistsError(format!("Code {code}"))),
+    }
+  }
+
+  pub fn update_manifest(&self, code: &str, manifest: &Manifest) -> ApiResult {
+    self.manifests.insert(code, IVec::try_from(manifest)?)?;
+    Ok(())
+  }
+
+  pub fn delete_manifest(&self, code: &str) -> ApiResult<Option<Manifest>> {
+    self
+      .manifests
+      .remove(code)?
+      .map(Manifest::try_from)
+      .transpose()
+  }
+
+  fn remove_from_manifest(&self, file_path: &FilePath) -> ApiResult {
+    self.manifests.fetch_and_update(&file_path.code, |value| {
+      let mut manifest = Manifest::try_from(&IVec::from(value?)).ok()?;
+      manifest
+        .file_names
+        .retain(|name| *name != file_path.file_name);
+      if manifest.file_names.is_empty() {
+        return None;
+      }
+      IVec::try_from(&manifest).ok()
+    })?;
+    Ok(())
+  }
+
+  pub fn fetch_upload(&self, id: &str) -> ApiResult<Option<Upload>> {
+    self.uploads.get(id)?.map(Upload::try_from).transpose()
+  }
+
+  pub fn fetch_uploads(&self) -> ApiResult<Vec<(String, Upload)>> {
+    let mut uploads = vec![];
+    for kv in self.uploads.iter() {
+      let (key, val) = kv?;
+      uploads.push((
+        std::str::from_utf8(&key)?.to_string(),
+        Upload::try_from(val)?,
+      ));
+    }
+    Ok(uploads)
+  }
+
+  pub fn store_upload(&self, id: &str, upload: &Upload) -> ApiResult {
+    let old = self
+      .uploads
+      .insert(id, IVec::try_from(upload)?)?
+      .map(Upload::try_from)
+      .transpose()?;
+    self.track_usage(|usage| {
+      if let Some(old) = &old {
+        usage.sub(old.owner.as_deref(), Usage::from(old));
+      }
+      usage.add(upload.owner.as_deref(), Usage::from(upload));
+    });
+    self.notify_gc();
+    Ok(())
+  }
+
+  pub fn delete_upload(&self, id: &str) -> ApiResult<Option<Upload>> {
+    let upload = self.uploads.remove(id)?.map(Upload::try_from).transpose()?;
+    if let Some(upload) = &upload {
+      self.track_usage(|usage| usage.sub(upload.owner.as_deref(), Usage::from(upload)));
+    }
+    Ok(upload)
+  }
+
+  /// Keys start with the big endian timestamp so events are kept in time order.
+  pub fn store_audit_event(&self, event: &AuditEvent) -> ApiResult {
+    let micros = event.timestamp.timestamp_micros().max(0) as u64;
+    let mut key = micros.to_be_bytes().to_vec();
+    key.extend_from_slice(&self.inner.generate_id()?.to_be_bytes());
+    self.audit.insert(key, bincode::serialize(event)?)?;
+    Ok(())
+  }
+
+  pub fn fetch_audit_events(
+    &self,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    mut filter: impl FnMut(&AuditEvent) -> bool,
+    limit: usize,
+  ) -> ApiResult<Vec<AuditEvent>> {
+    let micros = |time: DateTime<Utc>| time.timestamp_micros().max(0) as u64;
+    let start = from.map_or(0, micros);
+    let end = to.map_or(u64::MAX, |to| micros(to).saturating_add(1));
+    let mut events = vec![];
+    for kv in self.audit.range(start.to_be_bytes()..end.to_be_bytes()) {
+      let event = bincode::deserialize::<AuditEvent>(&kv?.1)?;
+      if filter(&event) {
+        events.push(event);
+        if events.len() == limit {
+          break;
+        }
+      }
+    }
+    Ok(events)
+  }
+
+  pub fn fetch_account(&self, username: &str) -> ApiResult<Option<Account>> {
+    self
+      .accounts
+      .get(username)?
+      .map(Account::try_from)
+      .transpose()
+  }
+
+  /// Stores the first admin account, the bootstrap key is set in the same transaction so
+  /// only one call ever succeeds.
+  pub fn store_first_admin(&self, account: &Account) -> ApiResult {
+    let value = IVec::try_from(account)?;
+    let result = (&self.accounts, &self.bootstrap).transaction(|(accounts, bootstrap)| {
+      if let Some(username) = bootstrap.get(FIRST_ADMIN_KEY)? {
+        return Ok(Err(String::from_utf8_lossy(&username).to_string()));
+      }
+      if accounts.get(&account.username)?.is_some() {
+        return Ok(Err(account.username.clone()));
+      }
+      accounts.insert(account.username.as_bytes(), value.clone())?;
+      bootstrap.insert(FIRST_ADMIN_KEY, account.username.as_bytes())?;
+      Ok(Ok(()))
+    })?;
+    result.map_err(|username| ApiError::ResourceExistsError(format!("Account {username}")))
+  }
+
+  pub fn store_account(&self, account: &Account) -> ApiResult {
+    let result = self.accounts.compare_and_swap(
+      &account.username,
+      Option::<IVec>::None,
+      Some(IVec::try_from(account)?),
+    )?;
+    match result {
+      Ok(_) => Ok(()),
+      Err(_) => Err(ApiError::ResourceExistsError(format!(
+        "Account {}",
+        account.username
+      ))),
     }
+  }
+
+  pub fn fetch_api_key(&self, id: &str) -> ApiResult<Option<ApiKey>> {
+    self.api_keys.get(id)?.map(ApiKey::try_from).transpose()
+  }
+
+  pub fn fetch_api_keys(&self, username: &str) -> ApiResult<Vec<ApiKey>> {
+    let mut api_keys = vec![];
+    for kv in self.api_keys.iter() {
+      let (_, val) = kv?;
+      let api_key = ApiKey::try_from(val)?;
+      if api_key.username == username {
+        api_keys.push(api_key);
+      }
+    }
+    Ok(api_keys)
+  }
+
+  pub fn store_api_key(&self, api_key: &ApiKey) -> ApiResult {
+    self
+      .api_keys
+      .insert(&api_key.id, IVec::try_from(api_key)?)?;
     Ok(())
   }
 
+  pub fn delete_api_key(&self, id: &str) -> ApiResult<Option<ApiKey>> {
+    self.api_keys.remove(id)?.map(ApiKey::try_from).transpose()
+  }
+
   fn notify_gc(&self) {
     self.notify.notify_one()
   }
@@ -195,14 +701,69 @@ impl Database {
   }
 }
 
+fn decode_refs(value: IVec) -> u64 {
+  let mut buf = [0u8; 8];
+  buf.copy_from_slice(&value);
+  u64::from_be_bytes(buf)
+}
+
 #[cfg(test)]
 mod tests {
 
   use super::*;
-  use crate::util::test::StateTestContext;
+  use crate::{
+    database::{blob::staging_path, meta_data_file::UploadState},
+    storage::{memory::MemoryStorage, ByteRange, StorageReader},
+    util::{path::get_fs_path, test::StateTestContext},
+  };
   use fake::{Fake, Faker};
+  use futures_util::{future::BoxFuture, FutureExt};
+  use std::sync::atomic::{AtomicBool, Ordering};
   use test_context::test_context;
 
+  // Moves with the default copy and delete, the first delete of a staged file fails.
+  #[derive(Default)]
+  struct FlakyMoveStorage {
+    inner: MemoryStorage,
+    failed: AtomicBool,
+  }
+
+  impl StorageBackend for FlakyMoveStorage {
+    fn put<'a>(
+      &'a self,
+      file_path: &'a FilePath,
+      reader: StorageReader<'a>,
+    ) -> BoxFuture<'a, ApiResult<u64>> {
+      self.inner.put(file_path, reader)
+    }
+
+    fn get<'a>(
+      &'a self,
+      file_path: &'a FilePath,
+      range: Option<ByteRange>,
+    ) -> BoxFuture<'a, ApiResult<StorageReader<'static>>> {
+      self.inner.get(file_path, range)
+    }
+
+    fn delete<'a>(&'a self, file_path: &'a FilePath) -> BoxFuture<'a, ApiResult> {
+      async move {
+        if file_path.code.starts_with(".staging") && !self.failed.swap(true, Ordering::SeqCst) {
+          return Err(ApiError::StorageError("delete failed".to_string()));
+        }
+        self.inner.delete(file_path).await
+      }
+      .boxed()
+    }
+
+    fn exists<'a>(&'a self, file_path: &'a FilePath) -> BoxFuture<'a, ApiResult<bool>> {
+      self.inner.exists(file_path)
+    }
+
+    fn size<'a>(&'a self, file_path: &'a FilePath) -> BoxFuture<'a, ApiResult<u64>> {
+      self.inner.size(file_path)
+    }
+  }
+
   #[test_context(StateTestContext)]
   #[tokio::test]
   async fn test_store_file_and_fetch(ctx: &mut StateTestContext) {
@@ -248,6 +809,33 @@ mod tests {
     assert_eq!(result.count_downloads, meta.count_downloads + 1);
   }
 
+  #[test_context(StateTestContext)]
+  #[tokio::test]
+  async fn test_modify_file_reschedules_expiry(ctx: &mut StateTestContext) {
+    let file_path: FilePath = Faker.fake();
+    let meta: MetaDataFile = Faker.fake();
+    ctx
+      .state
+      .db
+      .store(file_path.clone(), meta.clone())
+      .await
+      .unwrap();
+    let new_expire = meta.expire_date_time + chrono::Duration::days(1);
+    let updated = ctx
+      .state
+      .db
+      .modify(&file_path, |meta| {
+        meta.expire_date_time = new_expire;
+        meta.count_downloads = 0;
+      })
+      .unwrap()
+      .unwrap();
+    assert_eq!(updated.count_downloads, 0);
+    let expires = ctx.state.db.expires.read().unwrap();
+    assert!(expires.contains(&(new_expire, file_path.clone())));
+    assert!(!expires.contains(&(meta.expire_date_time, file_path)));
+  }
+
   #[test_context(StateTestContext)]
   #[tokio::test]
   async fn test_store_file_and_check_it_existence(ctx: &mut StateTestContext) {
@@ -266,8 +854,11 @@ mod tests {
   #[tokio::test]
   async fn test_store_file_and_expire_it(ctx: &mut StateTestContext) {
     let file_path: FilePath = Faker.fake();
-    let fs_path = get_fs_path(&ctx.state.config.fs.base_dir, &file_path);
-    tokio::fs::create_dir(fs_path.parent().unwrap())
+    let fs_path = get_fs_path(
+      &ctx.state.config.load().fs.base_dir,
+      &staging_path(&file_path),
+    );
+    tokio::fs::create_dir_all(fs_path.parent().unwrap())
       .await
       .unwrap();
     tokio::fs::write(&fs_path, Faker.fake::<String>())
@@ -275,6 +866,8 @@ mod tests {
       .unwrap();
     let mut meta: MetaDataFile = Faker.fake();
     meta.expire_date_time = Utc::now();
+    meta.digest = String::new();
+    meta.state = UploadState::Pending;
     ctx
       .state
       .db
@@ -323,6 +916,95 @@ mod tests {
     assert!(result.is_none())
   }
 
+  #[test_context(StateTestContext)]
+  #[tokio::test]
+  async fn test_delete_files_of_manifest(ctx: &mut StateTestContext) {
+    let mut manifest: Manifest = Faker.fake();
+    let code: String = Faker.fake();
+    let file_paths = ["a.txt", "b.txt"].map(|file_name| FilePath {
+      code: code.clone(),
+      file_name: file_name.to_string(),
+    });
+    manifest.file_names = file_paths.iter().map(|p| p.file_name.clone()).collect();
+    ctx.state.db.store_manifest(&code, &manifest).unwrap();
+    assert!(ctx.state.db.store_manifest(&code, &manifest).is_err());
+    for file_path in &file_paths {
+      ctx
+        .state
+        .db
+        .store(file_path.clone(), Faker.fake())
+        .await
+        .unwrap();
+    }
+    assert!(ctx.state.db.code_exist(&code).unwrap());
+    ctx.state.db.delete(file_paths[0].clone()).await.unwrap();
+    let result = ctx.state.db.fetch_manifest(&code).unwrap().unwrap();
+    assert_eq!(result.file_names, vec![file_paths[1].file_name.clone()]);
+    ctx.state.db.delete(file_paths[1].clone()).await.unwrap();
+    assert!(ctx.state.db.fetch_manifest(&code).unwrap().is_none());
+    assert!(!ctx.state.db.code_exist(&code).unwrap());
+  }
+
+  #[test_context(StateTestContext)]
+  #[tokio::test]
+  async fn test_store_upload_and_delete_it(ctx: &mut StateTestContext) {
+    let id: String = Faker.fake();
+    let upload: Upload = Faker.fake();
+    ctx.state.db.store_upload(&id, &upload).unwrap();
+    let result = ctx.state.db.fetch_upload(&id).unwrap().unwrap();
+    assert_eq!(result.file_name, upload.file_name);
+    assert_eq!(result.offset, upload.offset);
+    assert_eq!(result.length, upload.length);
+    assert!(ctx
+      .state
+      .db
+      .fetch_uploads()
+      .unwrap()
+      .iter()
+      .any(|(key, _)| *key == id));
+    ctx.state.db.delete_upload(&id).unwrap().unwrap();
+    assert!(ctx.state.db.fetch_upload(&id).unwrap().is_none());
+  }
+
+  #[tokio::test]
+  async fn test_usage_is_counted_again_when_database_is_opened() {
+    let config = DatabaseConfig {
+      path_dir: std::path::Path::new("test-dump").join(cuid2::create_id()),
+    };
+    let alice = Some("alice".to_string());
+    let db = Database::new(&config).unwrap();
+    let file_path: FilePath = Faker.fake();
+    let mut meta: MetaDataFile = Faker.fake();
+    meta.owner = alice.clone();
+    meta.size = 10;
+    meta.digest = "abc".to_string();
+    db.store(file_path.clone(), meta).await.unwrap();
+    db.modify(&file_path, |meta| meta.size = 20).unwrap();
+    let mut upload: Upload = Faker.fake();
+    upload.owner = alice.clone();
+    upload.length = 5;
+    upload.file_path = None;
+    db.store_upload("running", &upload).unwrap();
+    upload.file_path = Some(file_path);
+    db.store_upload("complete", &upload).unwrap();
+    let expected = Usage {
+      bytes: 25,
+      files: 2,
+    };
+    assert_eq!(db.usage(alice.as_deref()).unwrap(), expected);
+    db.flush().await.unwrap();
+    drop(db);
+    let db = Database::new(&config).unwrap();
+    assert_eq!(db.usage(alice.as_deref()).unwrap(), expected);
+    assert_eq!(db.usage(None).unwrap(), expected);
+    assert_eq!(db.usage(Some("bob")).unwrap(), Usage::default());
+    assert_eq!(db.stored_bytes().unwrap(), 20);
+    db.delete_upload("running").unwrap().unwrap();
+    assert_eq!(db.usage(alice.as_deref()).unwrap(), Usage::file(20));
+    drop(db);
+    tokio::fs::remove_dir_all(&config.path_dir).await.unwrap();
+  }
+
   #[test_context(StateTestContext)]
   #[tokio::test]
   async fn test_fetch_file_that_does_not_exist(ctx: &mut StateTestContext) {
@@ -338,4 +1020,37 @@ mod tests {
     let result = ctx.state.db.fetch(&file_path).unwrap();
     assert!(result.is_none())
   }
+
+  #[test_context(StateTestContext)]
+  #[tokio::test]
+  async fn test_store_blob_again_after_failed_move(ctx: &mut StateTestContext) {
+    let storage = FlakyMoveStorage::default();
+    let staged = staging_path(&Faker.fake());
+    let digest = Faker.fake::<String>();
+    let content = b"Hello World!".to_vec();
+    storage
+      .put(&staged, Box::pin(std::io::Cursor::new(content.clone())))
+      .await
+      .unwrap();
+    let result = ctx.state.db.store_blob(&storage, &staged, &digest).await;
+    assert!(result.is_err());
+    assert_eq!(ctx.state.db.fetch_blob_refs(&digest).unwrap(), 0);
+    assert!(!storage.exists(&blob_path(&digest)).await.unwrap());
+    storage
+      .put(&staged, Box::pin(std::io::Cursor::new(content.clone())))
+      .await
+      .unwrap();
+    ctx
+      .state
+      .db
+      .store_blob(&storage, &staged, &digest)
+      .await
+      .unwrap();
+    assert_eq!(ctx.state.db.fetch_blob_refs(&digest).unwrap(), 1);
+    assert!(!storage.exists(&staged).await.unwrap());
+    assert_eq!(
+      storage.size(&blob_path(&digest)).await.unwrap(),
+      content.len() as u64
+    );
+  }
 }
@@ -0,0 +1,56 @@
+use crate::{
+  error::{result::ApiResult, ApiError},
+  util::secret::SecretHash,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sled::IVec;
+
+use super::file_path::FilePath;
+
+#[derive(Debug, Clone, Serialize, Deserialize, fake::Dummy)]
+pub struct Upload {
+  pub file_name: String,
+  pub length: u64,
+  pub offset: u64,
+  pub created_at: DateTime<Utc>,
+  pub expire_date_time: DateTime<Utc>,
+  pub expire_secs: i64,
+  pub code_length: usize,
+  pub secret: Option<SecretHash>,
+  pub manual_deletion: bool,
+  pub max_download: Option<u32>,
+  pub owner: Option<String>,
+  pub sha256: Option<String>,
+  pub file_path: Option<FilePath>,
+}
+
+impl Upload {
+  pub fn is_complete(&self) -> bool {
+    self.file_path.is_some()
+  }
+}
+
+impl TryFrom<&IVec> for Upload {
+  type Error = ApiError;
+
+  fn try_from(value: &IVec) -> ApiResult<Self> {
+    Ok(bincode::deserialize::<Self>(value)?)
+  }
+}
+
+impl TryFrom<IVec> for Upload {
+  type Error = ApiError;
+
+  fn try_from(value: IVec) -> ApiResult<Self> {
+    Self::try_from(&value)
+  }
+}
+
+impl TryFrom<&Upload> for IVec {
+  type Error = ApiError;
+
+  fn try_from(value: &Upload) -> ApiResult<IVec> {
+    Ok(IVec::from(bincode::serialize(value)?))
+  }
+}
@@ -0,0 +1,247 @@
+use std::{
+  collections::HashMap,
+  sync::{Arc, Mutex},
+};
+
+use crate::error::{result::ApiResult, ApiError};
+
+use super::{meta_data_file::MetaDataFile, upload::Upload};
+
+/// Bytes and files counted against the quotas.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Usage {
+  pub bytes: u64,
+  pub files: usize,
+}
+
+impl Usage {
+  pub fn bytes(bytes: u64) -> Self {
+    Self { bytes, files: 0 }
+  }
+
+  pub fn file(bytes: u64) -> Self {
+    Self { bytes, files: 1 }
+  }
+
+  fn add(&mut self, other: Usage) {
+    self.bytes = self.bytes.saturating_add(other.bytes);
+    self.files = self.files.saturating_add(other.files);
+  }
+
+  fn sub(&mut self, other: Usage) {
+    self.bytes = self.bytes.saturating_sub(other.bytes);
+    self.files = self.files.saturating_sub(other.files);
+  }
+}
+
+impl From<&MetaDataFile> for Usage {
+  fn from(meta: &MetaDataFile) -> Self {
+    Usage::file(meta.size)
+  }
+}
+
+impl From<&Upload> for Usage {
+  /// A running resumable upload holds its declared length until it is complete, then
+  /// the file it created is counted instead.
+  fn from(upload: &Upload) -> Self {
+    if upload.is_complete() {
+      Usage::default()
+    } else {
+      Usage::file(upload.length)
+    }
+  }
+}
+
+/// The usage of every owner and of everyone, kept in memory and counted again when the
+/// database is opened.
+#[derive(Debug, Default)]
+pub struct UsageIndex {
+  total: Usage,
+  owners: HashMap<String, Usage>,
+  /// The number of files and the size of every blob, deduplicated files share one.
+  blobs: HashMap<String, (u64, u64)>,
+  stored_bytes: u64,
+}
+
+impl UsageIndex {
+  pub fn get(&self, owner: Option<&str>) -> Usage {
+    match owner {
+      Some(owner) => self.owners.get(owner).copied().unwrap_or_default(),
+      None => self.total,
+    }
+  }
+
+  pub fn add(&mut self, owner: Option<&str>, usage: Usage) {
+    self.total.add(usage);
+    if let Some(owner) = owner {
+      self.owners.entry(owner.to_string()).or_default().add(usage);
+    }
+  }
+
+  pub fn sub(&mut self, owner: Option<&str>, usage: Usage) {
+    self.total.sub(usage);
+    if let Some(owner) = owner {
+      if let Some(owned) = self.owners.get_mut(owner) {
+        owned.sub(usage);
+        if *owned == Usage::default() {
+          self.owners.remove(owner);
+        }
+      }
+    }
+  }
+
+  /// Counts the file against its owner and its blob once for all files sharing it.
+  pub fn add_file(&mut self, meta: &MetaDataFile) {
+    self.add(meta.owner.as_deref(), Usage::from(meta));
+    if meta.digest.is_empty() {
+      return;
+    }
+    let (files, size) = self.blobs.entry(meta.digest.clone()).or_default();
+    if *files == 0 {
+      *size = meta.size;
+      self.stored_bytes = self.stored_bytes.saturating_add(meta.size);
+    }
+    *files += 1;
+  }
+
+  pub fn sub_file(&mut self, meta: &MetaDataFile) {
+    self.sub(meta.owner.as_deref(), Usage::from(meta));
+    if let Some((files, size)) = self.blobs.get_mut(&meta.digest) {
+      *files -= 1;
+      if *files == 0 {
+        self.stored_bytes = self.stored_bytes.saturating_sub(*size);
+        self.blobs.remove(&meta.digest);
+      }
+    }
+  }
+
+  /// Bytes of the blobs in the storage backend, whatever the number of files sharing them.
+  pub fn stored_bytes(&self) -> u64 {
+    self.stored_bytes
+  }
+}
+
+pub type UsageCounter = Arc<Mutex<UsageIndex>>;
+
+/// Usage held for an upload before its records count it, released when dropped.
+pub struct Reservation {
+  counter: UsageCounter,
+  owner: Option<String>,
+  reserved: Usage,
+}
+
+impl Reservation {
+  pub fn new(counter: UsageCounter, owner: Option<String>) -> Self {
+    Self {
+      counter,
+      owner,
+      reserved: Usage::default(),
+    }
+  }
+
+  /// Adds `usage` when `check` accepts the total usage and the usage of the owner
+  /// without it, both are checked and updated under one lock.
+  pub fn grow(
+    &mut self,
+    usage: Usage,
+    check: impl FnOnce(Usage, Option<Usage>) -> ApiResult,
+  ) -> ApiResult {
+    let mut index = self
+      .counter
+      .lock()
+      .map_err(|err| ApiError::LockError(err.to_string()))?;
+    let owned = self.owner.as_deref().map(|owner| index.get(Some(owner)));
+    check(index.get(None), owned)?;
+    index.add(self.owner.as_deref(), usage);
+    self.reserved.add(usage);
+    Ok(())
+  }
+
+  /// Gives back the part of the reservation that a stored record counts now.
+  pub fn release(&mut self, usage: Usage) {
+    let usage = Usage {
+      bytes: usage.bytes.min(self.reserved.bytes),
+      files: usage.files.min(self.reserved.files),
+    };
+    match self.counter.lock() {
+      Ok(mut index) => index.sub(self.owner.as_deref(), usage),
+      Err(err) => tracing::error!("Failed to acquire usage lock, Error: {err}"),
+    }
+    self.reserved.sub(usage);
+  }
+}
+
+impl Drop for Reservation {
+  fn drop(&mut self) {
+    self.release(self.reserved);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use fake::Fake;
+
+  #[test]
+  fn test_reservation_is_released_on_drop() {
+    let counter = UsageCounter::default();
+    counter.lock().unwrap().add(Some("alice"), Usage::file(10));
+    let mut reservation = Reservation::new(counter.clone(), Some("alice".to_string()));
+    reservation
+      .grow(Usage::file(5), |total, owned| {
+        assert_eq!(total, Usage::file(10));
+        assert_eq!(owned, Some(Usage::file(10)));
+        Ok(())
+      })
+      .unwrap();
+    assert_eq!(
+      counter.lock().unwrap().get(Some("alice")),
+      Usage {
+        bytes: 15,
+        files: 2
+      }
+    );
+    reservation
+      .grow(Usage::bytes(1), |_, _| {
+        Err(ApiError::QuotaExceededError(String::new()))
+      })
+      .unwrap_err();
+    reservation.release(Usage::bytes(5));
+    assert_eq!(
+      counter.lock().unwrap().get(None),
+      Usage {
+        bytes: 10,
+        files: 2
+      }
+    );
+    drop(reservation);
+    assert_eq!(counter.lock().unwrap().get(Some("alice")), Usage::file(10));
+    counter.lock().unwrap().sub(Some("alice"), Usage::file(10));
+    assert!(counter.lock().unwrap().owners.is_empty());
+  }
+
+  #[test]
+  fn test_shared_blob_is_stored_once() {
+    let mut index = UsageIndex::default();
+    let mut meta = MetaDataFile {
+      size: 10,
+      digest: "abc".to_string(),
+      owner: None,
+      ..fake::Faker.fake()
+    };
+    index.add_file(&meta);
+    index.add_file(&meta);
+    assert_eq!(index.get(None).bytes, 20);
+    assert_eq!(index.stored_bytes(), 10);
+    meta.digest = String::new();
+    index.add_file(&meta);
+    assert_eq!(index.stored_bytes(), 10);
+    index.sub_file(&meta);
+    meta.digest = "abc".to_string();
+    index.sub_file(&meta);
+    assert_eq!(index.stored_bytes(), 10);
+    index.sub_file(&meta);
+    assert_eq!(index.stored_bytes(), 0);
+    assert!(index.blobs.is_empty());
+  }
+}
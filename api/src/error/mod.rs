@@ -16,10 +16,26 @@ pub enum ApiError {
   NotFoundError(String),
   #[error("{0}")]
   PermissionDeniedError(String),
+  #[error("{0}")]
+  UnauthorizedError(String),
   #[error("resource not available: {0}")]
   NotAvailableError(String),
+  #[error("range not satisfiable: {0}")]
+  RangeNotSatisfiableError(String),
   #[error("resource {0} exists already")]
   ResourceExistsError(String),
+  #[error("conflict: {0}")]
+  ConflictError(String),
+  #[error("precondition failed: {0}")]
+  PreconditionFailedError(String),
+  #[error("unsupported media type: {0}")]
+  UnsupportedMediaTypeError(String),
+  #[error("quota exceeded: {0}")]
+  QuotaExceededError(String),
+  #[error("checksum mismatch: {0}")]
+  ChecksumMismatchError(String),
+  #[error("too many requests: {0}")]
+  TooManyRequestsError(String, std::time::Duration),
   #[error(transparent)]
   ConfigError(#[from] config::ConfigError),
   #[error(transparent)]
@@ -50,6 +66,8 @@ pub enum ApiError {
   UrlError(#[from] url::ParseError),
   #[error(transparent)]
   TemplateError(#[from] askama::Error),
+  #[error("storage error: {0}")]
+  StorageError(String),
   #[error("lock error: {0}")]
   LockError(String),
   #[error("duration out of range error: {0}")]
@@ -69,9 +87,41 @@ impl ApiError {
       ),
       BadRequestError(err) => ("BAD_REQUEST", err.to_string(), StatusCode::BAD_REQUEST),
       PermissionDeniedError(err) => ("PERMISSION_DENIED", err.to_string(), StatusCode::FORBIDDEN),
+      UnauthorizedError(err) => ("UNAUTHORIZED", err.to_string(), StatusCode::UNAUTHORIZED),
       NotAvailableError(err) => ("NOT_AVAILABLE", err.to_string(), StatusCode::NOT_FOUND),
       NotFoundError(err) => ("NOT_FOUND", err.to_string(), StatusCode::NOT_FOUND),
+      RangeNotSatisfiableError(err) => (
+        "RANGE_NOT_SATISFIABLE",
+        err.to_string(),
+        StatusCode::RANGE_NOT_SATISFIABLE,
+      ),
       ResourceExistsError(err) => ("RESOURCE_EXISTS", err.to_string(), StatusCode::CONFLICT),
+      ConflictError(err) => ("CONFLICT", err.to_string(), StatusCode::CONFLICT),
+      PreconditionFailedError(err) => (
+        "PRECONDITION_FAILED",
+        err.to_string(),
+        StatusCode::PRECONDITION_FAILED,
+      ),
+      UnsupportedMediaTypeError(err) => (
+        "UNSUPPORTED_MEDIA_TYPE",
+        err.to_string(),
+        StatusCode::UNSUPPORTED_MEDIA_TYPE,
+      ),
+      QuotaExceededError(err) => (
+        "QUOTA_EXCEEDED",
+        err.to_string(),
+        StatusCode::PAYLOAD_TOO_LARGE,
+      ),
+      ChecksumMismatchError(err) => (
+        "CHECKSUM_MISMATCH",
+        err.to_string(),
+        StatusCode::BAD_REQUEST,
+      ),
+      TooManyRequestsError(err, _) => (
+        "TOO_MANY_REQUESTS",
+        err.to_string(),
+        StatusCode::TOO_MANY_REQUESTS,
+      ),
       ConfigError(err) => (
         "CONFIG_ERROR",
         err.to_string(),
@@ -157,6 +207,11 @@ impl ApiError {
         err.to_string(),
         StatusCode::INTERNAL_SERVER_ERROR,
       ),
+      StorageError(err) => (
+        "STORAGE_ERROR",
+        err.to_string(),
+        StatusCode::INTERNAL_SERVER_ERROR,
+      ),
       LockError(err) => (
         "LOCK_ERROR",
         err.to_string(),
@@ -180,3 +235,12 @@ pub fn invalid_input_error(field: &'static str, message: &'static str) -> ApiErr
   report.append(garde::Path::new(field), garde::Error::new(message));
   ApiError::InvalidInputError(report)
 }
+
+impl From<sled::transaction::TransactionError> for ApiError {
+  fn from(err: sled::transaction::TransactionError) -> Self {
+    match err {
+      sled::transaction::TransactionError::Abort(err)
+      | sled::transaction::TransactionError::Storage(err) => ApiError::DatabaseError(err),
+    }
+  }
+}
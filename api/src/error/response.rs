@@ -1,13 +1,20 @@
 use axum::{
+  http::header::RETRY_AFTER,
   response::{IntoResponse, Response},
   Json,
 };
 
 use super::ApiError;
+use crate::metrics::METRICS;
 
 impl IntoResponse for ApiError {
   fn into_response(self) -> Response {
     let (body, status_code) = self.response();
+    METRICS.reject(&body.error_type);
+    if let ApiError::TooManyRequestsError(_, retry_after) = self {
+      let secs = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+      return (status_code, [(RETRY_AFTER, secs.max(1))], Json(body)).into_response();
+    }
     (status_code, Json(body)).into_response()
   }
 }
@@ -0,0 +1,51 @@
+use axum::{
+  extract::{Path, State},
+  http::HeaderMap,
+  Json,
+};
+use garde::Validate;
+use pf_sdk::dto::{
+  request::{CreateAccountRequest, CreateApiKeyRequest},
+  response::{AccountResponse, ApiKeyResponse, MessageResponse},
+};
+
+use crate::{error::result::ApiResult, server::ApiState, service};
+
+pub async fn register(
+  State(state): State<ApiState>,
+  Json(req): Json<CreateAccountRequest>,
+) -> ApiResult<Json<AccountResponse>> {
+  req.validate(&())?;
+  let account = service::account::register(&state, &req).await?;
+  Ok(Json(AccountResponse::from(&account)))
+}
+
+pub async fn create_api_key(
+  State(state): State<ApiState>,
+  headers: HeaderMap,
+  Json(req): Json<CreateApiKeyRequest>,
+) -> ApiResult<Json<ApiKeyResponse>> {
+  req.validate(&())?;
+  let account = service::account::login(&state, &headers)?;
+  let api_key = service::account::create_api_key(&state, &account, &req).await?;
+  Ok(Json(api_key))
+}
+
+pub async fn list_api_keys(
+  State(state): State<ApiState>,
+  headers: HeaderMap,
+) -> ApiResult<Json<Vec<ApiKeyResponse>>> {
+  let account = service::account::login(&state, &headers)?;
+  let api_keys = service::account::list_api_keys(&state, &account)?;
+  Ok(Json(api_keys.iter().map(ApiKeyResponse::from).collect()))
+}
+
+pub async fn revoke_api_key(
+  State(state): State<ApiState>,
+  Path(id): Path<String>,
+  headers: HeaderMap,
+) -> ApiResult<Json<MessageResponse>> {
+  let account = service::account::login(&state, &headers)?;
+  service::account::revoke_api_key(&state, &account, &id).await?;
+  Ok(Json(MessageResponse::ok()))
+}
@@ -0,0 +1,117 @@
+use std::net::SocketAddr;
+
+use axum::{
+  extract::{ConnectInfo, Path, Query, State},
+  http::{HeaderMap, StatusCode},
+  Json,
+};
+use garde::Validate;
+use pf_sdk::dto::{
+  request::{AdminListFilesQueryParam, AuditQueryParam, UpdateFileRequest},
+  response::{
+    AuditAction, AuditLogResponse, FileListResponse, MessageResponse, MetaDataFileResponse,
+  },
+};
+
+use crate::{
+  constant::DEFAULT_PAGE_SIZE,
+  database::file_path::FilePath,
+  error::result::ApiResult,
+  handler::file::{file_list_item, peer},
+  server::ApiState,
+  service::{self, audit},
+};
+
+pub async fn list_files(
+  State(state): State<ApiState>,
+  Query(param): Query<AdminListFilesQueryParam>,
+  headers: HeaderMap,
+) -> ApiResult<Json<FileListResponse>> {
+  param.validate(&())?;
+  service::admin::authorize(&state, &headers)?;
+  let (files, total) = service::admin::list(&state, &param)?;
+  let domain_name = state.config.load().server.get_domain_name();
+  let files = files
+    .into_iter()
+    .map(|file| file_list_item(&domain_name, file))
+    .collect::<ApiResult<Vec<_>>>()?;
+  Ok(Json(FileListResponse {
+    page: param.page.unwrap_or(1),
+    page_size: param.page_size.unwrap_or(DEFAULT_PAGE_SIZE),
+    total,
+    files,
+  }))
+}
+
+pub async fn delete_file(
+  State(state): State<ApiState>,
+  connect_info: Option<ConnectInfo<SocketAddr>>,
+  Path((code, file_name)): Path<(String, String)>,
+  headers: HeaderMap,
+) -> ApiResult<Json<MessageResponse>> {
+  let actor = audit::actor(&state, &headers, peer(connect_info));
+  let file_path = FilePath { code, file_name };
+  let result = async {
+    service::admin::authorize(&state, &headers)?;
+    service::admin::delete(&state, file_path.clone()).await
+  }
+  .await;
+  audit::record(
+    &state,
+    &actor,
+    AuditAction::Delete,
+    Some(&file_path.code),
+    Some(&file_path.file_name),
+    result.as_ref().map(|_| StatusCode::OK),
+  )
+  .await;
+  result?;
+  Ok(Json(MessageResponse::ok()))
+}
+
+pub async fn update_file(
+  State(state): State<ApiState>,
+  connect_info: Option<ConnectInfo<SocketAddr>>,
+  Path((code, file_name)): Path<(String, String)>,
+  headers: HeaderMap,
+  Json(req): Json<UpdateFileRequest>,
+) -> ApiResult<Json<MetaDataFileResponse>> {
+  let actor = audit::actor(&state, &headers, peer(connect_info));
+  let file_path = FilePath { code, file_name };
+  let result = async {
+    req.validate(&())?;
+    service::admin::authorize(&state, &headers)?;
+    service::admin::update(&state, &file_path, &req).await
+  }
+  .await;
+  audit::record(
+    &state,
+    &actor,
+    AuditAction::Update,
+    Some(&file_path.code),
+    Some(&file_path.file_name),
+    result.as_ref().map(|_| StatusCode::OK),
+  )
+  .await;
+  Ok(Json(MetaDataFileResponse::from(&result?)))
+}
+
+pub async fn audit_log(
+  State(state): State<ApiState>,
+  Query(param): Query<AuditQueryParam>,
+  headers: HeaderMap,
+) -> ApiResult<Json<AuditLogResponse>> {
+  param.validate(&())?;
+  service::admin::authorize(&state, &headers)?;
+  let events = service::audit::query(&state, &param).await?;
+  Ok(Json(AuditLogResponse { events }))
+}
+
+pub async fn reload_config(
+  State(state): State<ApiState>,
+  headers: HeaderMap,
+) -> ApiResult<Json<MessageResponse>> {
+  service::admin::authorize(&state, &headers)?;
+  service::reload::reload(&state)?;
+  Ok(Json(MessageResponse::ok()))
+}
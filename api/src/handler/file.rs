@@ -1,40 +1,86 @@
 use anyhow::anyhow;
 use axum::{
   body::Body,
-  extract::{Multipart, Path, Query, State},
-  http::{header::HeaderMap, Request},
-  response::Response,
+  extract::{ConnectInfo, Multipart, Path, Query, State},
+  http::{
+    header::{
+      HeaderMap, ACCEPT_RANGES, CONTENT_DISPOSITION, CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE,
+      ETAG,
+    },
+    StatusCode,
+  },
+  response::{IntoResponse, Response},
   Json,
 };
+use chrono::{DateTime, Utc};
+use futures_util::TryStreamExt;
 use garde::Validate;
 use pf_sdk::{
   dto::{
-    request::UploadQueryParam,
-    response::{MessageResponse, MetaDataFileResponse, UploadResponse},
+    request::{ApiKeyScope, ListFilesQueryParam, UploadQueryParam},
+    response::{
+      AuditAction, BundleFileResponse, BundleResponse, FileListResponse, FileResponse,
+      MessageResponse, MetaDataFileResponse, UploadResponse,
+    },
+  },
+  util::{
+    digest::{legacy_digest, repr_digest, DIGEST_HEADER, REPR_DIGEST_HEADER},
+    url::{create_bundle_url, create_url},
   },
-  util::url::create_url,
 };
-use tower::ServiceExt;
-use tower_http::services::fs::ServeFileSystemResponseBody;
+use std::net::{IpAddr, SocketAddr};
+use tokio_util::io::ReaderStream;
 
-use crate::{error::result::ApiResult, server::ApiState, service, util::qr_code::generate_qr_code};
+use crate::{
+  constant::DEFAULT_PAGE_SIZE,
+  database::file_path::FilePath,
+  error::result::ApiResult,
+  metrics::METRICS,
+  server::ApiState,
+  service::{
+    self, audit,
+    file::{ArchiveContent, FileContent, OwnedFile},
+  },
+  util::{archive::ArchiveFormat, http::if_none_match, qr_code::generate_qr_code},
+};
 
 pub async fn upload(
   State(state): State<ApiState>,
+  connect_info: Option<ConnectInfo<SocketAddr>>,
   Query(param): Query<UploadQueryParam>,
   headers: HeaderMap,
   multipart: Multipart,
 ) -> ApiResult<Json<UploadResponse>> {
-  param.validate(&())?;
-  let secret = crate::util::http::parse_basic_auth(&headers)?;
-  let (file_path, expire_date_time) =
-    service::file::store(&state, &param, secret, multipart).await?;
-  let url = create_url(
-    &state.config.server.get_domain_name(),
-    &file_path.code,
-    &file_path.file_name,
-  )?
-  .to_string();
+  let actor = audit::actor(&state, &headers, peer(connect_info));
+  let result = store(&state, &param, &headers, multipart).await;
+  match &result {
+    Ok((file_paths, _)) => {
+      for file_path in file_paths {
+        audit::record(
+          &state,
+          &actor,
+          AuditAction::Upload,
+          Some(&file_path.code),
+          Some(&file_path.file_name),
+          Ok(StatusCode::OK),
+        )
+        .await;
+      }
+    }
+    Err(err) => audit::record(&state, &actor, AuditAction::Upload, None, None, Err(err)).await,
+  }
+  let (file_paths, expire_date_time) = result?;
+  let domain_name = state.config.load().server.get_domain_name();
+  let urls = file_paths
+    .iter()
+    .map(|file_path| {
+      create_url(&domain_name, &file_path.code, &file_path.file_name).map(|url| url.to_string())
+    })
+    .collect::<Result<Vec<_>, _>>()?;
+  let url = match urls.as_slice() {
+    [url] => url.clone(),
+    _ => create_bundle_url(&domain_name, &file_paths[0].code)?.to_string(),
+  };
   let qr_code = if let Some(qr_code_format) = param.qr_code_format {
     Some(generate_qr_code(qr_code_format, &url)?)
   } else {
@@ -42,42 +88,262 @@ pub async fn upload(
   };
   Ok(Json(UploadResponse {
     url,
+    urls,
     expire_date_time,
     qr_code,
   }))
 }
 
+async fn store(
+  state: &ApiState,
+  param: &UploadQueryParam,
+  headers: &HeaderMap,
+  multipart: Multipart,
+) -> ApiResult<(Vec<FilePath>, DateTime<Utc>)> {
+  param.validate(&())?;
+  let owner = service::account::authorize_upload(state, headers)?;
+  let secret = crate::util::http::parse_basic_auth(headers)?;
+  service::file::store(state, param, secret, owner, multipart).await
+}
+
+pub async fn list(
+  State(state): State<ApiState>,
+  connect_info: Option<ConnectInfo<SocketAddr>>,
+  Path(code): Path<String>,
+  headers: HeaderMap,
+) -> ApiResult<Response> {
+  let actor = audit::actor(&state, &headers, peer(connect_info));
+  let (action, bundle_code) = match ArchiveFormat::parse(&code) {
+    Some((bundle_code, _)) => (AuditAction::Download, bundle_code.to_string()),
+    None => (AuditAction::List, code.clone()),
+  };
+  let result = list_or_archive(&state, code, &headers).await;
+  audit::record(
+    &state,
+    &actor,
+    action,
+    Some(&bundle_code),
+    None,
+    result.as_ref().map(Response::status),
+  )
+  .await;
+  result
+}
+
+async fn list_or_archive(
+  state: &ApiState,
+  code: String,
+  headers: &HeaderMap,
+) -> ApiResult<Response> {
+  service::account::authorize(state, headers, ApiKeyScope::Download)?;
+  let secret = crate::util::http::parse_basic_auth(headers)?;
+  if let Some((code, format)) = ArchiveFormat::parse(&code) {
+    let archive = service::file::fetch_archive(state, code, secret, format).await?;
+    return archive_response(archive);
+  }
+  let manifest = service::file::list(state, &code, secret)?;
+  let domain_name = state.config.load().server.get_domain_name();
+  let files = manifest
+    .file_names
+    .into_iter()
+    .map(|file_name| {
+      let url = create_url(&domain_name, &code, &file_name)?.to_string();
+      Ok(BundleFileResponse { file_name, url })
+    })
+    .collect::<ApiResult<Vec<_>>>()?;
+  Ok(
+    Json(BundleResponse {
+      code,
+      created_at: manifest.created_at,
+      expire_date_time: manifest.expire_date_time,
+      files,
+    })
+    .into_response(),
+  )
+}
+
+fn archive_response(archive: ArchiveContent) -> ApiResult<Response> {
+  Ok(
+    Response::builder()
+      .status(StatusCode::OK)
+      .header(CONTENT_TYPE, archive.format.content_type())
+      .header(
+        CONTENT_DISPOSITION,
+        format!(
+          "attachment; filename=\"{}.{}\"",
+          archive.code,
+          archive.format.extension()
+        ),
+      )
+      .body(Body::from_stream(archive.stream.inspect_ok(|chunk| {
+        METRICS.download_bytes.inc_by(chunk.len() as u64);
+      })))
+      .map_err(|e| anyhow!("Download archive failed, Error: {e}"))?,
+  )
+}
+
 pub async fn download(
   State(state): State<ApiState>,
+  connect_info: Option<ConnectInfo<SocketAddr>>,
   Path((code, file_name)): Path<(String, String)>,
-  req: Request<Body>,
-) -> ApiResult<Response<ServeFileSystemResponseBody>> {
-  let secret = crate::util::http::parse_basic_auth(req.headers())?;
-  let file = service::file::fetch(&state, &code, &file_name, secret).await?;
+  headers: HeaderMap,
+) -> ApiResult<Response> {
+  let actor = audit::actor(&state, &headers, peer(connect_info));
+  let result = fetch(&state, &code, &file_name, &headers).await;
+  audit::record(
+    &state,
+    &actor,
+    AuditAction::Download,
+    Some(&code),
+    Some(&file_name),
+    result.as_ref().map(Response::status),
+  )
+  .await;
+  result
+}
+
+async fn fetch(
+  state: &ApiState,
+  code: &str,
+  file_name: &str,
+  headers: &HeaderMap,
+) -> ApiResult<Response> {
+  service::account::authorize(state, headers, ApiKeyScope::Download)?;
+  let secret = crate::util::http::parse_basic_auth(headers)?;
+  let file = service::file::fetch(state, code, file_name, secret, headers).await?;
+  if if_none_match(headers, &file.etag) {
+    return Ok(
+      Response::builder()
+        .status(StatusCode::NOT_MODIFIED)
+        .header(ETAG, format!("\"{}\"", file.etag))
+        .body(Body::empty())
+        .map_err(|e| anyhow!("Download file failed, Error: {e}"))?,
+    );
+  }
+  file_response(file)
+}
+
+fn file_response(file: FileContent) -> ApiResult<Response> {
+  let content_type = mime_guess::from_path(&file.file_path.file_name).first_or_octet_stream();
+  let mut builder = Response::builder()
+    .header(CONTENT_TYPE, content_type.essence_str())
+    .header(ACCEPT_RANGES, "bytes")
+    .header(ETAG, format!("\"{}\"", file.etag));
+  if !file.digest.is_empty() {
+    let sha256 = hex::decode(&file.digest)
+      .map_err(|e| anyhow!("Invalid digest {}, Error: {e}", file.digest))?;
+    builder = builder
+      .header(REPR_DIGEST_HEADER, repr_digest(&sha256))
+      .header(DIGEST_HEADER, legacy_digest(&sha256));
+  }
+  builder = match file.range {
+    Some(range) => builder
+      .status(StatusCode::PARTIAL_CONTENT)
+      .header(CONTENT_LENGTH, range.len())
+      .header(
+        CONTENT_RANGE,
+        format!("bytes {}-{}/{}", range.start, range.end, file.total_size),
+      ),
+    None => builder
+      .status(StatusCode::OK)
+      .header(CONTENT_LENGTH, file.total_size),
+  };
   Ok(
-    file
-      .oneshot(req)
-      .await
+    builder
+      .body(Body::from_stream(
+        ReaderStream::new(file.reader).inspect_ok(|chunk| {
+          METRICS.download_bytes.inc_by(chunk.len() as u64);
+        }),
+      ))
       .map_err(|e| anyhow!("Download file failed, Error: {e}"))?,
   )
 }
 
 pub async fn info(
   State(state): State<ApiState>,
+  connect_info: Option<ConnectInfo<SocketAddr>>,
   Path((code, file_name)): Path<(String, String)>,
   headers: HeaderMap,
 ) -> ApiResult<Json<MetaDataFileResponse>> {
-  let secret = crate::util::http::parse_basic_auth(&headers)?;
-  let meta = service::file::info(&state, &code, &file_name, secret).await?;
-  Ok(Json(MetaDataFileResponse::from(&meta)))
+  let actor = audit::actor(&state, &headers, peer(connect_info));
+  let result = async {
+    service::account::authorize(&state, &headers, ApiKeyScope::Download)?;
+    let secret = crate::util::http::parse_basic_auth(&headers)?;
+    service::file::info(&state, &code, &file_name, secret).await
+  }
+  .await;
+  audit::record(
+    &state,
+    &actor,
+    AuditAction::Info,
+    Some(&code),
+    Some(&file_name),
+    result.as_ref().map(|_| StatusCode::OK),
+  )
+  .await;
+  Ok(Json(MetaDataFileResponse::from(&result?)))
+}
+
+pub async fn list_files(
+  State(state): State<ApiState>,
+  Query(param): Query<ListFilesQueryParam>,
+  headers: HeaderMap,
+) -> ApiResult<Json<FileListResponse>> {
+  param.validate(&())?;
+  let principal = service::account::require(&state, &headers, ApiKeyScope::Download)?;
+  let page = param.page.unwrap_or(1);
+  let page_size = param.page_size.unwrap_or(DEFAULT_PAGE_SIZE);
+  let (owned_files, total) =
+    service::file::list_owned(&state, &principal.username, page, page_size)?;
+  let domain_name = state.config.load().server.get_domain_name();
+  let files = owned_files
+    .into_iter()
+    .map(|file| file_list_item(&domain_name, file))
+    .collect::<ApiResult<Vec<_>>>()?;
+  Ok(Json(FileListResponse {
+    page,
+    page_size,
+    total,
+    files,
+  }))
+}
+
+pub fn file_list_item(domain_name: &str, file: OwnedFile) -> ApiResult<FileResponse> {
+  let url = create_url(domain_name, &file.file_path.code, &file.file_path.file_name)?;
+  Ok(FileResponse {
+    url: url.to_string(),
+    meta: MetaDataFileResponse::from(&file.meta),
+    code: file.file_path.code,
+    file_name: file.file_path.file_name,
+  })
 }
 
 pub async fn delete(
   State(state): State<ApiState>,
+  connect_info: Option<ConnectInfo<SocketAddr>>,
   Path((code, file_name)): Path<(String, String)>,
   headers: HeaderMap,
 ) -> ApiResult<Json<MessageResponse>> {
-  let secret = crate::util::http::parse_basic_auth(&headers)?;
-  service::file::delete(&state, &code, &file_name, secret).await?;
+  let actor = audit::actor(&state, &headers, peer(connect_info));
+  let result = async {
+    let principal = service::account::authorize(&state, &headers, ApiKeyScope::Delete)?;
+    let secret = crate::util::http::parse_basic_auth(&headers)?;
+    service::file::delete(&state, &code, &file_name, secret, principal.as_ref()).await
+  }
+  .await;
+  audit::record(
+    &state,
+    &actor,
+    AuditAction::Delete,
+    Some(&code),
+    Some(&file_name),
+    result.as_ref().map(|_| StatusCode::OK),
+  )
+  .await;
+  result?;
   Ok(Json(MessageResponse::ok()))
 }
+
+pub fn peer(connect_info: Option<ConnectInfo<SocketAddr>>) -> Option<IpAddr> {
+  connect_info.map(|ConnectInfo(addr)| addr.ip())
+}
@@ -1,9 +1,38 @@
-use axum::Json;
+use anyhow::anyhow;
+use axum::{
+  body::Body,
+  extract::State,
+  http::{header::CONTENT_TYPE, StatusCode},
+  response::Response,
+  Json,
+};
 use pf_sdk::dto::response::MessageResponse;
 
+use crate::{error::result::ApiResult, metrics::METRICS, server::ApiState, service};
+
+pub mod account;
+pub mod admin;
 pub mod file;
 pub mod index;
+pub mod quota;
+pub mod rate_limit;
+pub mod tus;
 
 pub async fn health_check() -> Json<MessageResponse> {
   Json(MessageResponse::ok())
 }
+
+pub async fn metrics(State(state): State<ApiState>) -> ApiResult<Response> {
+  let usage = service::quota::usage(&state, None)?;
+  METRICS.files.set(usage.files as i64);
+  METRICS
+    .storage_bytes
+    .set(service::quota::stored_bytes(&state)? as i64);
+  Ok(
+    Response::builder()
+      .status(StatusCode::OK)
+      .header(CONTENT_TYPE, crate::metrics::CONTENT_TYPE)
+      .body(Body::from(METRICS.encode()?))
+      .map_err(|e| anyhow!("Build response failed, Error: {e}"))?,
+  )
+}
@@ -0,0 +1,13 @@
+use axum::{extract::State, http::HeaderMap, Json};
+use pf_sdk::dto::{request::ApiKeyScope, response::QuotaResponse};
+
+use crate::{error::result::ApiResult, server::ApiState, service};
+
+pub async fn quota(
+  State(state): State<ApiState>,
+  headers: HeaderMap,
+) -> ApiResult<Json<QuotaResponse>> {
+  let owner = service::account::authorize(&state, &headers, ApiKeyScope::Upload)?
+    .map(|principal| principal.username);
+  Ok(Json(service::quota::quota(&state, owner)?))
+}
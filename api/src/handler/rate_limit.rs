@@ -0,0 +1,51 @@
+use std::{net::SocketAddr, time::Instant};
+
+use axum::{
+  extract::{ConnectInfo, Request, State},
+  http::StatusCode,
+  middleware::Next,
+  response::Response,
+};
+
+use crate::{
+  error::result::ApiResult,
+  server::ApiState,
+  service::rate_limit::{classify, client, is_lookup, LimitKind},
+};
+
+pub async fn limit(State(state): State<ApiState>, req: Request, next: Next) -> ApiResult<Response> {
+  let config = &state.config.load().rate_limit;
+  if !config.enable {
+    return Ok(next.run(req).await);
+  }
+  let peer = req
+    .extensions()
+    .get::<ConnectInfo<SocketAddr>>()
+    .map(|info| info.0.ip());
+  let Some(client) = client(&state, req.headers(), peer) else {
+    return Ok(next.run(req).await);
+  };
+  let limiter = &state.rate_limiter;
+  let now = Instant::now();
+  let is_lookup = is_lookup(req.uri().path());
+  if is_lookup {
+    limiter.check(LimitKind::NotFound, &client, config.not_found, now)?;
+  }
+  match classify(req.method(), req.uri().path()) {
+    Some(LimitKind::Upload) => limiter.acquire(LimitKind::Upload, &client, config.upload, now)?,
+    Some(LimitKind::Download) => {
+      limiter.acquire(LimitKind::Download, &client, config.download, now)?
+    }
+    _ => {}
+  }
+  let resp = next.run(req).await;
+  if is_lookup && resp.status() == StatusCode::NOT_FOUND {
+    limiter.consume(
+      LimitKind::NotFound,
+      &client,
+      config.not_found,
+      Instant::now(),
+    )?;
+  }
+  Ok(resp)
+}
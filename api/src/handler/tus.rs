@@ -0,0 +1,233 @@
+use std::net::SocketAddr;
+
+use anyhow::anyhow;
+use axum::{
+  body::Body,
+  extract::{ConnectInfo, Path, Query, State},
+  http::{
+    header::{HeaderMap, CACHE_CONTROL, CONTENT_TYPE, LOCATION},
+    HeaderValue, StatusCode,
+  },
+  response::Response,
+};
+use garde::Validate;
+use pf_sdk::{
+  dto::{
+    request::UploadQueryParam,
+    response::AuditAction,
+    tus::{
+      decode_metadata, OFFSET_OCTET_STREAM, TUS_EXTENSION, TUS_EXTENSION_HEADER, TUS_MAX_SIZE,
+      TUS_RESUMABLE, TUS_VERSION, TUS_VERSION_HEADER, UPLOAD_FILE_EXPIRES, UPLOAD_FILE_URL,
+      UPLOAD_LENGTH, UPLOAD_METADATA, UPLOAD_OFFSET,
+    },
+  },
+  util::url::create_url,
+};
+
+use crate::{
+  database::upload::Upload,
+  error::{result::ApiResult, ApiError},
+  handler::file::peer,
+  server::ApiState,
+  service::{self, audit},
+};
+
+pub async fn options(State(state): State<ApiState>) -> ApiResult<Response> {
+  Ok(
+    Response::builder()
+      .status(StatusCode::NO_CONTENT)
+      .header(TUS_VERSION_HEADER, TUS_VERSION)
+      .header(TUS_EXTENSION_HEADER, TUS_EXTENSION)
+      .header(TUS_MAX_SIZE, state.config.load().max_upload_bytes_size)
+      .body(Body::empty())
+      .map_err(|e| anyhow!("Build response failed, Error: {e}"))?,
+  )
+}
+
+pub async fn create(
+  State(state): State<ApiState>,
+  connect_info: Option<ConnectInfo<SocketAddr>>,
+  Query(param): Query<UploadQueryParam>,
+  headers: HeaderMap,
+) -> ApiResult<Response> {
+  let actor = audit::actor(&state, &headers, peer(connect_info));
+  let result = create_upload(&state, &param, &headers).await;
+  record_upload(
+    &state,
+    &actor,
+    result.as_ref().map(|(_, upload)| upload),
+    StatusCode::CREATED,
+  )
+  .await;
+  let (id, upload) = result?;
+  let location = format!("{}/tus/{id}", state.config.load().server.get_domain_name());
+  let mut builder = Response::builder()
+    .status(StatusCode::CREATED)
+    .header(LOCATION, location);
+  if upload.is_complete() {
+    builder = upload_headers(&state, builder, &upload)?;
+  }
+  Ok(
+    builder
+      .body(Body::empty())
+      .map_err(|e| anyhow!("Build response failed, Error: {e}"))?,
+  )
+}
+
+async fn create_upload(
+  state: &ApiState,
+  param: &UploadQueryParam,
+  headers: &HeaderMap,
+) -> ApiResult<(String, Upload)> {
+  check_tus_resumable(headers)?;
+  param.validate(&())?;
+  let owner = service::account::authorize_upload(state, headers)?;
+  let secret = crate::util::http::parse_basic_auth(headers)?;
+  let length = parse_header::<u64>(headers, UPLOAD_LENGTH)?;
+  let metadata = headers
+    .get(UPLOAD_METADATA)
+    .map(|value| {
+      decode_metadata(value.to_str().unwrap_or_default())
+        .map_err(|e| ApiError::BadRequestError(format!("Invalid Upload-Metadata: {e}")))
+    })
+    .transpose()?
+    .unwrap_or_default();
+  let file_name = metadata.get("filename").cloned().ok_or_else(|| {
+    ApiError::BadRequestError("The filename is missing in Upload-Metadata.".to_string())
+  })?;
+  service::tus::create(state, param, secret, owner, file_name, length).await
+}
+
+pub async fn head(
+  State(state): State<ApiState>,
+  Path(id): Path<String>,
+  headers: HeaderMap,
+) -> ApiResult<Response> {
+  check_tus_resumable(&headers)?;
+  let secret = crate::util::http::parse_basic_auth(&headers)?;
+  let upload = service::tus::fetch(&state, &id, secret)?;
+  let builder = Response::builder()
+    .status(StatusCode::OK)
+    .header(CACHE_CONTROL, "no-store");
+  Ok(
+    upload_headers(&state, builder, &upload)?
+      .body(Body::empty())
+      .map_err(|e| anyhow!("Build response failed, Error: {e}"))?,
+  )
+}
+
+pub async fn patch(
+  State(state): State<ApiState>,
+  connect_info: Option<ConnectInfo<SocketAddr>>,
+  Path(id): Path<String>,
+  headers: HeaderMap,
+  body: Body,
+) -> ApiResult<Response> {
+  let actor = audit::actor(&state, &headers, peer(connect_info));
+  let result = append_upload(&state, &id, &headers, body).await;
+  record_upload(&state, &actor, result.as_ref(), StatusCode::NO_CONTENT).await;
+  let upload = result?;
+  let builder = Response::builder().status(StatusCode::NO_CONTENT);
+  Ok(
+    upload_headers(&state, builder, &upload)?
+      .body(Body::empty())
+      .map_err(|e| anyhow!("Build response failed, Error: {e}"))?,
+  )
+}
+
+async fn append_upload(
+  state: &ApiState,
+  id: &str,
+  headers: &HeaderMap,
+  body: Body,
+) -> ApiResult<Upload> {
+  check_tus_resumable(headers)?;
+  if headers.get(CONTENT_TYPE).and_then(|v| v.to_str().ok()) != Some(OFFSET_OCTET_STREAM) {
+    return Err(ApiError::UnsupportedMediaTypeError(format!(
+      "The Content-Type should be {OFFSET_OCTET_STREAM}."
+    )));
+  }
+  let secret = crate::util::http::parse_basic_auth(headers)?;
+  let offset = parse_header::<u64>(headers, UPLOAD_OFFSET)?;
+  service::tus::append(state, id, secret, offset, body).await
+}
+
+/// A resumable upload is recorded once its file is stored, or when a request fails.
+async fn record_upload(
+  state: &ApiState,
+  actor: &audit::Actor,
+  result: Result<&Upload, &ApiError>,
+  status: StatusCode,
+) {
+  match result.map(|upload| upload.file_path.as_ref()) {
+    Ok(Some(file_path)) => {
+      audit::record(
+        state,
+        actor,
+        AuditAction::Upload,
+        Some(&file_path.code),
+        Some(&file_path.file_name),
+        Ok(status),
+      )
+      .await
+    }
+    Ok(None) => {}
+    Err(err) => audit::record(state, actor, AuditAction::Upload, None, None, Err(err)).await,
+  }
+}
+
+pub async fn terminate(
+  State(state): State<ApiState>,
+  Path(id): Path<String>,
+  headers: HeaderMap,
+) -> ApiResult<StatusCode> {
+  check_tus_resumable(&headers)?;
+  let secret = crate::util::http::parse_basic_auth(&headers)?;
+  service::tus::terminate(&state, &id, secret).await?;
+  Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn set_tus_resumable(mut response: Response) -> Response {
+  response
+    .headers_mut()
+    .insert(TUS_RESUMABLE, HeaderValue::from_static(TUS_VERSION));
+  response
+}
+
+fn upload_headers(
+  state: &ApiState,
+  mut builder: axum::http::response::Builder,
+  upload: &Upload,
+) -> ApiResult<axum::http::response::Builder> {
+  builder = builder
+    .header(UPLOAD_OFFSET, upload.offset)
+    .header(UPLOAD_LENGTH, upload.length);
+  if let Some(file_path) = &upload.file_path {
+    let url = create_url(
+      &state.config.load().server.get_domain_name(),
+      &file_path.code,
+      &file_path.file_name,
+    )?;
+    builder = builder
+      .header(UPLOAD_FILE_URL, url.to_string())
+      .header(UPLOAD_FILE_EXPIRES, upload.expire_date_time.to_rfc3339());
+  }
+  Ok(builder)
+}
+
+fn check_tus_resumable(headers: &HeaderMap) -> ApiResult {
+  if headers.get(TUS_RESUMABLE).and_then(|v| v.to_str().ok()) != Some(TUS_VERSION) {
+    return Err(ApiError::PreconditionFailedError(format!(
+      "The Tus-Resumable header should be {TUS_VERSION}."
+    )));
+  }
+  Ok(())
+}
+
+fn parse_header<T: std::str::FromStr>(headers: &HeaderMap, name: &str) -> ApiResult<T> {
+  headers
+    .get(name)
+    .and_then(|v| v.to_str().ok())
+    .and_then(|v| v.parse().ok())
+    .ok_or_else(|| ApiError::BadRequestError(format!("The {name} header is missing or invalid.")))
+}
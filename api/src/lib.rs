@@ -3,7 +3,9 @@ pub mod constant;
 pub mod database;
 pub mod error;
 pub mod handler;
+pub mod metrics;
 pub mod router;
 pub mod server;
 pub mod service;
+pub mod storage;
 pub mod util;
@@ -0,0 +1,126 @@
+use once_cell::sync::Lazy;
+use prometheus_client::{
+  encoding::{text::encode, EncodeLabelSet},
+  metrics::{
+    counter::Counter,
+    family::Family,
+    gauge::Gauge,
+    histogram::{exponential_buckets, Histogram},
+  },
+  registry::Registry,
+};
+
+use crate::error::result::ApiResult;
+
+pub const CONTENT_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+pub static METRICS: Lazy<Metrics> = Lazy::new(Metrics::new);
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+pub struct RejectionLabels {
+  pub error_type: String,
+}
+
+pub struct Metrics {
+  registry: Registry,
+  pub upload_bytes: Counter,
+  pub upload_duration: Histogram,
+  pub download_bytes: Counter,
+  pub rejections: Family<RejectionLabels, Counter>,
+  pub files: Gauge,
+  pub storage_bytes: Gauge,
+  pub gc_runs: Counter,
+  pub gc_lag: Histogram,
+}
+
+impl Metrics {
+  fn new() -> Self {
+    let mut registry = Registry::with_prefix("pf");
+    let upload_bytes = Counter::default();
+    registry.register(
+      "upload_bytes",
+      "Bytes received by uploads",
+      upload_bytes.clone(),
+    );
+    // From 10ms up to about 3 hours.
+    let upload_duration = Histogram::new(exponential_buckets(0.01, 4.0, 12));
+    registry.register(
+      "upload_duration_seconds",
+      "Time taken by completed uploads",
+      upload_duration.clone(),
+    );
+    let download_bytes = Counter::default();
+    registry.register(
+      "download_bytes",
+      "Bytes sent by downloads",
+      download_bytes.clone(),
+    );
+    let rejections = Family::<RejectionLabels, Counter>::default();
+    registry.register(
+      "rejections",
+      "Requests answered with an error, by error type",
+      rejections.clone(),
+    );
+    let files = Gauge::default();
+    registry.register("files", "Number of live files", files.clone());
+    let storage_bytes = Gauge::default();
+    registry.register(
+      "storage_bytes",
+      "Bytes of the blobs in the storage backend, shared content counts once",
+      storage_bytes.clone(),
+    );
+    let gc_runs = Counter::default();
+    registry.register("gc_runs", "Runs of the garbage collector", gc_runs.clone());
+    // From 1ms up to about 4 minutes.
+    let gc_lag = Histogram::new(exponential_buckets(0.001, 4.0, 10));
+    registry.register(
+      "gc_lag_seconds",
+      "Delay between the expiration of the oldest expired file and its purge",
+      gc_lag.clone(),
+    );
+    Self {
+      registry,
+      upload_bytes,
+      upload_duration,
+      download_bytes,
+      rejections,
+      files,
+      storage_bytes,
+      gc_runs,
+      gc_lag,
+    }
+  }
+
+  pub fn reject(&self, error_type: &str) {
+    self
+      .rejections
+      .get_or_create(&RejectionLabels {
+        error_type: error_type.to_string(),
+      })
+      .inc();
+  }
+
+  pub fn encode(&self) -> ApiResult<String> {
+    let mut buf = String::new();
+    encode(&mut buf, &self.registry).map_err(|e| anyhow::anyhow!("Encode metrics failed: {e}"))?;
+    Ok(buf)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_encode_metrics() {
+    METRICS.upload_bytes.inc_by(10);
+    METRICS.reject("BAD_REQUEST");
+    let body = METRICS.encode().unwrap();
+    assert!(body.contains("pf_upload_bytes_total"), "{body}");
+    assert!(
+      body.contains("pf_rejections_total{error_type=\"BAD_REQUEST\"}"),
+      "{body}"
+    );
+    assert!(body.ends_with("# EOF\n"), "{body}");
+  }
+}
@@ -1,21 +1,118 @@
 use crate::{configure::cors::cors_layer, error::result::ApiResult, handler, server::ApiState};
 use axum::{
   extract::DefaultBodyLimit,
-  routing::{delete, get, post},
+  http::{HeaderValue, Request},
+  middleware::{from_fn_with_state, map_response},
+  routing::{delete, get, head, post},
   Router,
 };
+use tower::ServiceBuilder;
+use tower_http::{
+  request_id::{MakeRequestId, PropagateRequestIdLayer, RequestId, SetRequestIdLayer},
+  trace::{DefaultOnResponse, TraceLayer},
+};
+use tracing::{Level, Span};
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
 
 pub fn get_router(state: ApiState) -> ApiResult<Router> {
   Ok(
     Router::new()
       .route("/upload", post(handler::file::upload))
       .layer(DefaultBodyLimit::disable())
-      .route("/healthz", get(handler::health_check))
+      .merge(tus_router())
+      .merge(account_router())
+      .merge(admin_router())
       .route("/info/:code/:file_name", get(handler::file::info))
+      .route("/files", get(handler::file::list_files))
+      .route("/quota", get(handler::quota::quota))
+      .route("/:code", get(handler::file::list))
       .route("/:code/:file_name", get(handler::file::download))
       .route("/:code/:file_name", delete(handler::file::delete))
       .route("/", get(handler::index::page))
-      .layer(cors_layer(&state.config.server)?)
+      .layer(from_fn_with_state(
+        state.clone(),
+        handler::rate_limit::limit,
+      ))
+      .route("/healthz", get(handler::health_check))
+      .route("/metrics", get(handler::metrics))
+      .layer(cors_layer(state.config.clone())?)
+      .layer(
+        ServiceBuilder::new()
+          .layer(SetRequestIdLayer::x_request_id(MakeRequestCuid))
+          .layer(
+            TraceLayer::new_for_http()
+              .make_span_with(request_span)
+              .on_response(DefaultOnResponse::new().level(Level::INFO)),
+          )
+          .layer(PropagateRequestIdLayer::x_request_id()),
+      )
       .with_state(state),
   )
 }
+
+/// The span of every log of a request, the id is kept from the X-Request-Id header
+/// when the client sent one and returned in the response.
+fn request_span<B>(request: &Request<B>) -> Span {
+  let request_id = request
+    .headers()
+    .get(REQUEST_ID_HEADER)
+    .and_then(|id| id.to_str().ok())
+    .unwrap_or_default();
+  tracing::info_span!(
+    "request",
+    method = %request.method(),
+    uri = %request.uri(),
+    request_id,
+  )
+}
+
+#[derive(Clone, Copy)]
+struct MakeRequestCuid;
+
+impl MakeRequestId for MakeRequestCuid {
+  fn make_request_id<B>(&mut self, _request: &Request<B>) -> Option<RequestId> {
+    HeaderValue::from_str(&cuid2::create_id())
+      .ok()
+      .map(RequestId::new)
+  }
+}
+
+fn account_router() -> Router<ApiState> {
+  Router::new()
+    .route("/accounts", post(handler::account::register))
+    .route(
+      "/accounts/keys",
+      post(handler::account::create_api_key).get(handler::account::list_api_keys),
+    )
+    .route(
+      "/accounts/keys/:id",
+      delete(handler::account::revoke_api_key),
+    )
+}
+
+fn admin_router() -> Router<ApiState> {
+  Router::new()
+    .route("/admin/files", get(handler::admin::list_files))
+    .route("/admin/audit", get(handler::admin::audit_log))
+    .route("/admin/reload", post(handler::admin::reload_config))
+    .route(
+      "/admin/files/:code/:file_name",
+      delete(handler::admin::delete_file).patch(handler::admin::update_file),
+    )
+}
+
+fn tus_router() -> Router<ApiState> {
+  Router::new()
+    .route(
+      "/tus",
+      post(handler::tus::create).options(handler::tus::options),
+    )
+    .route(
+      "/tus/:id",
+      head(handler::tus::head)
+        .patch(handler::tus::patch)
+        .delete(handler::tus::terminate),
+    )
+    .layer(map_response(handler::tus::set_tus_resumable))
+}
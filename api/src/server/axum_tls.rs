@@ -1,6 +1,8 @@
 use std::fs::File;
 use std::sync::Arc;
 
+use arc_swap::ArcSwapOption;
+use axum::extract::ConnectInfo;
 use axum::Router;
 use futures_util::pin_mut;
 use hyper::body::Incoming;
@@ -9,16 +11,27 @@ use hyper_util::rt::{TokioExecutor, TokioIo};
 use tokio::net::TcpListener;
 use tokio_rustls::rustls::ServerConfig;
 use tokio_rustls::TlsAcceptor;
+use tokio_util::{sync::CancellationToken, task::TaskTracker};
 use tower_service::Service;
 
-// Async function to serve incoming connections over TLS
-pub async fn serve(tcp_listener: TcpListener, router: Router, config: ServerConfig) {
-  let tls_acceptor = TlsAcceptor::from(Arc::new(config));
+// Async function to serve incoming connections over TLS until the shutdown token is cancelled,
+// then wait for the open connections to finish their requests
+pub async fn serve(
+  tcp_listener: TcpListener,
+  router: Router,
+  tls: Arc<ArcSwapOption<ServerConfig>>,
+  shutdown: CancellationToken,
+) {
+  let connections = TaskTracker::new();
   pin_mut!(tcp_listener);
   // Continuously accept and handle incoming connections
   loop {
     // Wait for a new TCP connection
-    let (tcp_stream, addr) = match tcp_listener.accept().await {
+    let accepted = tokio::select! {
+      accepted = tcp_listener.accept() => accepted,
+      _ = shutdown.cancelled() => break,
+    };
+    let (tcp_stream, addr) = match accepted {
       Ok(s) => s,
       Err(err) => {
         tracing::error!("Error during accept TCP connection, Error: {err}");
@@ -26,9 +39,15 @@ pub async fn serve(tcp_listener: TcpListener, router: Router, config: ServerConf
       }
     };
 
+    // Reloaded certificates apply to the connections accepted afterwards.
+    let Some(config) = tls.load_full() else {
+      tracing::error!("The TLS config is not loaded, drop the connection from: {addr}");
+      continue;
+    };
+    let tls_acceptor = TlsAcceptor::from(config);
     let tower_service = router.clone();
-    let tls_acceptor = tls_acceptor.clone();
-    tokio::spawn(async move {
+    let shutdown = shutdown.clone();
+    connections.spawn(async move {
       // Handle TLS handshake
       let tls_stream = match tls_acceptor.accept(tcp_stream).await {
         Ok(s) => s,
@@ -45,21 +64,32 @@ pub async fn serve(tcp_listener: TcpListener, router: Router, config: ServerConf
       // Hyper also has its own `Service` trait and doesn't use tower. We can use
       // `hyper::service::service_fn` to create a hyper `Service` that calls our app through
       // `tower::Service::call`.
-      let hyper_service = hyper::service::service_fn(move |request: Request<Incoming>| {
+      let hyper_service = hyper::service::service_fn(move |mut request: Request<Incoming>| {
+        request.extensions_mut().insert(ConnectInfo(addr));
         // We have to clone `tower_service` because hyper's `Service` uses `&self` whereas
         // tower's `Service` requires `&mut self`.
         // We don't need to call `poll_ready` since `Router` is always ready.
         tower_service.clone().call(request)
       });
 
-      if let Err(err) = hyper_util::server::conn::auto::Builder::new(TokioExecutor::new())
-        .serve_connection_with_upgrades(stream, hyper_service)
-        .await
-      {
+      let builder = hyper_util::server::conn::auto::Builder::new(TokioExecutor::new());
+      let connection = builder.serve_connection_with_upgrades(stream, hyper_service);
+      pin_mut!(connection);
+      // Let the current request finish and close the connection once the shutdown starts
+      let result = tokio::select! {
+        result = connection.as_mut() => result,
+        _ = shutdown.cancelled() => {
+          connection.as_mut().graceful_shutdown();
+          connection.await
+        }
+      };
+      if let Err(err) = result {
         tracing::warn!("Failed serving connection from {addr}, Error: {err}");
       }
     });
   }
+  connections.close();
+  connections.wait().await;
 }
 
 // Function to create a Rustls ServerConfig from key and cert files
@@ -5,20 +5,52 @@ use crate::configure::{ApiConfig, UrlSchema};
 use crate::database::Database;
 use crate::error::result::ApiResult;
 use crate::router::get_router;
+use crate::service::audit::AuditLog;
+use crate::service::file::UploadLeases;
+use crate::service::rate_limit::RateLimiter;
+use crate::service::tus::UploadLocks;
+use crate::service::webhook::Webhooks;
+use crate::storage::{new_storage, StorageBackend};
+use arc_swap::{ArcSwap, ArcSwapOption};
+use futures_util::{pin_mut, Future, FutureExt};
+use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
 
 #[derive(Clone)]
 pub struct ApiState {
-  pub config: Arc<ApiConfig>,
+  /// Swapped as a whole when the settings are reloaded.
+  pub config: Arc<ArcSwap<ApiConfig>>,
   pub db: Arc<Database>,
+  pub storage: Arc<dyn StorageBackend>,
+  pub upload_locks: Arc<UploadLocks>,
+  pub upload_leases: Arc<UploadLeases>,
+  pub rate_limiter: Arc<RateLimiter>,
+  pub webhooks: Arc<Webhooks>,
+  pub audit: Arc<AuditLog>,
+  /// The certificates of the https server, loaded when it starts and replaced on reload.
+  pub tls: Arc<ArcSwapOption<tokio_rustls::rustls::ServerConfig>>,
+  /// Cancelled on SIGTERM or Ctrl-C, the server and the tasks stop once it is.
+  pub shutdown: CancellationToken,
 }
 
 impl ApiState {
   pub fn new(config: ApiConfig) -> ApiResult<Self> {
     let db = Database::new(&config.db)?;
+    let storage = new_storage(&config)?;
+    let webhooks = Arc::new(Webhooks::new(&config.webhook));
     Ok(Self {
-      config: Arc::new(config),
+      config: Arc::new(ArcSwap::from_pointee(config)),
       db: Arc::new(db),
+      storage,
+      upload_locks: Default::default(),
+      upload_leases: Default::default(),
+      rate_limiter: Default::default(),
+      webhooks,
+      audit: Default::default(),
+      tls: Default::default(),
+      shutdown: Default::default(),
     })
   }
 }
@@ -41,16 +73,65 @@ impl ApiServer {
     Ok(Self { state, tcp })
   }
 
+  /// Serves until the shutdown starts, then stops accepting connections and waits for the
+  /// in-flight requests.
   pub async fn run(self) -> ApiResult<()> {
-    match self.state.config.server.schema {
+    let shutdown = self.state.shutdown.clone();
+    let timeout = Duration::from_secs(self.state.config.load().server.shutdown_timeout_secs);
+    match self.state.config.load().server.schema {
       UrlSchema::Http => {
-        axum::serve(self.tcp, get_router(self.state)?).await?;
+        let server = axum::serve(
+          self.tcp,
+          get_router(self.state)?.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .with_graceful_shutdown(shutdown.clone().cancelled_owned());
+        drain(async { Ok(server.await?) }, &shutdown, timeout).await
       }
       UrlSchema::Https => {
-        let config_server = self.state.config.server.get_tls_config()?;
-        axum_tls::serve(self.tcp, get_router(self.state)?, config_server).await;
+        let tls_config = self.state.config.load().server.get_tls_config()?;
+        self.state.tls.store(Some(Arc::new(tls_config)));
+        let tls = self.state.tls.clone();
+        let server = axum_tls::serve(self.tcp, get_router(self.state)?, tls, shutdown.clone());
+        drain(server.map(Ok), &shutdown, timeout).await
       }
     }
-    Ok(())
+  }
+}
+
+/// Runs the server to completion, but at most `timeout` after the shutdown started.
+async fn drain(
+  server: impl Future<Output = ApiResult>,
+  shutdown: &CancellationToken,
+  timeout: Duration,
+) -> ApiResult {
+  pin_mut!(server);
+  tokio::select! {
+    result = server.as_mut() => return result,
+    _ = shutdown.cancelled() => {}
+  }
+  tracing::info!("Shutting down, waiting for the in-flight requests.");
+  match tokio::time::timeout(timeout, server).await {
+    Ok(result) => result,
+    Err(_) => {
+      tracing::warn!("The in-flight requests did not finish within {timeout:?}, they are aborted.");
+      Ok(())
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[tokio::test]
+  async fn test_drain_gives_up_after_timeout() {
+    let shutdown = CancellationToken::new();
+    shutdown.cancel();
+    let result = tokio::time::timeout(
+      Duration::from_secs(5),
+      drain(std::future::pending(), &shutdown, Duration::from_millis(10)),
+    )
+    .await;
+    assert!(matches!(result, Ok(Ok(()))));
   }
 }
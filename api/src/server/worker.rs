@@ -1,4 +1,20 @@
-use crate::error::result::ApiResult;
+use std::{sync::Arc, time::Duration};
+
+use axum::http::StatusCode;
+use chrono::Utc;
+use pf_sdk::dto::response::AuditAction;
+use tokio::sync::Semaphore;
+use tokio_util::task::TaskTracker;
+
+use crate::{
+  error::result::ApiResult,
+  metrics::METRICS,
+  service::{
+    self,
+    audit::{self, Actor},
+    webhook::{self, EventKind},
+  },
+};
 
 use super::ApiState;
 
@@ -11,24 +27,117 @@ impl GarbageCollectorTask {
     Self { state }
   }
 
+  /// Collects until the shutdown starts, a running collection is always finished.
   pub async fn run(self) -> ApiResult {
-    let base_dir = self.state.config.fs.base_dir.clone();
-    loop {
-      match self.state.db.purge(&base_dir).await {
-        Ok(Some(d)) => {
-          tokio::select! {
-            _ = tokio::time::sleep(d) => {},
-            _ = self.state.db.waiting_for_notify() => {},
-          }
-        }
-        Ok(None) => {
-          self.state.db.waiting_for_notify().await;
-        }
+    let shutdown = &self.state.shutdown;
+    while !shutdown.is_cancelled() {
+      let delay = match self.collect().await {
+        Ok(delay) => delay,
         Err(err) => {
           tracing::error!("Failed garbage collector task, Error: {err}");
-          self.state.db.waiting_for_notify().await;
+          None
+        }
+      };
+      let sleep = async {
+        match delay {
+          Some(d) => tokio::time::sleep(d).await,
+          None => std::future::pending().await,
         }
+      };
+      tokio::select! {
+        _ = sleep => {},
+        _ = self.state.db.waiting_for_notify() => {},
+        _ = shutdown.cancelled() => {},
+      }
+    }
+    Ok(())
+  }
+
+  async fn collect(&self) -> ApiResult<Option<Duration>> {
+    METRICS.gc_runs.inc();
+    if let Some(expire) = self.state.db.next_expire()? {
+      let lag = Utc::now() - expire;
+      if lag > chrono::Duration::zero() {
+        METRICS
+          .gc_lag
+          .observe(lag.num_milliseconds() as f64 / 1000.0);
+      }
+    }
+    let (expired, files) = self.state.db.purge(&*self.state.storage).await?;
+    for (file_path, meta) in expired {
+      webhook::notify(&self.state, EventKind::Expire, &file_path, &meta);
+      audit::record(
+        &self.state,
+        &Actor::default(),
+        AuditAction::Expire,
+        Some(&file_path.code),
+        Some(&file_path.file_name),
+        Ok(StatusCode::OK),
+      )
+      .await;
+    }
+    let uploads = service::tus::purge(&self.state).await?;
+    let pending = service::file::purge_pending(&self.state).await?;
+    Ok([files, uploads, pending].into_iter().flatten().min())
+  }
+}
+
+pub struct WebhookTask {
+  state: ApiState,
+}
+
+impl WebhookTask {
+  pub fn new(state: ApiState) -> Self {
+    Self { state }
+  }
+
+  /// Delivers the events in the background so a slow endpoint does not hold up the others.
+  /// At most `concurrency` deliveries run at once, the next events wait in the queue. On
+  /// shutdown the queued events are dropped and the running deliveries make no more retries,
+  /// they are given up to the shutdown timeout to finish.
+  pub async fn run(self) -> ApiResult {
+    let mut receiver = self.state.webhooks.take_receiver()?;
+    let config = Arc::new(self.state.config.load().webhook.clone());
+    let client = reqwest::Client::builder()
+      .timeout(Duration::from_secs(config.timeout_secs))
+      .build()?;
+    let permits = Arc::new(Semaphore::new(config.concurrency));
+    let deliveries = TaskTracker::new();
+    'events: loop {
+      let event = tokio::select! {
+        Some(event) = receiver.recv() => event,
+        _ = self.state.shutdown.cancelled() => break,
+      };
+      let event = Arc::new(event);
+      for url in &config.urls {
+        let permit = tokio::select! {
+          permit = permits.clone().acquire_owned() => permit.expect("the semaphore is never closed"),
+          _ = self.state.shutdown.cancelled() => break 'events,
+        };
+        let (client, config, event) = (client.clone(), config.clone(), event.clone());
+        let (url, shutdown) = (url.clone(), self.state.shutdown.clone());
+        deliveries.spawn(async move {
+          if let Err(err) = webhook::deliver(&client, &config, &url, &event, &shutdown).await {
+            tracing::error!(
+              "Failed to deliver the webhook event {} to {url}, Error: {err}",
+              event.id
+            );
+          }
+          drop(permit);
+        });
       }
     }
+    deliveries.close();
+    let timeout = Duration::from_secs(self.state.config.load().server.shutdown_timeout_secs);
+    if tokio::time::timeout(timeout, deliveries.wait())
+      .await
+      .is_err()
+    {
+      tracing::warn!(
+        "{} webhook deliveries did not finish before the shutdown timeout.",
+        deliveries.len()
+      );
+    }
+    Ok(())
   }
 }
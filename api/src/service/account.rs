@@ -0,0 +1,345 @@
+use chrono::Utc;
+use hyper::HeaderMap;
+use pf_sdk::dto::{
+  request::{ApiKeyScope, CreateAccountRequest, CreateApiKeyRequest},
+  response::ApiKeyResponse,
+};
+use sha2::{Digest, Sha256};
+
+use crate::{
+  database::{account::Account, api_key::ApiKey},
+  error::{
+    result::{ApiResult, ToApiResult},
+    ApiError,
+  },
+  server::ApiState,
+  util::{
+    http::{parse_api_key, parse_basic_credentials},
+    secret::Secret,
+  },
+};
+
+const API_KEY_PREFIX: &str = "pf";
+const API_KEY_SECRET_LENGTH: usize = 32;
+
+/// The account behind the API key of a request.
+#[derive(Debug, Clone)]
+pub struct Principal {
+  pub key_id: String,
+  pub username: String,
+  pub scopes: Vec<ApiKeyScope>,
+}
+
+impl Principal {
+  pub fn has_scope(&self, scope: ApiKeyScope) -> bool {
+    self
+      .scopes
+      .iter()
+      .any(|s| *s == scope || *s == ApiKeyScope::Admin)
+  }
+
+  pub fn require(&self, scope: ApiKeyScope) -> ApiResult {
+    if self.has_scope(scope) {
+      Ok(())
+    } else {
+      Err(ApiError::PermissionDeniedError(format!(
+        "The api key does not have the {scope} scope."
+      )))
+    }
+  }
+
+  pub fn can_manage(&self, owner: Option<&str>) -> bool {
+    self.has_scope(ApiKeyScope::Admin) || owner == Some(self.username.as_str())
+  }
+}
+
+/// Creates an account without admin rights, the first admin is created with
+/// [`create_first_admin`].
+pub async fn register(state: &ApiState, req: &CreateAccountRequest) -> ApiResult<Account> {
+  if !state.config.load().allow_registration {
+    return Err(ApiError::PermissionDeniedError(
+      "Registration is disabled.".to_string(),
+    ));
+  }
+  let account = Account {
+    username: req.username.clone(),
+    password: Secret::new(req.password.clone()).hash()?,
+    is_admin: false,
+    created_at: Utc::now(),
+  };
+  state.db.store_account(&account)?;
+  state.db.flush().await?;
+  Ok(account)
+}
+
+/// Creates the admin account of a new server, it fails once an admin was created.
+pub async fn create_first_admin(
+  state: &ApiState,
+  req: &CreateAccountRequest,
+) -> ApiResult<Account> {
+  let account = Account {
+    username: req.username.clone(),
+    password: Secret::new(req.password.clone()).hash()?,
+    is_admin: true,
+    created_at: Utc::now(),
+  };
+  state.db.store_first_admin(&account)?;
+  state.db.flush().await?;
+  Ok(account)
+}
+
+pub fn login(state: &ApiState, headers: &HeaderMap) -> ApiResult<Account> {
+  let (username, password) = parse_basic_credentials(headers)?
+    .ok_or_else(|| ApiError::UnauthorizedError("Authorization header required.".to_string()))?;
+  let invalid = || ApiError::UnauthorizedError("Invalid username or password.".to_string());
+  let account = state.db.fetch_account(&username)?.ok_or_else(invalid)?;
+  match Secret::new(password).verify(&account.password) {
+    Ok(()) => Ok(account),
+    Err(argon2::password_hash::Error::Password) => Err(invalid()),
+    Err(e) => Err(ApiError::HashError(e.to_string())),
+  }
+}
+
+pub async fn create_api_key(
+  state: &ApiState,
+  account: &Account,
+  req: &CreateApiKeyRequest,
+) -> ApiResult<ApiKeyResponse> {
+  if req.scopes.contains(&ApiKeyScope::Admin) && !account.is_admin {
+    return Err(ApiError::PermissionDeniedError(
+      "Only admins can create keys with the admin scope.".to_string(),
+    ));
+  }
+  let mut scopes = req.scopes.clone();
+  scopes.sort();
+  scopes.dedup();
+  let id = cuid2::create_id();
+  let key = format!(
+    "{API_KEY_PREFIX}_{id}_{}",
+    pf_sdk::util::random::generate_random_string(API_KEY_SECRET_LENGTH)
+  );
+  let api_key = ApiKey {
+    id,
+    username: account.username.clone(),
+    name: req.name.clone(),
+    scopes,
+    key_hash: hash_api_key(&key),
+    created_at: Utc::now(),
+  };
+  state.db.store_api_key(&api_key)?;
+  state.db.flush().await?;
+  let mut resp = ApiKeyResponse::from(&api_key);
+  resp.key = Some(key);
+  Ok(resp)
+}
+
+pub fn list_api_keys(state: &ApiState, account: &Account) -> ApiResult<Vec<ApiKey>> {
+  state.db.fetch_api_keys(&account.username)
+}
+
+pub async fn revoke_api_key(state: &ApiState, account: &Account, id: &str) -> ApiResult {
+  let api_key = state.db.fetch_api_key(id)?.to_result(id)?;
+  if api_key.username != account.username && !account.is_admin {
+    return Err(ApiError::NotFoundError(format!("{id} not found")));
+  }
+  state.db.delete_api_key(id)?;
+  state.db.flush().await?;
+  Ok(())
+}
+
+pub fn authenticate(state: &ApiState, headers: &HeaderMap) -> ApiResult<Option<Principal>> {
+  let Some(key) = parse_api_key(headers)? else {
+    return Ok(None);
+  };
+  let invalid = || ApiError::UnauthorizedError("The api key is invalid.".to_string());
+  let id = key
+    .strip_prefix(API_KEY_PREFIX)
+    .and_then(|key| key.strip_prefix('_'))
+    .and_then(|key| key.split_once('_'))
+    .map(|(id, _)| id)
+    .ok_or_else(invalid)?;
+  let api_key = state.db.fetch_api_key(id)?.ok_or_else(invalid)?;
+  if api_key.key_hash != hash_api_key(&key) || state.db.fetch_account(&api_key.username)?.is_none()
+  {
+    return Err(invalid());
+  }
+  Ok(Some(Principal {
+    key_id: api_key.id,
+    username: api_key.username,
+    scopes: api_key.scopes,
+  }))
+}
+
+/// Requests without an API key stay anonymous, a given key must carry the scope.
+pub fn authorize(
+  state: &ApiState,
+  headers: &HeaderMap,
+  scope: ApiKeyScope,
+) -> ApiResult<Option<Principal>> {
+  let principal = authenticate(state, headers)?;
+  if let Some(principal) = &principal {
+    principal.require(scope)?;
+  }
+  Ok(principal)
+}
+
+pub fn require(state: &ApiState, headers: &HeaderMap, scope: ApiKeyScope) -> ApiResult<Principal> {
+  authorize(state, headers, scope)?
+    .ok_or_else(|| ApiError::UnauthorizedError("An api key is required.".to_string()))
+}
+
+/// Returns the owner recorded on the uploaded files.
+pub fn authorize_upload(state: &ApiState, headers: &HeaderMap) -> ApiResult<Option<String>> {
+  match authorize(state, headers, ApiKeyScope::Upload)? {
+    Some(principal) => Ok(Some(principal.username)),
+    None if state.config.load().require_api_key => Err(ApiError::UnauthorizedError(
+      "An api key with the upload scope is required.".to_string(),
+    )),
+    None => Ok(None),
+  }
+}
+
+fn hash_api_key(key: &str) -> String {
+  hex::encode(Sha256::digest(key.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+  use std::sync::Arc;
+
+  use super::*;
+  use crate::{configure::ApiConfig, util::test::StateTestContext};
+  use hyper::header::{HeaderValue, AUTHORIZATION};
+  use pf_sdk::{assert_err, dto::API_KEY_HEADER};
+  use test_context::test_context;
+
+  fn account_request(username: &str) -> CreateAccountRequest {
+    CreateAccountRequest {
+      username: username.to_string(),
+      password: "password123".to_string(),
+    }
+  }
+
+  fn allow_registration(state: &ApiState) {
+    let mut config = ApiConfig::clone(&state.config.load());
+    config.allow_registration = true;
+    state.config.store(Arc::new(config));
+  }
+
+  fn api_key_headers(key: &str) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert(API_KEY_HEADER, HeaderValue::from_str(key).unwrap());
+    headers
+  }
+
+  #[test_context(StateTestContext)]
+  #[tokio::test]
+  async fn test_registered_accounts_are_not_admins(ctx: &mut StateTestContext) {
+    let result = register(&ctx.state, &account_request("user")).await;
+    assert_err!(result, |e: &ApiError| matches!(
+      e,
+      ApiError::PermissionDeniedError(_)
+    ));
+    allow_registration(&ctx.state);
+    let user = register(&ctx.state, &account_request("user"))
+      .await
+      .unwrap();
+    assert!(!user.is_admin);
+    let result = register(&ctx.state, &account_request("user")).await;
+    assert_err!(result, |e: &ApiError| matches!(
+      e,
+      ApiError::ResourceExistsError(_)
+    ));
+  }
+
+  #[test_context(StateTestContext)]
+  #[tokio::test]
+  async fn test_only_one_first_admin_is_created(ctx: &mut StateTestContext) {
+    let mut tasks = vec![];
+    for i in 0..8 {
+      let state = ctx.state.clone();
+      tasks.push(tokio::spawn(async move {
+        create_first_admin(&state, &account_request(&format!("admin{i}"))).await
+      }));
+    }
+    let mut admins = vec![];
+    for task in tasks {
+      match task.await.unwrap() {
+        Ok(account) => admins.push(account),
+        Err(err) => assert!(matches!(err, ApiError::ResourceExistsError(_))),
+      }
+    }
+    assert_eq!(admins.len(), 1);
+    assert!(admins[0].is_admin);
+    let account = ctx.state.db.fetch_account(&admins[0].username).unwrap();
+    assert!(account.unwrap().is_admin);
+  }
+
+  #[test_context(StateTestContext)]
+  #[tokio::test]
+  async fn test_login(ctx: &mut StateTestContext) {
+    allow_registration(&ctx.state);
+    register(&ctx.state, &account_request("alice"))
+      .await
+      .unwrap();
+    let mut headers = HeaderMap::new();
+    // alice:password123
+    headers.insert(
+      AUTHORIZATION,
+      HeaderValue::from_static("Basic YWxpY2U6cGFzc3dvcmQxMjM="),
+    );
+    let account = login(&ctx.state, &headers).unwrap();
+    assert_eq!(account.username, "alice");
+    // alice:wrong
+    headers.insert(
+      AUTHORIZATION,
+      HeaderValue::from_static("Basic YWxpY2U6d3Jvbmc="),
+    );
+    assert_err!(login(&ctx.state, &headers), |e: &ApiError| matches!(
+      e,
+      ApiError::UnauthorizedError(_)
+    ));
+  }
+
+  #[test_context(StateTestContext)]
+  #[tokio::test]
+  async fn test_api_key_scopes(ctx: &mut StateTestContext) {
+    allow_registration(&ctx.state);
+    create_first_admin(&ctx.state, &account_request("admin"))
+      .await
+      .unwrap();
+    let account = register(&ctx.state, &account_request("bob")).await.unwrap();
+    let req = CreateApiKeyRequest {
+      name: "ci".to_string(),
+      scopes: vec![ApiKeyScope::Upload],
+    };
+    let resp = create_api_key(&ctx.state, &account, &req).await.unwrap();
+    let headers = api_key_headers(&resp.key.unwrap());
+    let principal = authorize(&ctx.state, &headers, ApiKeyScope::Upload)
+      .unwrap()
+      .unwrap();
+    assert_eq!(principal.username, "bob");
+    assert!(principal.can_manage(Some("bob")));
+    assert!(!principal.can_manage(Some("admin")));
+    let result = authorize(&ctx.state, &headers, ApiKeyScope::Delete);
+    assert_err!(result, |e: &ApiError| e.to_string()
+      == "The api key does not have the delete scope.");
+    let req = CreateApiKeyRequest {
+      name: "root".to_string(),
+      scopes: vec![ApiKeyScope::Admin],
+    };
+    let result = create_api_key(&ctx.state, &account, &req).await;
+    assert_err!(result, |e: &ApiError| matches!(
+      e,
+      ApiError::PermissionDeniedError(_)
+    ));
+    revoke_api_key(&ctx.state, &account, &resp.id)
+      .await
+      .unwrap();
+    let result = authenticate(&ctx.state, &headers);
+    assert_err!(result, |e: &ApiError| matches!(
+      e,
+      ApiError::UnauthorizedError(_)
+    ));
+  }
+}
@@ -0,0 +1,194 @@
+use chrono::Utc;
+use hyper::HeaderMap;
+use pf_sdk::dto::request::{AdminListFilesQueryParam, ApiKeyScope, UpdateFileRequest};
+use sha2::{Digest, Sha256};
+
+use crate::{
+  constant::DEFAULT_PAGE_SIZE,
+  database::{
+    file_path::FilePath,
+    meta_data_file::{MetaDataFile, UploadState},
+  },
+  error::{
+    result::{ApiResult, ToApiResult},
+    ApiError,
+  },
+  server::ApiState,
+  service::{
+    file::{calc_expiration_date, OwnedFile},
+    webhook::{self, EventKind},
+  },
+  util::http::parse_bearer_token,
+};
+
+/// Accepts the configured admin token or an API key with the admin scope.
+pub fn authorize(state: &ApiState, headers: &HeaderMap) -> ApiResult {
+  if let Some(token) = parse_bearer_token(headers) {
+    return match &state.config.load().admin_token {
+      Some(admin_token) if token_eq(token, admin_token) => Ok(()),
+      _ => Err(ApiError::UnauthorizedError(
+        "The admin token is invalid.".to_string(),
+      )),
+    };
+  }
+  match super::account::authenticate(state, headers)? {
+    Some(principal) => principal.require(ApiKeyScope::Admin),
+    None => Err(ApiError::UnauthorizedError(
+      "An admin token or api key is required.".to_string(),
+    )),
+  }
+}
+
+/// Lists the complete files, unfinished uploads are left out.
+pub fn list(
+  state: &ApiState,
+  param: &AdminListFilesQueryParam,
+) -> ApiResult<(Vec<OwnedFile>, usize)> {
+  let files = state
+    .db
+    .fetch_all()?
+    .into_iter()
+    .filter(|(_, meta)| {
+      meta.state == UploadState::Complete
+        && param
+          .owner
+          .as_ref()
+          .is_none_or(|owner| meta.owner.as_ref() == Some(owner))
+        && param
+          .expires_before
+          .is_none_or(|before| meta.expire_date_time < before)
+        && param
+          .expires_after
+          .is_none_or(|after| meta.expire_date_time > after)
+        && param.min_size.is_none_or(|min| meta.size >= min)
+        && param.max_size.is_none_or(|max| meta.size <= max)
+    })
+    .collect::<Vec<_>>();
+  let total = files.len();
+  let page = param.page.unwrap_or(1);
+  let page_size = param.page_size.unwrap_or(DEFAULT_PAGE_SIZE);
+  let files = files
+    .into_iter()
+    .skip(page.saturating_sub(1) * page_size)
+    .take(page_size)
+    .map(|(file_path, meta)| OwnedFile { file_path, meta })
+    .collect();
+  Ok((files, total))
+}
+
+pub async fn delete(state: &ApiState, file_path: FilePath) -> ApiResult {
+  state
+    .db
+    .fetch(&file_path)?
+    .to_result(&file_path.to_string())?;
+  for (file_path, meta) in state
+    .db
+    .remove_file(&*state.storage, vec![file_path])
+    .await?
+  {
+    webhook::notify(state, EventKind::Delete, &file_path, &meta);
+  }
+  Ok(())
+}
+
+pub async fn update(
+  state: &ApiState,
+  file_path: &FilePath,
+  req: &UpdateFileRequest,
+) -> ApiResult<MetaDataFile> {
+  let expire_date_time = req
+    .expire_secs
+    .map(|secs| calc_expiration_date(Utc::now(), secs as i64))
+    .transpose()?;
+  let meta = state
+    .db
+    .modify(file_path, |meta| {
+      if let Some(expire_date_time) = expire_date_time {
+        meta.expire_date_time = expire_date_time;
+      }
+      if req.reset_downloads {
+        meta.count_downloads = 0;
+      }
+    })?
+    .to_result(&file_path.to_string())?;
+  state.db.flush().await?;
+  Ok(meta)
+}
+
+// Hashing first gives equal lengths, so the comparison does not leak the token length.
+fn token_eq(token: &str, admin_token: &str) -> bool {
+  let a = Sha256::digest(token.as_bytes());
+  let b = Sha256::digest(admin_token.as_bytes());
+  a.iter().zip(b.iter()).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::configure::ApiConfig;
+  use crate::util::{multipart::create_multipart_request, test::StateTestContext};
+  use fake::{Fake, Faker};
+  use hyper::header::{HeaderValue, AUTHORIZATION};
+  use pf_sdk::{assert_err, dto::request::UploadQueryParam};
+  use test_context::test_context;
+
+  #[test_context(StateTestContext)]
+  #[tokio::test]
+  async fn test_authorize_admin_token(ctx: &mut StateTestContext) {
+    let mut headers = HeaderMap::new();
+    assert_err!(authorize(&ctx.state, &headers), |e: &ApiError| matches!(
+      e,
+      ApiError::UnauthorizedError(_)
+    ));
+    headers.insert(AUTHORIZATION, HeaderValue::from_static("Bearer secret"));
+    assert_err!(authorize(&ctx.state, &headers), |e: &ApiError| matches!(
+      e,
+      ApiError::UnauthorizedError(_)
+    ));
+    let mut config = ApiConfig::clone(&ctx.state.config.load());
+    config.admin_token = Some("secret".to_string());
+    ctx.state.config.store(std::sync::Arc::new(config));
+    assert!(authorize(&ctx.state, &headers).is_ok());
+  }
+
+  #[test_context(StateTestContext)]
+  #[tokio::test]
+  async fn test_update_and_delete_file(ctx: &mut StateTestContext) {
+    let param = UploadQueryParam {
+      allow_manual_deletion: Some(false),
+      ..Default::default()
+    };
+    let multipart = create_multipart_request("file.txt", "data").await.unwrap();
+    let (mut file_paths, _) =
+      crate::service::file::store(&ctx.state, &param, None, None, multipart)
+        .await
+        .unwrap();
+    let file_path = file_paths.remove(0);
+    ctx.state.db.increment_downloads(&file_path).unwrap();
+    let req = UpdateFileRequest {
+      expire_secs: Some(10),
+      reset_downloads: true,
+    };
+    let meta = update(&ctx.state, &file_path, &req).await.unwrap();
+    assert_eq!(meta.count_downloads, 0);
+    assert!(meta.expire_date_time <= Utc::now() + chrono::Duration::seconds(10));
+    // An unfinished upload is neither listed nor breaks the size filter.
+    let mut pending: MetaDataFile = Faker.fake();
+    pending.expire_date_time = Utc::now() + chrono::Duration::hours(1);
+    pending.created_at = Utc::now();
+    pending.digest = String::new();
+    pending.state = UploadState::Pending;
+    ctx.state.db.store(Faker.fake(), pending).await.unwrap();
+    let (files, total) = list(&ctx.state, &AdminListFilesQueryParam::default()).unwrap();
+    assert_eq!(total, 1);
+    assert_eq!(files[0].meta.size, 4);
+    let param = AdminListFilesQueryParam {
+      min_size: Some(1),
+      ..Default::default()
+    };
+    assert_eq!(list(&ctx.state, &param).unwrap().1, 1);
+    delete(&ctx.state, file_path.clone()).await.unwrap();
+    assert!(!ctx.state.db.exist(&file_path).unwrap());
+    assert!(!ctx.state.storage.exists(&file_path).await.unwrap());
+  }
+}
@@ -0,0 +1,223 @@
+use std::{
+  fs::{File, OpenOptions},
+  io::Write,
+  net::IpAddr,
+  path::Path,
+  sync::Mutex,
+};
+
+use axum::http::StatusCode;
+use chrono::Utc;
+use hyper::HeaderMap;
+use pf_sdk::dto::{
+  request::AuditQueryParam,
+  response::{AuditAction, AuditEvent},
+};
+
+use crate::{
+  error::{result::ApiResult, ApiError},
+  server::ApiState,
+  service,
+};
+
+const LOG_FILE: &str = "audit.log";
+const DEFAULT_LIMIT: usize = 100;
+
+/// Who sent a request.
+#[derive(Debug, Clone, Default)]
+pub struct Actor {
+  pub ip: Option<IpAddr>,
+  pub username: Option<String>,
+  pub key_id: Option<String>,
+}
+
+pub fn actor(state: &ApiState, headers: &HeaderMap, peer: Option<IpAddr>) -> Actor {
+  let ip = peer.and_then(|peer| {
+    service::rate_limit::client_ip(&state.config.load().rate_limit, headers, peer)
+  });
+  match service::account::authenticate(state, headers) {
+    Ok(Some(principal)) => Actor {
+      ip,
+      username: Some(principal.username),
+      key_id: Some(principal.key_id),
+    },
+    _ => Actor {
+      ip,
+      ..Default::default()
+    },
+  }
+}
+
+/// The open log file, appended with one JSON line per event.
+#[derive(Default)]
+pub struct AuditLog(Mutex<Option<File>>);
+
+impl AuditLog {
+  fn append(&self, state: &ApiState, event: &AuditEvent) -> ApiResult {
+    let config = &state.config.load().audit;
+    let mut line = serde_json::to_vec(event)?;
+    line.push(b'\n');
+    let mut guard = self
+      .0
+      .lock()
+      .map_err(|e| ApiError::LockError(e.to_string()))?;
+    let dir = state.config.load().fs.get_audit_dir();
+    let path = dir.join(LOG_FILE);
+    let mut file = match guard.take() {
+      Some(file) => file,
+      None => {
+        std::fs::create_dir_all(&dir)?;
+        open_log(&path)?
+      }
+    };
+    let len = file.metadata()?.len();
+    if len > 0 && len + line.len() as u64 > config.max_file_bytes {
+      rotate(&dir, config.max_files)?;
+      file = open_log(&path)?;
+    }
+    file.write_all(&line)?;
+    *guard = Some(file);
+    if config.database {
+      state.db.store_audit_event(event)?;
+    }
+    Ok(())
+  }
+}
+
+fn open_log(path: &Path) -> std::io::Result<File> {
+  OpenOptions::new().create(true).append(true).open(path)
+}
+
+/// Shifts audit.log.1 to audit.log.2 and so on, the current file becomes audit.log.1.
+fn rotate(dir: &Path, max_files: usize) -> std::io::Result<()> {
+  let rotated = |i: usize| dir.join(format!("{LOG_FILE}.{i}"));
+  for i in (1..=max_files).rev() {
+    let path = rotated(i);
+    if !path.exists() {
+      continue;
+    }
+    if i == max_files {
+      std::fs::remove_file(path)?;
+    } else {
+      std::fs::rename(path, rotated(i + 1))?;
+    }
+  }
+  if max_files == 0 {
+    std::fs::remove_file(dir.join(LOG_FILE))
+  } else {
+    std::fs::rename(dir.join(LOG_FILE), rotated(1))
+  }
+}
+
+/// Appends the outcome of a file operation, failures are logged and never fail the request.
+/// The log file is written on the blocking pool and the caller waits for it, so the events
+/// of a client are kept in the order of its requests.
+pub async fn record(
+  state: &ApiState,
+  actor: &Actor,
+  action: AuditAction,
+  code: Option<&str>,
+  file_name: Option<&str>,
+  result: Result<StatusCode, &ApiError>,
+) {
+  if !state.config.load().audit.enable {
+    return;
+  }
+  let (status, error_type) = match result {
+    Ok(status) => (status, None),
+    Err(err) => {
+      let (body, status) = err.response();
+      (status, Some(body.error_type))
+    }
+  };
+  let event = AuditEvent {
+    timestamp: Utc::now(),
+    action,
+    code: code.map(str::to_string),
+    file_name: file_name.map(str::to_string),
+    ip: actor.ip,
+    username: actor.username.clone(),
+    key_id: actor.key_id.clone(),
+    status: status.as_u16(),
+    error_type,
+  };
+  let state = state.clone();
+  let written = tokio::task::spawn_blocking(move || {
+    if let Err(err) = state.audit.append(&state, &event) {
+      tracing::error!("Failed to write the audit event {event:?}, Error: {err}");
+    }
+  })
+  .await;
+  if let Err(err) = written {
+    tracing::error!("Failed to write an audit event, Error: {err}");
+  }
+}
+
+/// Events of the time range in chronological order, read from the database when it
+/// keeps them and from the log files otherwise.
+pub async fn query(state: &ApiState, param: &AuditQueryParam) -> ApiResult<Vec<AuditEvent>> {
+  let limit = param.limit.unwrap_or(DEFAULT_LIMIT);
+  let is_match = |event: &AuditEvent| {
+    param
+      .code
+      .as_ref()
+      .is_none_or(|code| event.code.as_ref() == Some(code))
+      && param.from.is_none_or(|from| event.timestamp >= from)
+      && param.to.is_none_or(|to| event.timestamp <= to)
+  };
+  if state.config.load().audit.database {
+    return state
+      .db
+      .fetch_audit_events(param.from, param.to, is_match, limit);
+  }
+  let dir = state.config.load().fs.get_audit_dir();
+  let paths = (1..=state.config.load().audit.max_files)
+    .rev()
+    .map(|i| dir.join(format!("{LOG_FILE}.{i}")))
+    .chain([dir.join(LOG_FILE)]);
+  let mut events = vec![];
+  for path in paths {
+    let content = match tokio::fs::read_to_string(&path).await {
+      Ok(content) => content,
+      Err(err) if err.kind() == std::io::ErrorKind::NotFound => continue,
+      Err(err) => return Err(err.into()),
+    };
+    for line in content.lines() {
+      let event = match serde_json::from_str::<AuditEvent>(line) {
+        Ok(event) => event,
+        Err(err) => {
+          tracing::warn!("Skip an invalid line of {}, Error: {err}", path.display());
+          continue;
+        }
+      };
+      if is_match(&event) {
+        events.push(event);
+        if events.len() == limit {
+          return Ok(events);
+        }
+      }
+    }
+  }
+  Ok(events)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_rotate_audit_log() {
+    let dir = Path::new("test-dump").join(cuid2::create_id());
+    std::fs::create_dir_all(&dir).unwrap();
+    let rotated = |i: usize| dir.join(format!("{LOG_FILE}.{i}"));
+    for content in ["first", "second", "third"] {
+      std::fs::write(dir.join(LOG_FILE), content).unwrap();
+      rotate(&dir, 2).unwrap();
+    }
+    assert!(!dir.join(LOG_FILE).exists());
+    assert_eq!(std::fs::read_to_string(rotated(1)).unwrap(), "third");
+    assert_eq!(std::fs::read_to_string(rotated(2)).unwrap(), "second");
+    assert!(!rotated(3).exists());
+    std::fs::remove_dir_all(dir).unwrap();
+  }
+}
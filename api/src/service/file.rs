@@ -1,57 +1,139 @@
-use crate::configure::ApiConfig;
+use crate::constant::RESERVED_CODES;
+use crate::database::blob::staging_path;
 use crate::database::file_path::FilePath;
-use crate::database::meta_data_file::MetaDataFile;
+use crate::database::manifest::Manifest;
+use crate::database::meta_data_file::{MetaDataFile, UploadState};
+use crate::database::usage::{Reservation, Usage};
 use crate::error::invalid_input_error;
 use crate::error::{
   result::{ApiResult, ToApiResult},
   ApiError,
 };
-use crate::util::path::get_fs_path;
+use crate::metrics::METRICS;
+use crate::service;
+use crate::service::account::Principal;
+use crate::service::webhook::{self, EventKind};
+use crate::storage::{ByteRange, StorageReader};
+use crate::util::archive::{check_zip_limits, ArchiveEncoder, ArchiveFormat};
+use crate::util::http::{if_range_matches, parse_range};
+use crate::util::reader::{
+  is_size_limit_exceeded, rejected_reason, CheckedReader, HashReader, LimitedReader,
+  OnCompleteReader, ProgressReader,
+};
 use crate::util::secret::{Secret, SecretHash};
 use anyhow::anyhow;
+use axum::body::Bytes;
 use axum::extract::multipart::Field;
 use axum::extract::Multipart;
 use chrono::{DateTime, Utc};
+use futures_util::stream::BoxStream;
 use futures_util::TryStreamExt;
+use hyper::HeaderMap;
 use pf_sdk::dto::request::UploadQueryParam;
-use std::path::PathBuf;
-use tokio::fs::File;
-use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufWriter};
-use tokio_util::bytes::BytesMut;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::AsyncReadExt;
 use tokio_util::io::StreamReader;
-use tower_http::services::ServeFile;
 use tracing::debug;
 
 use crate::server::ApiState;
 
 const BYTE_TO_MEGABYTE: usize = 1024 * 1024;
-const DEFAULT_BUF_SIZE: usize = 8192;
 
 pub async fn store(
   state: &ApiState,
   param: &UploadQueryParam,
   secret: Option<Secret>,
+  owner: Option<String>,
   mut multipart: Multipart,
-) -> ApiResult<(FilePath, DateTime<Utc>)> {
+) -> ApiResult<(Vec<FilePath>, DateTime<Utc>)> {
   let secret = secret.map(|s| s.hash()).transpose()?;
   let expire_secs = param
     .expire_secs
-    .unwrap_or(state.config.default_expire_secs) as i64;
+    .unwrap_or(state.config.load().default_expire_secs) as i64;
   let now = Utc::now();
   let expire_date_time = calc_expiration_date(now, expire_secs)?;
-  let mut code_length = param
+  let code_length = param
     .code_length
-    .unwrap_or(state.config.default_code_length);
+    .unwrap_or(state.config.load().default_code_length);
+  service::quota::remaining(state, owner.as_deref())?.check(Usage::file(0))?;
   let meta = MetaDataFile {
     created_at: now,
     expire_date_time,
     manual_deletion: param
       .allow_manual_deletion
-      .unwrap_or(state.config.allow_manual_deletion),
+      .unwrap_or(state.config.load().allow_manual_deletion),
     max_download: param.max_download,
-    secret,
+    secret: secret.clone(),
     count_downloads: 0,
+    etag: String::new(),
+    size: 0,
+    digest: String::new(),
+    owner: owner.clone(),
+    state: UploadState::Pending,
+  };
+  let mut manifest = Manifest {
+    file_names: vec![],
+    created_at: now,
+    expire_date_time,
+    secret,
+    owner,
   };
+  let code = reserve_code(state, &manifest, code_length).await?;
+  let mut file_paths = vec![];
+  match store_fields(
+    state,
+    &code,
+    &meta,
+    param.sha256.as_deref(),
+    &mut multipart,
+    &mut file_paths,
+  )
+  .await
+  {
+    Ok(()) if !file_paths.is_empty() => {}
+    result => {
+      // A failed cleanup is only logged, the caller needs the cause of the failure.
+      if let Err(err) = discard(state, file_paths).await {
+        tracing::error!("Failed to discard the files of {code}, Error: {err}");
+      }
+      if let Err(err) = state.db.delete_manifest(&code) {
+        tracing::error!("Failed to delete the manifest of {code}, Error: {err}");
+      }
+      result?;
+      return Err(ApiError::BadRequestError(
+        "The multipart/form-data body is empty.".to_string(),
+      ));
+    }
+  }
+  manifest.file_names = file_paths.iter().map(|p| p.file_name.clone()).collect();
+  state.db.update_manifest(&code, &manifest)?;
+  state.db.flush().await?;
+  METRICS
+    .upload_duration
+    .observe((Utc::now() - now).num_milliseconds() as f64 / 1000.0);
+  for file_path in &file_paths {
+    if let Some(meta) = state.db.fetch(file_path)? {
+      webhook::notify(state, EventKind::Upload, file_path, &meta);
+    }
+  }
+  Ok((file_paths, expire_date_time))
+}
+
+async fn store_fields(
+  state: &ApiState,
+  code: &str,
+  meta: &MetaDataFile,
+  sha256: Option<&str>,
+  multipart: &mut Multipart,
+  file_paths: &mut Vec<FilePath>,
+) -> ApiResult {
+  let mut remaining_size = state.config.load().max_upload_bytes_size;
+  // The bytes are reserved while they are received, the length of the body also counts
+  // the multipart framing.
+  let mut reservation = service::quota::reserve(state, meta.owner.clone(), Usage::default())?;
   while let Some(field) = multipart.next_field().await? {
     let file_name = match field.file_name() {
       Some(file_name) => {
@@ -60,84 +142,348 @@ pub async fn store(
       }
       None => continue,
     };
-    let file_path = loop {
-      let code = pf_sdk::util::random::generate_random_string(code_length);
-      let path = FilePath {
-        code,
-        file_name: file_name.to_string(),
-      };
-      if !state.db.exist(&path)? {
-        match state.db.store(path.clone(), meta.clone()).await {
-          Ok(_) => break path,
-          Err(ApiError::ResourceExistsError(e)) => {
-            debug!("Key already exist: {e}");
-            continue;
-          }
-          Err(e) => return Err(e),
+    // A part of a bundle carries its own checksum in its `Repr-Digest` header.
+    let part_sha256 = pf_sdk::util::digest::parse_sha256(field.headers()).map(hex::encode);
+    if sha256.is_some() && !file_paths.is_empty() {
+      return Err(ApiError::BadRequestError(
+        "The sha256 parameter applies to a single file.".to_string(),
+      ));
+    }
+    let file_reservation = service::quota::reserve(state, meta.owner.clone(), Usage::file(0))?;
+    let file_path = FilePath {
+      code: code.to_string(),
+      file_name: file_name.to_string(),
+    };
+    let mut meta = meta.clone();
+    meta.etag = cuid2::create_id();
+    match state.db.store(file_path.clone(), meta).await {
+      Ok(()) => file_paths.push(file_path.clone()),
+      Err(ApiError::ResourceExistsError(_)) => {
+        return Err(ApiError::BadRequestError(format!(
+          "The file name {file_name} is used more than once."
+        )))
+      }
+      Err(err) => return Err(err),
+    }
+    drop(file_reservation);
+    let bytes_size = store_stream(
+      state,
+      &file_path,
+      field,
+      remaining_size,
+      sha256.or(part_sha256.as_deref()),
+      &mut reservation,
+    )
+    .await?;
+    // The record of the file counts its size now.
+    reservation.release(Usage::bytes(bytes_size));
+    remaining_size -= bytes_size as usize;
+    METRICS.upload_bytes.inc_by(bytes_size);
+  }
+  Ok(())
+}
+
+pub async fn reserve_code(
+  state: &ApiState,
+  manifest: &Manifest,
+  mut code_length: usize,
+) -> ApiResult<String> {
+  loop {
+    let code = pf_sdk::util::random::generate_random_string(code_length);
+    if RESERVED_CODES.contains(&code.as_str()) {
+      continue;
+    }
+    if !state.db.code_exist(&code)? {
+      match state.db.store_manifest(&code, manifest) {
+        Ok(_) => return Ok(code),
+        Err(ApiError::ResourceExistsError(e)) => {
+          debug!("Key already exist: {e}");
+          continue;
         }
+        Err(e) => return Err(e),
       }
-      code_length += 1;
-    };
-    let fs_path = get_fs_path(&state.config.fs.base_dir, &file_path);
-    if let Err(e) = store_stream(&fs_path, field, state.config.max_upload_bytes_size).await {
-      state.db.delete(file_path).await?;
-      return Err(e);
     }
-    state.db.flush().await?;
-    return Ok((file_path, expire_date_time));
+    code_length += 1;
   }
-  Err(ApiError::BadRequestError(
-    "The multipart/form-data body is empty.".to_string(),
-  ))
 }
 
-pub async fn store_stream(fs_path: &PathBuf, field: Field<'_>, max_size: usize) -> ApiResult<()> {
-  let body_reader =
-    StreamReader::new(field.map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err)));
-  futures_util::pin_mut!(body_reader);
-  if let Some(parent) = fs_path.parent() {
-    tokio::fs::create_dir_all(parent).await?;
+pub async fn reserve_file_path(
+  state: &ApiState,
+  file_name: &str,
+  meta: &MetaDataFile,
+  code_length: usize,
+) -> ApiResult<FilePath> {
+  let manifest = Manifest {
+    file_names: vec![file_name.to_string()],
+    created_at: meta.created_at,
+    expire_date_time: meta.expire_date_time,
+    secret: meta.secret.clone(),
+    owner: meta.owner.clone(),
+  };
+  let code = reserve_code(state, &manifest, code_length).await?;
+  let file_path = FilePath {
+    code,
+    file_name: file_name.to_string(),
+  };
+  if let Err(err) = state.db.store(file_path.clone(), meta.clone()).await {
+    state.db.delete_manifest(&file_path.code)?;
+    return Err(err);
   }
-  let mut file = BufWriter::new(File::create(fs_path).await?);
-  copy(fs_path, &mut body_reader, &mut file, max_size).await?;
-  Ok(())
+  Ok(file_path)
 }
 
-pub async fn copy(
-  fs_path: &PathBuf,
-  mut reader: impl AsyncRead + Unpin,
-  mut writer: impl AsyncWrite + Unpin,
+pub fn list(state: &ApiState, code: &str, secret: Option<Secret>) -> ApiResult<Manifest> {
+  let manifest = state.db.fetch_manifest(code)?.to_result(code)?;
+  authorize_user(secret, &manifest.secret)?;
+  Ok(manifest)
+}
+
+/// Lists the complete files of an owner, unfinished uploads are left out.
+pub fn list_owned(
+  state: &ApiState,
+  owner: &str,
+  page: usize,
+  page_size: usize,
+) -> ApiResult<(Vec<OwnedFile>, usize)> {
+  let files = state
+    .db
+    .fetch_owned(owner)?
+    .into_iter()
+    .filter(|(_, meta)| {
+      meta.state == UploadState::Complete
+        && meta
+          .max_download
+          .is_none_or(|max| meta.count_downloads < max)
+    })
+    .collect::<Vec<_>>();
+  let total = files.len();
+  let owned_files = files
+    .into_iter()
+    .skip(page.saturating_sub(1) * page_size)
+    .take(page_size)
+    .map(|(file_path, meta)| OwnedFile { file_path, meta })
+    .collect();
+  Ok((owned_files, total))
+}
+
+pub async fn store_stream(
+  state: &ApiState,
+  file_path: &FilePath,
+  field: Field<'_>,
   max_size: usize,
-) -> ApiResult<usize> {
-  let mut buffer = BytesMut::with_capacity(DEFAULT_BUF_SIZE);
-  let mut bytes_size = 0;
-  loop {
-    let bytes_read = reader.read_buf(&mut buffer).await?;
-    if bytes_read == 0 {
-      break;
+  sha256: Option<&str>,
+  reservation: &mut Reservation,
+) -> ApiResult<u64> {
+  let body_reader = StreamReader::new(field.map_err(std::io::Error::other));
+  let body_reader = LimitedReader::new(body_reader, max_size);
+  let body_reader = CheckedReader::new(body_reader, |bytes| {
+    service::quota::grow(state, reservation, bytes).map_err(|err| match err {
+      ApiError::QuotaExceededError(reason) => reason,
+      err => err.to_string(),
+    })
+  });
+  match put_blob(state, file_path, Box::pin(body_reader), sha256).await {
+    Ok(bytes_size) => Ok(bytes_size),
+    Err(ApiError::IoError(err)) if is_size_limit_exceeded(&err) => {
+      handle_payload_too_large(state, file_path).await
     }
-    bytes_size += bytes_read;
-    if bytes_size > max_size {
-      return handle_payload_too_large(fs_path, writer, max_size).await;
+    Err(ApiError::IoError(err)) => match rejected_reason(&err) {
+      Some(reason) => {
+        let reason = reason.to_string();
+        remove_staged(state, file_path).await?;
+        Err(ApiError::QuotaExceededError(reason))
+      }
+      None => Err(err.into()),
+    },
+    Err(err) => Err(err),
+  }
+}
+
+/// Stores the content once under its digest and points the meta data of `file_path` to it.
+/// The content is written to the staging directory and dropped when it does not match the
+/// `expected` SHA-256, so a partial or invalid upload never reaches the blob. A failed call
+/// leaves neither a staged file nor a blob reference behind and can be retried.
+pub async fn put_blob(
+  state: &ApiState,
+  file_path: &FilePath,
+  reader: StorageReader<'_>,
+  expected: Option<&str>,
+) -> ApiResult<u64> {
+  let staged = staging_path(file_path);
+  let lease = state.upload_leases.acquire(file_path)?;
+  let mut reader = HashReader::new(ProgressReader::new(reader, || lease.renew()));
+  let size = state.storage.put(&staged, Box::pin(&mut reader)).await?;
+  let digest = reader.digest();
+  if let Some(expected) = expected.filter(|e| !e.eq_ignore_ascii_case(&digest)) {
+    state.storage.delete(&staged).await?;
+    return Err(ApiError::ChecksumMismatchError(format!(
+      "The SHA-256 of {file_path} is {digest}, expected {expected}."
+    )));
+  }
+  if let Err(err) = state.db.store_blob(&*state.storage, &staged, &digest).await {
+    if state.storage.exists(&staged).await.unwrap_or(true) {
+      if let Err(e) = state.storage.delete(&staged).await {
+        tracing::warn!("Failed to remove the staged file of {file_path}: {e}");
+      }
     }
-    writer.write_all(&buffer).await?;
-    buffer.clear();
+    return Err(err);
   }
-  writer.flush().await?;
-  Ok(bytes_size)
+  let meta = state.db.modify(file_path, |meta| {
+    meta.size = size;
+    meta.digest = digest.clone();
+    meta.state = UploadState::Complete;
+  });
+  let meta = match meta {
+    Ok(meta) => meta,
+    Err(err) => {
+      state.db.release_blob(&*state.storage, &digest).await?;
+      return Err(err);
+    }
+  };
+  if meta.is_none() {
+    // The file was deleted while it was uploaded.
+    state.db.release_blob(&*state.storage, &digest).await?;
+  }
+  Ok(size)
 }
 
-async fn handle_payload_too_large(
-  file_path: &PathBuf,
-  mut writer: impl AsyncWrite + Unpin,
-  max_size: usize,
-) -> ApiResult<usize> {
-  writer.shutdown().await?;
-  drop(writer);
-  tokio::fs::remove_file(file_path).await?;
+/// Marks the files failed before removing them, so they are never served when the
+/// removal fails half way.
+pub async fn discard(state: &ApiState, file_paths: Vec<FilePath>) -> ApiResult {
+  for file_path in &file_paths {
+    state
+      .db
+      .modify(file_path, |meta| meta.state = UploadState::Failed)?;
+  }
+  state.db.remove_file(&*state.storage, file_paths).await?;
+  Ok(())
+}
+
+/// When the running uploads last received data, a pending upload is only stale once it
+/// received nothing for the timeout. Purges run one at a time.
+#[derive(Default)]
+pub struct UploadLeases {
+  leases: Mutex<HashMap<FilePath, Arc<AtomicI64>>>,
+  purge: tokio::sync::Mutex<()>,
+}
+
+pub struct UploadLease<'a> {
+  leases: &'a UploadLeases,
+  file_path: FilePath,
+  renewed_at: Arc<AtomicI64>,
+}
+
+impl UploadLeases {
+  pub fn acquire(&self, file_path: &FilePath) -> ApiResult<UploadLease<'_>> {
+    let renewed_at = Arc::new(AtomicI64::new(Utc::now().timestamp_millis()));
+    self
+      .leases
+      .lock()
+      .map_err(|e| ApiError::LockError(e.to_string()))?
+      .insert(file_path.clone(), renewed_at.clone());
+    Ok(UploadLease {
+      leases: self,
+      file_path: file_path.clone(),
+      renewed_at,
+    })
+  }
+
+  pub fn renewed_at(&self, file_path: &FilePath) -> ApiResult<Option<DateTime<Utc>>> {
+    let leases = self
+      .leases
+      .lock()
+      .map_err(|e| ApiError::LockError(e.to_string()))?;
+    Ok(
+      leases
+        .get(file_path)
+        .and_then(|renewed_at| DateTime::from_timestamp_millis(renewed_at.load(Ordering::Relaxed))),
+    )
+  }
+}
+
+impl UploadLease<'_> {
+  pub fn renew(&self) {
+    self
+      .renewed_at
+      .store(Utc::now().timestamp_millis(), Ordering::Relaxed);
+  }
+}
+
+impl Drop for UploadLease<'_> {
+  fn drop(&mut self) {
+    match self.leases.leases.lock() {
+      Ok(mut leases) => {
+        leases.remove(&self.file_path);
+      }
+      Err(err) => tracing::error!(
+        "Failed to release the lease of {}, Error: {err}",
+        self.file_path
+      ),
+    }
+  }
+}
+
+/// Removes the failed uploads and the ones that received no data within the timeout,
+/// returns the time until the next pending upload times out.
+pub async fn purge_pending(state: &ApiState) -> ApiResult<Option<Duration>> {
+  let _guard = state.upload_leases.purge.lock().await;
+  let timeout_secs = state.config.load().pending_upload_timeout_secs as i64;
+  let now = Utc::now();
+  let mut stale = vec![];
+  let mut wakeup_next_time: Option<Duration> = None;
+  for (file_path, meta) in state.db.fetch_all()? {
+    match meta.state {
+      UploadState::Complete => {}
+      UploadState::Failed => stale.push(file_path),
+      UploadState::Pending => {
+        let renewed_at = state
+          .upload_leases
+          .renewed_at(&file_path)?
+          .map_or(meta.created_at, |renewed_at| {
+            renewed_at.max(meta.created_at)
+          });
+        let deadline = calc_expiration_date(renewed_at, timeout_secs)?;
+        if deadline <= now {
+          tracing::warn!(
+            "The upload of {file_path} is pending since {}.",
+            meta.created_at
+          );
+          stale.push(file_path);
+        } else {
+          let duration = (deadline - now).to_std()?;
+          wakeup_next_time = Some(wakeup_next_time.map_or(duration, |d| d.min(duration)));
+        }
+      }
+    }
+  }
+  discard(state, stale).await?;
+  Ok(wakeup_next_time)
+}
+
+/// Only complete files are served, a pending one is not readable yet.
+fn check_complete(file_path: &FilePath, meta: &MetaDataFile) -> ApiResult {
+  match meta.state {
+    UploadState::Complete => Ok(()),
+    UploadState::Pending => Err(ApiError::NotAvailableError(format!(
+      "{file_path} is still uploading"
+    ))),
+    UploadState::Failed => Err(ApiError::NotFoundError(format!("{file_path} not found"))),
+  }
+}
+
+async fn remove_staged(state: &ApiState, file_path: &FilePath) -> ApiResult {
+  let staged = staging_path(file_path);
+  if state.storage.exists(&staged).await? {
+    state.storage.delete(&staged).await?;
+  }
+  Ok(())
+}
+
+async fn handle_payload_too_large(state: &ApiState, file_path: &FilePath) -> ApiResult<u64> {
+  remove_staged(state, file_path).await?;
   Err(ApiError::PayloadTooLarge(format!(
     "The maximum allowed size for uploaded files is {}MB.",
-    max_size / BYTE_TO_MEGABYTE
+    state.config.load().max_upload_bytes_size / BYTE_TO_MEGABYTE
   )))
 }
 
@@ -157,11 +503,15 @@ pub async fn info(
     .to_result(&file_path.to_string())?;
   if let Some(max) = meta.max_download {
     if meta.count_downloads >= max {
-      state.db.delete(file_path.clone()).await?;
+      state
+        .db
+        .remove_file(&*state.storage, vec![file_path.clone()])
+        .await?;
       return Err(ApiError::NotFoundError(format!("{file_path} not found",)));
     }
   }
   authorize_user(secret, &meta.secret)?;
+  check_complete(&file_path, &meta)?;
   Ok(meta)
 }
 
@@ -170,7 +520,8 @@ pub async fn fetch(
   code: &str,
   file_name: &str,
   secret: Option<Secret>,
-) -> ApiResult<ServeFile> {
+  headers: &HeaderMap,
+) -> ApiResult<FileContent> {
   let file_path = FilePath {
     code: code.to_string(),
     file_name: file_name.to_string(),
@@ -180,16 +531,139 @@ pub async fn fetch(
     .fetch(&file_path)?
     .to_result(&file_path.to_string())?;
   authorize_user(secret, &meta_data.secret)?;
+  check_complete(&file_path, &meta_data)?;
   if let Some(max) = meta_data.max_download {
     if meta_data.count_downloads >= max {
-      state.db.delete(file_path.clone()).await?;
+      state
+        .db
+        .remove_file(&*state.storage, vec![file_path.clone()])
+        .await?;
       return Err(ApiError::NotFoundError(format!("{file_path} not found")));
     }
   }
-  let mut updated_meta_data = meta_data.clone();
-  updated_meta_data.count_downloads += 1;
-  state.db.update(&file_path, meta_data, updated_meta_data)?;
-  Ok(read_file(&state.config, &file_path))
+  let mut file = read_file(state, file_path.clone(), &meta_data, headers).await?;
+  // Only the response that delivers the last byte counts, once that byte is read, so an
+  // interrupted download resumed with a range is charged once. HEAD and not modified
+  // responses read no body and are never counted.
+  let len = file.range.map_or(file.total_size, |range| range.len());
+  if file
+    .range
+    .is_none_or(|range| range.end + 1 == file.total_size)
+  {
+    let mut event = webhook::event(state, EventKind::Download, &file_path, &meta_data);
+    event.count_downloads += 1;
+    if len == 0 {
+      state.db.increment_downloads(&file_path)?;
+      state.webhooks.send(event);
+    } else {
+      let db = state.db.clone();
+      let webhooks = state.webhooks.clone();
+      file.reader = Box::pin(OnCompleteReader::new(file.reader, len, move || {
+        match db.increment_downloads(&file_path) {
+          Ok(_) => webhooks.send(event),
+          Err(err) => {
+            tracing::error!("Failed to count the download of {file_path}, Error: {err}")
+          }
+        }
+      }));
+    }
+  }
+  Ok(file)
+}
+
+pub async fn fetch_archive(
+  state: &ApiState,
+  code: &str,
+  secret: Option<Secret>,
+  format: ArchiveFormat,
+) -> ApiResult<ArchiveContent> {
+  // Every file of a bundle shares the secret of its manifest.
+  let manifest = list(state, code, secret)?;
+  let mut entries = Vec::with_capacity(manifest.file_names.len());
+  let mut events = Vec::with_capacity(manifest.file_names.len());
+  for file_name in manifest.file_names {
+    let file_path = FilePath {
+      code: code.to_string(),
+      file_name,
+    };
+    let Some(meta_data) = state.db.fetch(&file_path)? else {
+      continue;
+    };
+    if meta_data.state != UploadState::Complete {
+      continue;
+    }
+    if let Some(max) = meta_data.max_download {
+      if meta_data.count_downloads >= max {
+        state
+          .db
+          .remove_file(&*state.storage, vec![file_path])
+          .await?;
+        continue;
+      }
+    }
+    let blob_path = meta_data.blob_path(&file_path);
+    let size = state.storage.size(&blob_path).await?;
+    let mut event = webhook::event(state, EventKind::Download, &file_path, &meta_data);
+    event.count_downloads += 1;
+    events.push(event);
+    entries.push((file_path, blob_path, meta_data.created_at, size));
+  }
+  if entries.is_empty() {
+    return Err(ApiError::NotFoundError(format!("{code} not found")));
+  }
+  if format == ArchiveFormat::Zip {
+    let sizes = entries
+      .iter()
+      .map(|(file_path, _, _, size)| (file_path.file_name.as_str(), *size));
+    check_zip_limits(sizes).map_err(|err| {
+      ApiError::BadRequestError(format!(
+        "{err} Download the files as {code}.tar.gz instead."
+      ))
+    })?;
+  }
+  let storage = state.storage.clone();
+  let db = state.db.clone();
+  let webhooks = state.webhooks.clone();
+  // The archive is built while it is sent and downloads are only counted once the
+  // last byte has been produced.
+  let stream = async_stream::try_stream! {
+    let mut encoder = ArchiveEncoder::new(format);
+    let mut buf = vec![0u8; 64 * 1024];
+    for (file_path, blob_path, created_at, size) in &entries {
+      yield Bytes::from(encoder.start_entry(&file_path.file_name, *size, *created_at)?);
+      let mut reader = storage
+        .get(blob_path, None)
+        .await
+        .map_err(std::io::Error::other)?;
+      let mut written = 0;
+      loop {
+        let n = reader.read(&mut buf).await?;
+        if n == 0 {
+          break;
+        }
+        written += n as u64;
+        yield Bytes::from(encoder.write(&buf[..n])?);
+      }
+      if written != *size {
+        Err(std::io::Error::other(format!("{file_path} changed while it was archived")))?;
+      }
+      yield Bytes::from(encoder.finish_entry()?);
+    }
+    yield Bytes::from(encoder.finish()?);
+    for ((file_path, _, _, _), event) in entries.iter().zip(events) {
+      match db.increment_downloads(file_path) {
+        Ok(_) => webhooks.send(event),
+        Err(err) => {
+          tracing::error!("Failed to count the download of {file_path}, Error: {err}")
+        }
+      }
+    }
+  };
+  Ok(ArchiveContent {
+    code: code.to_string(),
+    format,
+    stream: Box::pin(stream),
+  })
 }
 
 pub async fn delete(
@@ -197,16 +671,24 @@ pub async fn delete(
   code: &str,
   file_name: &str,
   secret: Option<Secret>,
+  principal: Option<&Principal>,
 ) -> ApiResult<()> {
   let file_path = FilePath {
     code: code.to_string(),
     file_name: file_name.to_string(),
   };
   if let Some(meta) = state.db.fetch(&file_path)? {
-    if meta.manual_deletion {
+    // Owners and admins may delete a file even when manual deletion is disabled.
+    let is_manager = principal.is_some_and(|p| p.can_manage(meta.owner.as_deref()));
+    if meta.manual_deletion || is_manager {
       authorize_user(secret, &meta.secret)?;
-      tokio::fs::remove_file(get_fs_path(&state.config.fs.base_dir, &file_path)).await?;
-      state.db.delete(file_path).await?;
+      for (file_path, meta) in state
+        .db
+        .remove_file(&*state.storage, vec![file_path])
+        .await?
+      {
+        webhook::notify(state, EventKind::Delete, &file_path, &meta);
+      }
     } else {
       return Err(ApiError::PermissionDeniedError(format!(
         "{file_path} is not deletable"
@@ -216,8 +698,61 @@ pub async fn delete(
   Ok(())
 }
 
-pub fn read_file(config: &ApiConfig, file_path: &FilePath) -> ServeFile {
-  ServeFile::new(get_fs_path(&config.fs.base_dir, file_path))
+pub struct OwnedFile {
+  pub file_path: FilePath,
+  pub meta: MetaDataFile,
+}
+
+pub struct ArchiveContent {
+  pub code: String,
+  pub format: ArchiveFormat,
+  pub stream: BoxStream<'static, std::io::Result<Bytes>>,
+}
+
+pub struct FileContent {
+  pub file_path: FilePath,
+  pub etag: String,
+  pub digest: String,
+  pub total_size: u64,
+  pub range: Option<ByteRange>,
+  pub reader: StorageReader<'static>,
+}
+
+impl std::fmt::Debug for FileContent {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("FileContent")
+      .field("file_path", &self.file_path)
+      .field("etag", &self.etag)
+      .field("digest", &self.digest)
+      .field("total_size", &self.total_size)
+      .field("range", &self.range)
+      .finish_non_exhaustive()
+  }
+}
+
+pub async fn read_file(
+  state: &ApiState,
+  file_path: FilePath,
+  meta: &MetaDataFile,
+  headers: &HeaderMap,
+) -> ApiResult<FileContent> {
+  let etag = meta.etag.clone();
+  let blob_path = meta.blob_path(&file_path);
+  let total_size = state.storage.size(&blob_path).await?;
+  let range = if if_range_matches(headers, &etag) {
+    parse_range(headers, total_size)?
+  } else {
+    None
+  };
+  let reader = state.storage.get(&blob_path, range).await?;
+  Ok(FileContent {
+    file_path,
+    etag,
+    digest: meta.digest.clone(),
+    total_size,
+    range,
+    reader,
+  })
 }
 
 pub fn authorize_user(secret: Option<Secret>, secret_hash: &Option<SecretHash>) -> ApiResult<()> {
@@ -248,11 +783,97 @@ pub fn calc_expiration_date(now: DateTime<Utc>, expire_secs: i64) -> ApiResult<D
 mod tests {
 
   use super::*;
-  use crate::util::{multipart::create_multipart_request, test::StateTestContext};
+  use crate::util::{
+    multipart::{create_multi_file_multipart_request, create_multipart_request},
+    test::StateTestContext,
+  };
   use fake::{Fake, Faker};
   use pf_sdk::assert_err;
   use test_context::test_context;
 
+  #[test_context(StateTestContext)]
+  #[tokio::test]
+  async fn test_purge_pending_uploads(ctx: &mut StateTestContext) {
+    let state = &ctx.state;
+    let now = Utc::now();
+    let timeout = chrono::Duration::seconds(state.config.load().pending_upload_timeout_secs as i64);
+    let mut paths = vec![];
+    for (created_at, upload_state) in [
+      (
+        now - timeout - chrono::Duration::seconds(1),
+        UploadState::Pending,
+      ),
+      (now, UploadState::Failed),
+      (now, UploadState::Pending),
+    ] {
+      let file_path: FilePath = Faker.fake();
+      let mut meta: MetaDataFile = Faker.fake();
+      meta.created_at = created_at;
+      meta.expire_date_time = now + chrono::Duration::hours(1);
+      meta.secret = None;
+      meta.max_download = None;
+      meta.digest = String::new();
+      meta.state = upload_state;
+      // The content is written first, the garbage collector may purge the record at once.
+      state
+        .storage
+        .put(&staging_path(&file_path), Box::pin(&b"partial"[..]))
+        .await
+        .unwrap();
+      state.db.store(file_path.clone(), meta).await.unwrap();
+      paths.push(file_path);
+    }
+    let wakeup = purge_pending(state).await.unwrap();
+    assert!(wakeup.is_some_and(|d| d <= timeout.to_std().unwrap()));
+    for file_path in &paths[..2] {
+      assert!(state.db.fetch(file_path).unwrap().is_none());
+      let staged = staging_path(file_path);
+      assert!(!state.storage.exists(&staged).await.unwrap());
+    }
+    let pending = &paths[2];
+    let result = fetch(
+      state,
+      &pending.code,
+      &pending.file_name,
+      None,
+      &HeaderMap::new(),
+    )
+    .await;
+    assert!(matches!(result, Err(ApiError::NotAvailableError(_))));
+    let result = info(state, &pending.code, &pending.file_name, None).await;
+    assert!(matches!(result, Err(ApiError::NotAvailableError(_))));
+  }
+
+  #[test_context(StateTestContext)]
+  #[tokio::test]
+  async fn test_purge_keeps_upload_in_progress(ctx: &mut StateTestContext) {
+    let state = &ctx.state;
+    let timeout = chrono::Duration::seconds(state.config.load().pending_upload_timeout_secs as i64);
+    let file_path: FilePath = Faker.fake();
+    let mut meta: MetaDataFile = Faker.fake();
+    meta.created_at = Utc::now() - timeout - chrono::Duration::seconds(1);
+    meta.expire_date_time = Utc::now() + chrono::Duration::hours(1);
+    meta.digest = String::new();
+    meta.state = UploadState::Pending;
+    // The lease is taken first, the garbage collector may purge the record at once.
+    let lease = state.upload_leases.acquire(&file_path).unwrap();
+    let staged = staging_path(&file_path);
+    state
+      .storage
+      .put(&staged, Box::pin(&b"partial"[..]))
+      .await
+      .unwrap();
+    state.db.store(file_path.clone(), meta).await.unwrap();
+    lease.renew();
+    let wakeup = purge_pending(state).await.unwrap();
+    assert!(wakeup.is_some_and(|d| d <= timeout.to_std().unwrap()));
+    assert!(state.db.exist(&file_path).unwrap());
+    drop(lease);
+    purge_pending(state).await.unwrap();
+    assert!(!state.db.exist(&file_path).unwrap());
+    assert!(!state.storage.exists(&staged).await.unwrap());
+  }
+
   #[test_context(StateTestContext)]
   #[tokio::test]
   async fn test_delete_unprivileged_file(ctx: &mut StateTestContext) {
@@ -262,11 +883,22 @@ mod tests {
       expire_secs: None,
       allow_manual_deletion: Some(false),
       qr_code_format: None,
+      sha256: None,
     };
     let file_name = format!("{}.txt", Faker.fake::<String>());
     let multipart = create_multipart_request(&file_name, "data").await.unwrap();
-    let (file_path, _) = store(&ctx.state, &param, None, multipart).await.unwrap();
-    let result = delete(&ctx.state, &file_path.code, &file_path.file_name, None).await;
+    let (mut file_paths, _) = store(&ctx.state, &param, None, None, multipart)
+      .await
+      .unwrap();
+    let file_path = file_paths.remove(0);
+    let result = delete(
+      &ctx.state,
+      &file_path.code,
+      &file_path.file_name,
+      None,
+      None,
+    )
+    .await;
     assert_err!(result, |e: &ApiError| e.to_string()
       == format!("{}/{file_name} is not deletable", file_path.code));
   }
@@ -280,14 +912,34 @@ mod tests {
       expire_secs: None,
       allow_manual_deletion: Some(false),
       qr_code_format: None,
+      sha256: None,
     };
     let file_name = format!("{}.txt", Faker.fake::<String>());
     let multipart = create_multipart_request(&file_name, "data").await.unwrap();
-    let (file_path, _) = store(&ctx.state, &param, None, multipart).await.unwrap();
-    fetch(&ctx.state, &file_path.code, &file_path.file_name, None)
+    let (mut file_paths, _) = store(&ctx.state, &param, None, None, multipart)
+      .await
+      .unwrap();
+    let file_path = file_paths.remove(0);
+    let mut file = fetch(
+      &ctx.state,
+      &file_path.code,
+      &file_path.file_name,
+      None,
+      &HeaderMap::new(),
+    )
+    .await
+    .unwrap();
+    tokio::io::copy(&mut file.reader, &mut tokio::io::sink())
       .await
       .unwrap();
-    let result = fetch(&ctx.state, &file_path.code, &file_path.file_name, None).await;
+    let result = fetch(
+      &ctx.state,
+      &file_path.code,
+      &file_path.file_name,
+      None,
+      &HeaderMap::new(),
+    )
+    .await;
     assert_err!(result, |e: &ApiError| e.to_string()
       == format!(
         "resource not found: {}/{file_name} not found",
@@ -305,16 +957,32 @@ mod tests {
       expire_secs: None,
       allow_manual_deletion: Some(true),
       qr_code_format: None,
+      sha256: None,
     };
     let file_name = format!("{}.txt", Faker.fake::<String>());
     let multipart = create_multipart_request(&file_name, "data").await.unwrap();
-    let (file_path, _) = store(&ctx.state, &param, Some(secret), multipart)
+    let (mut file_paths, _) = store(&ctx.state, &param, Some(secret), None, multipart)
       .await
       .unwrap();
-    let result = delete(&ctx.state, &file_path.code, &file_path.file_name, None).await;
+    let file_path = file_paths.remove(0);
+    let result = delete(
+      &ctx.state,
+      &file_path.code,
+      &file_path.file_name,
+      None,
+      None,
+    )
+    .await;
     assert_err!(result, |e: &ApiError| e.to_string()
       == "Authorization header required.");
-    let result = fetch(&ctx.state, &file_path.code, &file_path.file_name, None).await;
+    let result = fetch(
+      &ctx.state,
+      &file_path.code,
+      &file_path.file_name,
+      None,
+      &HeaderMap::new(),
+    )
+    .await;
     assert_err!(result, |e: &ApiError| e.to_string()
       == "Authorization header required.");
   }
@@ -329,18 +997,21 @@ mod tests {
       expire_secs: None,
       allow_manual_deletion: Some(true),
       qr_code_format: None,
+      sha256: None,
     };
     let file_name = format!("{}.txt", Faker.fake::<String>());
     let multipart = create_multipart_request(&file_name, "data").await.unwrap();
-    let (file_path, _) = store(&ctx.state, &param, Some(secret), multipart)
+    let (mut file_paths, _) = store(&ctx.state, &param, Some(secret), None, multipart)
       .await
       .unwrap();
+    let file_path = file_paths.remove(0);
     secret = Secret::new(Faker.fake::<String>());
     let result = delete(
       &ctx.state,
       &file_path.code,
       &file_path.file_name,
       Some(secret.clone()),
+      None,
     )
     .await;
     assert_err!(result, |e: &ApiError| e.to_string()
@@ -351,6 +1022,7 @@ mod tests {
       &file_path.code,
       &file_path.file_name,
       Some(secret),
+      &HeaderMap::new(),
     )
     .await;
     assert_err!(result, |e: &ApiError| e.to_string()
@@ -367,10 +1039,14 @@ mod tests {
       expire_secs: None,
       allow_manual_deletion: Some(false),
       qr_code_format: None,
+      sha256: None,
     };
     let file_name = format!("{}.txt", Faker.fake::<String>());
     let multipart = create_multipart_request(&file_name, "data").await.unwrap();
-    let (file_path, _) = store(&ctx.state, &param, None, multipart).await.unwrap();
+    let (mut file_paths, _) = store(&ctx.state, &param, None, None, multipart)
+      .await
+      .unwrap();
+    let file_path = file_paths.remove(0);
     assert_eq!(file_path.code.len(), code_length);
   }
 
@@ -378,7 +1054,14 @@ mod tests {
   #[tokio::test]
   async fn test_file_does_not_exist_error(ctx: &mut StateTestContext) {
     let file_path = Faker.fake::<FilePath>();
-    let result = fetch(&ctx.state, &file_path.code, &file_path.file_name, None).await;
+    let result = fetch(
+      &ctx.state,
+      &file_path.code,
+      &file_path.file_name,
+      None,
+      &HeaderMap::new(),
+    )
+    .await;
     assert_err!(result, |e: &ApiError| e.to_string()
       == format!(
         "resource not found: {}/{} not found",
@@ -391,4 +1074,161 @@ mod tests {
         file_path.code, file_path.file_name
       ));
   }
+
+  #[test_context(StateTestContext)]
+  #[tokio::test]
+  async fn test_store_multiple_files_in_one_bundle(ctx: &mut StateTestContext) {
+    let param = UploadQueryParam {
+      max_download: None,
+      code_length: None,
+      expire_secs: None,
+      allow_manual_deletion: Some(true),
+      qr_code_format: None,
+      sha256: None,
+    };
+    let multipart =
+      create_multi_file_multipart_request(&[("first.txt", "first"), ("second.txt", "second")])
+        .await
+        .unwrap();
+    let (file_paths, _) = store(&ctx.state, &param, None, None, multipart)
+      .await
+      .unwrap();
+    assert_eq!(file_paths.len(), 2);
+    assert_eq!(file_paths[0].code, file_paths[1].code);
+    let manifest = list(&ctx.state, &file_paths[0].code, None).unwrap();
+    assert_eq!(manifest.file_names, vec!["first.txt", "second.txt"]);
+    delete(&ctx.state, &file_paths[0].code, "first.txt", None, None)
+      .await
+      .unwrap();
+    let manifest = list(&ctx.state, &file_paths[0].code, None).unwrap();
+    assert_eq!(manifest.file_names, vec!["second.txt"]);
+  }
+
+  #[test_context(StateTestContext)]
+  #[tokio::test]
+  async fn test_store_same_content_once(ctx: &mut StateTestContext) {
+    let multipart =
+      create_multi_file_multipart_request(&[("first.txt", "same"), ("second.txt", "same")])
+        .await
+        .unwrap();
+    let (file_paths, _) = store(
+      &ctx.state,
+      &UploadQueryParam::default(),
+      None,
+      None,
+      multipart,
+    )
+    .await
+    .unwrap();
+    let first = ctx.state.db.fetch(&file_paths[0]).unwrap().unwrap();
+    let second = ctx.state.db.fetch(&file_paths[1]).unwrap().unwrap();
+    assert_eq!(first.digest, second.digest);
+    assert_eq!(ctx.state.db.fetch_blob_refs(&first.digest).unwrap(), 2);
+    let blob_path = first.blob_path(&file_paths[0]);
+    assert!(ctx.state.storage.exists(&blob_path).await.unwrap());
+    assert!(!ctx.state.storage.exists(&file_paths[0]).await.unwrap());
+    delete(&ctx.state, &file_paths[0].code, "first.txt", None, None)
+      .await
+      .unwrap();
+    assert_eq!(ctx.state.db.fetch_blob_refs(&first.digest).unwrap(), 1);
+    let mut file = fetch(
+      &ctx.state,
+      &file_paths[1].code,
+      "second.txt",
+      None,
+      &HeaderMap::new(),
+    )
+    .await
+    .unwrap();
+    let mut content = String::new();
+    file.reader.read_to_string(&mut content).await.unwrap();
+    assert_eq!(content, "same");
+    delete(&ctx.state, &file_paths[1].code, "second.txt", None, None)
+      .await
+      .unwrap();
+    assert_eq!(ctx.state.db.fetch_blob_refs(&first.digest).unwrap(), 0);
+    assert!(!ctx.state.storage.exists(&blob_path).await.unwrap());
+  }
+
+  #[test_context(StateTestContext)]
+  #[tokio::test]
+  async fn test_checksum_mismatch_leaves_no_file(ctx: &mut StateTestContext) {
+    let file_path: FilePath = Faker.fake();
+    let result = put_blob(
+      &ctx.state,
+      &file_path,
+      Box::pin(b"data".as_slice()),
+      Some(&"0".repeat(64)),
+    )
+    .await;
+    assert_err!(result, |e: &ApiError| matches!(
+      e,
+      ApiError::ChecksumMismatchError(_)
+    ));
+    let staged = staging_path(&file_path);
+    assert!(!ctx.state.storage.exists(&staged).await.unwrap());
+    assert!(!ctx.state.storage.exists(&file_path).await.unwrap());
+  }
+
+  #[test_context(StateTestContext)]
+  #[tokio::test]
+  async fn test_list_owned_files(ctx: &mut StateTestContext) {
+    let param = UploadQueryParam {
+      max_download: Some(1),
+      ..Default::default()
+    };
+    let owner = Some("alice".to_string());
+    for file_name in ["a.txt", "b.txt", "c.txt"] {
+      let multipart = create_multipart_request(file_name, "data").await.unwrap();
+      store(&ctx.state, &param, None, owner.clone(), multipart)
+        .await
+        .unwrap();
+    }
+    let multipart = create_multipart_request("d.txt", "data").await.unwrap();
+    store(&ctx.state, &param, None, None, multipart)
+      .await
+      .unwrap();
+    // An unfinished upload has no content to list yet.
+    let mut meta: MetaDataFile = Faker.fake();
+    meta.owner = owner.clone();
+    meta.max_download = None;
+    meta.expire_date_time = Utc::now() + chrono::Duration::hours(1);
+    meta.created_at = Utc::now();
+    meta.digest = String::new();
+    meta.state = UploadState::Pending;
+    ctx.state.db.store(Faker.fake(), meta).await.unwrap();
+    let (files, total) = list_owned(&ctx.state, "alice", 2, 2).unwrap();
+    assert_eq!(total, 3);
+    assert_eq!(files.len(), 1);
+    assert_eq!(files[0].meta.size, 4);
+    let (files, _) = list_owned(&ctx.state, "alice", 1, 2).unwrap();
+    ctx
+      .state
+      .db
+      .increment_downloads(&files[0].file_path)
+      .unwrap();
+    let (_, total) = list_owned(&ctx.state, "alice", 1, 2).unwrap();
+    assert_eq!(total, 2);
+  }
+
+  #[test_context(StateTestContext)]
+  #[tokio::test]
+  async fn test_store_duplicate_file_names_error(ctx: &mut StateTestContext) {
+    let param = UploadQueryParam {
+      max_download: None,
+      code_length: None,
+      expire_secs: None,
+      allow_manual_deletion: None,
+      qr_code_format: None,
+      sha256: None,
+    };
+    let multipart = create_multi_file_multipart_request(&[("same.txt", "a"), ("same.txt", "b")])
+      .await
+      .unwrap();
+    let result = store(&ctx.state, &param, None, None, multipart).await;
+    assert_err!(result, |e: &ApiError| matches!(
+      e,
+      ApiError::BadRequestError(_)
+    ));
+  }
 }
@@ -0,0 +1,424 @@
+use std::{
+  collections::{BTreeSet, HashMap},
+  fmt,
+  path::{Path, PathBuf},
+};
+
+use chrono::Utc;
+
+use crate::{
+  configure::{RepairMode, StorageBackendKind},
+  database::{
+    blob::{staging_path, BLOB_CODE, STAGING_CODE},
+    file_path::FilePath,
+    manifest::Manifest,
+    meta_data_file::{MetaDataFile, UploadState},
+  },
+  error::result::ApiResult,
+  server::ApiState,
+  service::file::calc_expiration_date,
+  util::{path::get_fs_path, reader::HashReader},
+};
+
+/// What a consistency check found, and how much of it was repaired.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct FsckReport {
+  /// Meta data whose content is missing or whose upload never finished.
+  pub missing_files: Vec<FilePath>,
+  /// Stored files that no meta data points to.
+  pub orphan_files: Vec<PathBuf>,
+  /// Blobs as (digest, recorded, found) when the recorded reference count is wrong.
+  pub wrong_refs: Vec<(String, u64, u64)>,
+  pub repaired: usize,
+}
+
+impl FsckReport {
+  pub fn is_clean(&self) -> bool {
+    self.missing_files.is_empty() && self.orphan_files.is_empty() && self.wrong_refs.is_empty()
+  }
+}
+
+impl fmt::Display for FsckReport {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    for file_path in &self.missing_files {
+      writeln!(f, "missing file: {file_path}")?;
+    }
+    for path in &self.orphan_files {
+      writeln!(f, "orphan file: {}", path.display())?;
+    }
+    for (digest, recorded, found) in &self.wrong_refs {
+      writeln!(
+        f,
+        "wrong references: blob {digest} has {recorded} recorded and {found} found"
+      )?;
+    }
+    write!(
+      f,
+      "{} missing files, {} orphan files, {} wrong reference counts, {} repaired",
+      self.missing_files.len(),
+      self.orphan_files.len(),
+      self.wrong_refs.len(),
+      self.repaired
+    )
+  }
+}
+
+/// Reconciles the meta data in the database with the stored files. Uploads in progress
+/// look like missing files, so it only runs while the server does not serve requests.
+pub async fn check(state: &ApiState, repair: RepairMode) -> ApiResult<FsckReport> {
+  let mut report = FsckReport::default();
+  let mut refs = HashMap::<String, u64>::new();
+  let mut known = BTreeSet::new();
+  for (file_path, meta) in state.db.fetch_all()? {
+    let stored = meta.blob_path(&file_path);
+    if meta.state == UploadState::Complete && state.storage.exists(&stored).await? {
+      if meta.digest.is_empty() {
+        // A legacy file is kept under its own path until it is moved into the blobs.
+        known.insert(stored);
+      } else {
+        *refs.entry(meta.digest).or_default() += 1;
+      }
+      continue;
+    }
+    report.missing_files.push(file_path.clone());
+    if repair == RepairMode::None {
+      // The partial content of an unfinished upload is kept in the staging directory.
+      known.insert(staging_path(&file_path));
+      continue;
+    }
+    // Blob references are counted again below, so only the staged content is deleted.
+    state.db.delete(file_path.clone()).await?;
+    let staged = staging_path(&file_path);
+    if meta.digest.is_empty() && state.storage.exists(&staged).await? {
+      state.storage.delete(&staged).await?;
+    }
+    report.repaired += 1;
+  }
+  let recorded = state
+    .db
+    .fetch_blobs()?
+    .into_iter()
+    .collect::<HashMap<_, _>>();
+  let digests = recorded.keys().chain(refs.keys()).collect::<BTreeSet<_>>();
+  for digest in digests {
+    let recorded = recorded.get(digest).copied().unwrap_or(0);
+    let found = refs.get(digest).copied().unwrap_or(0);
+    if recorded == found {
+      continue;
+    }
+    report.wrong_refs.push((digest.clone(), recorded, found));
+    if repair != RepairMode::None {
+      state.db.set_blob_refs(digest, found).await?;
+      report.repaired += 1;
+    }
+  }
+  if state.config.load().storage.backend == StorageBackendKind::Local {
+    for file_path in stored_files(state).await? {
+      let is_orphan = if file_path.code == BLOB_CODE {
+        !refs.contains_key(&file_path.file_name)
+      } else {
+        !known.contains(&file_path)
+      };
+      if !is_orphan {
+        continue;
+      }
+      report
+        .orphan_files
+        .push(get_fs_path(&state.config.load().fs.base_dir, &file_path));
+      match repair {
+        RepairMode::None => continue,
+        // Staged content is incomplete, so it is never registered.
+        RepairMode::Register
+          if file_path.code != BLOB_CODE && !file_path.code.starts_with(STAGING_CODE) =>
+        {
+          let digest = register(state, &file_path).await?;
+          *refs.entry(digest).or_default() += 1;
+        }
+        _ => state.storage.delete(&file_path).await?,
+      }
+      report.repaired += 1;
+    }
+  }
+  // Resumable uploads are always kept on the local file system.
+  for path in list_files(&state.config.load().fs.get_upload_dir()).await? {
+    let Some(id) = path.file_name().and_then(|name| name.to_str()) else {
+      continue;
+    };
+    if state.db.fetch_upload(id)?.is_some() {
+      continue;
+    }
+    if repair != RepairMode::None {
+      tokio::fs::remove_file(&path).await?;
+      report.repaired += 1;
+    }
+    report.orphan_files.push(path);
+  }
+  Ok(report)
+}
+
+pub async fn on_startup(state: &ApiState) -> ApiResult {
+  let report = check(state, state.config.load().fsck.repair).await?;
+  if report.is_clean() {
+    tracing::info!("The consistency check found no problems.");
+  } else {
+    tracing::warn!("The consistency check found problems:\n{report}");
+  }
+  Ok(())
+}
+
+/// Gives a stored file without meta data a new record that expires after the default time.
+async fn register(state: &ApiState, file_path: &FilePath) -> ApiResult<String> {
+  let now = Utc::now();
+  let expire_date_time = calc_expiration_date(now, state.config.load().default_expire_secs as i64)?;
+  let mut reader = HashReader::new(state.storage.get(file_path, None).await?);
+  let size = tokio::io::copy(&mut reader, &mut tokio::io::sink()).await?;
+  let digest = reader.digest();
+  let meta = MetaDataFile {
+    created_at: now,
+    expire_date_time,
+    secret: None,
+    manual_deletion: state.config.load().allow_manual_deletion,
+    max_download: None,
+    count_downloads: 0,
+    etag: cuid2::create_id(),
+    size,
+    digest: digest.clone(),
+    owner: None,
+    state: UploadState::Complete,
+  };
+  state
+    .db
+    .store_blob(&*state.storage, file_path, &digest)
+    .await?;
+  state.db.store(file_path.clone(), meta).await?;
+  match state.db.fetch_manifest(&file_path.code)? {
+    Some(mut manifest) => {
+      manifest.file_names.push(file_path.file_name.clone());
+      state.db.update_manifest(&file_path.code, &manifest)?;
+    }
+    None => {
+      let manifest = Manifest {
+        file_names: vec![file_path.file_name.clone()],
+        created_at: now,
+        expire_date_time,
+        secret: None,
+        owner: None,
+      };
+      state.db.store_manifest(&file_path.code, &manifest)?;
+    }
+  }
+  tracing::info!("The orphan file {file_path} is registered with the digest {digest}.");
+  Ok(digest)
+}
+
+/// Files of the local storage as code and file name, the directories of the server
+/// itself are skipped.
+async fn stored_files(state: &ApiState) -> ApiResult<Vec<FilePath>> {
+  let config = state.config.load();
+  let mut skipped = vec![
+    config.fs.get_upload_dir(),
+    config.fs.get_audit_dir(),
+    config.db.path_dir.clone(),
+  ];
+  skipped.extend(
+    config
+      .log
+      .file
+      .as_ref()
+      .and_then(|file| file.parent())
+      .map(Path::to_path_buf),
+  );
+  let skipped = skipped
+    .iter()
+    .filter_map(|dir| std::fs::canonicalize(dir).ok())
+    .collect::<Vec<_>>();
+  let mut files = vec![];
+  for dir in list_dirs(&config.fs.base_dir).await? {
+    if skipped.contains(&std::fs::canonicalize(&dir)?) {
+      continue;
+    }
+    let Some(code) = dir.file_name().and_then(|name| name.to_str()) else {
+      continue;
+    };
+    if code == STAGING_CODE {
+      for dir in list_dirs(&dir).await? {
+        let Some(code) = dir.file_name().and_then(|name| name.to_str()) else {
+          continue;
+        };
+        files.extend(files_of(&dir, &format!("{STAGING_CODE}/{code}")).await?);
+      }
+    } else {
+      files.extend(files_of(&dir, code).await?);
+    }
+  }
+  files.sort();
+  Ok(files)
+}
+
+async fn files_of(dir: &Path, code: &str) -> ApiResult<Vec<FilePath>> {
+  let mut files = vec![];
+  for path in list_files(dir).await? {
+    let Some(file_name) = path.file_name().and_then(|name| name.to_str()) else {
+      tracing::warn!("Skip the file {} with a non UTF-8 name.", path.display());
+      continue;
+    };
+    files.push(FilePath {
+      code: code.to_string(),
+      file_name: file_name.to_string(),
+    });
+  }
+  Ok(files)
+}
+
+async fn list_dirs(dir: &Path) -> ApiResult<Vec<PathBuf>> {
+  list(dir, true).await
+}
+
+async fn list_files(dir: &Path) -> ApiResult<Vec<PathBuf>> {
+  list(dir, false).await
+}
+
+async fn list(dir: &Path, dirs: bool) -> ApiResult<Vec<PathBuf>> {
+  let mut entries = match tokio::fs::read_dir(dir).await {
+    Ok(entries) => entries,
+    Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+    Err(err) => return Err(err.into()),
+  };
+  let mut paths = vec![];
+  while let Some(entry) = entries.next_entry().await? {
+    if entry.file_type().await?.is_dir() == dirs {
+      paths.push(entry.path());
+    }
+  }
+  paths.sort();
+  Ok(paths)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    database::blob::blob_path,
+    service::file::store,
+    util::{multipart::create_multipart_request, test::StateTestContext},
+  };
+  use fake::{Fake, Faker};
+  use pf_sdk::dto::request::UploadQueryParam;
+  use sha2::{Digest, Sha256};
+  use test_context::test_context;
+
+  async fn put(state: &ApiState, file_path: &FilePath, content: &'static [u8]) {
+    state
+      .storage
+      .put(file_path, Box::pin(content))
+      .await
+      .unwrap();
+  }
+
+  fn file_path(code: &str, file_name: &str) -> FilePath {
+    FilePath {
+      code: code.to_string(),
+      file_name: file_name.to_string(),
+    }
+  }
+
+  #[test_context(StateTestContext)]
+  #[tokio::test]
+  async fn test_fsck_reports_and_deletes(ctx: &mut StateTestContext) {
+    let state = &ctx.state;
+    let multipart = create_multipart_request("file.txt", "data").await.unwrap();
+    let (file_paths, _) = store(state, &UploadQueryParam::default(), None, None, multipart)
+      .await
+      .unwrap();
+    let digest = state.db.fetch(&file_paths[0]).unwrap().unwrap().digest;
+    state.db.set_blob_refs(&digest, 3).await.unwrap();
+    let orphan = file_path("orphan", "file.txt");
+    put(state, &orphan, b"orphan").await;
+    let orphan_blob = blob_path(&"f".repeat(64));
+    put(state, &orphan_blob, b"blob").await;
+    let staged = staging_path(&file_path("staged", "file.txt"));
+    put(state, &staged, b"staged").await;
+    let upload_dir = state.config.load().fs.get_upload_dir();
+    tokio::fs::create_dir_all(&upload_dir).await.unwrap();
+    tokio::fs::write(upload_dir.join("upload"), b"upload")
+      .await
+      .unwrap();
+    let missing = file_path("missing", "file.txt");
+    let mut meta: MetaDataFile = Faker.fake();
+    meta.expire_date_time = Utc::now() + chrono::Duration::hours(1);
+    meta.digest = "0".repeat(64);
+    meta.state = UploadState::Complete;
+    state.db.store(missing.clone(), meta).await.unwrap();
+
+    let report = check(state, RepairMode::None).await.unwrap();
+    let base_dir = &state.config.load().fs.base_dir;
+    assert_eq!(
+      report,
+      FsckReport {
+        missing_files: vec![missing.clone()],
+        orphan_files: vec![
+          get_fs_path(base_dir, &orphan_blob),
+          get_fs_path(base_dir, &staged),
+          get_fs_path(base_dir, &orphan),
+          upload_dir.join("upload"),
+        ],
+        wrong_refs: vec![(digest.clone(), 3, 1)],
+        repaired: 0,
+      }
+    );
+    let report = check(state, RepairMode::Delete).await.unwrap();
+    assert_eq!(report.repaired, 6);
+    assert!(check(state, RepairMode::None).await.unwrap().is_clean());
+    assert!(state.db.fetch(&missing).unwrap().is_none());
+    assert!(!state.storage.exists(&orphan).await.unwrap());
+    assert!(!state.storage.exists(&orphan_blob).await.unwrap());
+    assert!(!state.storage.exists(&staged).await.unwrap());
+    assert!(state.storage.exists(&blob_path(&digest)).await.unwrap());
+    assert_eq!(state.db.fetch_blob_refs(&digest).unwrap(), 1);
+  }
+
+  #[test_context(StateTestContext)]
+  #[tokio::test]
+  async fn test_fsck_keeps_legacy_file(ctx: &mut StateTestContext) {
+    let state = &ctx.state;
+    let legacy = file_path("legacy", "file.txt");
+    put(state, &legacy, b"legacy").await;
+    let mut meta: MetaDataFile = Faker.fake();
+    meta.expire_date_time = Utc::now() + chrono::Duration::hours(1);
+    meta.digest = String::new();
+    meta.state = UploadState::Complete;
+    state.db.store(legacy.clone(), meta).await.unwrap();
+    let report = check(state, RepairMode::Delete).await.unwrap();
+    assert!(report.is_clean(), "report: {report}");
+    assert!(state.db.fetch(&legacy).unwrap().is_some());
+    assert!(state.storage.exists(&legacy).await.unwrap());
+  }
+
+  #[test_context(StateTestContext)]
+  #[tokio::test]
+  async fn test_fsck_registers_orphan_file(ctx: &mut StateTestContext) {
+    let state = &ctx.state;
+    let orphan = file_path("orphan", "file.txt");
+    put(state, &orphan, b"data").await;
+    let staged = file_path("staged", "file.txt");
+    put(state, &staging_path(&staged), b"partial").await;
+    let report = check(state, RepairMode::Register).await.unwrap();
+    assert_eq!(report.orphan_files.len(), 2);
+    assert_eq!(report.repaired, 2);
+    assert!(state.db.fetch(&staged).unwrap().is_none());
+    assert!(!state.storage.exists(&staging_path(&staged)).await.unwrap());
+    let meta = state.db.fetch(&orphan).unwrap().unwrap();
+    assert_eq!(meta.size, 4);
+    assert_eq!(meta.digest, hex::encode(Sha256::digest(b"data")));
+    assert_eq!(
+      state
+        .db
+        .fetch_manifest("orphan")
+        .unwrap()
+        .unwrap()
+        .file_names,
+      vec!["file.txt".to_string()]
+    );
+    assert!(check(state, RepairMode::None).await.unwrap().is_clean());
+  }
+}
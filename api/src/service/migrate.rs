@@ -0,0 +1,180 @@
+use crate::{
+  database::{
+    file_path::FilePath,
+    manifest::Manifest,
+    meta_data_file::{MetaDataFile, UploadState},
+  },
+  error::result::ApiResult,
+  server::ApiState,
+  util::reader::HashReader,
+};
+
+/// Moves the content of the files stored before it was kept by digest into the blobs. The
+/// records get their size and digest, and their code a manifest. A file whose content is
+/// missing is left to the consistency check.
+pub async fn legacy_files(state: &ApiState) -> ApiResult<usize> {
+  let mut moved = 0;
+  for (file_path, meta) in state.db.fetch_all()? {
+    if meta.state != UploadState::Complete || !meta.digest.is_empty() {
+      continue;
+    }
+    match move_legacy_file(state, &file_path, &meta).await {
+      Ok(()) => moved += 1,
+      Err(err) => tracing::warn!("Failed to move the legacy file {file_path}, Error: {err}"),
+    }
+  }
+  if moved > 0 {
+    tracing::info!("{moved} legacy files are moved into the blobs.");
+  }
+  Ok(moved)
+}
+
+async fn move_legacy_file(
+  state: &ApiState,
+  file_path: &FilePath,
+  meta: &MetaDataFile,
+) -> ApiResult {
+  let mut reader = HashReader::new(state.storage.get(file_path, None).await?);
+  let size = tokio::io::copy(&mut reader, &mut tokio::io::sink()).await?;
+  let digest = reader.digest();
+  state
+    .db
+    .store_blob(&*state.storage, file_path, &digest)
+    .await?;
+  state.db.modify(file_path, |meta| {
+    meta.size = size;
+    meta.digest = digest.clone();
+  })?;
+  match state.db.fetch_manifest(&file_path.code)? {
+    Some(manifest) if manifest.file_names.contains(&file_path.file_name) => {}
+    Some(mut manifest) => {
+      manifest.file_names.push(file_path.file_name.clone());
+      state.db.update_manifest(&file_path.code, &manifest)?;
+    }
+    None => {
+      let manifest = Manifest {
+        file_names: vec![file_path.file_name.clone()],
+        created_at: meta.created_at,
+        expire_date_time: meta.expire_date_time,
+        secret: meta.secret.clone(),
+        owner: None,
+      };
+      state.db.store_manifest(&file_path.code, &manifest)?;
+    }
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use std::path::{Path, PathBuf};
+
+  use axum::http::HeaderMap;
+  use chrono::{DateTime, Utc};
+  use serde::Serialize;
+  use tokio::io::AsyncReadExt;
+
+  use super::*;
+  use crate::{
+    configure::{ApiConfig, CONFIG},
+    database::blob::blob_path,
+    service,
+    util::{path::get_fs_path, secret::SecretHash},
+  };
+
+  // The record as the first release wrote it.
+  #[derive(Serialize)]
+  struct BaselineMetaDataFile {
+    created_at: DateTime<Utc>,
+    expire_date_time: DateTime<Utc>,
+    secret: Option<SecretHash>,
+    manual_deletion: bool,
+    max_download: Option<u32>,
+    count_downloads: u32,
+  }
+
+  // A dropped database releases its lock once the background flush has stopped.
+  async fn open_state(config: &ApiConfig) -> ApiState {
+    for _ in 0..50 {
+      match ApiState::new(config.clone()) {
+        Ok(state) => return state,
+        Err(_) => tokio::time::sleep(std::time::Duration::from_millis(100)).await,
+      }
+    }
+    ApiState::new(config.clone()).unwrap()
+  }
+
+  #[tokio::test]
+  async fn test_open_database_of_baseline_format() {
+    let workspace = Path::new("test-dump").join(PathBuf::from(cuid2::create_id()));
+    let mut config = CONFIG.clone();
+    config.fs.base_dir = workspace.join("files");
+    config.db.path_dir = workspace.join("db");
+    let file_path = FilePath {
+      code: "code".to_string(),
+      file_name: "file.txt".to_string(),
+    };
+    let content = b"Hello World!".to_vec();
+    let fs_path = get_fs_path(&config.fs.base_dir, &file_path);
+    tokio::fs::create_dir_all(fs_path.parent().unwrap())
+      .await
+      .unwrap();
+    tokio::fs::write(&fs_path, &content).await.unwrap();
+    let now = Utc::now();
+    let baseline = BaselineMetaDataFile {
+      created_at: now,
+      expire_date_time: now + chrono::Duration::hours(1),
+      secret: None,
+      manual_deletion: true,
+      max_download: Some(2),
+      count_downloads: 1,
+    };
+    {
+      let db = sled::Config::new()
+        .path(&config.db.path_dir)
+        .flush_every_ms(None)
+        .open()
+        .unwrap();
+      db.insert(
+        bincode::serialize(&file_path).unwrap(),
+        bincode::serialize(&baseline).unwrap(),
+      )
+      .unwrap();
+      db.flush().unwrap();
+    }
+
+    let state = open_state(&config).await;
+    let meta = state.db.fetch(&file_path).unwrap().unwrap();
+    assert_eq!(meta.count_downloads, 1);
+    assert_eq!(meta.max_download, Some(2));
+    assert_eq!(meta.state, UploadState::Complete);
+    assert_eq!(meta.blob_path(&file_path), file_path);
+    assert_eq!(legacy_files(&state).await.unwrap(), 1);
+    assert_eq!(legacy_files(&state).await.unwrap(), 0);
+    let meta = state.db.fetch(&file_path).unwrap().unwrap();
+    assert_eq!(meta.size, content.len() as u64);
+    assert_eq!(meta.blob_path(&file_path), blob_path(&meta.digest));
+    assert!(!fs_path.exists());
+    let manifest = state.db.fetch_manifest(&file_path.code).unwrap().unwrap();
+    assert_eq!(manifest.file_names, vec![file_path.file_name.clone()]);
+    drop(state);
+
+    // The migrated records are read as they are once the database is opened again.
+    let state = open_state(&config).await;
+    let mut file = service::file::fetch(
+      &state,
+      &file_path.code,
+      &file_path.file_name,
+      None,
+      &HeaderMap::new(),
+    )
+    .await
+    .unwrap();
+    let mut body = vec![];
+    file.reader.read_to_end(&mut body).await.unwrap();
+    assert_eq!(body, content);
+    drop(file);
+    drop(state);
+    tokio::fs::remove_dir_all(&workspace).await.unwrap();
+  }
+}
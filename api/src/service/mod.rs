@@ -1 +1,11 @@
+pub mod account;
+pub mod admin;
+pub mod audit;
 pub mod file;
+pub mod fsck;
+pub mod migrate;
+pub mod quota;
+pub mod rate_limit;
+pub mod reload;
+pub mod tus;
+pub mod webhook;
@@ -0,0 +1,165 @@
+use pf_sdk::dto::response::QuotaResponse;
+
+use crate::{
+  configure::QuotaConfig,
+  database::usage::{Reservation, Usage},
+  error::{result::ApiResult, ApiError},
+  server::ApiState,
+};
+
+/// What is left of the quotas, `None` when there is no limit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Remaining {
+  pub bytes: Option<u64>,
+  pub files: Option<usize>,
+}
+
+impl Remaining {
+  /// What the quotas leave with the total usage and the usage of the owner, if any.
+  pub fn new(config: &QuotaConfig, total: Usage, owned: Option<Usage>) -> Self {
+    let mut remaining = Remaining {
+      bytes: config
+        .max_bytes
+        .map(|max_bytes| max_bytes.saturating_sub(total.bytes)),
+      files: None,
+    };
+    let Some(owned) = owned else {
+      return remaining;
+    };
+    if let Some(max_bytes) = config.user_max_bytes {
+      let user_bytes = max_bytes.saturating_sub(owned.bytes);
+      remaining.bytes = Some(remaining.bytes.map_or(user_bytes, |b| b.min(user_bytes)));
+    }
+    remaining.files = config
+      .user_max_files
+      .map(|max_files| max_files.saturating_sub(owned.files));
+    remaining
+  }
+
+  /// Fails when `usage` more would exceed a quota. A new file also needs a byte left.
+  pub fn check(&self, usage: Usage) -> ApiResult {
+    if self.files.is_some_and(|files| usage.files > files) {
+      return Err(ApiError::QuotaExceededError(
+        "The file count quota is used up.".to_string(),
+      ));
+    }
+    match self.bytes {
+      Some(remaining) if (remaining == 0 && usage.files > 0) || usage.bytes > remaining => Err(
+        ApiError::QuotaExceededError(format!("The storage quota has {remaining} bytes left.")),
+      ),
+      _ => Ok(()),
+    }
+  }
+}
+
+/// The files and the running resumable uploads of the owner, or of everyone when the
+/// owner is `None`.
+pub fn usage(state: &ApiState, owner: Option<&str>) -> ApiResult<Usage> {
+  state.db.usage(owner)
+}
+
+/// Bytes in the storage backend, a blob shared by several files counts once.
+pub fn stored_bytes(state: &ApiState) -> ApiResult<u64> {
+  state.db.stored_bytes()
+}
+
+pub fn remaining(state: &ApiState, owner: Option<&str>) -> ApiResult<Remaining> {
+  let total = state.db.usage(None)?;
+  let owned = owner.map(|owner| state.db.usage(Some(owner))).transpose()?;
+  Ok(Remaining::new(&state.config.load().quota, total, owned))
+}
+
+/// Holds `usage` for the owner until the reservation is dropped, fails when it would
+/// exceed a quota.
+pub fn reserve(state: &ApiState, owner: Option<String>, usage: Usage) -> ApiResult<Reservation> {
+  let config = state.config.load();
+  state.db.reserve(owner, usage, |total, owned| {
+    Remaining::new(&config.quota, total, owned).check(usage)
+  })
+}
+
+/// Adds `bytes` to the reservation, fails when they would exceed a quota.
+pub fn grow(state: &ApiState, reservation: &mut Reservation, bytes: u64) -> ApiResult {
+  let config = state.config.load();
+  let usage = Usage::bytes(bytes);
+  reservation.grow(usage, |total, owned| {
+    Remaining::new(&config.quota, total, owned).check(usage)
+  })
+}
+
+/// Fails when the usage of the owner, which already counts its running uploads, is over
+/// a quota. It can be when the quotas were lowered while an upload was running.
+pub fn check_usage(state: &ApiState, owner: Option<&str>) -> ApiResult {
+  let config = &state.config.load().quota;
+  let total = state.db.usage(None)?;
+  let owned = owner.map(|owner| state.db.usage(Some(owner))).transpose()?;
+  let over_bytes = config.max_bytes.is_some_and(|max| total.bytes > max)
+    || owned.is_some_and(|owned| config.user_max_bytes.is_some_and(|max| owned.bytes > max));
+  if over_bytes {
+    return Err(ApiError::QuotaExceededError(
+      "The storage quota is exceeded.".to_string(),
+    ));
+  }
+  if owned.is_some_and(|owned| config.user_max_files.is_some_and(|max| owned.files > max)) {
+    return Err(ApiError::QuotaExceededError(
+      "The file count quota is exceeded.".to_string(),
+    ));
+  }
+  Ok(())
+}
+
+pub fn quota(state: &ApiState, owner: Option<String>) -> ApiResult<QuotaResponse> {
+  let config = &state.config.load().quota;
+  let usage = usage(state, owner.as_deref())?;
+  let remaining = remaining(state, owner.as_deref())?;
+  let (max_bytes, max_files) = match owner {
+    Some(_) => (config.user_max_bytes, config.user_max_files),
+    None => (config.max_bytes, None),
+  };
+  Ok(QuotaResponse {
+    owner,
+    used_bytes: usage.bytes,
+    used_files: usage.files,
+    max_bytes,
+    max_files,
+    remaining_bytes: remaining.bytes,
+    remaining_files: remaining.files,
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_remaining_quota_check() {
+    let config = QuotaConfig {
+      user_max_bytes: Some(20),
+      user_max_files: Some(2),
+      max_bytes: Some(100),
+    };
+    let remaining = Remaining::new(&config, Usage::file(90), Some(Usage::file(10)));
+    assert_eq!(
+      remaining,
+      Remaining {
+        bytes: Some(10),
+        files: Some(1)
+      }
+    );
+    remaining.check(Usage::file(10)).unwrap();
+    assert!(matches!(
+      remaining.check(Usage::file(11)),
+      Err(ApiError::QuotaExceededError(_))
+    ));
+    let remaining = Remaining::new(&config, Usage::file(90), Some(Usage::file(20)));
+    remaining.check(Usage::bytes(0)).unwrap();
+    assert!(matches!(
+      remaining.check(Usage::file(0)),
+      Err(ApiError::QuotaExceededError(_))
+    ));
+    let remaining = Remaining::new(&config, Usage::file(0), None);
+    assert_eq!(remaining.files, None);
+    remaining.check(Usage::file(100)).unwrap();
+    Remaining::default().check(Usage::file(u64::MAX)).unwrap();
+  }
+}
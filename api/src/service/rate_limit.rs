@@ -0,0 +1,304 @@
+use std::{
+  collections::HashMap,
+  net::IpAddr,
+  sync::Mutex,
+  time::{Duration, Instant},
+};
+
+use hyper::{HeaderMap, Method};
+
+use crate::{
+  configure::{RateLimitConfig, RateLimitRule},
+  error::{result::ApiResult, ApiError},
+  server::ApiState,
+  service,
+};
+
+const FORWARDED_FOR_HEADER: &str = "x-forwarded-for";
+// Full buckets are dropped once this many clients are tracked.
+const MAX_BUCKETS: usize = 10_000;
+// First path segments of routes that are neither uploads nor downloads.
+const UNLIMITED_ROUTES: [&str; 9] = [
+  "", "healthz", "metrics", "tus", "upload", "accounts", "files", "admin", "quota",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, strum::Display)]
+pub enum LimitKind {
+  #[strum(serialize = "upload")]
+  Upload,
+  #[strum(serialize = "download")]
+  Download,
+  #[strum(serialize = "not found")]
+  NotFound,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Client {
+  ApiKey(String),
+  Ip(IpAddr),
+}
+
+struct Bucket {
+  tokens: f64,
+  updated_at: Instant,
+  full_at: Instant,
+}
+
+#[derive(Default)]
+pub struct RateLimiter(Mutex<HashMap<(LimitKind, Client), Bucket>>);
+
+impl RateLimiter {
+  /// Takes a token, or fails with the time until the next token when the bucket is empty.
+  pub fn acquire(
+    &self,
+    kind: LimitKind,
+    client: &Client,
+    rule: RateLimitRule,
+    now: Instant,
+  ) -> ApiResult {
+    self.update(kind, client, rule, now, |tokens| {
+      if *tokens >= 1.0 {
+        *tokens -= 1.0;
+        true
+      } else {
+        false
+      }
+    })
+  }
+
+  /// Fails like `acquire` without taking a token.
+  pub fn check(
+    &self,
+    kind: LimitKind,
+    client: &Client,
+    rule: RateLimitRule,
+    now: Instant,
+  ) -> ApiResult {
+    self.update(kind, client, rule, now, |tokens| *tokens >= 1.0)
+  }
+
+  /// Takes a token if there is one left.
+  pub fn consume(
+    &self,
+    kind: LimitKind,
+    client: &Client,
+    rule: RateLimitRule,
+    now: Instant,
+  ) -> ApiResult {
+    self.update(kind, client, rule, now, |tokens| {
+      *tokens = (*tokens - 1.0).max(0.0);
+      true
+    })
+  }
+
+  fn update(
+    &self,
+    kind: LimitKind,
+    client: &Client,
+    rule: RateLimitRule,
+    now: Instant,
+    f: impl FnOnce(&mut f64) -> bool,
+  ) -> ApiResult {
+    if rule.is_unlimited() {
+      return Ok(());
+    }
+    let mut guard = self
+      .0
+      .lock()
+      .map_err(|e| ApiError::LockError(e.to_string()))?;
+    if guard.len() >= MAX_BUCKETS {
+      guard.retain(|_, bucket| bucket.full_at > now);
+    }
+    let burst = rule.burst as f64;
+    let per_sec = rule.per_minute as f64 / 60.0;
+    let bucket = guard
+      .entry((kind, client.clone()))
+      .or_insert_with(|| Bucket {
+        tokens: burst,
+        updated_at: now,
+        full_at: now,
+      });
+    let elapsed = now.saturating_duration_since(bucket.updated_at);
+    bucket.tokens = (bucket.tokens + elapsed.as_secs_f64() * per_sec).min(burst);
+    bucket.updated_at = now;
+    let allowed = f(&mut bucket.tokens);
+    bucket.full_at = now + Duration::from_secs_f64((burst - bucket.tokens) / per_sec);
+    if allowed {
+      return Ok(());
+    }
+    let retry_after = Duration::from_secs_f64((1.0 - bucket.tokens) / per_sec);
+    Err(ApiError::TooManyRequestsError(
+      format!("The {kind} rate limit is exceeded, retry after {retry_after:?}."),
+      retry_after,
+    ))
+  }
+}
+
+/// The rule that applies to a request before it is handled.
+pub fn classify(method: &Method, path: &str) -> Option<LimitKind> {
+  let segment = path.trim_start_matches('/').split('/').next()?;
+  if *method == Method::POST && (segment == "upload" || segment == "tus") {
+    Some(LimitKind::Upload)
+  } else if (*method == Method::GET || *method == Method::HEAD)
+    && !UNLIMITED_ROUTES.contains(&segment)
+  {
+    Some(LimitKind::Download)
+  } else {
+    None
+  }
+}
+
+/// Requests on the code and file routes, only their 404 responses count as guesses.
+pub fn is_lookup(path: &str) -> bool {
+  let segment = path
+    .trim_start_matches('/')
+    .split('/')
+    .next()
+    .unwrap_or_default();
+  !UNLIMITED_ROUTES.contains(&segment)
+}
+
+/// Requests with a valid API key are limited per key, the others per client IP address.
+pub fn client(state: &ApiState, headers: &HeaderMap, peer: Option<IpAddr>) -> Option<Client> {
+  if let Ok(Some(principal)) = service::account::authenticate(state, headers) {
+    return Some(Client::ApiKey(principal.key_id));
+  }
+  client_ip(&state.config.load().rate_limit, headers, peer?).map(Client::Ip)
+}
+
+/// Walks the X-Forwarded-For chain from the right while the hops are trusted proxies.
+pub fn client_ip(config: &RateLimitConfig, headers: &HeaderMap, peer: IpAddr) -> Option<IpAddr> {
+  if !config.trusted_proxies.contains(&peer) {
+    return Some(peer);
+  }
+  let mut ip = peer;
+  for value in headers.get_all(FORWARDED_FOR_HEADER).iter().rev() {
+    let Ok(value) = value.to_str() else {
+      return Some(ip);
+    };
+    for hop in value.rsplit(',') {
+      let Ok(hop) = hop.trim().parse() else {
+        return Some(ip);
+      };
+      ip = hop;
+      if !config.trusted_proxies.contains(&ip) {
+        return Some(ip);
+      }
+    }
+  }
+  Some(ip)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn rule(burst: u32, per_minute: u32) -> RateLimitRule {
+    RateLimitRule { burst, per_minute }
+  }
+
+  #[test]
+  fn test_token_bucket_refill() {
+    let limiter = RateLimiter::default();
+    let client = Client::Ip([10, 0, 0, 1].into());
+    let limit = rule(2, 60);
+    let now = Instant::now();
+    limiter
+      .acquire(LimitKind::Upload, &client, limit, now)
+      .unwrap();
+    limiter
+      .acquire(LimitKind::Upload, &client, limit, now)
+      .unwrap();
+    let err = limiter
+      .acquire(LimitKind::Upload, &client, limit, now)
+      .unwrap_err();
+    assert!(
+      matches!(err, ApiError::TooManyRequestsError(_, retry) if retry == Duration::from_secs(1)),
+      "{err:?}"
+    );
+    limiter
+      .acquire(LimitKind::Download, &client, limit, now)
+      .unwrap();
+    let other = Client::Ip([10, 0, 0, 2].into());
+    limiter
+      .acquire(LimitKind::Upload, &other, limit, now)
+      .unwrap();
+    limiter
+      .acquire(
+        LimitKind::Upload,
+        &client,
+        limit,
+        now + Duration::from_secs(1),
+      )
+      .unwrap();
+  }
+
+  #[test]
+  fn test_check_does_not_take_tokens() {
+    let limiter = RateLimiter::default();
+    let client = Client::ApiKey("key".to_string());
+    let limit = rule(1, 1);
+    let now = Instant::now();
+    limiter
+      .check(LimitKind::NotFound, &client, limit, now)
+      .unwrap();
+    limiter
+      .consume(LimitKind::NotFound, &client, limit, now)
+      .unwrap();
+    limiter
+      .consume(LimitKind::NotFound, &client, limit, now)
+      .unwrap();
+    assert!(limiter
+      .check(LimitKind::NotFound, &client, limit, now)
+      .is_err());
+    limiter
+      .check(LimitKind::NotFound, &client, rule(0, 0), now)
+      .unwrap();
+  }
+
+  #[test]
+  fn test_classify_request() {
+    assert_eq!(classify(&Method::POST, "/upload"), Some(LimitKind::Upload));
+    assert_eq!(classify(&Method::POST, "/tus"), Some(LimitKind::Upload));
+    assert_eq!(classify(&Method::PATCH, "/tus/id"), None);
+    assert_eq!(
+      classify(&Method::GET, "/code/file.txt"),
+      Some(LimitKind::Download)
+    );
+    assert_eq!(
+      classify(&Method::GET, "/info/code/file.txt"),
+      Some(LimitKind::Download)
+    );
+    assert_eq!(classify(&Method::GET, "/healthz"), None);
+    assert_eq!(classify(&Method::GET, "/"), None);
+    assert_eq!(classify(&Method::DELETE, "/code/file.txt"), None);
+    assert!(is_lookup("/code/file.txt"));
+    assert!(is_lookup("/info/code/file.txt"));
+    assert!(!is_lookup("/admin/files/code/file.txt"));
+    assert!(!is_lookup("/tus/id"));
+  }
+
+  #[test]
+  fn test_client_ip_behind_trusted_proxy() {
+    let proxy: IpAddr = [10, 0, 0, 1].into();
+    let config = RateLimitConfig {
+      enable: true,
+      trusted_proxies: vec![proxy, [10, 0, 0, 2].into()],
+      upload: rule(1, 1),
+      download: rule(1, 1),
+      not_found: rule(1, 1),
+    };
+    let mut headers = HeaderMap::new();
+    headers.insert(
+      FORWARDED_FOR_HEADER,
+      "1.1.1.1, 2.2.2.2, 10.0.0.2".parse().unwrap(),
+    );
+    assert_eq!(
+      client_ip(&config, &headers, proxy),
+      Some([2, 2, 2, 2].into())
+    );
+    let peer: IpAddr = [3, 3, 3, 3].into();
+    assert_eq!(client_ip(&config, &headers, peer), Some(peer));
+    assert_eq!(client_ip(&config, &HeaderMap::new(), proxy), Some(proxy));
+  }
+}
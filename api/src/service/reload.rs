@@ -0,0 +1,78 @@
+use std::sync::Arc;
+
+use crate::{
+  configure::{env::get_env_source, ApiConfig, UrlSchema},
+  constant::ENV_PREFIX,
+  error::{result::ApiResult, ApiError},
+  server::ApiState,
+};
+
+/// Reads the settings file and the environment again and applies them.
+pub fn reload(state: &ApiState) -> ApiResult {
+  let settings = state.config.load().settings.clone();
+  let config = ApiConfig::read(settings, get_env_source(ENV_PREFIX))?;
+  apply(state, config)
+}
+
+/// Swaps in the new settings, the next requests use them. Nothing changes when they are
+/// invalid or change a setting that is only read at startup.
+pub fn apply(state: &ApiState, config: ApiConfig) -> ApiResult {
+  config.validate()?;
+  if let Some(setting) = state.config.load().restart_required(&config) {
+    return Err(ApiError::ConfigError(config::ConfigError::Message(
+      format!("Changing the {setting} setting requires a restart."),
+    )));
+  }
+  if config.server.schema == UrlSchema::Https && state.tls.load().is_some() {
+    let tls_config = config.server.get_tls_config()?;
+    state.tls.store(Some(Arc::new(tls_config)));
+  }
+  state.config.store(Arc::new(config));
+  tracing::info!("The settings are reloaded.");
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::util::test::StateTestContext;
+  use pf_sdk::assert_err;
+  use test_context::test_context;
+
+  #[test_context(StateTestContext)]
+  #[tokio::test]
+  async fn test_apply_reloadable_settings(ctx: &mut StateTestContext) {
+    let mut config = ApiConfig::clone(&ctx.state.config.load());
+    config.default_expire_secs = 60;
+    config.default_code_length = 6;
+    config.allow_manual_deletion = false;
+    apply(&ctx.state, config).unwrap();
+    let config = ctx.state.config.load();
+    assert_eq!(config.default_expire_secs, 60);
+    assert_eq!(config.default_code_length, 6);
+    assert!(!config.allow_manual_deletion);
+  }
+
+  #[test_context(StateTestContext)]
+  #[tokio::test]
+  async fn test_apply_rejects_restart_required_and_invalid_settings(ctx: &mut StateTestContext) {
+    let current = ApiConfig::clone(&ctx.state.config.load());
+    let mut config = current.clone();
+    config.server.port += 1;
+    config.default_expire_secs = 60;
+    let result = apply(&ctx.state, config);
+    assert_err!(result, |e: &ApiError| e.to_string().contains("server.port"));
+    let mut config = current.clone();
+    config.fs.base_dir = config.fs.base_dir.join("other");
+    let result = apply(&ctx.state, config);
+    assert_err!(result, |e: &ApiError| e.to_string().contains("fs"));
+    let mut config = current.clone();
+    config.default_code_length = 1;
+    let result = apply(&ctx.state, config);
+    assert_err!(result);
+    assert_eq!(
+      ctx.state.config.load().default_expire_secs,
+      current.default_expire_secs
+    );
+  }
+}
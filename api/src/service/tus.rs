@@ -0,0 +1,426 @@
+use std::{collections::HashSet, io::SeekFrom, path::PathBuf, sync::Mutex, time::Duration};
+
+use axum::body::Body;
+use chrono::Utc;
+use futures_util::TryStreamExt;
+use pf_sdk::dto::request::UploadQueryParam;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use tokio_util::io::StreamReader;
+
+use crate::{
+  database::{
+    meta_data_file::{MetaDataFile, UploadState},
+    upload::Upload,
+    usage::Usage,
+  },
+  error::{
+    result::{ApiResult, ToApiResult},
+    ApiError,
+  },
+  metrics::METRICS,
+  server::ApiState,
+  service,
+  util::{
+    reader::{is_size_limit_exceeded, LimitedReader},
+    secret::Secret,
+  },
+};
+
+use super::file::{authorize_user, calc_expiration_date, discard, put_blob, reserve_file_path};
+
+const BUF_SIZE: usize = 64 * 1024;
+
+#[derive(Default)]
+pub struct UploadLocks(Mutex<HashSet<String>>);
+
+pub struct UploadLockGuard<'a> {
+  locks: &'a UploadLocks,
+  id: String,
+}
+
+impl UploadLocks {
+  pub fn acquire(&self, id: &str) -> ApiResult<UploadLockGuard<'_>> {
+    let mut guard = self
+      .0
+      .lock()
+      .map_err(|e| ApiError::LockError(e.to_string()))?;
+    if !guard.insert(id.to_string()) {
+      return Err(ApiError::ConflictError(format!(
+        "Upload {id} is in use by another request"
+      )));
+    }
+    Ok(UploadLockGuard {
+      locks: self,
+      id: id.to_string(),
+    })
+  }
+}
+
+impl Drop for UploadLockGuard<'_> {
+  fn drop(&mut self) {
+    match self.locks.0.lock() {
+      Ok(mut guard) => {
+        guard.remove(&self.id);
+      }
+      Err(err) => tracing::error!("Failed to release upload lock, Error: {err}"),
+    }
+  }
+}
+
+pub async fn create(
+  state: &ApiState,
+  param: &UploadQueryParam,
+  secret: Option<Secret>,
+  owner: Option<String>,
+  file_name: String,
+  length: u64,
+) -> ApiResult<(String, Upload)> {
+  crate::util::file_name::validate(&file_name)?;
+  if length > state.config.load().max_upload_bytes_size as u64 {
+    return Err(ApiError::PayloadTooLarge(format!(
+      "The maximum allowed size for uploaded files is {} bytes.",
+      state.config.load().max_upload_bytes_size
+    )));
+  }
+  // The declared length is held until the upload record counts it.
+  let reservation = service::quota::reserve(state, owner.clone(), Usage::file(length))?;
+  let expire_secs = param
+    .expire_secs
+    .unwrap_or(state.config.load().default_expire_secs) as i64;
+  let now = Utc::now();
+  let mut upload = Upload {
+    file_name,
+    length,
+    offset: 0,
+    created_at: now,
+    expire_date_time: calc_expiration_date(now, expire_secs)?,
+    expire_secs,
+    code_length: param
+      .code_length
+      .unwrap_or(state.config.load().default_code_length),
+    secret: secret.map(|s| s.hash()).transpose()?,
+    manual_deletion: param
+      .allow_manual_deletion
+      .unwrap_or(state.config.load().allow_manual_deletion),
+    max_download: param.max_download,
+    owner,
+    sha256: param.sha256.clone(),
+    file_path: None,
+  };
+  let id = cuid2::create_id();
+  tokio::fs::create_dir_all(state.config.load().fs.get_upload_dir()).await?;
+  tokio::fs::File::create(get_upload_path(state, &id)).await?;
+  state.db.store_upload(&id, &upload)?;
+  drop(reservation);
+  if upload.length == 0 {
+    upload = complete(state, &id, upload).await?;
+  }
+  state.db.flush().await?;
+  Ok((id, upload))
+}
+
+pub fn fetch(state: &ApiState, id: &str, secret: Option<Secret>) -> ApiResult<Upload> {
+  let upload = state.db.fetch_upload(id)?.to_result(id)?;
+  authorize_user(secret, &upload.secret)?;
+  Ok(upload)
+}
+
+pub async fn append(
+  state: &ApiState,
+  id: &str,
+  secret: Option<Secret>,
+  offset: u64,
+  body: Body,
+) -> ApiResult<Upload> {
+  let _guard = state.upload_locks.acquire(id)?;
+  let mut upload = fetch(state, id, secret)?;
+  if upload.is_complete() {
+    return Err(ApiError::ConflictError(format!(
+      "Upload {id} is already complete"
+    )));
+  }
+  if upload.offset != offset {
+    return Err(ApiError::ConflictError(format!(
+      "Upload-Offset {offset} does not match the current offset {}",
+      upload.offset
+    )));
+  }
+  let mut file = tokio::fs::OpenOptions::new()
+    .write(true)
+    .open(get_upload_path(state, id))
+    .await?;
+  file.set_len(offset).await?;
+  file.seek(SeekFrom::Start(offset)).await?;
+  let body_reader = StreamReader::new(body.into_data_stream().map_err(std::io::Error::other));
+  let mut reader = LimitedReader::new(body_reader, (upload.length - offset) as usize);
+  let mut buf = vec![0u8; BUF_SIZE];
+  // Bytes received before a dropped connection are kept so the client can resume after them.
+  let result = loop {
+    match reader.read(&mut buf).await {
+      Ok(0) => break Ok(()),
+      Ok(n) => {
+        file.write_all(&buf[..n]).await?;
+        upload.offset += n as u64;
+        METRICS.upload_bytes.inc_by(n as u64);
+      }
+      Err(err) => break Err(err),
+    }
+  };
+  file.flush().await?;
+  state.db.store_upload(id, &upload)?;
+  match result {
+    Err(err) if is_size_limit_exceeded(&err) => {
+      return Err(ApiError::BadRequestError(
+        "The request body exceeds the declared Upload-Length.".to_string(),
+      ))
+    }
+    Err(err) => return Err(err.into()),
+    Ok(()) => {}
+  }
+  if upload.offset == upload.length {
+    upload = complete(state, id, upload).await?;
+  }
+  state.db.flush().await?;
+  Ok(upload)
+}
+
+async fn complete(state: &ApiState, id: &str, mut upload: Upload) -> ApiResult<Upload> {
+  service::quota::check_usage(state, upload.owner.as_deref())?;
+  let now = Utc::now();
+  let meta = MetaDataFile {
+    created_at: now,
+    expire_date_time: calc_expiration_date(now, upload.expire_secs)?,
+    secret: upload.secret.clone(),
+    manual_deletion: upload.manual_deletion,
+    max_download: upload.max_download,
+    count_downloads: 0,
+    etag: cuid2::create_id(),
+    size: upload.length,
+    digest: String::new(),
+    owner: upload.owner.clone(),
+    state: UploadState::Pending,
+  };
+  let file_path = reserve_file_path(state, &upload.file_name, &meta, upload.code_length).await?;
+  let upload_path = get_upload_path(state, id);
+  let file = tokio::fs::File::open(&upload_path).await?;
+  if let Err(err) = put_blob(state, &file_path, Box::pin(file), upload.sha256.as_deref()).await {
+    discard(state, vec![file_path]).await?;
+    if matches!(err, ApiError::ChecksumMismatchError(_)) {
+      // Resuming cannot fix content that was received in full.
+      remove_upload(state, id).await?;
+    }
+    return Err(err);
+  }
+  tokio::fs::remove_file(upload_path).await?;
+  if let Some(meta) = state.db.fetch(&file_path)? {
+    service::webhook::notify(
+      state,
+      service::webhook::EventKind::Upload,
+      &file_path,
+      &meta,
+    );
+  }
+  upload.expire_date_time = meta.expire_date_time;
+  METRICS
+    .upload_duration
+    .observe((now - upload.created_at).num_milliseconds() as f64 / 1000.0);
+  upload.file_path = Some(file_path);
+  state.db.store_upload(id, &upload)?;
+  Ok(upload)
+}
+
+pub async fn terminate(state: &ApiState, id: &str, secret: Option<Secret>) -> ApiResult {
+  let _guard = state.upload_locks.acquire(id)?;
+  fetch(state, id, secret)?;
+  remove_upload(state, id).await
+}
+
+pub async fn purge(state: &ApiState) -> ApiResult<Option<Duration>> {
+  let mut wakeup_next_time: Option<Duration> = None;
+  let now = Utc::now();
+  for (id, upload) in state.db.fetch_uploads()? {
+    if upload.expire_date_time < now {
+      remove_upload(state, &id).await?;
+    } else {
+      let duration = (upload.expire_date_time - now).to_std()?;
+      wakeup_next_time = Some(wakeup_next_time.map_or(duration, |d| d.min(duration)));
+    }
+  }
+  Ok(wakeup_next_time)
+}
+
+async fn remove_upload(state: &ApiState, id: &str) -> ApiResult {
+  match tokio::fs::remove_file(get_upload_path(state, id)).await {
+    Err(err) if err.kind() != std::io::ErrorKind::NotFound => return Err(err.into()),
+    _ => {}
+  }
+  state.db.delete_upload(id)?;
+  Ok(())
+}
+
+/// The parts of a resumable upload are appended to a file on the local disk, whatever the
+/// storage backend is, and only the finished file is stored in the backend. Fails when the
+/// upload directory cannot be written, so the server does not start without it.
+pub async fn check_upload_dir(state: &ApiState) -> ApiResult {
+  let dir = state.config.load().fs.get_upload_dir();
+  let probe = dir.join(format!(".probe-{}", cuid2::create_id()));
+  let result = async {
+    tokio::fs::create_dir_all(&dir).await?;
+    tokio::fs::write(&probe, b"").await?;
+    tokio::fs::remove_file(&probe).await
+  }
+  .await;
+  result.map_err(|err| {
+    ApiError::ConfigError(config::ConfigError::Message(format!(
+      "The resumable uploads need the writable local directory {}, Error: {err}",
+      dir.display()
+    )))
+  })
+}
+
+fn get_upload_path(state: &ApiState, id: &str) -> PathBuf {
+  state.config.load().fs.get_upload_dir().join(id)
+}
+
+#[cfg(test)]
+mod tests {
+
+  use super::*;
+  use crate::util::test::StateTestContext;
+  use fake::{Fake, Faker};
+  use pf_sdk::assert_err;
+  use test_context::test_context;
+
+  fn upload_param() -> UploadQueryParam {
+    UploadQueryParam {
+      max_download: None,
+      code_length: None,
+      expire_secs: None,
+      allow_manual_deletion: Some(true),
+      qr_code_format: None,
+      sha256: None,
+    }
+  }
+
+  #[test_context(StateTestContext)]
+  #[tokio::test]
+  async fn test_check_upload_dir(ctx: &mut StateTestContext) {
+    check_upload_dir(&ctx.state).await.unwrap();
+    let dir = ctx.state.config.load().fs.get_upload_dir();
+    assert!(dir.is_dir());
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+    tokio::fs::remove_dir(&dir).await.unwrap();
+    tokio::fs::write(&dir, b"").await.unwrap();
+    let result = check_upload_dir(&ctx.state).await;
+    assert_err!(result, |e: &ApiError| matches!(e, ApiError::ConfigError(_)));
+  }
+
+  #[test_context(StateTestContext)]
+  #[tokio::test]
+  async fn test_append_chunks_and_complete_upload(ctx: &mut StateTestContext) {
+    let file_name = format!("{}.txt", Faker.fake::<String>());
+    let (id, _) = create(
+      &ctx.state,
+      &upload_param(),
+      None,
+      None,
+      file_name.clone(),
+      11,
+    )
+    .await
+    .unwrap();
+    let upload = append(&ctx.state, &id, None, 0, Body::from("Hello "))
+      .await
+      .unwrap();
+    assert_eq!(upload.offset, 6);
+    assert!(!upload.is_complete());
+    let upload = append(&ctx.state, &id, None, 6, Body::from("World"))
+      .await
+      .unwrap();
+    let file_path = upload.file_path.unwrap();
+    assert_eq!(file_path.file_name, file_name);
+    let meta = ctx.state.db.fetch(&file_path).unwrap().unwrap();
+    assert_eq!(meta.size, 11);
+    assert_eq!(
+      ctx
+        .state
+        .storage
+        .size(&meta.blob_path(&file_path))
+        .await
+        .unwrap(),
+      11
+    );
+  }
+
+  #[test_context(StateTestContext)]
+  #[tokio::test]
+  async fn test_append_with_wrong_offset(ctx: &mut StateTestContext) {
+    let file_name = format!("{}.txt", Faker.fake::<String>());
+    let (id, _) = create(&ctx.state, &upload_param(), None, None, file_name, 10)
+      .await
+      .unwrap();
+    let result = append(&ctx.state, &id, None, 4, Body::from("data")).await;
+    assert_err!(result, |e: &ApiError| matches!(
+      e,
+      ApiError::ConflictError(_)
+    ));
+  }
+
+  #[test_context(StateTestContext)]
+  #[tokio::test]
+  async fn test_append_more_than_upload_length(ctx: &mut StateTestContext) {
+    let file_name = format!("{}.txt", Faker.fake::<String>());
+    let (id, _) = create(&ctx.state, &upload_param(), None, None, file_name, 4)
+      .await
+      .unwrap();
+    let result = append(&ctx.state, &id, None, 0, Body::from("Hello World")).await;
+    assert_err!(result, |e: &ApiError| matches!(
+      e,
+      ApiError::BadRequestError(_)
+    ));
+  }
+
+  #[test_context(StateTestContext)]
+  #[tokio::test]
+  async fn test_terminate_upload(ctx: &mut StateTestContext) {
+    let secret = Secret::new(Faker.fake::<String>());
+    let file_name = format!("{}.txt", Faker.fake::<String>());
+    let (id, _) = create(
+      &ctx.state,
+      &upload_param(),
+      Some(secret.clone()),
+      None,
+      file_name,
+      10,
+    )
+    .await
+    .unwrap();
+    let result = terminate(&ctx.state, &id, None).await;
+    assert_err!(result, |e: &ApiError| e.to_string()
+      == "Authorization header required.");
+    terminate(&ctx.state, &id, Some(secret)).await.unwrap();
+    assert!(ctx.state.db.fetch_upload(&id).unwrap().is_none());
+    assert!(!get_upload_path(&ctx.state, &id).exists());
+  }
+
+  #[test_context(StateTestContext)]
+  #[tokio::test]
+  async fn test_complete_upload_with_wrong_checksum(ctx: &mut StateTestContext) {
+    let param = UploadQueryParam {
+      sha256: Some("0".repeat(64)),
+      ..upload_param()
+    };
+    let file_name = format!("{}.txt", Faker.fake::<String>());
+    let (id, _) = create(&ctx.state, &param, None, None, file_name, 4)
+      .await
+      .unwrap();
+    let result = append(&ctx.state, &id, None, 0, Body::from("data")).await;
+    assert_err!(result, |e: &ApiError| matches!(
+      e,
+      ApiError::ChecksumMismatchError(_)
+    ));
+    assert!(ctx.state.db.fetch_upload(&id).unwrap().is_none());
+    assert!(!get_upload_path(&ctx.state, &id).exists());
+    assert!(ctx.state.db.fetch_all().unwrap().is_empty());
+  }
+}
@@ -0,0 +1,188 @@
+use std::{sync::Mutex, time::Duration};
+
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use pf_sdk::util::url::create_url;
+use reqwest::header::CONTENT_TYPE;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+  configure::WebhookConfig,
+  database::{file_path::FilePath, meta_data_file::MetaDataFile},
+  error::{result::ApiResult, ApiError},
+  server::ApiState,
+};
+
+pub const EVENT_HEADER: &str = "x-pf-event";
+pub const DELIVERY_HEADER: &str = "x-pf-delivery";
+pub const SIGNATURE_HEADER: &str = "x-pf-signature";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, strum::Display)]
+pub enum EventKind {
+  #[serde(rename = "upload")]
+  #[strum(serialize = "upload")]
+  Upload,
+  #[serde(rename = "download")]
+  #[strum(serialize = "download")]
+  Download,
+  #[serde(rename = "delete")]
+  #[strum(serialize = "delete")]
+  Delete,
+  #[serde(rename = "expire")]
+  #[strum(serialize = "expire")]
+  Expire,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WebhookEvent {
+  pub id: String,
+  pub event: EventKind,
+  pub code: String,
+  pub file_name: String,
+  pub url: Option<String>,
+  pub owner: Option<String>,
+  pub size: u64,
+  pub count_downloads: u32,
+  pub expire_date_time: DateTime<Utc>,
+  pub timestamp: DateTime<Utc>,
+}
+
+/// Queue of the events waiting for the webhook task.
+pub struct Webhooks {
+  enable: bool,
+  sender: mpsc::Sender<WebhookEvent>,
+  receiver: Mutex<Option<mpsc::Receiver<WebhookEvent>>>,
+}
+
+impl Webhooks {
+  pub fn new(config: &WebhookConfig) -> Self {
+    let (sender, receiver) = mpsc::channel(config.queue_size.max(1));
+    Self {
+      enable: !config.urls.is_empty(),
+      sender,
+      receiver: Mutex::new(Some(receiver)),
+    }
+  }
+
+  /// Queues the event without waiting, it is dropped when the queue is full.
+  pub fn send(&self, event: WebhookEvent) {
+    if !self.enable {
+      return;
+    }
+    match self.sender.try_send(event) {
+      Ok(()) => {}
+      Err(TrySendError::Full(event)) => {
+        tracing::warn!(
+          "The webhook queue is full, the {} event {} is dropped.",
+          event.event,
+          event.id
+        );
+      }
+      Err(TrySendError::Closed(_)) => tracing::error!("The webhook queue is closed."),
+    }
+  }
+
+  pub fn take_receiver(&self) -> ApiResult<mpsc::Receiver<WebhookEvent>> {
+    self
+      .receiver
+      .lock()
+      .map_err(|e| ApiError::LockError(e.to_string()))?
+      .take()
+      .ok_or_else(|| {
+        ApiError::UnknownError(anyhow::anyhow!("The webhook task is running already."))
+      })
+  }
+}
+
+pub fn event(
+  state: &ApiState,
+  kind: EventKind,
+  file_path: &FilePath,
+  meta: &MetaDataFile,
+) -> WebhookEvent {
+  let domain_name = state.config.load().server.get_domain_name();
+  WebhookEvent {
+    id: cuid2::create_id(),
+    event: kind,
+    code: file_path.code.clone(),
+    file_name: file_path.file_name.clone(),
+    url: create_url(&domain_name, &file_path.code, &file_path.file_name)
+      .ok()
+      .map(|url| url.to_string()),
+    owner: meta.owner.clone(),
+    size: meta.size,
+    count_downloads: meta.count_downloads,
+    expire_date_time: meta.expire_date_time,
+    timestamp: Utc::now(),
+  }
+}
+
+pub fn notify(state: &ApiState, kind: EventKind, file_path: &FilePath, meta: &MetaDataFile) {
+  state.webhooks.send(event(state, kind, file_path, meta));
+}
+
+/// Posts the event to the endpoint and retries with an exponential backoff. No retry is made
+/// once `shutdown` is cancelled.
+pub async fn deliver(
+  client: &reqwest::Client,
+  config: &WebhookConfig,
+  url: &str,
+  event: &WebhookEvent,
+  shutdown: &CancellationToken,
+) -> ApiResult {
+  let body = serde_json::to_vec(event)?;
+  let mut delay = Duration::from_millis(config.retry_delay_ms);
+  let mut attempt = 1;
+  loop {
+    let mut request = client
+      .post(url)
+      .header(CONTENT_TYPE, "application/json")
+      .header(EVENT_HEADER, event.event.to_string())
+      .header(DELIVERY_HEADER, &event.id)
+      .body(body.clone());
+    if let Some(secret) = config.secret.as_ref() {
+      request = request.header(SIGNATURE_HEADER, signature(secret, &body));
+    }
+    match request.send().await.and_then(|r| r.error_for_status()) {
+      Ok(_) => return Ok(()),
+      Err(err) if attempt < config.max_attempts && !shutdown.is_cancelled() => {
+        tracing::warn!(
+          "Failed to deliver the webhook event {} to {url}, attempt {attempt}, Error: {err}",
+          event.id
+        );
+        tokio::select! {
+          _ = tokio::time::sleep(delay) => {}
+          _ = shutdown.cancelled() => return Err(err.into()),
+        }
+        delay *= 2;
+        attempt += 1;
+      }
+      Err(err) => return Err(err.into()),
+    }
+  }
+}
+
+/// `sha256=` followed by the hex HMAC-SHA256 of the body.
+pub fn signature(secret: &str, body: &[u8]) -> String {
+  let mut mac =
+    Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC can take key of any size");
+  mac.update(body);
+  format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_webhook_signature() {
+    // Test vector 2 of RFC 4231.
+    assert_eq!(
+      signature("Jefe", b"what do ya want for nothing?"),
+      "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+    );
+  }
+}
@@ -0,0 +1,170 @@
+use std::{
+  io::SeekFrom,
+  path::{Path, PathBuf},
+};
+
+use futures_util::{future::BoxFuture, FutureExt};
+use tokio::{
+  fs::File,
+  io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt, BufWriter},
+};
+
+use crate::{database::file_path::FilePath, error::result::ApiResult, util::path::get_fs_path};
+
+use super::{ByteRange, StorageBackend, StorageReader};
+
+pub struct LocalStorage {
+  base_dir: PathBuf,
+}
+
+impl LocalStorage {
+  pub fn new(base_dir: PathBuf) -> Self {
+    Self { base_dir }
+  }
+}
+
+impl StorageBackend for LocalStorage {
+  fn put<'a>(
+    &'a self,
+    file_path: &'a FilePath,
+    mut reader: StorageReader<'a>,
+  ) -> BoxFuture<'a, ApiResult<u64>> {
+    async move {
+      let fs_path = get_fs_path(&self.base_dir, file_path);
+      if let Some(parent) = fs_path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+      }
+      let mut file = BufWriter::new(File::create(&fs_path).await?);
+      let bytes_size = tokio::io::copy(&mut reader, &mut file).await?;
+      file.flush().await?;
+      file.get_ref().sync_all().await?;
+      Ok(bytes_size)
+    }
+    .boxed()
+  }
+
+  fn get<'a>(
+    &'a self,
+    file_path: &'a FilePath,
+    range: Option<ByteRange>,
+  ) -> BoxFuture<'a, ApiResult<StorageReader<'static>>> {
+    async move {
+      let mut file = File::open(get_fs_path(&self.base_dir, file_path)).await?;
+      let reader: StorageReader<'static> = match range {
+        Some(range) => {
+          file.seek(SeekFrom::Start(range.start)).await?;
+          Box::pin(file.take(range.len()))
+        }
+        None => Box::pin(file),
+      };
+      Ok(reader)
+    }
+    .boxed()
+  }
+
+  fn delete<'a>(&'a self, file_path: &'a FilePath) -> BoxFuture<'a, ApiResult> {
+    async move {
+      tokio::fs::remove_file(get_fs_path(&self.base_dir, file_path)).await?;
+      Ok(())
+    }
+    .boxed()
+  }
+
+  fn exists<'a>(&'a self, file_path: &'a FilePath) -> BoxFuture<'a, ApiResult<bool>> {
+    async move { Ok(tokio::fs::try_exists(get_fs_path(&self.base_dir, file_path)).await?) }.boxed()
+  }
+
+  fn size<'a>(&'a self, file_path: &'a FilePath) -> BoxFuture<'a, ApiResult<u64>> {
+    async move {
+      let metadata = tokio::fs::metadata(get_fs_path(&self.base_dir, file_path)).await?;
+      Ok(metadata.len())
+    }
+    .boxed()
+  }
+
+  fn rename<'a>(&'a self, from: &'a FilePath, to: &'a FilePath) -> BoxFuture<'a, ApiResult> {
+    async move {
+      let fs_path = get_fs_path(&self.base_dir, to);
+      if let Some(parent) = fs_path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+      }
+      let from = get_fs_path(&self.base_dir, from);
+      tokio::fs::rename(&from, &fs_path).await?;
+      // The rename survives a crash only once both directory entries are on disk.
+      for dir in [fs_path.parent(), from.parent()].into_iter().flatten() {
+        sync_dir(dir).await?;
+      }
+      Ok(())
+    }
+    .boxed()
+  }
+}
+
+async fn sync_dir(dir: &Path) -> std::io::Result<()> {
+  if cfg!(unix) {
+    File::open(dir).await?.sync_all().await?;
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+  use super::*;
+  use fake::{Fake, Faker};
+  use test_context::test_context;
+
+  use crate::util::test::StateTestContext;
+
+  #[test_context(StateTestContext)]
+  #[tokio::test]
+  async fn test_put_and_get_range_of_file(ctx: &mut StateTestContext) {
+    let storage = LocalStorage::new(ctx.state.config.load().fs.base_dir.clone());
+    let file_path: FilePath = Faker.fake();
+    let content = b"Hello World!".to_vec();
+    let size = storage
+      .put(&file_path, Box::pin(std::io::Cursor::new(content.clone())))
+      .await
+      .unwrap();
+    assert_eq!(size, content.len() as u64);
+    assert_eq!(storage.size(&file_path).await.unwrap(), size);
+    let mut buf = Vec::new();
+    storage
+      .get(&file_path, Some(ByteRange { start: 6, end: 10 }))
+      .await
+      .unwrap()
+      .read_to_end(&mut buf)
+      .await
+      .unwrap();
+    assert_eq!(buf, b"World");
+  }
+
+  #[test_context(StateTestContext)]
+  #[tokio::test]
+  async fn test_put_and_delete_file(ctx: &mut StateTestContext) {
+    let storage = LocalStorage::new(ctx.state.config.load().fs.base_dir.clone());
+    let file_path: FilePath = Faker.fake();
+    storage
+      .put(&file_path, Box::pin(std::io::Cursor::new(b"data".to_vec())))
+      .await
+      .unwrap();
+    assert!(storage.exists(&file_path).await.unwrap());
+    storage.delete(&file_path).await.unwrap();
+    assert!(!storage.exists(&file_path).await.unwrap());
+  }
+
+  #[test_context(StateTestContext)]
+  #[tokio::test]
+  async fn test_rename_file(ctx: &mut StateTestContext) {
+    let storage = LocalStorage::new(ctx.state.config.load().fs.base_dir.clone());
+    let from: FilePath = Faker.fake();
+    let to: FilePath = Faker.fake();
+    storage
+      .put(&from, Box::pin(std::io::Cursor::new(b"data".to_vec())))
+      .await
+      .unwrap();
+    storage.rename(&from, &to).await.unwrap();
+    assert!(!storage.exists(&from).await.unwrap());
+    assert_eq!(storage.size(&to).await.unwrap(), 4);
+  }
+}
@@ -0,0 +1,152 @@
+use std::{collections::BTreeMap, io::Cursor, sync::RwLock};
+
+use futures_util::{future::BoxFuture, FutureExt};
+use tokio::io::AsyncReadExt;
+use tokio_util::bytes::Bytes;
+
+use crate::{
+  database::file_path::FilePath,
+  error::{result::ApiResult, ApiError},
+};
+
+use super::{ByteRange, StorageBackend, StorageReader};
+
+#[derive(Default)]
+pub struct MemoryStorage {
+  files: RwLock<BTreeMap<FilePath, Bytes>>,
+}
+
+impl MemoryStorage {
+  fn read(&self, file_path: &FilePath) -> ApiResult<Option<Bytes>> {
+    let guard = self
+      .files
+      .read()
+      .map_err(|err| ApiError::LockError(err.to_string()))?;
+    Ok(guard.get(file_path).cloned())
+  }
+
+  fn not_found(file_path: &FilePath) -> ApiError {
+    ApiError::IoError(std::io::Error::new(
+      std::io::ErrorKind::NotFound,
+      format!("{file_path} not found in memory storage"),
+    ))
+  }
+}
+
+impl StorageBackend for MemoryStorage {
+  fn put<'a>(
+    &'a self,
+    file_path: &'a FilePath,
+    mut reader: StorageReader<'a>,
+  ) -> BoxFuture<'a, ApiResult<u64>> {
+    async move {
+      let mut buf = Vec::new();
+      let bytes_size = reader.read_to_end(&mut buf).await? as u64;
+      self
+        .files
+        .write()
+        .map_err(|err| ApiError::LockError(err.to_string()))?
+        .insert(file_path.clone(), Bytes::from(buf));
+      Ok(bytes_size)
+    }
+    .boxed()
+  }
+
+  fn get<'a>(
+    &'a self,
+    file_path: &'a FilePath,
+    range: Option<ByteRange>,
+  ) -> BoxFuture<'a, ApiResult<StorageReader<'static>>> {
+    async move {
+      let content = self
+        .read(file_path)?
+        .ok_or_else(|| Self::not_found(file_path))?;
+      let content = match range {
+        Some(range) => {
+          let end = (range.end + 1).min(content.len() as u64);
+          content.slice(range.start as usize..end as usize)
+        }
+        None => content,
+      };
+      let reader: StorageReader<'static> = Box::pin(Cursor::new(content));
+      Ok(reader)
+    }
+    .boxed()
+  }
+
+  fn delete<'a>(&'a self, file_path: &'a FilePath) -> BoxFuture<'a, ApiResult> {
+    async move {
+      self
+        .files
+        .write()
+        .map_err(|err| ApiError::LockError(err.to_string()))?
+        .remove(file_path)
+        .ok_or_else(|| Self::not_found(file_path))?;
+      Ok(())
+    }
+    .boxed()
+  }
+
+  fn exists<'a>(&'a self, file_path: &'a FilePath) -> BoxFuture<'a, ApiResult<bool>> {
+    async move { Ok(self.read(file_path)?.is_some()) }.boxed()
+  }
+
+  fn size<'a>(&'a self, file_path: &'a FilePath) -> BoxFuture<'a, ApiResult<u64>> {
+    async move {
+      self
+        .read(file_path)?
+        .map(|content| content.len() as u64)
+        .ok_or_else(|| Self::not_found(file_path))
+    }
+    .boxed()
+  }
+
+  fn rename<'a>(&'a self, from: &'a FilePath, to: &'a FilePath) -> BoxFuture<'a, ApiResult> {
+    async move {
+      let mut guard = self
+        .files
+        .write()
+        .map_err(|err| ApiError::LockError(err.to_string()))?;
+      let content = guard.remove(from).ok_or_else(|| Self::not_found(from))?;
+      guard.insert(to.clone(), content);
+      Ok(())
+    }
+    .boxed()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+
+  use super::*;
+  use fake::{Fake, Faker};
+
+  #[tokio::test]
+  async fn test_put_and_get_range_of_file() {
+    let storage = MemoryStorage::default();
+    let file_path: FilePath = Faker.fake();
+    let content = b"Hello World!".to_vec();
+    let size = storage
+      .put(&file_path, Box::pin(Cursor::new(content.clone())))
+      .await
+      .unwrap();
+    assert_eq!(size, content.len() as u64);
+    let mut buf = Vec::new();
+    storage
+      .get(&file_path, Some(ByteRange { start: 0, end: 4 }))
+      .await
+      .unwrap()
+      .read_to_end(&mut buf)
+      .await
+      .unwrap();
+    assert_eq!(buf, b"Hello");
+  }
+
+  #[tokio::test]
+  async fn test_delete_file_that_does_not_exist() {
+    let storage = MemoryStorage::default();
+    let file_path: FilePath = Faker.fake();
+    assert!(!storage.exists(&file_path).await.unwrap());
+    assert!(storage.delete(&file_path).await.is_err());
+  }
+}
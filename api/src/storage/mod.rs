@@ -0,0 +1,80 @@
+use std::{pin::Pin, sync::Arc};
+
+use futures_util::{future::BoxFuture, FutureExt};
+use tokio::io::AsyncRead;
+
+use crate::{
+  configure::{ApiConfig, StorageBackendKind},
+  database::file_path::FilePath,
+  error::{result::ApiResult, ApiError},
+};
+
+pub mod local;
+pub mod memory;
+pub mod s3;
+
+pub type StorageReader<'a> = Pin<Box<dyn AsyncRead + Send + 'a>>;
+
+/// Inclusive byte range of a stored file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ByteRange {
+  pub start: u64,
+  pub end: u64,
+}
+
+impl ByteRange {
+  pub fn len(&self) -> u64 {
+    self.end - self.start + 1
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.end < self.start
+  }
+}
+
+pub trait StorageBackend: Send + Sync {
+  fn put<'a>(
+    &'a self,
+    file_path: &'a FilePath,
+    reader: StorageReader<'a>,
+  ) -> BoxFuture<'a, ApiResult<u64>>;
+
+  fn get<'a>(
+    &'a self,
+    file_path: &'a FilePath,
+    range: Option<ByteRange>,
+  ) -> BoxFuture<'a, ApiResult<StorageReader<'static>>>;
+
+  fn delete<'a>(&'a self, file_path: &'a FilePath) -> BoxFuture<'a, ApiResult>;
+
+  fn exists<'a>(&'a self, file_path: &'a FilePath) -> BoxFuture<'a, ApiResult<bool>>;
+
+  fn size<'a>(&'a self, file_path: &'a FilePath) -> BoxFuture<'a, ApiResult<u64>>;
+
+  /// Moves a file and replaces `to` when it exists. The local backend moves atomically,
+  /// the others copy the content and delete the source, so a failed move may leave a partial
+  /// `to` or both files behind. Callers only trust `to` once the move returned and overwrite
+  /// it when they try again.
+  fn rename<'a>(&'a self, from: &'a FilePath, to: &'a FilePath) -> BoxFuture<'a, ApiResult> {
+    async move {
+      let reader = self.get(from, None).await?;
+      self.put(to, reader).await?;
+      self.delete(from).await
+    }
+    .boxed()
+  }
+}
+
+pub fn new_storage(config: &ApiConfig) -> ApiResult<Arc<dyn StorageBackend>> {
+  let storage: Arc<dyn StorageBackend> = match config.storage.backend {
+    StorageBackendKind::Local => Arc::new(local::LocalStorage::new(config.fs.base_dir.clone())),
+    StorageBackendKind::Memory => Arc::new(memory::MemoryStorage::default()),
+    StorageBackendKind::S3 => {
+      let s3 = config.storage.s3.clone().ok_or_else(|| {
+        ApiError::StorageError("The s3 storage backend is not configured.".to_string())
+      })?;
+      Arc::new(s3::S3Storage::new(s3)?)
+    }
+  };
+  Ok(storage)
+}
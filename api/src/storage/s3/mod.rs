@@ -0,0 +1,711 @@
+use std::collections::BTreeMap;
+
+use chrono::Utc;
+use futures_util::{future::BoxFuture, FutureExt, TryStreamExt};
+use reqwest::{Method, StatusCode};
+use tokio::io::AsyncReadExt;
+use tokio_util::io::StreamReader;
+
+use crate::{
+  configure::S3Config,
+  database::file_path::FilePath,
+  error::{result::ApiResult, ApiError},
+};
+
+use self::signer::{sha256_hex, sign, uri_encode, Credentials, EMPTY_PAYLOAD_HASH};
+
+use super::{ByteRange, StorageBackend, StorageReader};
+
+pub mod signer;
+
+pub const COPY_SOURCE_HEADER: &str = "x-amz-copy-source";
+pub const COPY_SOURCE_RANGE_HEADER: &str = "x-amz-copy-source-range";
+/// The largest object a single CopyObject request copies.
+pub const MAX_COPY_OBJECT_BYTES: u64 = 5 * 1024 * 1024 * 1024;
+const MAX_UPLOAD_PARTS: u64 = 10_000;
+
+pub struct S3Storage {
+  client: reqwest::Client,
+  config: S3Config,
+  host: String,
+  max_copy_object_bytes: u64,
+}
+
+impl S3Storage {
+  pub fn new(config: S3Config) -> ApiResult<Self> {
+    let endpoint = url::Url::parse(&config.endpoint)?;
+    let host = match (endpoint.host_str(), endpoint.port()) {
+      (Some(host), Some(port)) => format!("{host}:{port}"),
+      (Some(host), None) => host.to_string(),
+      (None, _) => {
+        return Err(ApiError::StorageError(format!(
+          "The S3 endpoint {} has no host.",
+          config.endpoint
+        )))
+      }
+    };
+    Ok(Self {
+      client: reqwest::Client::new(),
+      config,
+      host,
+      max_copy_object_bytes: MAX_COPY_OBJECT_BYTES,
+    })
+  }
+
+  fn object_path(&self, file_path: &FilePath) -> String {
+    format!(
+      "/{}/{}/{}",
+      self.config.bucket, file_path.code, file_path.file_name
+    )
+  }
+
+  async fn send(
+    &self,
+    method: Method,
+    file_path: &FilePath,
+    query: &[(&str, &str)],
+    extra_headers: &[(&str, String)],
+    body: Vec<u8>,
+  ) -> ApiResult<reqwest::Response> {
+    let path = self.object_path(file_path);
+    let mut headers = BTreeMap::from([("host".to_string(), self.host.clone())]);
+    headers.extend(
+      extra_headers
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.clone())),
+    );
+    let payload_hash = if body.is_empty() {
+      EMPTY_PAYLOAD_HASH.to_string()
+    } else {
+      sha256_hex(&body)
+    };
+    let signed = sign(
+      &Credentials {
+        access_key: &self.config.access_key,
+        secret_key: &self.config.secret_key,
+        region: &self.config.region,
+      },
+      method.as_str(),
+      &path,
+      query,
+      &headers,
+      &payload_hash,
+      Utc::now(),
+    );
+    let mut url = format!(
+      "{}{}",
+      self.config.endpoint.trim_end_matches('/'),
+      uri_encode(&path, false)
+    );
+    if !query.is_empty() {
+      let query = query
+        .iter()
+        .map(|(k, v)| format!("{}={}", uri_encode(k, true), uri_encode(v, true)))
+        .collect::<Vec<_>>()
+        .join("&");
+      url = format!("{url}?{query}");
+    }
+    let mut builder = self
+      .client
+      .request(method, url)
+      .header("authorization", signed.authorization)
+      .header("x-amz-date", signed.amz_date)
+      .header("x-amz-content-sha256", signed.payload_hash);
+    for (name, value) in extra_headers {
+      builder = builder.header(*name, value);
+    }
+    let resp = builder.body(body).send().await?;
+    check_status(file_path, resp).await
+  }
+
+  async fn put_object(&self, file_path: &FilePath, body: Vec<u8>) -> ApiResult {
+    self.send(Method::PUT, file_path, &[], &[], body).await?;
+    Ok(())
+  }
+
+  async fn put_multipart(
+    &self,
+    file_path: &FilePath,
+    first_part: Vec<u8>,
+    reader: &mut StorageReader<'_>,
+  ) -> ApiResult<u64> {
+    let upload_id = self.create_multipart(file_path).await?;
+    let result = self
+      .upload_parts(file_path, &upload_id, first_part, reader)
+      .await;
+    if result.is_err() {
+      self.abort_multipart(file_path, &upload_id).await;
+    }
+    result
+  }
+
+  async fn create_multipart(&self, file_path: &FilePath) -> ApiResult<String> {
+    let resp = self
+      .send(Method::POST, file_path, &[("uploads", "")], &[], vec![])
+      .await?;
+    extract_xml_tag(&resp.text().await?, "UploadId").ok_or_else(|| {
+      ApiError::StorageError("The S3 multipart upload response has no UploadId.".to_string())
+    })
+  }
+
+  async fn abort_multipart(&self, file_path: &FilePath, upload_id: &str) {
+    if let Err(err) = self
+      .send(
+        Method::DELETE,
+        file_path,
+        &[("uploadId", upload_id)],
+        &[],
+        vec![],
+      )
+      .await
+    {
+      tracing::warn!("Aborting S3 multipart upload {upload_id} failed, Error: {err}");
+    }
+  }
+
+  /// Copies an object too large for CopyObject with UploadPartCopy requests, each one for a
+  /// range of the source.
+  async fn copy_multipart(&self, from: &FilePath, to: &FilePath, size: u64) -> ApiResult {
+    let upload_id = self.create_multipart(to).await?;
+    let result = self.copy_parts(from, to, &upload_id, size).await;
+    if result.is_err() {
+      self.abort_multipart(to, &upload_id).await;
+    }
+    result
+  }
+
+  async fn copy_parts(
+    &self,
+    from: &FilePath,
+    to: &FilePath,
+    upload_id: &str,
+    size: u64,
+  ) -> ApiResult {
+    let part_size = (self.config.part_bytes_size as u64).max(size.div_ceil(MAX_UPLOAD_PARTS));
+    let source = copy_source(&self.object_path(from));
+    let mut etags = vec![];
+    let mut start = 0;
+    while start < size {
+      let end = (start + part_size).min(size) - 1;
+      let part_number = (etags.len() + 1).to_string();
+      let headers = [
+        (COPY_SOURCE_HEADER, source.clone()),
+        (COPY_SOURCE_RANGE_HEADER, format!("bytes={start}-{end}")),
+      ];
+      let resp = self
+        .send(
+          Method::PUT,
+          to,
+          &[("partNumber", &part_number), ("uploadId", upload_id)],
+          &headers,
+          vec![],
+        )
+        .await?;
+      let etag = extract_xml_tag(&resp.text().await?, "ETag").ok_or_else(|| {
+        ApiError::StorageError(format!("Copying a part of the S3 object {from} failed."))
+      })?;
+      etags.push(etag);
+      start = end + 1;
+    }
+    self.complete_multipart(to, upload_id, &etags).await
+  }
+
+  async fn complete_multipart(
+    &self,
+    file_path: &FilePath,
+    upload_id: &str,
+    etags: &[String],
+  ) -> ApiResult {
+    let body = format!(
+      "<CompleteMultipartUpload>{}</CompleteMultipartUpload>",
+      etags
+        .iter()
+        .enumerate()
+        .map(|(i, etag)| format!(
+          "<Part><PartNumber>{}</PartNumber><ETag>{etag}</ETag></Part>",
+          i + 1
+        ))
+        .collect::<String>()
+    );
+    let resp = self
+      .send(
+        Method::POST,
+        file_path,
+        &[("uploadId", upload_id)],
+        &[],
+        body.into_bytes(),
+      )
+      .await?;
+    // S3 may report a failed completion with a 200 status and an error document.
+    let text = resp.text().await?;
+    if text.contains("<Error>") {
+      return Err(ApiError::StorageError(format!(
+        "Completing the S3 multipart upload failed: {text}"
+      )));
+    }
+    Ok(())
+  }
+
+  async fn upload_parts(
+    &self,
+    file_path: &FilePath,
+    upload_id: &str,
+    mut part: Vec<u8>,
+    reader: &mut StorageReader<'_>,
+  ) -> ApiResult<u64> {
+    let mut bytes_size = 0;
+    let mut etags = vec![];
+    while !part.is_empty() {
+      bytes_size += part.len() as u64;
+      let part_number = (etags.len() + 1).to_string();
+      let resp = self
+        .send(
+          Method::PUT,
+          file_path,
+          &[("partNumber", &part_number), ("uploadId", upload_id)],
+          &[],
+          part,
+        )
+        .await?;
+      let etag = resp
+        .headers()
+        .get("etag")
+        .and_then(|v| v.to_str().ok())
+        .ok_or_else(|| ApiError::StorageError("The S3 part upload has no ETag.".to_string()))?
+        .to_string();
+      etags.push(etag);
+      part = read_part(reader, self.config.part_bytes_size).await?;
+    }
+    self
+      .complete_multipart(file_path, upload_id, &etags)
+      .await?;
+    Ok(bytes_size)
+  }
+}
+
+impl StorageBackend for S3Storage {
+  fn put<'a>(
+    &'a self,
+    file_path: &'a FilePath,
+    mut reader: StorageReader<'a>,
+  ) -> BoxFuture<'a, ApiResult<u64>> {
+    async move {
+      let part = read_part(&mut reader, self.config.part_bytes_size).await?;
+      if part.len() < self.config.part_bytes_size {
+        let bytes_size = part.len() as u64;
+        self.put_object(file_path, part).await?;
+        return Ok(bytes_size);
+      }
+      self.put_multipart(file_path, part, &mut reader).await
+    }
+    .boxed()
+  }
+
+  fn get<'a>(
+    &'a self,
+    file_path: &'a FilePath,
+    range: Option<ByteRange>,
+  ) -> BoxFuture<'a, ApiResult<StorageReader<'static>>> {
+    async move {
+      let headers = range
+        .map(|range| ("range", format!("bytes={}-{}", range.start, range.end)))
+        .into_iter()
+        .collect::<Vec<_>>();
+      let resp = self
+        .send(Method::GET, file_path, &[], &headers, vec![])
+        .await?;
+      let reader: StorageReader<'static> = Box::pin(StreamReader::new(
+        resp.bytes_stream().map_err(std::io::Error::other),
+      ));
+      Ok(reader)
+    }
+    .boxed()
+  }
+
+  fn delete<'a>(&'a self, file_path: &'a FilePath) -> BoxFuture<'a, ApiResult> {
+    async move {
+      self
+        .send(Method::DELETE, file_path, &[], &[], vec![])
+        .await?;
+      Ok(())
+    }
+    .boxed()
+  }
+
+  fn exists<'a>(&'a self, file_path: &'a FilePath) -> BoxFuture<'a, ApiResult<bool>> {
+    async move {
+      match self.send(Method::HEAD, file_path, &[], &[], vec![]).await {
+        Ok(_) => Ok(true),
+        Err(ApiError::IoError(err)) if err.kind() == std::io::ErrorKind::NotFound => Ok(false),
+        Err(err) => Err(err),
+      }
+    }
+    .boxed()
+  }
+
+  /// Copies the object on the server side and deletes the source, so the content is not
+  /// transferred again. Readers never see a partial object, but a failure after the copy
+  /// leaves both objects behind. Objects over the 5GB limit of CopyObject are copied in parts.
+  fn rename<'a>(&'a self, from: &'a FilePath, to: &'a FilePath) -> BoxFuture<'a, ApiResult> {
+    async move {
+      let size = self.size(from).await?;
+      if size > self.max_copy_object_bytes {
+        self.copy_multipart(from, to, size).await?;
+        return self.delete(from).await;
+      }
+      let headers = [(COPY_SOURCE_HEADER, copy_source(&self.object_path(from)))];
+      let resp = self.send(Method::PUT, to, &[], &headers, vec![]).await?;
+      // S3 may report a failed copy with a 200 status and an error document.
+      let text = resp.text().await?;
+      if text.contains("<Error>") {
+        return Err(ApiError::StorageError(format!(
+          "Copying the S3 object {from} to {to} failed: {text}"
+        )));
+      }
+      self.delete(from).await
+    }
+    .boxed()
+  }
+
+  fn size<'a>(&'a self, file_path: &'a FilePath) -> BoxFuture<'a, ApiResult<u64>> {
+    async move {
+      let resp = self.send(Method::HEAD, file_path, &[], &[], vec![]).await?;
+      resp
+        .headers()
+        .get(reqwest::header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse().ok())
+        .ok_or_else(|| {
+          ApiError::StorageError(format!("The S3 object {file_path} has no content length."))
+        })
+    }
+    .boxed()
+  }
+}
+
+/// The value of the copy source header, the object path of the source URL encoded.
+pub fn copy_source(object_path: &str) -> String {
+  uri_encode(object_path, false)
+}
+
+async fn read_part(reader: &mut StorageReader<'_>, part_size: usize) -> ApiResult<Vec<u8>> {
+  let mut part = Vec::with_capacity(part_size);
+  reader.take(part_size as u64).read_to_end(&mut part).await?;
+  Ok(part)
+}
+
+async fn check_status(
+  file_path: &FilePath,
+  resp: reqwest::Response,
+) -> ApiResult<reqwest::Response> {
+  let status = resp.status();
+  if status.is_success() {
+    return Ok(resp);
+  }
+  if status == StatusCode::NOT_FOUND {
+    return Err(ApiError::IoError(std::io::Error::new(
+      std::io::ErrorKind::NotFound,
+      format!("{file_path} not found in S3 storage"),
+    )));
+  }
+  let body = resp.text().await.unwrap_or_default();
+  Err(ApiError::StorageError(format!(
+    "S3 request for {file_path} failed with status {status}: {body}"
+  )))
+}
+
+fn extract_xml_tag(xml: &str, tag: &str) -> Option<String> {
+  let start = xml.find(&format!("<{tag}>"))? + tag.len() + 2;
+  let end = start + xml[start..].find(&format!("</{tag}>"))?;
+  Some(xml[start..end].to_string())
+}
+
+#[cfg(test)]
+mod tests {
+  use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+  };
+
+  use axum::{
+    body::Bytes,
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+  };
+  use fake::{Fake, Faker};
+
+  use super::*;
+
+  type Parts = BTreeMap<u32, Vec<u8>>;
+
+  #[derive(Clone, Default)]
+  struct FakeS3 {
+    objects: Arc<Mutex<HashMap<String, Vec<u8>>>>,
+    uploads: Arc<Mutex<HashMap<String, Parts>>>,
+  }
+
+  // A minimal S3-compatible stand-in serving path-style requests.
+  async fn spawn_fake_s3() -> (String, FakeS3) {
+    let state = FakeS3::default();
+    let router = Router::new()
+      .route(
+        "/:bucket/*key",
+        get(get_object)
+          .put(put_object)
+          .post(post_object)
+          .delete(delete_object),
+      )
+      .with_state(state.clone());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, router).await });
+    (format!("http://{addr}"), state)
+  }
+
+  fn is_signed(headers: &HeaderMap) -> bool {
+    headers
+      .get("authorization")
+      .and_then(|v| v.to_str().ok())
+      .is_some_and(|v| v.starts_with("AWS4-HMAC-SHA256 Credential="))
+  }
+
+  async fn get_object(
+    State(s3): State<FakeS3>,
+    Path((_, key)): Path<(String, String)>,
+    headers: HeaderMap,
+  ) -> Response {
+    if !is_signed(&headers) {
+      return StatusCode::FORBIDDEN.into_response();
+    }
+    let Some(object) = s3.objects.lock().unwrap().get(&key).cloned() else {
+      return StatusCode::NOT_FOUND.into_response();
+    };
+    match crate::util::http::parse_range(&headers, object.len() as u64).unwrap() {
+      Some(range) => (
+        StatusCode::PARTIAL_CONTENT,
+        object[range.start as usize..=range.end as usize].to_vec(),
+      )
+        .into_response(),
+      None => object.into_response(),
+    }
+  }
+
+  fn percent_decode(input: &str) -> String {
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+      if bytes[i] == b'%' {
+        decoded.push(u8::from_str_radix(&input[i + 1..i + 3], 16).unwrap());
+        i += 3;
+      } else {
+        decoded.push(bytes[i]);
+        i += 1;
+      }
+    }
+    String::from_utf8(decoded).unwrap()
+  }
+
+  async fn put_object(
+    State(s3): State<FakeS3>,
+    Path((_, key)): Path<(String, String)>,
+    Query(query): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+    body: Bytes,
+  ) -> Response {
+    if !is_signed(&headers) {
+      return StatusCode::FORBIDDEN.into_response();
+    }
+    if let (Some(upload_id), Some(part)) = (query.get("uploadId"), query.get("partNumber")) {
+      let mut uploads = s3.uploads.lock().unwrap();
+      let Some(parts) = uploads.get_mut(upload_id) else {
+        return StatusCode::NOT_FOUND.into_response();
+      };
+      if let Some(source) = headers.get(COPY_SOURCE_HEADER) {
+        let source = percent_decode(source.to_str().unwrap().splitn(3, '/').nth(2).unwrap());
+        let Some(object) = s3.objects.lock().unwrap().get(&source).cloned() else {
+          return StatusCode::NOT_FOUND.into_response();
+        };
+        let range = headers[COPY_SOURCE_RANGE_HEADER].to_str().unwrap();
+        let (start, end) = range["bytes=".len()..].split_once('-').unwrap();
+        let (start, end): (usize, usize) = (start.parse().unwrap(), end.parse().unwrap());
+        parts.insert(part.parse().unwrap(), object[start..=end].to_vec());
+        return format!("<CopyPartResult><ETag>\"{part}\"</ETag></CopyPartResult>").into_response();
+      }
+      parts.insert(part.parse().unwrap(), body.to_vec());
+      return ([("etag", format!("\"{part}\""))], "").into_response();
+    }
+    if let Some(source) = headers.get(COPY_SOURCE_HEADER) {
+      let source = source.to_str().unwrap().splitn(3, '/').nth(2).unwrap();
+      let source = percent_decode(source);
+      let mut objects = s3.objects.lock().unwrap();
+      let Some(object) = objects.get(&source).cloned() else {
+        return StatusCode::NOT_FOUND.into_response();
+      };
+      objects.insert(key, object);
+      return "<CopyObjectResult></CopyObjectResult>".into_response();
+    }
+    s3.objects.lock().unwrap().insert(key, body.to_vec());
+    StatusCode::OK.into_response()
+  }
+
+  async fn post_object(
+    State(s3): State<FakeS3>,
+    Path((_, key)): Path<(String, String)>,
+    Query(query): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+  ) -> Response {
+    if !is_signed(&headers) {
+      return StatusCode::FORBIDDEN.into_response();
+    }
+    if query.contains_key("uploads") {
+      let upload_id = cuid2::create_id();
+      s3.uploads
+        .lock()
+        .unwrap()
+        .insert(upload_id.clone(), BTreeMap::new());
+      return format!("<InitiateMultipartUploadResult><UploadId>{upload_id}</UploadId></InitiateMultipartUploadResult>")
+        .into_response();
+    }
+    let Some(parts) = query
+      .get("uploadId")
+      .and_then(|id| s3.uploads.lock().unwrap().remove(id))
+    else {
+      return StatusCode::NOT_FOUND.into_response();
+    };
+    s3.objects
+      .lock()
+      .unwrap()
+      .insert(key, parts.into_values().flatten().collect());
+    "<CompleteMultipartUploadResult></CompleteMultipartUploadResult>".into_response()
+  }
+
+  async fn delete_object(
+    State(s3): State<FakeS3>,
+    Path((_, key)): Path<(String, String)>,
+    Query(query): Query<HashMap<String, String>>,
+  ) -> StatusCode {
+    match query.get("uploadId") {
+      Some(upload_id) => {
+        s3.uploads.lock().unwrap().remove(upload_id);
+      }
+      None => {
+        s3.objects.lock().unwrap().remove(&key);
+      }
+    }
+    StatusCode::NO_CONTENT
+  }
+
+  fn s3_config(endpoint: String, part_bytes_size: usize) -> S3Config {
+    S3Config {
+      endpoint,
+      region: "us-east-1".to_string(),
+      bucket: "pf".to_string(),
+      access_key: Faker.fake(),
+      secret_key: Faker.fake(),
+      part_bytes_size,
+    }
+  }
+
+  #[tokio::test]
+  async fn test_put_get_and_delete_object() {
+    let (endpoint, _) = spawn_fake_s3().await;
+    let storage = S3Storage::new(s3_config(endpoint, 1024)).unwrap();
+    let file_path: FilePath = Faker.fake();
+    let size = storage
+      .put(
+        &file_path,
+        Box::pin(std::io::Cursor::new(b"Hello World!".to_vec())),
+      )
+      .await
+      .unwrap();
+    assert_eq!(size, 12);
+    assert_eq!(storage.size(&file_path).await.unwrap(), 12);
+    let mut buf = Vec::new();
+    storage
+      .get(&file_path, Some(ByteRange { start: 6, end: 10 }))
+      .await
+      .unwrap()
+      .read_to_end(&mut buf)
+      .await
+      .unwrap();
+    assert_eq!(buf, b"World");
+    storage.delete(&file_path).await.unwrap();
+    assert!(!storage.exists(&file_path).await.unwrap());
+  }
+
+  #[tokio::test]
+  async fn test_put_object_with_multipart_upload() {
+    let (endpoint, s3) = spawn_fake_s3().await;
+    let storage = S3Storage::new(s3_config(endpoint, 4)).unwrap();
+    let file_path: FilePath = Faker.fake();
+    let content = b"Hello World!!".to_vec();
+    let size = storage
+      .put(&file_path, Box::pin(std::io::Cursor::new(content.clone())))
+      .await
+      .unwrap();
+    assert_eq!(size, content.len() as u64);
+    assert!(s3.uploads.lock().unwrap().is_empty());
+    let mut buf = Vec::new();
+    storage
+      .get(&file_path, None)
+      .await
+      .unwrap()
+      .read_to_end(&mut buf)
+      .await
+      .unwrap();
+    assert_eq!(buf, content);
+  }
+
+  #[tokio::test]
+  async fn test_rename_object_with_server_side_copy() {
+    let (endpoint, s3) = spawn_fake_s3().await;
+    let storage = S3Storage::new(s3_config(endpoint, 1024)).unwrap();
+    let from = FilePath {
+      code: ".staging/code".to_string(),
+      file_name: "my file.txt".to_string(),
+    };
+    let to: FilePath = Faker.fake();
+    storage
+      .put(&from, Box::pin(std::io::Cursor::new(b"data".to_vec())))
+      .await
+      .unwrap();
+    storage.rename(&from, &to).await.unwrap();
+    assert!(!storage.exists(&from).await.unwrap());
+    assert_eq!(
+      s3.objects.lock().unwrap().get(&format!("{to}")).unwrap(),
+      b"data"
+    );
+  }
+
+  #[tokio::test]
+  async fn test_rename_large_object_with_multipart_copy() {
+    let (endpoint, s3) = spawn_fake_s3().await;
+    let mut storage = S3Storage::new(s3_config(endpoint, 4)).unwrap();
+    storage.max_copy_object_bytes = 8;
+    let from: FilePath = Faker.fake();
+    let to: FilePath = Faker.fake();
+    let content = b"Hello World!!".to_vec();
+    storage
+      .put(&from, Box::pin(std::io::Cursor::new(content.clone())))
+      .await
+      .unwrap();
+    storage.rename(&from, &to).await.unwrap();
+    assert!(!storage.exists(&from).await.unwrap());
+    assert!(s3.uploads.lock().unwrap().is_empty());
+    assert_eq!(
+      s3.objects.lock().unwrap().get(&format!("{to}")).unwrap(),
+      &content
+    );
+  }
+
+  #[test]
+  fn test_extract_xml_tag() {
+    let xml = "<Result><UploadId>abc</UploadId></Result>";
+    assert_eq!(extract_xml_tag(xml, "UploadId"), Some("abc".to_string()));
+    assert_eq!(extract_xml_tag(xml, "Key"), None);
+  }
+}
@@ -0,0 +1,348 @@
+use std::io::Write;
+
+use chrono::{DateTime, Datelike, Timelike, Utc};
+use flate2::{write::GzEncoder, Compression};
+use pf_sdk::util::tar;
+
+const ZIP_VERSION: u16 = 20;
+// Sizes are written after the data and file names are UTF-8.
+const ZIP_FLAGS: u16 = 0x0808;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+  Zip,
+  TarGz,
+}
+
+impl ArchiveFormat {
+  /// Splits a path segment like `{code}.zip` into the code and the archive format.
+  pub fn parse(value: &str) -> Option<(&str, Self)> {
+    if let Some(code) = value.strip_suffix(".zip") {
+      Some((code, Self::Zip))
+    } else {
+      value
+        .strip_suffix(".tar.gz")
+        .map(|code| (code, Self::TarGz))
+    }
+  }
+
+  pub fn extension(&self) -> &'static str {
+    match self {
+      Self::Zip => "zip",
+      Self::TarGz => "tar.gz",
+    }
+  }
+
+  pub fn content_type(&self) -> &'static str {
+    match self {
+      Self::Zip => "application/zip",
+      Self::TarGz => "application/gzip",
+    }
+  }
+}
+
+/// Fails when the zip archive of the entries, given by name and size, would need ZIP64
+/// records, which are not written: more than 65535 entries, a name longer than 65535
+/// bytes or an archive over 4 GiB. Checked before the response starts.
+pub fn check_zip_limits<'a>(
+  entries: impl ExactSizeIterator<Item = (&'a str, u64)>,
+) -> std::io::Result<()> {
+  if entries.len() > u16::MAX as usize {
+    return Err(std::io::Error::other(format!(
+      "A zip archive holds at most {} files.",
+      u16::MAX
+    )));
+  }
+  // Local header, data and data descriptor, then the central directory header.
+  let mut size = 22u64;
+  for (name, entry_size) in entries {
+    if name.len() > u16::MAX as usize {
+      return Err(std::io::Error::other(format!(
+        "The file name {name} is too long for a zip archive."
+      )));
+    }
+    size += 30 + name.len() as u64 + entry_size + 16 + 46 + name.len() as u64;
+  }
+  if size > u32::MAX as u64 {
+    return Err(std::io::Error::other("The zip archive exceeds 4 GiB."));
+  }
+  Ok(())
+}
+
+pub struct ZipEntry {
+  name: String,
+  crc: u32,
+  size: u32,
+  offset: u32,
+  time: u16,
+  date: u16,
+}
+
+/// Incrementally encodes files into an archive, each call returns the bytes ready to be sent.
+pub enum ArchiveEncoder {
+  Zip {
+    entries: Vec<ZipEntry>,
+    hasher: crc32fast::Hasher,
+    offset: u64,
+  },
+  TarGz {
+    encoder: GzEncoder<Vec<u8>>,
+    size: u64,
+  },
+}
+
+impl ArchiveEncoder {
+  pub fn new(format: ArchiveFormat) -> Self {
+    match format {
+      ArchiveFormat::Zip => Self::Zip {
+        entries: Vec::new(),
+        hasher: crc32fast::Hasher::new(),
+        offset: 0,
+      },
+      ArchiveFormat::TarGz => Self::TarGz {
+        encoder: GzEncoder::new(Vec::new(), Compression::default()),
+        size: 0,
+      },
+    }
+  }
+
+  pub fn start_entry(
+    &mut self,
+    name: &str,
+    size: u64,
+    modified: DateTime<Utc>,
+  ) -> std::io::Result<Vec<u8>> {
+    match self {
+      Self::Zip {
+        entries,
+        hasher,
+        offset,
+      } => {
+        let (size, local_offset) = match (u32::try_from(size), u32::try_from(*offset)) {
+          (Ok(size), Ok(offset)) => (size, offset),
+          _ => return Err(std::io::Error::other("The zip archive exceeds 4 GiB.")),
+        };
+        let name_len = u16::try_from(name.len())
+          .map_err(|_| std::io::Error::other("The file name is too long for a zip archive."))?;
+        let (time, date) = dos_date_time(modified);
+        let mut buf = Vec::with_capacity(30 + name.len());
+        buf.extend_from_slice(&0x04034b50u32.to_le_bytes());
+        buf.extend_from_slice(&ZIP_VERSION.to_le_bytes());
+        buf.extend_from_slice(&ZIP_FLAGS.to_le_bytes());
+        buf.extend_from_slice(&0u16.to_le_bytes());
+        buf.extend_from_slice(&time.to_le_bytes());
+        buf.extend_from_slice(&date.to_le_bytes());
+        buf.extend_from_slice(&0u32.to_le_bytes());
+        buf.extend_from_slice(&size.to_le_bytes());
+        buf.extend_from_slice(&size.to_le_bytes());
+        buf.extend_from_slice(&name_len.to_le_bytes());
+        buf.extend_from_slice(&0u16.to_le_bytes());
+        buf.extend_from_slice(name.as_bytes());
+        *hasher = crc32fast::Hasher::new();
+        *offset += buf.len() as u64;
+        entries.push(ZipEntry {
+          name: name.to_string(),
+          crc: 0,
+          size,
+          offset: local_offset,
+          time,
+          date,
+        });
+        Ok(buf)
+      }
+      Self::TarGz {
+        size: entry_size, ..
+      } => {
+        let header = tar::entry_header(name, size, 0o644, modified.timestamp(), tar::REGULAR_FILE);
+        *entry_size = size;
+        self.compress(&header)
+      }
+    }
+  }
+
+  pub fn write(&mut self, data: &[u8]) -> std::io::Result<Vec<u8>> {
+    match self {
+      Self::Zip { hasher, offset, .. } => {
+        hasher.update(data);
+        *offset += data.len() as u64;
+        Ok(data.to_vec())
+      }
+      Self::TarGz { .. } => self.compress(data),
+    }
+  }
+
+  pub fn finish_entry(&mut self) -> std::io::Result<Vec<u8>> {
+    match self {
+      Self::Zip {
+        entries,
+        hasher,
+        offset,
+      } => {
+        let entry = entries
+          .last_mut()
+          .ok_or_else(|| std::io::Error::other("No zip entry is started."))?;
+        entry.crc = std::mem::take(hasher).finalize();
+        let mut buf = Vec::with_capacity(16);
+        buf.extend_from_slice(&0x08074b50u32.to_le_bytes());
+        buf.extend_from_slice(&entry.crc.to_le_bytes());
+        buf.extend_from_slice(&entry.size.to_le_bytes());
+        buf.extend_from_slice(&entry.size.to_le_bytes());
+        *offset += buf.len() as u64;
+        Ok(buf)
+      }
+      Self::TarGz { size, .. } => {
+        let padding = vec![0; tar::padding(*size)];
+        self.compress(&padding)
+      }
+    }
+  }
+
+  pub fn finish(self) -> std::io::Result<Vec<u8>> {
+    match self {
+      Self::Zip {
+        entries, offset, ..
+      } => {
+        let central_offset = u32::try_from(offset)
+          .map_err(|_| std::io::Error::other("The zip archive exceeds 4 GiB."))?;
+        let count = u16::try_from(entries.len())
+          .map_err(|_| std::io::Error::other("The zip archive has too many files."))?;
+        let mut buf = Vec::new();
+        for entry in &entries {
+          buf.extend_from_slice(&0x02014b50u32.to_le_bytes());
+          buf.extend_from_slice(&ZIP_VERSION.to_le_bytes());
+          buf.extend_from_slice(&ZIP_VERSION.to_le_bytes());
+          buf.extend_from_slice(&ZIP_FLAGS.to_le_bytes());
+          buf.extend_from_slice(&0u16.to_le_bytes());
+          buf.extend_from_slice(&entry.time.to_le_bytes());
+          buf.extend_from_slice(&entry.date.to_le_bytes());
+          buf.extend_from_slice(&entry.crc.to_le_bytes());
+          buf.extend_from_slice(&entry.size.to_le_bytes());
+          buf.extend_from_slice(&entry.size.to_le_bytes());
+          buf.extend_from_slice(&(entry.name.len() as u16).to_le_bytes());
+          // Extra field, comment, disk number, internal and external attributes.
+          buf.extend_from_slice(&[0; 12]);
+          buf.extend_from_slice(&entry.offset.to_le_bytes());
+          buf.extend_from_slice(entry.name.as_bytes());
+        }
+        let central_size = u32::try_from(buf.len())
+          .map_err(|_| std::io::Error::other("The zip archive exceeds 4 GiB."))?;
+        if central_offset.checked_add(central_size).is_none() {
+          return Err(std::io::Error::other("The zip archive exceeds 4 GiB."));
+        }
+        buf.extend_from_slice(&0x06054b50u32.to_le_bytes());
+        buf.extend_from_slice(&[0; 4]);
+        buf.extend_from_slice(&count.to_le_bytes());
+        buf.extend_from_slice(&count.to_le_bytes());
+        buf.extend_from_slice(&central_size.to_le_bytes());
+        buf.extend_from_slice(&central_offset.to_le_bytes());
+        buf.extend_from_slice(&0u16.to_le_bytes());
+        Ok(buf)
+      }
+      Self::TarGz { mut encoder, .. } => {
+        encoder.write_all(&tar::end_of_archive())?;
+        encoder.finish()
+      }
+    }
+  }
+
+  fn compress(&mut self, data: &[u8]) -> std::io::Result<Vec<u8>> {
+    let Self::TarGz { encoder, .. } = self else {
+      return Err(std::io::Error::other("The archive is not compressed."));
+    };
+    encoder.write_all(data)?;
+    Ok(std::mem::take(encoder.get_mut()))
+  }
+}
+
+fn dos_date_time(date_time: DateTime<Utc>) -> (u16, u16) {
+  if date_time.year() < 1980 {
+    return (0, (1 << 5) | 1);
+  }
+  let time = (date_time.hour() << 11) | (date_time.minute() << 5) | (date_time.second() / 2);
+  let date = (((date_time.year() - 1980) as u32) << 9) | (date_time.month() << 5) | date_time.day();
+  (time as u16, date as u16)
+}
+
+#[cfg(test)]
+mod tests {
+  use std::io::Read;
+
+  use super::*;
+
+  fn encode(format: ArchiveFormat, files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut encoder = ArchiveEncoder::new(format);
+    let mut output = Vec::new();
+    for (name, content) in files {
+      output.extend(
+        encoder
+          .start_entry(name, content.len() as u64, Utc::now())
+          .unwrap(),
+      );
+      output.extend(encoder.write(content).unwrap());
+      output.extend(encoder.finish_entry().unwrap());
+    }
+    output.extend(encoder.finish().unwrap());
+    output
+  }
+
+  #[test]
+  fn test_parse_archive_format() {
+    assert_eq!(
+      ArchiveFormat::parse("abc.zip"),
+      Some(("abc", ArchiveFormat::Zip))
+    );
+    assert_eq!(
+      ArchiveFormat::parse("abc.tar.gz"),
+      Some(("abc", ArchiveFormat::TarGz))
+    );
+    assert_eq!(ArchiveFormat::parse("abc"), None);
+  }
+
+  #[test]
+  fn test_encode_tar_gz() {
+    let long_name = format!("{}.txt", "a".repeat(120));
+    let output = encode(
+      ArchiveFormat::TarGz,
+      &[("hello.txt", b"hello"), (&long_name, b"world")],
+    );
+    let mut tar = Vec::new();
+    flate2::read::GzDecoder::new(output.as_slice())
+      .read_to_end(&mut tar)
+      .unwrap();
+    assert_eq!(tar.len() % tar::BLOCK_SIZE, 0);
+    assert_eq!(&tar[..9], b"hello.txt");
+    assert_eq!(&tar[257..262], b"ustar");
+    assert_eq!(&tar[tar::BLOCK_SIZE..tar::BLOCK_SIZE + 5], b"hello");
+    assert_eq!(tar[tar::BLOCK_SIZE * 2 + 156], b'L');
+    assert_eq!(
+      &tar[tar::BLOCK_SIZE * 3..tar::BLOCK_SIZE * 3 + long_name.len()],
+      long_name.as_bytes()
+    );
+  }
+
+  #[test]
+  fn test_encode_zip() {
+    let output = encode(ArchiveFormat::Zip, &[("hello.txt", b"hello")]);
+    assert_eq!(&output[..4], &0x04034b50u32.to_le_bytes());
+    assert_eq!(&output[30..39], b"hello.txt");
+    assert_eq!(&output[39..44], b"hello");
+    let crc = crc32fast::hash(b"hello").to_le_bytes();
+    assert_eq!(&output[48..52], &crc);
+    let end = &output[output.len() - 22..];
+    assert_eq!(&end[..4], &0x06054b50u32.to_le_bytes());
+    assert_eq!(u16::from_le_bytes([end[10], end[11]]), 1);
+    assert_eq!(u32::from_le_bytes([end[16], end[17], end[18], end[19]]), 60);
+  }
+
+  #[test]
+  fn test_check_zip_limits() {
+    let output = encode(ArchiveFormat::Zip, &[("hello.txt", b"hello")]);
+    let limit = u32::MAX as u64 - output.len() as u64 + 5;
+    check_zip_limits([("hello.txt", limit)].into_iter()).unwrap();
+    check_zip_limits([("hello.txt", limit + 1)].into_iter()).unwrap_err();
+    check_zip_limits(std::iter::repeat_n(("a", 0), u16::MAX as usize)).unwrap();
+    check_zip_limits(std::iter::repeat_n(("a", 0), u16::MAX as usize + 1)).unwrap_err();
+    let long_name = "a".repeat(u16::MAX as usize + 1);
+    check_zip_limits([(long_name.as_str(), 0)].into_iter()).unwrap_err();
+  }
+}
@@ -1,6 +1,14 @@
-use hyper::HeaderMap;
+use base64::Engine;
+use hyper::{
+  header::{AUTHORIZATION, IF_NONE_MATCH, IF_RANGE, RANGE},
+  HeaderMap,
+};
 
-use crate::error::{invalid_input_error, result::ApiResult};
+use crate::{
+  error::{invalid_input_error, result::ApiResult, ApiError},
+  storage::ByteRange,
+};
+use pf_sdk::dto::API_KEY_HEADER;
 
 use super::secret::Secret;
 
@@ -19,3 +27,174 @@ pub fn parse_basic_auth(headers: &HeaderMap) -> ApiResult<Option<Secret>> {
     Ok(None)
   }
 }
+
+pub fn parse_bearer_token(headers: &HeaderMap) -> Option<&str> {
+  headers
+    .get(AUTHORIZATION)
+    .and_then(|v| v.to_str().ok())
+    .and_then(|v| v.strip_prefix("Bearer "))
+    .map(str::trim)
+}
+
+pub fn parse_api_key(headers: &HeaderMap) -> ApiResult<Option<String>> {
+  headers
+    .get(API_KEY_HEADER)
+    .map(|value| {
+      value
+        .to_str()
+        .map(|v| v.trim().to_string())
+        .map_err(|_e| invalid_input_error(API_KEY_HEADER, "Invalid api key header"))
+    })
+    .transpose()
+}
+
+// Account endpoints decode the header into the `username:password` pair it was built from.
+pub fn parse_basic_credentials(headers: &HeaderMap) -> ApiResult<Option<(String, String)>> {
+  let Some(secret) = parse_basic_auth(headers)? else {
+    return Ok(None);
+  };
+  let credentials = base64::engine::general_purpose::STANDARD
+    .decode(secret.as_str())
+    .ok()
+    .and_then(|bytes| String::from_utf8(bytes).ok())
+    .ok_or_else(|| invalid_input_error("Authorization", "Invalid auth header"))?;
+  let (username, password) = credentials
+    .split_once(':')
+    .ok_or_else(|| invalid_input_error("Authorization", "Invalid auth header"))?;
+  Ok(Some((username.to_string(), password.to_string())))
+}
+
+pub fn parse_range(headers: &HeaderMap, total_size: u64) -> ApiResult<Option<ByteRange>> {
+  let Some(value) = headers.get(RANGE).and_then(|v| v.to_str().ok()) else {
+    return Ok(None);
+  };
+  // Malformed or multi-range headers are ignored and the whole file is served.
+  let Some(spec) = value.trim().strip_prefix("bytes=") else {
+    return Ok(None);
+  };
+  if spec.contains(',') {
+    return Ok(None);
+  }
+  let Some((start, end)) = spec.trim().split_once('-') else {
+    return Ok(None);
+  };
+  let range = match (start.parse::<u64>().ok(), end.parse::<u64>().ok()) {
+    (Some(start), Some(end)) if start <= end => ByteRange {
+      start,
+      end: end.min(total_size.saturating_sub(1)),
+    },
+    (Some(start), None) if end.is_empty() => ByteRange {
+      start,
+      end: total_size.saturating_sub(1),
+    },
+    (None, Some(suffix)) if start.is_empty() && suffix > 0 => ByteRange {
+      start: total_size.saturating_sub(suffix),
+      end: total_size.saturating_sub(1),
+    },
+    _ => return Ok(None),
+  };
+  if total_size == 0 || range.start >= total_size {
+    return Err(ApiError::RangeNotSatisfiableError(format!(
+      "The range {value} is not satisfiable for a file of {total_size} bytes."
+    )));
+  }
+  Ok(Some(range))
+}
+
+pub fn if_none_match(headers: &HeaderMap, etag: &str) -> bool {
+  let Some(value) = headers.get(IF_NONE_MATCH).and_then(|v| v.to_str().ok()) else {
+    return false;
+  };
+  value
+    .split(',')
+    .map(str::trim)
+    .any(|tag| tag == "*" || tag.strip_prefix("W/").unwrap_or(tag).trim_matches('"') == etag)
+}
+
+// A date or weak validator never matches, so the full file is served instead of a range.
+pub fn if_range_matches(headers: &HeaderMap, etag: &str) -> bool {
+  match headers.get(IF_RANGE).and_then(|v| v.to_str().ok()) {
+    Some(value) => {
+      let value = value.trim();
+      value.starts_with('"') && value.trim_matches('"') == etag
+    }
+    None => true,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use hyper::header::HeaderValue;
+
+  use super::*;
+
+  fn range_headers(value: &'static str) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert(RANGE, HeaderValue::from_static(value));
+    headers
+  }
+
+  #[test]
+  fn test_if_none_match() {
+    let mut headers = HeaderMap::new();
+    assert!(!if_none_match(&headers, "abc"));
+    headers.insert(
+      IF_NONE_MATCH,
+      HeaderValue::from_static("\"xyz\", W/\"abc\""),
+    );
+    assert!(if_none_match(&headers, "abc"));
+    assert!(!if_none_match(&headers, "def"));
+    headers.insert(IF_NONE_MATCH, HeaderValue::from_static("*"));
+    assert!(if_none_match(&headers, "def"));
+  }
+
+  #[test]
+  fn test_if_range_matches() {
+    let mut headers = HeaderMap::new();
+    assert!(if_range_matches(&headers, "abc"));
+    headers.insert(IF_RANGE, HeaderValue::from_static("\"abc\""));
+    assert!(if_range_matches(&headers, "abc"));
+    assert!(!if_range_matches(&headers, "def"));
+    headers.insert(
+      IF_RANGE,
+      HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"),
+    );
+    assert!(!if_range_matches(&headers, "abc"));
+  }
+
+  #[test]
+  fn test_parse_basic_credentials() {
+    let mut headers = HeaderMap::new();
+    assert_eq!(parse_basic_credentials(&headers).unwrap(), None);
+    headers.insert(
+      hyper::header::AUTHORIZATION,
+      HeaderValue::from_static("Basic YWxpY2U6czNjcjN0OnBhc3M="),
+    );
+    assert_eq!(
+      parse_basic_credentials(&headers).unwrap(),
+      Some(("alice".to_string(), "s3cr3t:pass".to_string()))
+    );
+    headers.insert(
+      hyper::header::AUTHORIZATION,
+      HeaderValue::from_static("Basic not-base64"),
+    );
+    assert!(parse_basic_credentials(&headers).is_err());
+  }
+
+  #[test]
+  fn test_parse_range() {
+    let range = parse_range(&range_headers("bytes=0-4"), 10).unwrap();
+    assert_eq!(range, Some(ByteRange { start: 0, end: 4 }));
+    let range = parse_range(&range_headers("bytes=5-"), 10).unwrap();
+    assert_eq!(range, Some(ByteRange { start: 5, end: 9 }));
+    let range = parse_range(&range_headers("bytes=-3"), 10).unwrap();
+    assert_eq!(range, Some(ByteRange { start: 7, end: 9 }));
+    let range = parse_range(&range_headers("bytes=5-100"), 10).unwrap();
+    assert_eq!(range, Some(ByteRange { start: 5, end: 9 }));
+    let range = parse_range(&range_headers("bytes=0-1,4-5"), 10).unwrap();
+    assert_eq!(range, None);
+    let range = parse_range(&HeaderMap::new(), 10).unwrap();
+    assert_eq!(range, None);
+    assert!(parse_range(&range_headers("bytes=10-"), 10).is_err());
+  }
+}
@@ -1,9 +1,11 @@
+pub mod archive;
 pub mod file_name;
 pub mod hash;
 pub mod http;
 pub mod multipart;
 pub mod path;
 pub mod qr_code;
+pub mod reader;
 pub mod secret;
 pub mod task;
 pub mod test;
@@ -5,7 +5,17 @@ use axum::{
 use hyper::header::CONTENT_TYPE;
 
 pub async fn create_multipart_request(file_name: &str, data: &str) -> anyhow::Result<Multipart> {
-  let data = format!("--X-BOUNDARY\r\nContent-Disposition: form-data; name=\"file\"; filename=\"{file_name}\"\r\n\r\n{data}\r\n--X-BOUNDARY--\r\n");
+  create_multi_file_multipart_request(&[(file_name, data)]).await
+}
+
+pub async fn create_multi_file_multipart_request(
+  files: &[(&str, &str)],
+) -> anyhow::Result<Multipart> {
+  let mut data = String::new();
+  for (file_name, content) in files {
+    data.push_str(&format!("--X-BOUNDARY\r\nContent-Disposition: form-data; name=\"file\"; filename=\"{file_name}\"\r\n\r\n{content}\r\n"));
+  }
+  data.push_str("--X-BOUNDARY--\r\n");
   let body = Body::from(Bytes::from(data));
   let request = Request::builder()
     .header(CONTENT_TYPE, "multipart/form-data; boundary=X-BOUNDARY")
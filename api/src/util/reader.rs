@@ -0,0 +1,269 @@
+use std::{
+  pin::Pin,
+  task::{Context, Poll},
+};
+
+use sha2::{Digest, Sha256};
+use tokio::io::{AsyncRead, ReadBuf};
+
+#[derive(Debug, thiserror::Error)]
+#[error("stream exceeded the maximum size of {0} bytes")]
+pub struct SizeLimitExceeded(pub usize);
+
+#[derive(Debug, thiserror::Error)]
+#[error("stream was rejected: {0}")]
+pub struct Rejected(pub String);
+
+/// Fails the read as soon as more than `max_size` bytes have passed through.
+pub struct LimitedReader<R> {
+  inner: R,
+  max_size: usize,
+  bytes_size: usize,
+}
+
+impl<R> LimitedReader<R> {
+  pub fn new(inner: R, max_size: usize) -> Self {
+    Self {
+      inner,
+      max_size,
+      bytes_size: 0,
+    }
+  }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for LimitedReader<R> {
+  fn poll_read(
+    mut self: Pin<&mut Self>,
+    cx: &mut Context<'_>,
+    buf: &mut ReadBuf<'_>,
+  ) -> Poll<std::io::Result<()>> {
+    let filled = buf.filled().len();
+    match Pin::new(&mut self.inner).poll_read(cx, buf) {
+      Poll::Ready(Ok(())) => {
+        self.bytes_size += buf.filled().len() - filled;
+        if self.bytes_size > self.max_size {
+          buf.set_filled(filled);
+          return Poll::Ready(Err(std::io::Error::other(SizeLimitExceeded(self.max_size))));
+        }
+        Poll::Ready(Ok(()))
+      }
+      poll => poll,
+    }
+  }
+}
+
+/// Runs `on_complete` once `len` bytes have been read. Servers stop polling a
+/// body after Content-Length bytes, so the end of the stream is never observed.
+pub struct OnCompleteReader<R, F> {
+  inner: R,
+  remaining: u64,
+  on_complete: Option<F>,
+}
+
+impl<R, F> OnCompleteReader<R, F> {
+  pub fn new(inner: R, len: u64, on_complete: F) -> Self {
+    Self {
+      inner,
+      remaining: len,
+      on_complete: Some(on_complete),
+    }
+  }
+}
+
+impl<R: AsyncRead + Unpin, F: FnOnce() + Unpin> AsyncRead for OnCompleteReader<R, F> {
+  fn poll_read(
+    mut self: Pin<&mut Self>,
+    cx: &mut Context<'_>,
+    buf: &mut ReadBuf<'_>,
+  ) -> Poll<std::io::Result<()>> {
+    let filled = buf.filled().len();
+    let poll = Pin::new(&mut self.inner).poll_read(cx, buf);
+    if let Poll::Ready(Ok(())) = poll {
+      let read = (buf.filled().len() - filled) as u64;
+      self.remaining = self.remaining.saturating_sub(read);
+      if self.remaining == 0 {
+        if let Some(on_complete) = self.on_complete.take() {
+          on_complete();
+        }
+      }
+    }
+    poll
+  }
+}
+
+/// Runs `on_progress` after every read that returned data.
+pub struct ProgressReader<R, F> {
+  inner: R,
+  on_progress: F,
+}
+
+impl<R, F> ProgressReader<R, F> {
+  pub fn new(inner: R, on_progress: F) -> Self {
+    Self { inner, on_progress }
+  }
+}
+
+impl<R: AsyncRead + Unpin, F: FnMut() + Unpin> AsyncRead for ProgressReader<R, F> {
+  fn poll_read(
+    mut self: Pin<&mut Self>,
+    cx: &mut Context<'_>,
+    buf: &mut ReadBuf<'_>,
+  ) -> Poll<std::io::Result<()>> {
+    let filled = buf.filled().len();
+    let poll = Pin::new(&mut self.inner).poll_read(cx, buf);
+    if let Poll::Ready(Ok(())) = poll {
+      if buf.filled().len() > filled {
+        (self.on_progress)();
+      }
+    }
+    poll
+  }
+}
+
+/// Runs `check` with the size of every read that returned data, the read fails with
+/// `Rejected` when the check fails and the data is not passed on.
+pub struct CheckedReader<R, F> {
+  inner: R,
+  check: F,
+}
+
+impl<R, F> CheckedReader<R, F> {
+  pub fn new(inner: R, check: F) -> Self {
+    Self { inner, check }
+  }
+}
+
+impl<R, F> AsyncRead for CheckedReader<R, F>
+where
+  R: AsyncRead + Unpin,
+  F: FnMut(u64) -> Result<(), String> + Unpin,
+{
+  fn poll_read(
+    mut self: Pin<&mut Self>,
+    cx: &mut Context<'_>,
+    buf: &mut ReadBuf<'_>,
+  ) -> Poll<std::io::Result<()>> {
+    let filled = buf.filled().len();
+    let poll = Pin::new(&mut self.inner).poll_read(cx, buf);
+    if let Poll::Ready(Ok(())) = poll {
+      let read = (buf.filled().len() - filled) as u64;
+      if read > 0 {
+        if let Err(reason) = (self.check)(read) {
+          buf.set_filled(filled);
+          return Poll::Ready(Err(std::io::Error::other(Rejected(reason))));
+        }
+      }
+    }
+    poll
+  }
+}
+
+/// Hashes the bytes with SHA-256 while they are read.
+pub struct HashReader<R> {
+  inner: R,
+  hasher: Sha256,
+}
+
+impl<R> HashReader<R> {
+  pub fn new(inner: R) -> Self {
+    Self {
+      inner,
+      hasher: Sha256::new(),
+    }
+  }
+
+  /// Hex digest of the bytes read so far.
+  pub fn digest(self) -> String {
+    hex::encode(self.hasher.finalize())
+  }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for HashReader<R> {
+  fn poll_read(
+    mut self: Pin<&mut Self>,
+    cx: &mut Context<'_>,
+    buf: &mut ReadBuf<'_>,
+  ) -> Poll<std::io::Result<()>> {
+    let filled = buf.filled().len();
+    let poll = Pin::new(&mut self.inner).poll_read(cx, buf);
+    if let Poll::Ready(Ok(())) = poll {
+      self.hasher.update(&buf.filled()[filled..]);
+    }
+    poll
+  }
+}
+
+pub fn is_size_limit_exceeded(err: &std::io::Error) -> bool {
+  err
+    .get_ref()
+    .is_some_and(|inner| inner.is::<SizeLimitExceeded>())
+}
+
+/// The reason a `CheckedReader` rejected the stream, if it did.
+pub fn rejected_reason(err: &std::io::Error) -> Option<&str> {
+  err
+    .get_ref()
+    .and_then(|inner| inner.downcast_ref::<Rejected>())
+    .map(|rejected| rejected.0.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+  use tokio::io::AsyncReadExt;
+
+  use super::*;
+
+  #[tokio::test]
+  async fn test_limited_reader_exceeds_max_size() {
+    let mut reader = LimitedReader::new(std::io::Cursor::new(vec![0u8; 10]), 5);
+    let err = reader.read_to_end(&mut Vec::new()).await.unwrap_err();
+    assert!(is_size_limit_exceeded(&err));
+  }
+
+  #[tokio::test]
+  async fn test_on_complete_reader_runs_callback_after_last_byte() {
+    let called = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+    let flag = called.clone();
+    let mut reader = OnCompleteReader::new(std::io::Cursor::new(vec![0u8; 10]), 10, move || {
+      flag.store(true, std::sync::atomic::Ordering::SeqCst)
+    });
+    let mut buf = [0u8; 4];
+    reader.read_exact(&mut buf).await.unwrap();
+    assert!(!called.load(std::sync::atomic::Ordering::SeqCst));
+    let mut buf = [0u8; 6];
+    reader.read_exact(&mut buf).await.unwrap();
+    assert!(called.load(std::sync::atomic::Ordering::SeqCst));
+  }
+
+  #[tokio::test]
+  async fn test_hash_reader_digest() {
+    let mut reader = HashReader::new(std::io::Cursor::new(b"hello".to_vec()));
+    reader.read_to_end(&mut Vec::new()).await.unwrap();
+    assert_eq!(
+      reader.digest(),
+      "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+    );
+  }
+
+  #[tokio::test]
+  async fn test_limited_reader_within_max_size() {
+    let mut reader = LimitedReader::new(std::io::Cursor::new(vec![0u8; 10]), 10);
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf).await.unwrap();
+    assert_eq!(buf.len(), 10);
+  }
+
+  #[tokio::test]
+  async fn test_checked_reader_rejects_read() {
+    let mut left = 6u64;
+    let reader = std::io::Cursor::new(vec![0u8; 10]);
+    let mut reader = CheckedReader::new(reader, |n| {
+      left = left.checked_sub(n).ok_or("no bytes left")?;
+      Ok(())
+    });
+    let mut buf = [0u8; 4];
+    reader.read_exact(&mut buf).await.unwrap();
+    let err = reader.read_exact(&mut buf).await.unwrap_err();
+    assert_eq!(rejected_reason(&err), Some("no bytes left"));
+  }
+}
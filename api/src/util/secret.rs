@@ -13,6 +13,10 @@ impl Secret {
     Self(secret)
   }
 
+  pub fn as_str(&self) -> &str {
+    &self.0
+  }
+
   pub fn verify(&self, hash: &SecretHash) -> Result<(), argon2::password_hash::Error> {
     crate::util::hash::argon_verify(&self.0, &hash.0)
   }
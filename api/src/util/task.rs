@@ -1,6 +1,11 @@
+use tokio_util::sync::CancellationToken;
 use tracing::error;
 
-use crate::error::{result::ApiResult, ApiError};
+use crate::{
+  error::{result::ApiResult, ApiError},
+  server::ApiState,
+  service,
+};
 
 /// If a task is fail fast after encounter an error node goes down.
 pub type IsFailFast = bool;
@@ -10,30 +15,77 @@ pub type ApiTask = (
   futures_util::future::BoxFuture<'static, ApiResult>,
 );
 
+/// Waits until every task is finished, or the first fail fast task fails.
 pub async fn join_all(tasks: Vec<ApiTask>) -> ApiResult {
   let (sender, mut receiver) = tokio::sync::mpsc::channel::<ApiError>(1);
+  let mut handles = vec![];
   for (name, is_fail_fast, task) in tasks {
     let sender = if is_fail_fast {
       Some(sender.clone())
     } else {
       None
     };
-    tokio::spawn(async move {
+    handles.push(tokio::spawn(async move {
       tracing::info!("Task {name} started.");
-      if let Err(e) = task.await {
-        if let Some(sender) = sender {
-          sender
-            .send(e)
-            .await
-            .unwrap_or_else(|_| unreachable!("This channel never closed."));
-        } else {
-          error!("A task failed: {e}.");
+      match task.await {
+        Ok(()) => tracing::info!("Task {name} stopped."),
+        Err(e) => {
+          if let Some(sender) = sender {
+            // The receiver is gone once another task failed first.
+            let _ = sender.send(e).await;
+          } else {
+            error!("A task failed: {e}.");
+          }
         }
       }
-    });
+    }));
   }
-  match receiver.recv().await {
-    Some(err) => Err(err),
-    None => unreachable!("This channel never closed."),
+  drop(sender);
+  tokio::select! {
+    Some(err) = receiver.recv() => return Err(err),
+    _ = futures_util::future::join_all(handles) => {}
+  }
+  match receiver.try_recv() {
+    Ok(err) => Err(err),
+    Err(_) => Ok(()),
+  }
+}
+
+/// Cancels the token on SIGTERM or Ctrl-C.
+pub async fn shutdown_signal(shutdown: CancellationToken) -> ApiResult {
+  #[cfg(unix)]
+  let mut terminate = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())?;
+  #[cfg(unix)]
+  let terminate = terminate.recv();
+  #[cfg(not(unix))]
+  let terminate = std::future::pending::<Option<()>>();
+  tokio::select! {
+    result = tokio::signal::ctrl_c() => result?,
+    _ = terminate => {},
+    _ = shutdown.cancelled() => return Ok(()),
+  }
+  tracing::info!("Received the shutdown signal.");
+  shutdown.cancel();
+  Ok(())
+}
+
+/// Reloads the settings on every SIGHUP until the shutdown starts, a rejected reload keeps
+/// the current settings.
+pub async fn reload_signal(state: ApiState) -> ApiResult {
+  #[cfg(unix)]
+  let mut hangup = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup())?;
+  loop {
+    #[cfg(unix)]
+    let hangup = hangup.recv();
+    #[cfg(not(unix))]
+    let hangup = std::future::pending::<Option<()>>();
+    tokio::select! {
+      _ = hangup => {},
+      _ = state.shutdown.cancelled() => return Ok(()),
+    }
+    tracing::info!("Received the reload signal.");
+    if let Err(err) = service::reload::reload(&state) {
+      error!("Failed to reload the settings, Error: {err}");
+    }
   }
 }
@@ -30,10 +30,10 @@ impl AsyncTestContext for StateTestContext {
 
   async fn teardown(self) {
     self.gc_task.abort();
-    tokio::fs::remove_dir_all(&self.state.config.db.path_dir)
+    tokio::fs::remove_dir_all(&self.state.config.load().db.path_dir)
       .await
       .unwrap();
-    tokio::fs::remove_dir_all(&self.state.config.fs.base_dir)
+    tokio::fs::remove_dir_all(&self.state.config.load().fs.base_dir)
       .await
       .unwrap();
   }
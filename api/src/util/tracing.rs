@@ -1,3 +1,48 @@
 use once_cell::sync::Lazy;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_subscriber::{
+  fmt::writer::BoxMakeWriter, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter, Layer,
+};
 
-pub static INIT_SUBSCRIBER: Lazy<()> = Lazy::new(|| tracing_subscriber::fmt().init());
+use crate::{
+  configure::{LogConfig, LogFormat, CONFIG},
+  error::result::ApiResult,
+};
+
+pub static INIT_SUBSCRIBER: Lazy<Option<WorkerGuard>> =
+  Lazy::new(|| init_subscriber(&CONFIG.log).unwrap());
+
+/// Installs the global subscriber, logs written to a file are flushed when the guard is dropped.
+pub fn init_subscriber(config: &LogConfig) -> ApiResult<Option<WorkerGuard>> {
+  let filter = match std::env::var(EnvFilter::DEFAULT_ENV) {
+    Ok(directives) => EnvFilter::try_new(directives),
+    Err(_) => EnvFilter::try_new(&config.level),
+  }
+  .map_err(|err| anyhow::anyhow!("Invalid log filter, Error: {err}"))?;
+  let (writer, guard, ansi) = match config.file.as_ref() {
+    Some(path) => {
+      let dir = path.parent().unwrap_or(path);
+      let file_name = path
+        .file_name()
+        .ok_or_else(|| anyhow::anyhow!("Invalid log file path {}", path.display()))?;
+      std::fs::create_dir_all(dir)?;
+      let (writer, guard) =
+        tracing_appender::non_blocking(tracing_appender::rolling::never(dir, file_name));
+      (BoxMakeWriter::new(writer), Some(guard), false)
+    }
+    None => (BoxMakeWriter::new(std::io::stdout), None, true),
+  };
+  let layer = tracing_subscriber::fmt::layer()
+    .with_writer(writer)
+    .with_ansi(ansi);
+  let layer = match config.format {
+    LogFormat::Text => layer.boxed(),
+    LogFormat::Pretty => layer.pretty().boxed(),
+    LogFormat::Json => layer.json().with_current_span(true).boxed(),
+  };
+  tracing_subscriber::registry()
+    .with(layer.with_filter(filter))
+    .try_init()
+    .map_err(|err| anyhow::anyhow!("Failed to install the tracing subscriber, Error: {err}"))?;
+  Ok(guard)
+}
@@ -0,0 +1,176 @@
+use crate::helper::ApiTestContext;
+use crate::{assert_response_err, assert_response_ok, unwrap};
+use pf_api::configure::CONFIG;
+use pf_sdk::dto::{
+  request::{ApiKeyScope, UploadQueryParam},
+  response::BodyResponseError,
+  FileUrlPath,
+};
+use test_context::{test_context, AsyncTestContext};
+
+fn auth(username: &str) -> (String, String) {
+  (username.to_string(), "password123".to_string())
+}
+
+#[test_context(ApiTestContext)]
+#[tokio::test]
+pub async fn test_create_list_and_revoke_api_key(ctx: &mut ApiTestContext) {
+  let (status, resp) = ctx
+    .create_account("alice".to_string(), "password123".to_string())
+    .await
+    .unwrap();
+  assert!(status.is_success(), "status: {status}");
+  assert!(!unwrap!(resp).is_admin);
+  let (status, resp) = ctx
+    .create_account("alice".to_string(), "password123".to_string())
+    .await
+    .unwrap();
+  assert_response_err!(resp, |e: &BodyResponseError| e.error_type
+    == "RESOURCE_EXISTS");
+  assert_eq!(status, reqwest::StatusCode::CONFLICT);
+  let (_, resp) = ctx
+    .create_api_key(
+      auth("alice"),
+      "ci".to_string(),
+      vec![ApiKeyScope::Upload, ApiKeyScope::Download],
+    )
+    .await
+    .unwrap();
+  let api_key = unwrap!(resp);
+  assert!(api_key.key.is_some());
+  let (_, resp) = ctx.list_api_keys(auth("alice")).await.unwrap();
+  let api_keys = unwrap!(resp);
+  assert_eq!(api_keys.len(), 1);
+  assert_eq!(api_keys[0].id, api_key.id);
+  assert!(api_keys[0].key.is_none());
+  let (_, resp) = ctx
+    .revoke_api_key(auth("alice"), &api_key.id)
+    .await
+    .unwrap();
+  assert_response_ok!(resp);
+  let (_, resp) = ctx.list_api_keys(auth("alice")).await.unwrap();
+  assert!(unwrap!(resp).is_empty());
+}
+
+#[tokio::test]
+pub async fn test_registration_is_disabled_by_default() {
+  let ctx = ApiTestContext::with_config(|config| config.allow_registration = false).await;
+  let (status, resp) = ctx
+    .create_account("alice".to_string(), "password123".to_string())
+    .await
+    .unwrap();
+  assert_response_err!(resp, |e: &BodyResponseError| e.error_type
+    == "PERMISSION_DENIED");
+  assert_eq!(status, reqwest::StatusCode::FORBIDDEN);
+  assert!(!CONFIG.allow_registration);
+  ctx.teardown().await;
+}
+
+#[test_context(ApiTestContext)]
+#[tokio::test]
+pub async fn test_list_api_keys_with_invalid_password(ctx: &mut ApiTestContext) {
+  ctx
+    .create_account("alice".to_string(), "password123".to_string())
+    .await
+    .unwrap();
+  let (status, resp) = ctx
+    .list_api_keys(("alice".to_string(), "wrong-password".to_string()))
+    .await
+    .unwrap();
+  assert_response_err!(resp, |e: &BodyResponseError| e.error_type == "UNAUTHORIZED");
+  assert_eq!(status, reqwest::StatusCode::UNAUTHORIZED);
+}
+
+#[test_context(ApiTestContext)]
+#[tokio::test]
+pub async fn test_upload_with_api_key_records_owner(ctx: &mut ApiTestContext) {
+  let client = ctx
+    .api_key_client("alice", vec![ApiKeyScope::Upload, ApiKeyScope::Download])
+    .await;
+  let (_, resp) = client
+    .upload(
+      "owned.txt".to_string(),
+      "text/plain",
+      b"owned".to_vec(),
+      &UploadQueryParam::default(),
+      None,
+    )
+    .await
+    .unwrap();
+  let url_path = FileUrlPath::from_url(&unwrap!(resp).url).unwrap();
+  let (_, resp) = client.info(&url_path, None).await.unwrap();
+  assert_eq!(unwrap!(resp).owner.as_deref(), Some("alice"));
+}
+
+#[test_context(ApiTestContext)]
+#[tokio::test]
+pub async fn test_api_key_without_scope_is_rejected(ctx: &mut ApiTestContext) {
+  let client = ctx
+    .api_key_client("alice", vec![ApiKeyScope::Download])
+    .await;
+  let (status, resp) = client
+    .upload(
+      "file.txt".to_string(),
+      "text/plain",
+      b"data".to_vec(),
+      &UploadQueryParam::default(),
+      None,
+    )
+    .await
+    .unwrap();
+  assert_response_err!(resp, |e: &BodyResponseError| e.error_type
+    == "PERMISSION_DENIED");
+  assert_eq!(status, reqwest::StatusCode::FORBIDDEN);
+}
+
+#[test_context(ApiTestContext)]
+#[tokio::test]
+pub async fn test_invalid_api_key_is_rejected(ctx: &mut ApiTestContext) {
+  let file = ctx
+    .upload_dummy_file(None, None, None, None, None, None)
+    .await;
+  let client = pf_sdk::client::PasteFileClient::new(ctx.addr.clone())
+    .with_api_key("pf_unknown_key")
+    .unwrap();
+  let (status, resp) = client.info(&file.url_path, None).await.unwrap();
+  assert_response_err!(resp, |e: &BodyResponseError| e.error_type == "UNAUTHORIZED");
+  assert_eq!(status, reqwest::StatusCode::UNAUTHORIZED);
+}
+
+#[test_context(ApiTestContext)]
+#[tokio::test]
+pub async fn test_owner_can_delete_undeletable_file(ctx: &mut ApiTestContext) {
+  let owner = ctx
+    .api_key_client(
+      "alice",
+      vec![
+        ApiKeyScope::Upload,
+        ApiKeyScope::Download,
+        ApiKeyScope::Delete,
+      ],
+    )
+    .await;
+  let other = ctx.api_key_client("bob", vec![ApiKeyScope::Delete]).await;
+  let param = UploadQueryParam {
+    allow_manual_deletion: Some(false),
+    ..Default::default()
+  };
+  let (_, resp) = owner
+    .upload(
+      "file.txt".to_string(),
+      "text/plain",
+      b"data".to_vec(),
+      &param,
+      None,
+    )
+    .await
+    .unwrap();
+  let url_path = FileUrlPath::from_url(&unwrap!(resp).url).unwrap();
+  let (_, resp) = other.delete(&url_path, None).await.unwrap();
+  assert_response_err!(resp, |e: &BodyResponseError| e.error_type
+    == "PERMISSION_DENIED");
+  let (_, resp) = owner.delete(&url_path, None).await.unwrap();
+  assert_response_ok!(resp);
+  let (status, _) = owner.info(&url_path, None).await.unwrap();
+  assert_eq!(status, reqwest::StatusCode::NOT_FOUND);
+}
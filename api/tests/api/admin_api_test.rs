@@ -0,0 +1,155 @@
+use crate::helper::{ApiTestContext, ADMIN_TOKEN};
+use crate::{assert_response_err, assert_response_ok, unwrap};
+use pf_sdk::dto::{
+  request::{AdminListFilesQueryParam, ApiKeyScope, UpdateFileRequest, UploadQueryParam},
+  response::BodyResponseError,
+  FileUrlPath,
+};
+use test_context::test_context;
+
+#[test_context(ApiTestContext)]
+#[tokio::test]
+pub async fn test_admin_list_files_with_filters(ctx: &mut ApiTestContext) {
+  let client = ctx.api_key_client("alice", vec![ApiKeyScope::Upload]).await;
+  let (_, resp) = client
+    .upload(
+      "large.txt".to_string(),
+      "text/plain",
+      vec![b'a'; 100],
+      &UploadQueryParam::default(),
+      None,
+    )
+    .await
+    .unwrap();
+  unwrap!(resp);
+  ctx
+    .upload_dummy_file(None, None, None, None, None, None)
+    .await;
+  let (_, resp) = ctx
+    .admin_list_files(Some(ADMIN_TOKEN), &AdminListFilesQueryParam::default())
+    .await
+    .unwrap();
+  assert_eq!(unwrap!(resp).total, 2);
+  let param = AdminListFilesQueryParam {
+    owner: Some("alice".to_string()),
+    ..Default::default()
+  };
+  let (_, resp) = ctx
+    .admin_list_files(Some(ADMIN_TOKEN), &param)
+    .await
+    .unwrap();
+  let files = unwrap!(resp);
+  assert_eq!(files.total, 1);
+  assert_eq!(files.files[0].file_name, "large.txt");
+  let param = AdminListFilesQueryParam {
+    min_size: Some(100),
+    ..Default::default()
+  };
+  let (_, resp) = ctx
+    .admin_list_files(Some(ADMIN_TOKEN), &param)
+    .await
+    .unwrap();
+  let files = unwrap!(resp);
+  assert_eq!(files.total, 1);
+  assert_eq!(files.files[0].meta.size, 100);
+  let param = AdminListFilesQueryParam {
+    expires_before: Some(chrono::Utc::now()),
+    ..Default::default()
+  };
+  let (_, resp) = ctx
+    .admin_list_files(Some(ADMIN_TOKEN), &param)
+    .await
+    .unwrap();
+  assert_eq!(unwrap!(resp).total, 0);
+}
+
+#[test_context(ApiTestContext)]
+#[tokio::test]
+pub async fn test_admin_update_file(ctx: &mut ApiTestContext) {
+  let file = ctx
+    .upload_dummy_file(Some(5), None, None, None, None, None)
+    .await;
+  ctx.download_bytes(&file.url_path, None).await.unwrap();
+  let (_, resp) = ctx.info(&file.url_path, None).await.unwrap();
+  let meta = unwrap!(resp);
+  assert_eq!(meta.count_downloads, 1);
+  let req = UpdateFileRequest {
+    expire_secs: Some(100_000),
+    reset_downloads: true,
+  };
+  let (_, resp) = ctx
+    .admin_update_file(Some(ADMIN_TOKEN), &file.url_path, &req)
+    .await
+    .unwrap();
+  let updated = unwrap!(resp);
+  assert_eq!(updated.count_downloads, 0);
+  assert!(updated.expire_date_time > meta.expire_date_time);
+}
+
+#[test_context(ApiTestContext)]
+#[tokio::test]
+pub async fn test_admin_force_delete_file(ctx: &mut ApiTestContext) {
+  let file = ctx
+    .upload_dummy_file(None, None, None, Some(false), None, Some(auth()))
+    .await;
+  let (_, resp) = ctx
+    .admin_delete_file(Some(ADMIN_TOKEN), &file.url_path)
+    .await
+    .unwrap();
+  assert_response_ok!(resp);
+  let (status, _) = ctx.info(&file.url_path, Some(auth())).await.unwrap();
+  assert_eq!(status, reqwest::StatusCode::NOT_FOUND);
+  let url_path = FileUrlPath {
+    code: "unknown".to_string(),
+    file_name: "unknown.txt".to_string(),
+  };
+  let (status, _) = ctx
+    .admin_delete_file(Some(ADMIN_TOKEN), &url_path)
+    .await
+    .unwrap();
+  assert_eq!(status, reqwest::StatusCode::NOT_FOUND);
+}
+
+#[test_context(ApiTestContext)]
+#[tokio::test]
+pub async fn test_admin_api_requires_admin(ctx: &mut ApiTestContext) {
+  let param = AdminListFilesQueryParam::default();
+  let (status, resp) = ctx.admin_list_files(None, &param).await.unwrap();
+  assert_response_err!(resp, |e: &BodyResponseError| e.error_type == "UNAUTHORIZED");
+  assert_eq!(status, reqwest::StatusCode::UNAUTHORIZED);
+  let (status, _) = ctx
+    .admin_list_files(Some("wrong-token"), &param)
+    .await
+    .unwrap();
+  assert_eq!(status, reqwest::StatusCode::UNAUTHORIZED);
+  // Only the admin created by the create-admin command gets the admin scope.
+  let admin = ctx.admin_api_key_client("root").await;
+  let (_, resp) = admin.admin_list_files(None, &param).await.unwrap();
+  assert_response_ok!(resp);
+  let user = ctx.api_key_client("alice", vec![ApiKeyScope::Upload]).await;
+  let (status, resp) = user.admin_list_files(None, &param).await.unwrap();
+  assert_response_err!(resp, |e: &BodyResponseError| e.error_type
+    == "PERMISSION_DENIED");
+  assert_eq!(status, reqwest::StatusCode::FORBIDDEN);
+}
+
+#[test_context(ApiTestContext)]
+#[tokio::test]
+pub async fn test_admin_reload_config(ctx: &mut ApiTestContext) {
+  let (status, _) = ctx.admin_reload_config(None).await.unwrap();
+  assert_eq!(status, reqwest::StatusCode::UNAUTHORIZED);
+  // The test server listens on another port than the settings file, so the reload is
+  // rejected and the current settings are kept.
+  let (status, resp) = ctx.admin_reload_config(Some(ADMIN_TOKEN)).await.unwrap();
+  assert_response_err!(resp, |e: &BodyResponseError| e.error_type == "CONFIG_ERROR"
+    && e.error_message.contains("requires a restart"));
+  assert_eq!(status, reqwest::StatusCode::INTERNAL_SERVER_ERROR);
+  assert_eq!(
+    ctx.state.config.load().admin_token.as_deref(),
+    Some(ADMIN_TOKEN)
+  );
+}
+
+fn auth() -> (String, String) {
+  ("username".to_string(), "password".to_string())
+}
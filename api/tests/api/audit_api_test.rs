@@ -0,0 +1,180 @@
+use crate::helper::{ApiTestContext, ADMIN_TOKEN};
+use crate::{assert_response_err, unwrap};
+use chrono::Utc;
+use pf_sdk::dto::{
+  request::{ApiKeyScope, AuditQueryParam, UpdateFileRequest},
+  response::{AuditAction, BodyResponseError},
+  FileUrlPath,
+};
+use std::time::Duration;
+use test_context::{test_context, AsyncTestContext};
+
+#[test_context(ApiTestContext)]
+#[tokio::test]
+pub async fn test_audit_log_of_file_operations(ctx: &mut ApiTestContext) {
+  let file = ctx
+    .upload_dummy_file(None, None, None, None, None, None)
+    .await;
+  let (_, body) = ctx.download_bytes(&file.url_path, None).await.unwrap();
+  unwrap!(body);
+  let not_found = FileUrlPath {
+    code: file.url_path.code.clone(),
+    file_name: "missing.txt".to_string(),
+  };
+  ctx.info(&not_found, None).await.unwrap();
+  ctx.delete(&file.url_path, None).await.unwrap();
+  ctx
+    .upload_dummy_file(None, None, None, None, None, None)
+    .await;
+  let param = AuditQueryParam {
+    code: Some(file.url_path.code.clone()),
+    ..Default::default()
+  };
+  let (_, resp) = ctx
+    .admin_audit_log(Some(ADMIN_TOKEN), &param)
+    .await
+    .unwrap();
+  let events = unwrap!(resp).events;
+  let actions = events.iter().map(|e| e.action).collect::<Vec<_>>();
+  assert_eq!(
+    actions,
+    [
+      AuditAction::Upload,
+      AuditAction::Download,
+      AuditAction::Info,
+      AuditAction::Delete
+    ]
+  );
+  assert!(events
+    .iter()
+    .all(|e| e.ip == Some([127, 0, 0, 1].into()) && e.username.is_none()));
+  assert_eq!(events[0].file_name.as_ref(), Some(&file.file_name));
+  assert_eq!(events[2].status, 404);
+  assert_eq!(events[2].error_type.as_deref(), Some("NOT_FOUND"));
+  let (_, resp) = ctx
+    .admin_audit_log(Some(ADMIN_TOKEN), &AuditQueryParam::default())
+    .await
+    .unwrap();
+  assert_eq!(unwrap!(resp).events.len(), 5);
+  let param = AuditQueryParam {
+    from: Some(Utc::now()),
+    ..Default::default()
+  };
+  let (_, resp) = ctx
+    .admin_audit_log(Some(ADMIN_TOKEN), &param)
+    .await
+    .unwrap();
+  assert!(unwrap!(resp).events.is_empty());
+}
+
+#[tokio::test]
+pub async fn test_audit_log_in_database_with_api_key() {
+  let ctx = ApiTestContext::with_config(|config| {
+    config.audit.database = true;
+  })
+  .await;
+  let client = ctx.api_key_client("alice", vec![ApiKeyScope::Upload]).await;
+  let (_, resp) = client
+    .upload(
+      "file.txt".to_string(),
+      "text/plain",
+      b"data".to_vec(),
+      &Default::default(),
+      None,
+    )
+    .await
+    .unwrap();
+  unwrap!(resp);
+  let (status, resp) = client
+    .admin_audit_log(None, &AuditQueryParam::default())
+    .await
+    .unwrap();
+  assert_eq!(status, reqwest::StatusCode::FORBIDDEN);
+  assert_response_err!(resp, |e: &BodyResponseError| e.error_type
+    == "PERMISSION_DENIED");
+  let param = AuditQueryParam {
+    limit: Some(1),
+    ..Default::default()
+  };
+  let (_, resp) = ctx
+    .admin_audit_log(Some(ADMIN_TOKEN), &param)
+    .await
+    .unwrap();
+  let events = unwrap!(resp).events;
+  assert_eq!(events.len(), 1);
+  assert_eq!(events[0].action, AuditAction::Upload);
+  assert_eq!(events[0].username.as_deref(), Some("alice"));
+  assert!(events[0].key_id.is_some());
+  ctx.teardown().await;
+}
+
+#[test_context(ApiTestContext)]
+#[tokio::test]
+pub async fn test_audit_log_of_resumable_upload_admin_and_expiry(ctx: &mut ApiTestContext) {
+  let content = b"resumable".to_vec();
+  let (_, resp) = ctx
+    .create_upload(
+      "file.txt".to_string(),
+      content.len() as u64,
+      &Default::default(),
+      None,
+    )
+    .await
+    .unwrap();
+  let upload_url = unwrap!(resp);
+  let (_, resp) = ctx
+    .upload_chunk_from_reader(&upload_url, 0, std::io::Cursor::new(content), None)
+    .await
+    .unwrap();
+  let url = unwrap!(resp).upload.unwrap().url;
+  let url_path = FileUrlPath::from_url(&url).unwrap();
+  let req = UpdateFileRequest {
+    expire_secs: Some(1),
+    reset_downloads: false,
+  };
+  let (_, resp) = ctx
+    .admin_update_file(Some(ADMIN_TOKEN), &url_path, &req)
+    .await
+    .unwrap();
+  unwrap!(resp);
+  let deleted = ctx
+    .upload_dummy_file(None, None, None, None, None, None)
+    .await;
+  let (_, resp) = ctx
+    .admin_delete_file(Some(ADMIN_TOKEN), &deleted.url_path)
+    .await
+    .unwrap();
+  unwrap!(resp);
+  assert_eq!(
+    audit_actions(ctx, &deleted.url_path.code).await,
+    [AuditAction::Upload, AuditAction::Delete]
+  );
+  let mut expired = vec![];
+  for _ in 0..50 {
+    expired = audit_actions(ctx, &url_path.code).await;
+    if expired.len() == 3 {
+      break;
+    }
+    tokio::time::sleep(Duration::from_millis(100)).await;
+  }
+  assert_eq!(
+    expired,
+    [
+      AuditAction::Upload,
+      AuditAction::Update,
+      AuditAction::Expire
+    ]
+  );
+}
+
+async fn audit_actions(ctx: &ApiTestContext, code: &str) -> Vec<AuditAction> {
+  let param = AuditQueryParam {
+    code: Some(code.to_string()),
+    ..Default::default()
+  };
+  let (_, resp) = ctx
+    .admin_audit_log(Some(ADMIN_TOKEN), &param)
+    .await
+    .unwrap();
+  unwrap!(resp).events.iter().map(|e| e.action).collect()
+}
@@ -1,7 +1,19 @@
 use crate::{assert_response_err, unwrap};
+use axum::body::Bytes;
 use fake::{Fake, Faker};
-use pf_sdk::dto::response::BodyResponseError;
-use std::time::Duration;
+use pf_api::database::file_path::FilePath;
+use pf_sdk::{
+  client::etag_path,
+  dto::{request::UploadQueryParam, response::BodyResponseError, FileUrlPath},
+  util::{
+    crypto::{KeyNonce, KeyType, NonceType},
+    digest::parse_sha256,
+    random::generate_random_string,
+  },
+};
+use reqwest::multipart::Part;
+use sha2::{Digest, Sha256};
+use std::{io::Read, time::Duration};
 use test_context::test_context;
 
 use crate::helper::ApiTestContext;
@@ -59,3 +71,388 @@ pub async fn test_download_file_with_auth(ctx: &mut ApiTestContext) {
   let (status, _) = ctx.download_bytes(&file.url_path, auth).await.unwrap();
   assert!(status.is_success(), "status: {status}");
 }
+
+#[test_context(ApiTestContext)]
+#[tokio::test]
+pub async fn test_download_range_of_file(ctx: &mut ApiTestContext) {
+  let file = ctx
+    .upload_dummy_file(None, None, None, None, None, None)
+    .await;
+  assert_eq!(file.url_path.file_name, file.file_name);
+  let resp = ctx
+    .get(file.url_path.to_url(&ctx.addr).unwrap())
+    .header(reqwest::header::RANGE, "bytes=0-0")
+    .send()
+    .await
+    .unwrap();
+  assert_eq!(resp.status(), reqwest::StatusCode::PARTIAL_CONTENT);
+  assert_eq!(
+    resp.headers()[reqwest::header::CONTENT_TYPE],
+    file.content_type.as_str()
+  );
+  assert_eq!(
+    resp.headers()[reqwest::header::CONTENT_RANGE],
+    format!("bytes 0-0/{}", file.content.len()).as_str()
+  );
+  assert_eq!(resp.bytes().await.unwrap().to_vec(), file.content[..1]);
+}
+
+#[test_context(ApiTestContext)]
+#[tokio::test]
+pub async fn test_ranged_downloads_count_once(ctx: &mut ApiTestContext) {
+  let file = ctx
+    .upload_dummy_file(Some(1), None, None, None, None, None)
+    .await;
+  let last = file.content.len() - 1;
+  let url = file.url_path.to_url(&ctx.addr).unwrap();
+  let resp = ctx
+    .get(url.clone())
+    .header(reqwest::header::RANGE, format!("bytes=0-{}", last - 1))
+    .send()
+    .await
+    .unwrap();
+  assert_eq!(resp.status(), reqwest::StatusCode::PARTIAL_CONTENT);
+  let etag = resp.headers()[reqwest::header::ETAG].clone();
+  assert_eq!(resp.bytes().await.unwrap().to_vec(), file.content[..last]);
+  let resp = ctx
+    .get(url.clone())
+    .header(reqwest::header::RANGE, format!("bytes={last}-"))
+    .header(reqwest::header::IF_RANGE, etag)
+    .send()
+    .await
+    .unwrap();
+  assert_eq!(resp.status(), reqwest::StatusCode::PARTIAL_CONTENT);
+  assert_eq!(resp.bytes().await.unwrap().to_vec(), file.content[last..]);
+  let (status, resp) = ctx.download_bytes(&file.url_path, None).await.unwrap();
+  assert_response_err!(resp, |e: &BodyResponseError| e.error_type == "NOT_FOUND");
+  assert_eq!(status, reqwest::StatusCode::NOT_FOUND);
+}
+
+#[test_context(ApiTestContext)]
+#[tokio::test]
+pub async fn test_ranges_in_the_middle_of_file_are_not_counted(ctx: &mut ApiTestContext) {
+  let file = ctx
+    .upload_dummy_file(Some(1), None, None, None, None, None)
+    .await;
+  let last = file.content.len() - 1;
+  let resp = ctx
+    .get(file.url_path.to_url(&ctx.addr).unwrap())
+    .header(reqwest::header::RANGE, format!("bytes=1-{}", last - 1))
+    .send()
+    .await
+    .unwrap();
+  assert_eq!(resp.status(), reqwest::StatusCode::PARTIAL_CONTENT);
+  assert_eq!(resp.bytes().await.unwrap().to_vec(), file.content[1..last]);
+  let (status, body) = ctx.download_bytes(&file.url_path, None).await.unwrap();
+  assert!(status.is_success(), "status: {status}");
+  assert_eq!(unwrap!(body), file.content);
+}
+
+#[test_context(ApiTestContext)]
+#[tokio::test]
+pub async fn test_download_with_stale_if_range_serves_whole_file(ctx: &mut ApiTestContext) {
+  let file = ctx
+    .upload_dummy_file(None, None, None, None, None, None)
+    .await;
+  let resp = ctx
+    .get(file.url_path.to_url(&ctx.addr).unwrap())
+    .header(reqwest::header::RANGE, "bytes=1-")
+    .header(reqwest::header::IF_RANGE, "\"stale\"")
+    .send()
+    .await
+    .unwrap();
+  assert_eq!(resp.status(), reqwest::StatusCode::OK);
+  assert_eq!(resp.bytes().await.unwrap().to_vec(), file.content);
+}
+
+#[test_context(ApiTestContext)]
+#[tokio::test]
+pub async fn test_download_not_modified(ctx: &mut ApiTestContext) {
+  let file = ctx
+    .upload_dummy_file(Some(1), None, None, None, None, None)
+    .await;
+  let url = file.url_path.to_url(&ctx.addr).unwrap();
+  let resp = ctx.head(url.clone()).send().await.unwrap();
+  let etag = resp.headers()[reqwest::header::ETAG].clone();
+  let resp = ctx
+    .get(url)
+    .header(reqwest::header::IF_NONE_MATCH, etag)
+    .send()
+    .await
+    .unwrap();
+  assert_eq!(resp.status(), reqwest::StatusCode::NOT_MODIFIED);
+  let (status, body) = ctx.download_bytes(&file.url_path, None).await.unwrap();
+  assert!(status.is_success());
+  assert_eq!(unwrap!(body), file.content);
+}
+
+#[test_context(ApiTestContext)]
+#[tokio::test]
+pub async fn test_resume_download_of_partial_file(ctx: &mut ApiTestContext) {
+  let file = ctx
+    .upload_dummy_file(None, None, None, None, None, None)
+    .await;
+  let destination = ctx.workspace.join(&file.file_name);
+  let half = file.content.len() / 2;
+  tokio::fs::write(&destination, &file.content[..half])
+    .await
+    .unwrap();
+  let (status, resp) = ctx
+    .resume_download_file(&file.url_path, None, destination.clone())
+    .await
+    .unwrap();
+  assert_eq!(status, reqwest::StatusCode::PARTIAL_CONTENT);
+  assert_eq!(unwrap!(resp), destination);
+  assert_eq!(tokio::fs::read(&destination).await.unwrap(), file.content);
+  let (status, resp) = ctx
+    .resume_download_file(&file.url_path, None, destination.clone())
+    .await
+    .unwrap();
+  assert!(status.is_success(), "status: {status}");
+  assert_eq!(unwrap!(resp), destination);
+  assert_eq!(tokio::fs::read(&destination).await.unwrap(), file.content);
+}
+
+#[test_context(ApiTestContext)]
+#[tokio::test]
+pub async fn test_resume_download_of_changed_file_starts_over(ctx: &mut ApiTestContext) {
+  let file = ctx
+    .upload_dummy_file(None, None, None, None, None, None)
+    .await;
+  let destination = ctx.workspace.join(&file.file_name);
+  let half = file.content.len() / 2;
+  let mut stale = file.content[..half].to_vec();
+  stale.iter_mut().for_each(|b| *b = !*b);
+  tokio::fs::write(&destination, &stale).await.unwrap();
+  tokio::fs::write(etag_path(&destination), "\"stale\"")
+    .await
+    .unwrap();
+  let (status, resp) = ctx
+    .resume_download_file(&file.url_path, None, destination.clone())
+    .await
+    .unwrap();
+  assert_eq!(status, reqwest::StatusCode::OK);
+  assert_eq!(unwrap!(resp), destination);
+  assert_eq!(tokio::fs::read(&destination).await.unwrap(), file.content);
+  assert!(!etag_path(&destination).exists());
+}
+
+#[test_context(ApiTestContext)]
+#[tokio::test]
+pub async fn test_resume_interrupted_download_counts_once(ctx: &mut ApiTestContext) {
+  let content = vec![7u8; 4 * 1024 * 1024];
+  let param = UploadQueryParam {
+    max_download: Some(1),
+    ..Default::default()
+  };
+  let (_, resp) = ctx
+    .upload(
+      "large.bin".to_string(),
+      "application/octet-stream",
+      content.clone(),
+      &param,
+      None,
+    )
+    .await
+    .unwrap();
+  let url_path = FileUrlPath::from_url(&unwrap!(resp).url).unwrap();
+  let mut resp = ctx.download(&url_path, None).await.unwrap();
+  assert!(resp.status().is_success(), "status: {}", resp.status());
+  let etag = resp.headers().get(reqwest::header::ETAG).unwrap().clone();
+  let first = resp.chunk().await.unwrap().unwrap();
+  assert!(first.len() < content.len());
+  drop(resp);
+  let destination = ctx.workspace.join("large.bin");
+  tokio::fs::write(&destination, &first).await.unwrap();
+  tokio::fs::write(etag_path(&destination), etag.as_bytes())
+    .await
+    .unwrap();
+  let (status, resp) = ctx
+    .resume_download_file(&url_path, None, destination.clone())
+    .await
+    .unwrap();
+  assert_eq!(status, reqwest::StatusCode::PARTIAL_CONTENT);
+  assert_eq!(unwrap!(resp), destination);
+  assert_eq!(tokio::fs::read(&destination).await.unwrap(), content);
+  assert!(!etag_path(&destination).exists());
+  let (status, resp) = ctx.download_bytes(&url_path, None).await.unwrap();
+  assert_response_err!(resp, |e: &BodyResponseError| e.error_type == "NOT_FOUND");
+  assert_eq!(status, reqwest::StatusCode::NOT_FOUND);
+}
+
+#[test_context(ApiTestContext)]
+#[tokio::test]
+pub async fn test_download_bundle_as_tar_gz(ctx: &mut ApiTestContext) {
+  let (code, files) = ctx.upload_dummy_bundle(None, None).await;
+  let resp = ctx.download_archive(&code, "tar.gz", None).await.unwrap();
+  assert!(resp.status().is_success(), "status: {}", resp.status());
+  assert_eq!(
+    resp.headers().get(reqwest::header::CONTENT_TYPE).unwrap(),
+    "application/gzip"
+  );
+  let body = resp.bytes().await.unwrap();
+  let mut tar = Vec::new();
+  flate2::read::GzDecoder::new(body.as_ref())
+    .read_to_end(&mut tar)
+    .unwrap();
+  let mut offset = 0;
+  for (file_name, content) in files {
+    assert_eq!(&tar[offset..offset + file_name.len()], file_name.as_bytes());
+    offset += 512;
+    assert_eq!(&tar[offset..offset + content.len()], content.as_slice());
+    offset += content.len().div_ceil(512) * 512;
+  }
+}
+
+#[test_context(ApiTestContext)]
+#[tokio::test]
+pub async fn test_download_bundle_as_zip_counts_downloads(ctx: &mut ApiTestContext) {
+  let (code, files) = ctx.upload_dummy_bundle(Some(1), None).await;
+  let resp = ctx.download_archive(&code, "zip", None).await.unwrap();
+  assert!(resp.status().is_success(), "status: {}", resp.status());
+  let body = resp.bytes().await.unwrap();
+  assert_eq!(&body[..4], b"PK\x03\x04");
+  for (_, content) in &files {
+    assert!(body
+      .windows(content.len())
+      .any(|window| window == content.as_slice()));
+  }
+  let url_path = format!("{code}/{}", files[0].0).parse().unwrap();
+  let (status, resp) = ctx.download_bytes(&url_path, None).await.unwrap();
+  assert_response_err!(resp, |e: &BodyResponseError| e.error_type == "NOT_FOUND");
+  assert_eq!(status, reqwest::StatusCode::NOT_FOUND);
+  let resp = ctx.download_archive(&code, "zip", None).await.unwrap();
+  assert_eq!(resp.status(), reqwest::StatusCode::NOT_FOUND);
+}
+
+#[test_context(ApiTestContext)]
+#[tokio::test]
+pub async fn test_download_bundle_archive_requires_auth(ctx: &mut ApiTestContext) {
+  let auth = Some((Faker.fake::<String>(), Faker.fake::<String>()));
+  let (code, _) = ctx.upload_dummy_bundle(None, auth.clone()).await;
+  let resp = ctx.download_archive(&code, "zip", None).await.unwrap();
+  assert!(!resp.status().is_success(), "status: {}", resp.status());
+  let resp = ctx.download_archive(&code, "zip", auth).await.unwrap();
+  assert!(resp.status().is_success(), "status: {}", resp.status());
+}
+
+#[test_context(ApiTestContext)]
+#[tokio::test]
+pub async fn test_download_sends_repr_digest(ctx: &mut ApiTestContext) {
+  let file = ctx
+    .upload_dummy_file(None, None, None, None, None, None)
+    .await;
+  let resp = ctx.download(&file.url_path, None).await.unwrap();
+  assert!(resp.status().is_success());
+  let sha256 = Sha256::digest(&file.content).to_vec();
+  assert_eq!(parse_sha256(resp.headers()), Some(sha256));
+}
+
+#[test_context(ApiTestContext)]
+#[tokio::test]
+pub async fn test_download_file_with_corrupted_content(ctx: &mut ApiTestContext) {
+  let file = ctx
+    .upload_dummy_file(None, None, None, None, None, None)
+    .await;
+  let file_path = FilePath {
+    code: file.url_path.code.clone(),
+    file_name: file.file_name.clone(),
+  };
+  let meta = ctx.state.db.fetch(&file_path).unwrap().unwrap();
+  let corrupted = vec![b'x'; file.content.len()];
+  ctx
+    .state
+    .storage
+    .put(
+      &meta.blob_path(&file_path),
+      Box::pin(std::io::Cursor::new(corrupted)),
+    )
+    .await
+    .unwrap();
+  let destination = ctx.workspace.join(&file.file_name);
+  let err = ctx
+    .download_file(&file.url_path, None, destination.clone())
+    .await
+    .unwrap_err();
+  assert!(err.to_string().contains("SHA-256"), "{err}");
+  assert!(!destination.exists());
+}
+
+#[test_context(ApiTestContext)]
+#[tokio::test]
+pub async fn test_paste_file_with_corrupted_content(ctx: &mut ApiTestContext) {
+  let file = ctx
+    .upload_dummy_file(None, None, None, None, None, None)
+    .await;
+  let file_path = FilePath {
+    code: file.url_path.code.clone(),
+    file_name: file.file_name.clone(),
+  };
+  let meta = ctx.state.db.fetch(&file_path).unwrap().unwrap();
+  let corrupted = vec![b'x'; file.content.len()];
+  ctx
+    .state
+    .storage
+    .put(
+      &meta.blob_path(&file_path),
+      Box::pin(std::io::Cursor::new(corrupted)),
+    )
+    .await
+    .unwrap();
+  let mut output = Vec::new();
+  let err = ctx
+    .download_to_writer(&file.url_path, None, &mut output)
+    .await
+    .unwrap_err();
+  assert!(err.to_string().contains("SHA-256"), "{err}");
+  assert!(output.is_empty());
+  let key_nonce = KeyNonce {
+    key: KeyType::new(&generate_random_string(32)).unwrap(),
+    nonce: NonceType::new(&generate_random_string(19)).unwrap(),
+  };
+  let err = ctx
+    .download_and_decrypt(&key_nonce, &file.url_path, None, &mut output)
+    .await
+    .unwrap_err();
+  assert!(err.to_string().contains("SHA-256"), "{err}");
+  assert!(output.is_empty());
+}
+
+#[test_context(ApiTestContext)]
+#[tokio::test]
+pub async fn test_download_pending_upload(ctx: &mut ApiTestContext) {
+  let (resume, wait) = tokio::sync::oneshot::channel::<()>();
+  let body = async_stream::stream! {
+    yield Ok::<_, std::io::Error>(Bytes::from_static(b"first part, "));
+    let _ = wait.await;
+    yield Ok(Bytes::from_static(b"second part"));
+  };
+  let part = Part::stream(reqwest::Body::wrap_stream(body)).file_name("slow.txt");
+  let param = UploadQueryParam::default();
+  let pending = async {
+    let url_path = loop {
+      tokio::time::sleep(Duration::from_millis(50)).await;
+      if let Some((file_path, _)) = ctx.state.db.fetch_all().unwrap().pop() {
+        break FileUrlPath {
+          code: file_path.code,
+          file_name: file_path.file_name,
+        };
+      }
+    };
+    let (status, resp) = ctx.download_bytes(&url_path, None).await.unwrap();
+    assert_eq!(status, reqwest::StatusCode::NOT_FOUND);
+    assert_response_err!(resp, |e: &BodyResponseError| e.error_type
+      == "NOT_AVAILABLE");
+    let (_, resp) = ctx.info(&url_path, None).await.unwrap();
+    assert_response_err!(resp, |e: &BodyResponseError| e.error_type
+      == "NOT_AVAILABLE");
+    resume.send(()).unwrap();
+    url_path
+  };
+  let (upload, url_path) = tokio::join!(ctx.upload_file_part(part, &param, None), pending);
+  let (status, resp) = upload.unwrap();
+  unwrap!(resp);
+  assert!(status.is_success(), "status: {status}");
+  let (_, body) = ctx.download_bytes(&url_path, None).await.unwrap();
+  assert_eq!(unwrap!(body), b"first part, second part");
+}
@@ -1,5 +1,6 @@
 use crate::assert_response_ok;
 use crate::helper::ApiTestContext;
+use pf_api::router::REQUEST_ID_HEADER;
 use test_context::test_context;
 
 #[test_context(ApiTestContext)]
@@ -9,3 +10,24 @@ pub async fn test_health_check(ctx: &mut ApiTestContext) {
   assert_response_ok!(body);
   assert!(status.is_success(), "status: {status}");
 }
+
+#[test_context(ApiTestContext)]
+#[tokio::test]
+pub async fn test_request_id_header(ctx: &mut ApiTestContext) {
+  let url = format!("{}/healthz", ctx.state.config.load().server.get_http_addr());
+  let resp = ctx.get(&url).send().await.unwrap();
+  let id = resp.headers().get(REQUEST_ID_HEADER).unwrap().clone();
+  assert!(!id.is_empty());
+  let resp = ctx.get(&url).send().await.unwrap();
+  assert_ne!(resp.headers().get(REQUEST_ID_HEADER).unwrap(), &id);
+  let resp = ctx
+    .get(&url)
+    .header(REQUEST_ID_HEADER, "client-request-id")
+    .send()
+    .await
+    .unwrap();
+  assert_eq!(
+    resp.headers().get(REQUEST_ID_HEADER).unwrap(),
+    "client-request-id"
+  );
+}
@@ -4,39 +4,60 @@ use std::path::{Path, PathBuf};
 use crate::unwrap;
 use fake::{Fake, Faker};
 use once_cell::sync::Lazy;
-use pf_api::configure::CONFIG;
+use pf_api::configure::{ApiConfig, CONFIG};
 use pf_api::error::result::ApiResult;
-use pf_api::server::worker::GarbageCollectorTask;
+use pf_api::server::worker::{GarbageCollectorTask, WebhookTask};
 use pf_api::server::{ApiServer, ApiState};
+use pf_api::service::account::create_first_admin;
 use pf_api::util::tracing::INIT_SUBSCRIBER;
 use pf_sdk::client::PasteFileClient;
-use pf_sdk::dto::request::{QrCodeFormat, UploadQueryParam};
+use pf_sdk::dto::request::{ApiKeyScope, CreateAccountRequest, QrCodeFormat, UploadQueryParam};
 use pf_sdk::dto::FileUrlPath;
 use test_context::AsyncTestContext;
 
 pub mod assert;
 
+pub const ADMIN_TOKEN: &str = "admin-token";
+
 pub struct ApiTestContext {
   pub state: ApiState,
   pub workspace: PathBuf,
   client: PasteFileClient,
   server_task: tokio::task::JoinHandle<ApiResult>,
   gc_task: tokio::task::JoinHandle<ApiResult>,
+  pub webhook_task: tokio::task::JoinHandle<ApiResult>,
 }
 
 impl AsyncTestContext for ApiTestContext {
   async fn setup() -> Self {
+    Self::with_config(|_| {}).await
+  }
+
+  async fn teardown(self) {
+    self.gc_task.abort();
+    self.webhook_task.abort();
+    self.server_task.abort();
+    tokio::fs::remove_dir_all(&self.workspace).await.unwrap();
+  }
+}
+
+impl ApiTestContext {
+  pub async fn with_config(f: impl FnOnce(&mut ApiConfig)) -> Self {
     Lazy::force(&INIT_SUBSCRIBER);
     let workspace = Path::new("test-dump").join(PathBuf::from(cuid2::create_id()));
     tokio::fs::create_dir_all(&workspace).await.unwrap();
     let mut config = CONFIG.clone();
+    config.allow_registration = true;
+    f(&mut config);
     config.server.port = 0;
     config.db.path_dir = workspace.join(PathBuf::from(cuid2::create_id()));
     config.fs.base_dir = workspace.clone();
+    config.admin_token = Some(ADMIN_TOKEN.to_string());
     let server = ApiServer::new(config).await.unwrap();
     let state = server.state.clone();
-    let client = PasteFileClient::new(server.state.config.server.get_http_addr());
+    let client = PasteFileClient::new(server.state.config.load().server.get_http_addr());
     let gc_task = tokio::task::spawn(GarbageCollectorTask::new(state.clone()).run());
+    let webhook_task = tokio::task::spawn(WebhookTask::new(state.clone()).run());
     let server_task = tokio::task::spawn(server.run());
     Self {
       state,
@@ -44,14 +65,9 @@ impl AsyncTestContext for ApiTestContext {
       workspace,
       server_task,
       gc_task,
+      webhook_task,
     }
   }
-
-  async fn teardown(self) {
-    self.gc_task.abort();
-    self.server_task.abort();
-    tokio::fs::remove_dir_all(&self.workspace).await.unwrap();
-  }
 }
 
 impl Deref for ApiTestContext {
@@ -81,6 +97,7 @@ impl ApiTestContext {
       expire_secs: exp,
       allow_manual_deletion: del,
       qr_code_format: qr,
+      sha256: None,
     };
     let (_, resp) = self
       .client
@@ -104,6 +121,84 @@ impl ApiTestContext {
   }
 }
 
+impl ApiTestContext {
+  pub async fn upload_dummy_bundle(
+    &self,
+    max: Option<u32>,
+    auth: Option<(String, String)>,
+  ) -> (String, Vec<(String, Vec<u8>)>) {
+    let files = (0..2)
+      .map(|i| {
+        let file_name = format!("{i}-{}.txt", Faker.fake::<String>());
+        (file_name, Faker.fake::<String>().as_bytes().to_vec())
+      })
+      .collect::<Vec<_>>();
+    let file_parts = files
+      .iter()
+      .map(|(file_name, content)| {
+        reqwest::multipart::Part::bytes(content.clone())
+          .file_name(file_name.clone())
+          .mime_str("text/plain")
+          .unwrap()
+      })
+      .collect();
+    let param = UploadQueryParam {
+      max_download: max,
+      ..Default::default()
+    };
+    let (_, resp) = self
+      .client
+      .upload_file_parts(file_parts, &param, auth)
+      .await
+      .unwrap();
+    let resp = unwrap!(resp);
+    let code = url::Url::parse(&resp.url).unwrap().path()[1..].to_string();
+    (code, files)
+  }
+}
+
+impl ApiTestContext {
+  /// Registers an account and returns a client that sends a new key with the given scopes.
+  pub async fn api_key_client(&self, username: &str, scopes: Vec<ApiKeyScope>) -> PasteFileClient {
+    let auth = (username.to_string(), "password123".to_string());
+    let (_, resp) = self
+      .client
+      .create_account(auth.0.clone(), auth.1.clone())
+      .await
+      .unwrap();
+    unwrap!(resp);
+    self.create_api_key_client(auth, scopes).await
+  }
+
+  /// Creates the admin account the way the create-admin command does.
+  pub async fn admin_api_key_client(&self, username: &str) -> PasteFileClient {
+    let req = CreateAccountRequest {
+      username: username.to_string(),
+      password: "password123".to_string(),
+    };
+    create_first_admin(&self.state, &req).await.unwrap();
+    self
+      .create_api_key_client((req.username, req.password), vec![ApiKeyScope::Admin])
+      .await
+  }
+
+  async fn create_api_key_client(
+    &self,
+    auth: (String, String),
+    scopes: Vec<ApiKeyScope>,
+  ) -> PasteFileClient {
+    let (_, resp) = self
+      .client
+      .create_api_key(auth, "test".to_string(), scopes)
+      .await
+      .unwrap();
+    let key = unwrap!(resp).key.unwrap();
+    PasteFileClient::new(self.client.addr.clone())
+      .with_api_key(&key)
+      .unwrap()
+  }
+}
+
 #[derive(Clone)]
 pub struct DummyFile {
   pub content: Vec<u8>,
@@ -5,6 +5,6 @@ use test_context::test_context;
 #[tokio::test]
 pub async fn test_index_page(ctx: &mut ApiTestContext) {
   let (status, html) = ctx.index_page().await.unwrap();
-  assert!(html.contains(&ctx.state.config.server.get_domain_name()));
+  assert!(html.contains(&ctx.state.config.load().server.get_domain_name()));
   assert!(status.is_success(), "status: {status}");
 }
@@ -2,6 +2,7 @@ use crate::helper::ApiTestContext;
 use crate::{assert_response_err, unwrap};
 use fake::{Fake, Faker};
 use pf_sdk::dto::{response::BodyResponseError, FileUrlPath};
+use sha2::{Digest, Sha256};
 use test_context::test_context;
 
 #[test_context(ApiTestContext)]
@@ -14,6 +15,11 @@ pub async fn test_info(ctx: &mut ApiTestContext) {
   let resp = unwrap!(resp);
   assert!(status.is_success(), "status: {status}");
   assert!(resp.allow_manual_deletion);
+  assert_eq!(resp.size, file.content.len() as u64);
+  assert_eq!(
+    resp.sha256,
+    Some(hex::encode(Sha256::digest(&file.content)))
+  );
 }
 
 #[test_context(ApiTestContext)]
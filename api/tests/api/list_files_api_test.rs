@@ -0,0 +1,75 @@
+use crate::helper::ApiTestContext;
+use crate::{assert_response_err, unwrap};
+use pf_sdk::dto::{
+  request::{ApiKeyScope, ListFilesQueryParam, UploadQueryParam},
+  response::BodyResponseError,
+};
+use test_context::test_context;
+
+#[test_context(ApiTestContext)]
+#[tokio::test]
+pub async fn test_list_files_of_the_owner(ctx: &mut ApiTestContext) {
+  let alice = ctx
+    .api_key_client("alice", vec![ApiKeyScope::Upload, ApiKeyScope::Download])
+    .await;
+  let bob = ctx
+    .api_key_client("bob", vec![ApiKeyScope::Upload, ApiKeyScope::Download])
+    .await;
+  for (client, file_name) in [(&alice, "a.txt"), (&alice, "b.txt"), (&bob, "c.txt")] {
+    let (_, resp) = client
+      .upload(
+        file_name.to_string(),
+        "text/plain",
+        b"data".to_vec(),
+        &UploadQueryParam::default(),
+        None,
+      )
+      .await
+      .unwrap();
+    unwrap!(resp);
+  }
+  ctx
+    .upload_dummy_file(None, None, None, None, None, None)
+    .await;
+  let param = ListFilesQueryParam {
+    page: Some(1),
+    page_size: Some(1),
+  };
+  let (_, resp) = alice.list_files(&param).await.unwrap();
+  let first_page = unwrap!(resp);
+  assert_eq!(first_page.total, 2);
+  assert_eq!(first_page.files.len(), 1);
+  assert_eq!(first_page.files[0].meta.size, 4);
+  assert_eq!(first_page.files[0].meta.owner.as_deref(), Some("alice"));
+  let param = ListFilesQueryParam {
+    page: Some(2),
+    page_size: Some(1),
+  };
+  let (_, resp) = alice.list_files(&param).await.unwrap();
+  let second_page = unwrap!(resp);
+  assert_eq!(second_page.files.len(), 1);
+  let mut file_names = vec![
+    first_page.files[0].file_name.clone(),
+    second_page.files[0].file_name.clone(),
+  ];
+  file_names.sort();
+  assert_eq!(file_names, vec!["a.txt", "b.txt"]);
+  let (_, resp) = bob
+    .list_files(&ListFilesQueryParam::default())
+    .await
+    .unwrap();
+  let files = unwrap!(resp);
+  assert_eq!(files.total, 1);
+  assert_eq!(files.files[0].file_name, "c.txt");
+}
+
+#[test_context(ApiTestContext)]
+#[tokio::test]
+pub async fn test_list_files_requires_api_key(ctx: &mut ApiTestContext) {
+  let (status, resp) = ctx
+    .list_files(&ListFilesQueryParam::default())
+    .await
+    .unwrap();
+  assert_response_err!(resp, |e: &BodyResponseError| e.error_type == "UNAUTHORIZED");
+  assert_eq!(status, reqwest::StatusCode::UNAUTHORIZED);
+}
@@ -1,9 +1,19 @@
 extern crate core;
 
+pub(crate) mod account_api_test;
+pub(crate) mod admin_api_test;
+pub(crate) mod audit_api_test;
 pub(crate) mod delete_api_test;
 pub(crate) mod download_api_test;
 pub(crate) mod healthz_api_test;
 pub(crate) mod helper;
 pub(crate) mod index_page_test;
 pub(crate) mod info_api_test;
+pub(crate) mod list_files_api_test;
+pub(crate) mod metrics_api_test;
+pub(crate) mod quota_api_test;
+pub(crate) mod rate_limit_api_test;
+pub(crate) mod shutdown_api_test;
+pub(crate) mod tus_api_test;
 pub(crate) mod upload_api_test;
+pub(crate) mod webhook_api_test;
@@ -0,0 +1,34 @@
+use crate::helper::ApiTestContext;
+use crate::unwrap;
+use pf_sdk::dto::FileUrlPath;
+use test_context::test_context;
+
+#[test_context(ApiTestContext)]
+#[tokio::test]
+pub async fn test_metrics(ctx: &mut ApiTestContext) {
+  let file = ctx
+    .upload_dummy_file(None, None, None, None, None, None)
+    .await;
+  let (_, body) = ctx.download_bytes(&file.url_path, None).await.unwrap();
+  unwrap!(body);
+  let url_path = FileUrlPath {
+    code: "not_found".to_string(),
+    file_name: file.file_name.clone(),
+  };
+  let (status, _) = ctx.info(&url_path, None).await.unwrap();
+  assert!(!status.is_success());
+  let (status, body) = ctx.metrics().await.unwrap();
+  assert!(status.is_success(), "status: {status}");
+  for name in [
+    "pf_upload_bytes_total",
+    "pf_upload_duration_seconds_count",
+    "pf_download_bytes_total",
+    "pf_files ",
+    "pf_storage_bytes ",
+    "pf_gc_runs_total",
+    "pf_gc_lag_seconds_count",
+    "pf_rejections_total{error_type=\"NOT_FOUND\"}",
+  ] {
+    assert!(body.contains(name), "missing {name} in {body}");
+  }
+}
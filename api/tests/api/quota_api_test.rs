@@ -0,0 +1,189 @@
+use crate::helper::ApiTestContext;
+use crate::{assert_response_err, unwrap};
+use pf_sdk::dto::{
+  request::{ApiKeyScope, UploadQueryParam},
+  response::BodyResponseError,
+};
+use reqwest::StatusCode;
+use test_context::AsyncTestContext;
+
+#[tokio::test]
+pub async fn test_user_file_count_quota() {
+  let ctx = ApiTestContext::with_config(|config| {
+    config.quota.user_max_files = Some(2);
+    config.quota.user_max_bytes = Some(100);
+  })
+  .await;
+  let client = ctx.api_key_client("alice", vec![ApiKeyScope::Upload]).await;
+  for file_name in ["a.txt", "b.txt"] {
+    let (_, resp) = client
+      .upload(
+        file_name.to_string(),
+        "text/plain",
+        b"data".to_vec(),
+        &UploadQueryParam::default(),
+        None,
+      )
+      .await
+      .unwrap();
+    unwrap!(resp);
+  }
+  let (status, resp) = client.quota().await.unwrap();
+  assert!(status.is_success(), "status: {status}");
+  let quota = unwrap!(resp);
+  assert_eq!(quota.owner.as_deref(), Some("alice"));
+  assert_eq!(quota.used_bytes, 8);
+  assert_eq!(quota.used_files, 2);
+  assert_eq!(quota.remaining_bytes, Some(92));
+  assert_eq!(quota.remaining_files, Some(0));
+  let (status, resp) = client
+    .upload(
+      "c.txt".to_string(),
+      "text/plain",
+      b"data".to_vec(),
+      &UploadQueryParam::default(),
+      None,
+    )
+    .await
+    .unwrap();
+  assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+  assert_response_err!(resp, |e: &BodyResponseError| e.error_type
+    == "QUOTA_EXCEEDED");
+  ctx
+    .upload_dummy_file(None, None, None, None, None, None)
+    .await;
+  ctx.teardown().await;
+}
+
+#[tokio::test]
+pub async fn test_global_storage_quota() {
+  let ctx = ApiTestContext::with_config(|config| {
+    config.quota.max_bytes = Some(10);
+  })
+  .await;
+  let (status, resp) = ctx
+    .upload(
+      "file.txt".to_string(),
+      "text/plain",
+      vec![0; 20],
+      &UploadQueryParam::default(),
+      None,
+    )
+    .await
+    .unwrap();
+  assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+  assert_response_err!(resp, |e: &BodyResponseError| e.error_type
+    == "QUOTA_EXCEEDED");
+  let (status, resp) = ctx
+    .create_upload(
+      "file.txt".to_string(),
+      20,
+      &UploadQueryParam::default(),
+      None,
+    )
+    .await
+    .unwrap();
+  assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+  assert_response_err!(resp, |e: &BodyResponseError| e.error_type
+    == "QUOTA_EXCEEDED");
+  let (_, resp) = ctx.quota().await.unwrap();
+  let quota = unwrap!(resp);
+  assert_eq!(quota.owner, None);
+  assert_eq!(quota.used_files, 0);
+  assert_eq!(quota.max_bytes, Some(10));
+  assert_eq!(quota.remaining_bytes, Some(10));
+  let (_, resp) = ctx
+    .upload(
+      "file.txt".to_string(),
+      "text/plain",
+      vec![0; 10],
+      &UploadQueryParam::default(),
+      None,
+    )
+    .await
+    .unwrap();
+  unwrap!(resp);
+  ctx.teardown().await;
+}
+
+#[tokio::test]
+pub async fn test_concurrent_uploads_cannot_exceed_quota() {
+  let ctx = ApiTestContext::with_config(|config| {
+    config.quota.max_bytes = Some(1024 * 1024);
+  })
+  .await;
+  let param = UploadQueryParam::default();
+  let uploads = (0..4).map(|i| {
+    ctx.upload(
+      format!("file{i}.bin"),
+      "application/octet-stream",
+      vec![0; 512 * 1024],
+      &param,
+      None,
+    )
+  });
+  let mut stored = 0;
+  for result in futures_util::future::join_all(uploads).await {
+    let (status, resp) = result.unwrap();
+    match status {
+      StatusCode::PAYLOAD_TOO_LARGE => {
+        assert_response_err!(resp, |e: &BodyResponseError| e.error_type
+          == "QUOTA_EXCEEDED")
+      }
+      _ => {
+        unwrap!(resp);
+        stored += 1;
+      }
+    }
+  }
+  assert!(stored <= 2, "stored: {stored}");
+  let (_, resp) = ctx.quota().await.unwrap();
+  let quota = unwrap!(resp);
+  assert_eq!(quota.used_files, stored);
+  assert_eq!(quota.used_bytes, stored as u64 * 512 * 1024);
+  ctx.teardown().await;
+}
+
+#[tokio::test]
+pub async fn test_resumable_upload_holds_its_length() {
+  let ctx = ApiTestContext::with_config(|config| {
+    config.quota.user_max_bytes = Some(10);
+  })
+  .await;
+  let client = ctx.api_key_client("alice", vec![ApiKeyScope::Upload]).await;
+  let (status, resp) = client
+    .create_upload("a.txt".to_string(), 8, &UploadQueryParam::default(), None)
+    .await
+    .unwrap();
+  assert!(status.is_success(), "status: {status}");
+  let upload_url = unwrap!(resp);
+  let (_, resp) = client.quota().await.unwrap();
+  let quota = unwrap!(resp);
+  assert_eq!(quota.used_bytes, 8);
+  assert_eq!(quota.used_files, 1);
+  let (status, _) = client
+    .create_upload("b.txt".to_string(), 4, &UploadQueryParam::default(), None)
+    .await
+    .unwrap();
+  assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+  let (status, resp) = client
+    .upload(
+      "c.txt".to_string(),
+      "text/plain",
+      b"data".to_vec(),
+      &UploadQueryParam::default(),
+      None,
+    )
+    .await
+    .unwrap();
+  assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+  assert_response_err!(resp, |e: &BodyResponseError| e.error_type
+    == "QUOTA_EXCEEDED");
+  let status = client.terminate_upload(&upload_url, None).await.unwrap();
+  assert!(status.is_success(), "status: {status}");
+  let (_, resp) = client.quota().await.unwrap();
+  let quota = unwrap!(resp);
+  assert_eq!(quota.used_bytes, 0);
+  assert_eq!(quota.remaining_bytes, Some(10));
+  ctx.teardown().await;
+}
@@ -0,0 +1,79 @@
+use crate::assert_response_err;
+use crate::helper::{ApiTestContext, ADMIN_TOKEN};
+use pf_api::configure::RateLimitRule;
+use pf_sdk::dto::{request::UploadQueryParam, response::BodyResponseError, FileUrlPath};
+use reqwest::{header::RETRY_AFTER, StatusCode};
+use test_context::AsyncTestContext;
+
+#[tokio::test]
+pub async fn test_rate_limit_uploads() {
+  let ctx = ApiTestContext::with_config(|config| {
+    config.rate_limit.enable = true;
+    config.rate_limit.upload = RateLimitRule {
+      burst: 2,
+      per_minute: 1,
+    };
+  })
+  .await;
+  for _ in 0..2 {
+    ctx
+      .upload_dummy_file(None, None, None, None, None, None)
+      .await;
+  }
+  let (status, resp) = ctx
+    .upload(
+      "file.txt".to_string(),
+      "text/plain",
+      b"data".to_vec(),
+      &UploadQueryParam::default(),
+      None,
+    )
+    .await
+    .unwrap();
+  assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+  assert_response_err!(resp, |e: &BodyResponseError| e.error_type
+    == "TOO_MANY_REQUESTS");
+  ctx.teardown().await;
+}
+
+#[tokio::test]
+pub async fn test_rate_limit_not_found_responses() {
+  let ctx = ApiTestContext::with_config(|config| {
+    config.rate_limit.enable = true;
+    config.rate_limit.not_found = RateLimitRule {
+      burst: 2,
+      per_minute: 1,
+    };
+  })
+  .await;
+  let url_path = FileUrlPath {
+    code: "missing".to_string(),
+    file_name: "file.txt".to_string(),
+  };
+  // A 404 outside of the code and file routes is no guess.
+  for _ in 0..3 {
+    let (status, _) = ctx
+      .admin_delete_file(Some(ADMIN_TOKEN), &url_path)
+      .await
+      .unwrap();
+    assert_eq!(status, StatusCode::NOT_FOUND);
+  }
+  for _ in 0..2 {
+    let resp = ctx.download(&url_path, None).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+  }
+  let resp = ctx.download(&url_path, None).await.unwrap();
+  assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+  let retry_after: u64 = resp.headers()[RETRY_AFTER]
+    .to_str()
+    .unwrap()
+    .parse()
+    .unwrap();
+  assert!(retry_after > 0 && retry_after <= 60, "{retry_after}");
+  let (status, _) = ctx.health_check().await.unwrap();
+  assert!(status.is_success());
+  ctx
+    .upload_dummy_file(None, None, None, None, None, None)
+    .await;
+  ctx.teardown().await;
+}
@@ -0,0 +1,36 @@
+use axum::body::Bytes;
+use pf_sdk::dto::request::UploadQueryParam;
+use reqwest::multipart::Part;
+use std::time::Duration;
+use test_context::AsyncTestContext;
+
+use crate::helper::ApiTestContext;
+use crate::unwrap;
+
+#[tokio::test]
+pub async fn test_shutdown_waits_for_in_flight_upload() {
+  let ctx = ApiTestContext::setup().await;
+  let (resume, wait) = tokio::sync::oneshot::channel::<()>();
+  let body = async_stream::stream! {
+    yield Ok::<_, std::io::Error>(Bytes::from_static(b"first part, "));
+    let _ = wait.await;
+    yield Ok(Bytes::from_static(b"second part"));
+  };
+  let part = Part::stream(reqwest::Body::wrap_stream(body)).file_name("slow.txt");
+  let param = UploadQueryParam::default();
+  let shutdown = async {
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    ctx.state.shutdown.cancel();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    resume.send(()).unwrap();
+  };
+  let (upload, ()) = tokio::join!(ctx.upload_file_part(part, &param, None), shutdown);
+  let (status, resp) = upload.unwrap();
+  unwrap!(resp);
+  assert!(status.is_success(), "status: {status}");
+  let files = ctx.state.db.fetch_all().unwrap();
+  assert_eq!(files.len(), 1);
+  assert_eq!(files[0].1.size, "first part, second part".len() as u64);
+  assert!(ctx.health_check().await.is_err());
+  ctx.teardown().await;
+}
@@ -0,0 +1,101 @@
+use crate::helper::ApiTestContext;
+use crate::{assert_response_err, assert_response_ok};
+use fake::{Fake, Faker};
+use pf_sdk::dto::{request::UploadQueryParam, response::BodyResponseError, FileUrlPath};
+use test_context::test_context;
+
+fn upload_param() -> UploadQueryParam {
+  UploadQueryParam {
+    max_download: None,
+    code_length: None,
+    expire_secs: None,
+    allow_manual_deletion: None,
+    qr_code_format: None,
+    sha256: None,
+  }
+}
+
+#[test_context(ApiTestContext)]
+#[tokio::test]
+pub async fn test_resume_interrupted_upload(ctx: &mut ApiTestContext) {
+  let file_name = format!("{}.txt", Faker.fake::<String>());
+  let content = Faker.fake::<String>().repeat(10);
+  let (status, resp) = ctx
+    .create_upload(file_name, content.len() as u64, &upload_param(), None)
+    .await
+    .unwrap();
+  assert!(status.is_success(), "status: {status}");
+  let upload_url = resp.unwrap();
+  let half = content.len() / 2;
+  let chunk = std::io::Cursor::new(content.as_bytes()[..half].to_vec());
+  let (_, resp) = ctx
+    .upload_chunk_from_reader(&upload_url, 0, chunk, None)
+    .await
+    .unwrap();
+  assert!(resp.unwrap().upload.is_none());
+  let (_, resp) = ctx.upload_status(&upload_url, None).await.unwrap();
+  assert_eq!(resp.unwrap().offset, half as u64);
+  let chunk = std::io::Cursor::new(content.as_bytes()[half..].to_vec());
+  let (status, resp) = ctx
+    .upload_chunk_from_reader(&upload_url, half as u64, chunk, None)
+    .await
+    .unwrap();
+  assert!(status.is_success(), "status: {status}");
+  let upload = resp.unwrap().upload.unwrap();
+  let url_path = FileUrlPath::from_url(&upload.url).unwrap();
+  let (_, resp) = ctx.download_bytes(&url_path, None).await.unwrap();
+  assert_eq!(resp.unwrap(), content.as_bytes());
+}
+
+#[test_context(ApiTestContext)]
+#[tokio::test]
+pub async fn test_upload_chunk_with_mismatched_offset(ctx: &mut ApiTestContext) {
+  let file_name = format!("{}.txt", Faker.fake::<String>());
+  let (_, resp) = ctx
+    .create_upload(file_name, 10, &upload_param(), None)
+    .await
+    .unwrap();
+  let upload_url = resp.unwrap();
+  let chunk = std::io::Cursor::new(b"data".to_vec());
+  let (status, resp) = ctx
+    .upload_chunk_from_reader(&upload_url, 5, chunk, None)
+    .await
+    .unwrap();
+  assert_response_err!(resp, |e: &BodyResponseError| e.error_type == "CONFLICT");
+  assert_eq!(status, reqwest::StatusCode::CONFLICT);
+}
+
+#[test_context(ApiTestContext)]
+#[tokio::test]
+pub async fn test_terminate_upload(ctx: &mut ApiTestContext) {
+  let file_name = format!("{}.txt", Faker.fake::<String>());
+  let (_, resp) = ctx
+    .create_upload(file_name, 10, &upload_param(), None)
+    .await
+    .unwrap();
+  let upload_url = resp.unwrap();
+  let status = ctx.terminate_upload(&upload_url, None).await.unwrap();
+  assert_eq!(status, reqwest::StatusCode::NO_CONTENT);
+  let (status, _) = ctx.upload_status(&upload_url, None).await.unwrap();
+  assert_eq!(status, reqwest::StatusCode::NOT_FOUND);
+}
+
+#[test_context(ApiTestContext)]
+#[tokio::test]
+pub async fn test_upload_file_resumable(ctx: &mut ApiTestContext) {
+  let source = ctx
+    .workspace
+    .join(format!("{}.txt", Faker.fake::<String>()));
+  let content = Faker.fake::<String>();
+  tokio::fs::write(&source, &content).await.unwrap();
+  let (status, resp) = ctx
+    .upload_file_resumable(&source, &upload_param(), None)
+    .await
+    .unwrap();
+  assert!(status.is_success(), "status: {status}");
+  assert_response_ok!(resp);
+  let upload = resp.unwrap().upload.unwrap();
+  let url_path = FileUrlPath::from_url(&upload.url).unwrap();
+  let (_, resp) = ctx.download_bytes(&url_path, None).await.unwrap();
+  assert_eq!(resp.unwrap(), content.as_bytes());
+}
@@ -1,5 +1,5 @@
 use crate::{assert_response_err, assert_response_ok};
-use pf_sdk::dto::{request::UploadQueryParam, response::BodyResponseError};
+use pf_sdk::dto::{request::UploadQueryParam, response::BodyResponseError, FileUrlPath};
 use test_context::test_context;
 
 use crate::helper::ApiTestContext;
@@ -37,3 +37,149 @@ pub async fn test_upload_with_invalid_len_param_query(ctx: &mut ApiTestContext)
     == "INVALID_INPUT");
   assert!(!status.is_success(), "status: {status}");
 }
+
+#[test_context(ApiTestContext)]
+#[tokio::test]
+pub async fn test_upload_multiple_files_as_bundle(ctx: &mut ApiTestContext) {
+  let files = [("first.txt", "first file"), ("second.txt", "second file")];
+  let file_parts = files
+    .iter()
+    .map(|(file_name, content)| {
+      reqwest::multipart::Part::bytes(content.as_bytes().to_vec())
+        .file_name(file_name.to_string())
+        .mime_str("text/plain")
+        .unwrap()
+    })
+    .collect();
+  let param: UploadQueryParam = Default::default();
+  let (status, resp) = ctx
+    .upload_file_parts(file_parts, &param, None)
+    .await
+    .unwrap();
+  assert!(status.is_success(), "status: {status}");
+  let resp = resp.unwrap();
+  assert_eq!(resp.urls.len(), files.len());
+  let code = url::Url::parse(&resp.url).unwrap().path()[1..].to_string();
+  let (status, bundle) = ctx.list_bundle(&code, None).await.unwrap();
+  assert!(status.is_success(), "status: {status}");
+  let bundle = bundle.unwrap();
+  assert_eq!(bundle.code, code);
+  for ((file_name, content), file) in files.iter().zip(bundle.files) {
+    assert_eq!(file.file_name, *file_name);
+    let url_path = FileUrlPath::from_url(&file.url).unwrap();
+    let (status, body) = ctx.download_bytes(&url_path, None).await.unwrap();
+    assert!(status.is_success(), "status: {status}");
+    assert_eq!(body.unwrap(), content.as_bytes());
+  }
+}
+
+#[test_context(ApiTestContext)]
+#[tokio::test]
+pub async fn test_list_bundle_not_found(ctx: &mut ApiTestContext) {
+  let (status, resp) = ctx.list_bundle("notexist", None).await.unwrap();
+  assert_response_err!(resp, |e: &BodyResponseError| e.error_type == "NOT_FOUND");
+  assert_eq!(status, reqwest::StatusCode::NOT_FOUND);
+}
+
+#[test_context(ApiTestContext)]
+#[tokio::test]
+pub async fn test_upload_with_sha256_checksum(ctx: &mut ApiTestContext) {
+  let param = UploadQueryParam {
+    sha256: Some("0".repeat(64)),
+    ..Default::default()
+  };
+  let (status, resp) = ctx
+    .upload(
+      "hello.txt".to_string(),
+      "text/plain",
+      b"hello".to_vec(),
+      &param,
+      None,
+    )
+    .await
+    .unwrap();
+  assert_eq!(status, reqwest::StatusCode::BAD_REQUEST);
+  assert_response_err!(resp, |e: &BodyResponseError| e.error_type
+    == "CHECKSUM_MISMATCH");
+  assert!(ctx.state.db.fetch_all().unwrap().is_empty());
+  let param = UploadQueryParam {
+    sha256: Some("2CF24DBA5FB0A30E26E83B2AC5B9E29E1B161E5C1FA7425E73043362938B9824".to_string()),
+    ..Default::default()
+  };
+  let (status, resp) = ctx
+    .upload(
+      "hello.txt".to_string(),
+      "text/plain",
+      b"hello".to_vec(),
+      &param,
+      None,
+    )
+    .await
+    .unwrap();
+  assert_response_ok!(resp);
+  assert!(status.is_success(), "status: {status}");
+}
+
+#[test_context(ApiTestContext)]
+#[tokio::test]
+pub async fn test_upload_with_non_hex_sha256(ctx: &mut ApiTestContext) {
+  let param = UploadQueryParam {
+    sha256: Some("z".repeat(64)),
+    ..Default::default()
+  };
+  let (status, resp) = ctx
+    .upload(
+      "hello.txt".to_string(),
+      "text/plain",
+      b"hello".to_vec(),
+      &param,
+      None,
+    )
+    .await
+    .unwrap();
+  assert_response_err!(resp, |e: &BodyResponseError| e.error_type
+    == "INVALID_INPUT");
+  assert!(status.is_client_error(), "status: {status}");
+  assert!(ctx.state.db.fetch_all().unwrap().is_empty());
+}
+
+#[test_context(ApiTestContext)]
+#[tokio::test]
+pub async fn test_upload_bundle_with_checksum_of_each_part(ctx: &mut ApiTestContext) {
+  let part = |file_name: &str, content: &str, checksum: &str| {
+    let sha256 = <sha2::Sha256 as sha2::Digest>::digest(checksum.as_bytes());
+    let mut headers = reqwest::header::HeaderMap::new();
+    headers.insert(
+      pf_sdk::util::digest::REPR_DIGEST_HEADER,
+      pf_sdk::util::digest::repr_digest(&sha256).parse().unwrap(),
+    );
+    reqwest::multipart::Part::bytes(content.as_bytes().to_vec())
+      .file_name(file_name.to_string())
+      .mime_str("text/plain")
+      .unwrap()
+      .headers(headers)
+  };
+  let param: UploadQueryParam = Default::default();
+  let file_parts = vec![
+    part("first.txt", "first file", "first file"),
+    part("second.txt", "second file", "another file"),
+  ];
+  let (status, resp) = ctx
+    .upload_file_parts(file_parts, &param, None)
+    .await
+    .unwrap();
+  assert_eq!(status, reqwest::StatusCode::BAD_REQUEST);
+  assert_response_err!(resp, |e: &BodyResponseError| e.error_type
+    == "CHECKSUM_MISMATCH");
+  assert!(ctx.state.db.fetch_all().unwrap().is_empty());
+  let file_parts = vec![
+    part("first.txt", "first file", "first file"),
+    part("second.txt", "second file", "second file"),
+  ];
+  let (status, resp) = ctx
+    .upload_file_parts(file_parts, &param, None)
+    .await
+    .unwrap();
+  assert_response_ok!(resp);
+  assert!(status.is_success(), "status: {status}");
+}
@@ -0,0 +1,273 @@
+use std::sync::{
+  atomic::{AtomicUsize, Ordering},
+  Arc,
+};
+use std::time::Duration;
+
+use axum::{body::Bytes, extract::State, http::HeaderMap, http::StatusCode, routing::post};
+use pf_api::service::webhook::{
+  signature, EventKind, WebhookEvent, DELIVERY_HEADER, EVENT_HEADER, SIGNATURE_HEADER,
+};
+use test_context::AsyncTestContext;
+use tokio::sync::mpsc;
+
+use crate::helper::ApiTestContext;
+use crate::unwrap;
+
+const SECRET: &str = "webhook-secret";
+
+struct Delivery {
+  headers: HeaderMap,
+  body: Bytes,
+}
+
+#[derive(Clone)]
+struct StandIn {
+  failures: Arc<AtomicUsize>,
+  sender: mpsc::UnboundedSender<Delivery>,
+}
+
+/// Local endpoint that records every delivery and fails the first `failures` ones.
+async fn stand_in(failures: usize) -> (String, mpsc::UnboundedReceiver<Delivery>) {
+  let (sender, receiver) = mpsc::unbounded_channel();
+  let state = StandIn {
+    failures: Arc::new(AtomicUsize::new(failures)),
+    sender,
+  };
+  let router = axum::Router::new()
+    .route(
+      "/hook",
+      post(
+        |State(state): State<StandIn>, headers: HeaderMap, body: Bytes| async move {
+          state.sender.send(Delivery { headers, body }).unwrap();
+          let failed = state
+            .failures
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+            .is_ok();
+          if failed {
+            StatusCode::INTERNAL_SERVER_ERROR
+          } else {
+            StatusCode::OK
+          }
+        },
+      ),
+    )
+    .with_state(state);
+  let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+  let addr = listener.local_addr().unwrap();
+  tokio::spawn(async move { axum::serve(listener, router).await });
+  (format!("http://{addr}/hook"), receiver)
+}
+
+async fn with_webhook(url: String) -> ApiTestContext {
+  ApiTestContext::with_config(|config| {
+    config.webhook.urls = vec![url];
+    config.webhook.secret = Some(SECRET.to_string());
+    config.webhook.retry_delay_ms = 10;
+  })
+  .await
+}
+
+async fn next_event(receiver: &mut mpsc::UnboundedReceiver<Delivery>) -> (Delivery, WebhookEvent) {
+  let delivery = tokio::time::timeout(Duration::from_secs(10), receiver.recv())
+    .await
+    .unwrap()
+    .unwrap();
+  assert_eq!(
+    delivery.headers.get(SIGNATURE_HEADER).unwrap(),
+    &signature(SECRET, &delivery.body)
+  );
+  let event: WebhookEvent = serde_json::from_slice(&delivery.body).unwrap();
+  assert_eq!(
+    delivery.headers.get(EVENT_HEADER).unwrap(),
+    &event.event.to_string()
+  );
+  (delivery, event)
+}
+
+#[tokio::test]
+pub async fn test_webhook_upload_download_and_delete_events() {
+  let (url, mut receiver) = stand_in(0).await;
+  let ctx = with_webhook(url).await;
+  let file = ctx
+    .upload_dummy_file(None, None, None, None, None, None)
+    .await;
+  let (_, event) = next_event(&mut receiver).await;
+  assert_eq!(event.event, EventKind::Upload);
+  assert_eq!(event.code, file.url_path.code);
+  assert_eq!(event.file_name, file.file_name);
+  assert_eq!(event.size, file.content.len() as u64);
+  let (_, body) = ctx.download_bytes(&file.url_path, None).await.unwrap();
+  unwrap!(body);
+  let (_, event) = next_event(&mut receiver).await;
+  assert_eq!(event.event, EventKind::Download);
+  assert_eq!(event.count_downloads, 1);
+  ctx.delete(&file.url_path, None).await.unwrap();
+  let (_, event) = next_event(&mut receiver).await;
+  assert_eq!(event.event, EventKind::Delete);
+  assert_eq!(event.file_name, file.file_name);
+  ctx.teardown().await;
+}
+
+#[tokio::test]
+pub async fn test_webhook_expire_event() {
+  let (url, mut receiver) = stand_in(0).await;
+  let ctx = with_webhook(url).await;
+  let file = ctx
+    .upload_dummy_file(None, None, Some(1), None, None, None)
+    .await;
+  let (_, event) = next_event(&mut receiver).await;
+  assert_eq!(event.event, EventKind::Upload);
+  let (_, event) = next_event(&mut receiver).await;
+  assert_eq!(event.event, EventKind::Expire);
+  assert_eq!(event.file_name, file.file_name);
+  ctx.teardown().await;
+}
+
+#[tokio::test]
+pub async fn test_webhook_retries_failed_delivery() {
+  let (url, mut receiver) = stand_in(2).await;
+  let ctx = with_webhook(url).await;
+  ctx
+    .upload_dummy_file(None, None, None, None, None, None)
+    .await;
+  let mut ids = vec![];
+  for _ in 0..3 {
+    let (delivery, event) = next_event(&mut receiver).await;
+    assert_eq!(event.event, EventKind::Upload);
+    ids.push(delivery.headers.get(DELIVERY_HEADER).unwrap().clone());
+  }
+  assert!(ids.iter().all(|id| *id == ids[0]));
+  assert!(receiver.try_recv().is_err());
+  ctx.teardown().await;
+}
+
+#[derive(Clone, Default)]
+struct SlowStandIn {
+  running: Arc<AtomicUsize>,
+  max_running: Arc<AtomicUsize>,
+  started: Arc<AtomicUsize>,
+  finished: Arc<AtomicUsize>,
+}
+
+/// Local endpoint that takes a while to answer and records how many deliveries overlap.
+async fn slow_stand_in() -> (String, SlowStandIn) {
+  let state = SlowStandIn::default();
+  let router = axum::Router::new()
+    .route(
+      "/hook",
+      post(|State(state): State<SlowStandIn>| async move {
+        state.started.fetch_add(1, Ordering::SeqCst);
+        let running = state.running.fetch_add(1, Ordering::SeqCst) + 1;
+        state.max_running.fetch_max(running, Ordering::SeqCst);
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        state.running.fetch_sub(1, Ordering::SeqCst);
+        state.finished.fetch_add(1, Ordering::SeqCst);
+        StatusCode::OK
+      }),
+    )
+    .with_state(state.clone());
+  let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+  let addr = listener.local_addr().unwrap();
+  tokio::spawn(async move { axum::serve(listener, router).await });
+  (format!("http://{addr}/hook"), state)
+}
+
+#[tokio::test]
+pub async fn test_webhook_bounds_and_drains_deliveries() {
+  let (url, stand_in) = slow_stand_in().await;
+  let mut ctx = ApiTestContext::with_config(|config| {
+    config.webhook.urls = vec![url.clone(), url];
+    config.webhook.concurrency = 1;
+  })
+  .await;
+  for _ in 0..3 {
+    ctx
+      .upload_dummy_file(None, None, None, None, None, None)
+      .await;
+  }
+  tokio::time::timeout(Duration::from_secs(10), async {
+    while stand_in.finished.load(Ordering::SeqCst) == 0 {
+      tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+  })
+  .await
+  .unwrap();
+  ctx.state.shutdown.cancel();
+  let result = tokio::time::timeout(Duration::from_secs(10), &mut ctx.webhook_task)
+    .await
+    .unwrap()
+    .unwrap();
+  assert!(result.is_ok());
+  assert_eq!(stand_in.max_running.load(Ordering::SeqCst), 1);
+  assert_eq!(
+    stand_in.started.load(Ordering::SeqCst),
+    stand_in.finished.load(Ordering::SeqCst)
+  );
+  assert!(stand_in.finished.load(Ordering::SeqCst) < 6);
+  ctx.teardown().await;
+}
+
+#[tokio::test]
+pub async fn test_webhook_stops_retrying_on_shutdown() {
+  let (url, mut receiver) = stand_in(usize::MAX).await;
+  let mut ctx = ApiTestContext::with_config(|config| {
+    config.webhook.urls = vec![url];
+    config.webhook.secret = Some(SECRET.to_string());
+    config.webhook.retry_delay_ms = 60_000;
+  })
+  .await;
+  ctx
+    .upload_dummy_file(None, None, None, None, None, None)
+    .await;
+  next_event(&mut receiver).await;
+  ctx.state.shutdown.cancel();
+  let result = tokio::time::timeout(Duration::from_secs(5), &mut ctx.webhook_task)
+    .await
+    .unwrap()
+    .unwrap();
+  assert!(result.is_ok());
+  assert!(receiver.try_recv().is_err());
+  ctx.teardown().await;
+}
+
+#[tokio::test]
+pub async fn test_webhook_drain_is_bounded_by_shutdown_timeout() {
+  let started = Arc::new(AtomicUsize::new(0));
+  let router = axum::Router::new()
+    .route(
+      "/hook",
+      post(|State(started): State<Arc<AtomicUsize>>| async move {
+        started.fetch_add(1, Ordering::SeqCst);
+        tokio::time::sleep(Duration::from_secs(60)).await;
+        StatusCode::OK
+      }),
+    )
+    .with_state(started.clone());
+  let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+  let addr = listener.local_addr().unwrap();
+  tokio::spawn(async move { axum::serve(listener, router).await });
+  let mut ctx = ApiTestContext::with_config(|config| {
+    config.webhook.urls = vec![format!("http://{addr}/hook")];
+    config.webhook.timeout_secs = 120;
+    config.server.shutdown_timeout_secs = 1;
+  })
+  .await;
+  ctx
+    .upload_dummy_file(None, None, None, None, None, None)
+    .await;
+  tokio::time::timeout(Duration::from_secs(10), async {
+    while started.load(Ordering::SeqCst) == 0 {
+      tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+  })
+  .await
+  .unwrap();
+  ctx.state.shutdown.cancel();
+  let result = tokio::time::timeout(Duration::from_secs(5), &mut ctx.webhook_task)
+    .await
+    .unwrap()
+    .unwrap();
+  assert!(result.is_ok());
+  ctx.teardown().await;
+}
@@ -1,11 +1,15 @@
+use chrono::{DateTime, Utc};
 use clap::{Parser, Subcommand, ValueEnum};
-use pf_sdk::{dto::FileUrlPath, util::crypto::KeyNonce};
+use pf_sdk::{
+  dto::{request::ApiKeyScope, FileUrlPath},
+  util::crypto::KeyNonce,
+};
 
 use std::path::PathBuf;
 
 use crate::parse::{
-  parse_auth, parse_destination, parse_expire_time, parse_file_name, parse_file_url_path,
-  parse_key_nonce, parse_source_file,
+  parse_api_key_scope, parse_auth, parse_destination, parse_expire_time, parse_file_name,
+  parse_file_url_path, parse_key_nonce, parse_source_file, parse_upload_source,
 };
 
 const HELP_ENCRYPT :&str = "The encrypt format should be `key:nonce`, with the key being 32 characters in length and the nonce being 19 characters.";
@@ -22,6 +26,11 @@ pub struct Args {
   pub server_addr: Option<String>,
   #[clap(short, long, value_parser = parse_auth, help = "The auth format should be `username:password`")]
   pub auth: Option<(String, String)>,
+  #[clap(
+    long,
+    help = "An API key created with `create-key`, sent with every request"
+  )]
+  pub api_key: Option<String>,
   #[clap(subcommand)]
   pub cmd: SubCommand,
 }
@@ -44,10 +53,34 @@ pub enum SubCommand {
     output: UploadOutput,
     #[clap(default_value_t = false, short, long)]
     progress_bar: bool,
-    #[clap(short, long, value_parser = parse_source_file)]
-    source_file: PathBuf,
+    #[clap(
+      short,
+      long,
+      required = true,
+      value_parser = parse_upload_source,
+      help = "File or directory to upload, repeat it to upload several files as one bundle"
+    )]
+    source_file: Vec<PathBuf>,
+    #[clap(
+      default_value_t = false,
+      long,
+      help = "Compress a directory with gzip while it is packed into a tar archive"
+    )]
+    compress: bool,
     #[clap(long, value_parser = parse_key_nonce, help = HELP_ENCRYPT)]
     key_nonce: Option<KeyNonce>,
+    #[clap(
+      default_value_t = false,
+      long,
+      conflicts_with = "resume_url",
+      help = "Upload with the tus protocol so an interrupted transfer can be resumed"
+    )]
+    resumable: bool,
+    #[clap(
+      long,
+      help = "Resume an interrupted resumable upload from its upload url"
+    )]
+    resume_url: Option<String>,
   },
   #[clap(about = "Copy text data from standard input (stdin) to the server")]
   Copy {
@@ -86,6 +119,18 @@ pub enum SubCommand {
     destination: PathBuf,
     #[clap(long, value_parser = parse_key_nonce, help = HELP_DECRYPT)]
     key_nonce: Option<KeyNonce>,
+    #[clap(
+      default_value_t = false,
+      long,
+      help = "Continue a partially downloaded destination file instead of starting over"
+    )]
+    resume: bool,
+    #[clap(
+      default_value_t = false,
+      long,
+      help = "Unpack a downloaded tar or tar.gz archive next to the destination file"
+    )]
+    extract: bool,
   },
   #[clap(about = "Retrieve text data from the server and paste it to standard output (stdout)")]
   Paste {
@@ -94,6 +139,48 @@ pub enum SubCommand {
     #[clap(long, value_parser = parse_key_nonce, help = HELP_DECRYPT)]
     key_nonce: Option<KeyNonce>,
   },
+  #[clap(about = "List the files uploaded with the API key given by --api-key")]
+  List {
+    #[clap(long)]
+    page: Option<usize>,
+    #[clap(long)]
+    page_size: Option<usize>,
+    #[clap(default_value_t = ListOutput::Table, short, long)]
+    output: ListOutput,
+  },
+  #[clap(about = "Query the audit log, requires an API key with the admin scope or --admin-token")]
+  Audit {
+    #[clap(long, help = "Only the events of this code")]
+    code: Option<String>,
+    #[clap(
+      long,
+      help = "Start of the time range in RFC 3339, e.g. 2024-01-31T10:00:00Z"
+    )]
+    from: Option<DateTime<Utc>>,
+    #[clap(long, help = "End of the time range in RFC 3339")]
+    to: Option<DateTime<Utc>>,
+    #[clap(long)]
+    limit: Option<usize>,
+    #[clap(long)]
+    admin_token: Option<String>,
+    #[clap(default_value_t = ListOutput::Table, short, long)]
+    output: ListOutput,
+  },
+  #[clap(about = "Create an account with the credentials given by --auth")]
+  Register,
+  #[clap(about = "Create an API key for the account given by --auth")]
+  CreateKey {
+    #[clap(short, long)]
+    name: String,
+    #[clap(
+      long,
+      required = true,
+      value_delimiter = ',',
+      value_parser = parse_api_key_scope,
+      help = "Comma separated scopes: upload, download, delete or admin"
+    )]
+    scopes: Vec<ApiKeyScope>,
+  },
   #[clap(about = "Encrypt a file before uploading to the server")]
   Encrypt {
     #[clap(default_value_t = false, short, long)]
@@ -135,3 +222,19 @@ impl std::fmt::Display for UploadOutput {
       .fmt(f)
   }
 }
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListOutput {
+  Table,
+  Json,
+}
+
+impl std::fmt::Display for ListOutput {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    self
+      .to_possible_value()
+      .expect("no values are skipped")
+      .get_name()
+      .fmt(f)
+  }
+}
@@ -1,20 +1,22 @@
 use std::{
+  io::SeekFrom,
   ops::Deref,
   path::{Path, PathBuf},
 };
 
 use pf_sdk::{
-  client::PasteFileClient,
+  client::{remove_etag, verify_file_size, PasteFileClient},
   dto::{
     request::UploadQueryParam,
-    response::{ApiResponseResult, BodyResponseError, UploadResponse},
+    response::{ApiResponseResult, BodyResponseError, UploadResponse, UploadStatusResponse},
     FileUrlPath,
   },
+  util::digest::{parse_sha256, verify_file_sha256},
 };
 
 use futures_util::StreamExt;
 use reqwest::StatusCode;
-use tokio::io::AsyncWriteExt;
+use tokio::io::{AsyncSeekExt, AsyncWriteExt};
 
 use crate::util::progress::progress_bar;
 
@@ -23,10 +25,14 @@ pub struct CommandLineClient {
 }
 
 impl CommandLineClient {
-  pub fn new(addr: String) -> Self {
-    Self {
-      inner: PasteFileClient::new(addr),
+  pub fn new(addr: String, api_key: Option<String>) -> Self {
+    let mut inner = PasteFileClient::new(addr);
+    if let Some(api_key) = api_key {
+      inner = inner
+        .with_api_key(&api_key)
+        .expect("The api key should be a valid header value.");
     }
+    Self { inner }
   }
 
   pub async fn upload_with_progress_bar(
@@ -54,6 +60,77 @@ impl CommandLineClient {
       .await
   }
 
+  pub async fn upload_resumable(
+    &self,
+    source: &Path,
+    param: &UploadQueryParam,
+    auth: Option<(String, String)>,
+    resume_url: Option<String>,
+    progress_bar: bool,
+  ) -> anyhow::Result<(StatusCode, ApiResponseResult<UploadResponse>)> {
+    let upload_url = match resume_url {
+      Some(upload_url) => upload_url,
+      None => {
+        let file_name = pf_sdk::util::file::get_file_name(source)?;
+        let length = tokio::fs::metadata(source).await?.len();
+        let (status, resp) = self
+          .create_upload(file_name, length, param, auth.clone())
+          .await?;
+        match resp {
+          ApiResponseResult::Ok(upload_url) => upload_url,
+          ApiResponseResult::Err(err) => return Ok((status, ApiResponseResult::Err(err))),
+        }
+      }
+    };
+    eprintln!("To resume an interrupted upload, pass `--resume-url {upload_url}`.");
+    let (status, resp) =
+      pf_sdk::retry!(|| self.resume_upload(source, &upload_url, auth.clone(), progress_bar))?;
+    match resp {
+      ApiResponseResult::Ok(UploadStatusResponse {
+        upload: Some(upload),
+        ..
+      }) => Ok((status, ApiResponseResult::Ok(upload))),
+      ApiResponseResult::Ok(upload_status) => Err(anyhow::anyhow!(
+        "The upload stopped at {} of {} bytes.",
+        upload_status.offset,
+        upload_status.length
+      )),
+      ApiResponseResult::Err(err) => Ok((status, ApiResponseResult::Err(err))),
+    }
+  }
+
+  async fn resume_upload(
+    &self,
+    source: &Path,
+    upload_url: &str,
+    auth: Option<(String, String)>,
+    progress_bar: bool,
+  ) -> anyhow::Result<(StatusCode, ApiResponseResult<UploadStatusResponse>)> {
+    if !progress_bar {
+      return self.resume_upload_file(source, upload_url, auth).await;
+    }
+    let (status, resp) = self.upload_status(upload_url, auth.clone()).await?;
+    let upload_status = match resp {
+      ApiResponseResult::Ok(upload_status) if upload_status.upload.is_none() => upload_status,
+      resp => return Ok((status, resp)),
+    };
+    let mut file = tokio::fs::File::open(source).await?;
+    file.seek(SeekFrom::Start(upload_status.offset)).await?;
+    let pb = crate::util::progress::progress_bar(upload_status.length)?;
+    pb.set_position(upload_status.offset);
+    self
+      .upload_chunk_from_reader(
+        upload_url,
+        upload_status.offset,
+        pb.wrap_async_read(file)
+          .with_finish(indicatif::ProgressFinish::WithMessage(
+            "Upload completed successfully.".into(),
+          )),
+        auth,
+      )
+      .await
+  }
+
   pub async fn download_with_progress_bar(
     &self,
     url_path: &FileUrlPath,
@@ -79,6 +156,7 @@ impl CommandLineClient {
     if let Some(parent) = destination.parent() {
       tokio::fs::create_dir_all(parent).await?;
     }
+    let sha256 = parse_sha256(resp.headers());
     let mut file = tokio::fs::File::create(&destination).await?;
     let pb = progress_bar(total_size)?;
     let mut stream = resp.bytes_stream();
@@ -89,6 +167,44 @@ impl CommandLineClient {
       downloaded += chunk.len() as u64;
       pb.set_position(downloaded.min(total_size));
     }
+    file.flush().await?;
+    verify_file_sha256(&destination, sha256.as_deref()).await?;
+    pb.finish_with_message("Download completed successfully.");
+    Ok((status, ApiResponseResult::Ok(destination)))
+  }
+
+  pub async fn resume_download_with_progress_bar(
+    &self,
+    url_path: &FileUrlPath,
+    auth: Option<(String, String)>,
+    mut destination: PathBuf,
+  ) -> anyhow::Result<(StatusCode, ApiResponseResult<PathBuf>)> {
+    if destination.is_dir() {
+      destination.push(&url_path.file_name);
+    }
+    let (status, resp) = self.resume_download(url_path, auth, &destination).await?;
+    let download = match resp {
+      ApiResponseResult::Ok(Some(download)) => download,
+      ApiResponseResult::Ok(None) => return Ok((status, ApiResponseResult::Ok(destination))),
+      ApiResponseResult::Err(err) => return Ok((status, ApiResponseResult::Err(err))),
+    };
+    let total_size = download.total_size;
+    let sha256 = parse_sha256(download.resp.headers());
+    let mut file = download.open_destination(&destination).await?;
+    let pb = progress_bar(total_size)?;
+    let mut downloaded = download.offset;
+    pb.set_position(downloaded);
+    let mut stream = download.resp.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+      let chunk = chunk?;
+      file.write_all(&chunk).await?;
+      downloaded += chunk.len() as u64;
+      pb.set_position(downloaded.min(total_size));
+    }
+    file.flush().await?;
+    verify_file_size(&destination, total_size).await?;
+    verify_file_sha256(&destination, sha256.as_deref()).await?;
+    remove_etag(&destination).await?;
     pb.finish_with_message("Download completed successfully.");
     Ok((status, ApiResponseResult::Ok(destination)))
   }
@@ -1,12 +1,16 @@
 use pf_sdk::{
   dto::{
-    request::UploadQueryParam,
-    response::{ApiResponseResult, BodyResponseError, UploadResponse},
+    request::{ApiKeyScope, AuditQueryParam, ListFilesQueryParam, UploadQueryParam},
+    response::{
+      ApiResponseResult, AuditLogResponse, BodyResponseError, FileListResponse, UploadResponse,
+    },
     FileUrlPath,
   },
   util::{
     crypto::KeyNonce,
-    file::{add_extension, rm_extra_extension},
+    digest::{file_sha256, SharedSha256},
+    file::{add_extension, get_content_type, get_file_name, rm_extra_extension},
+    tar::{archive_dir, archive_name},
   },
 };
 use std::path::{Path, PathBuf};
@@ -14,27 +18,50 @@ use tokio::io::{AsyncRead, AsyncWrite};
 use url::Url;
 
 use crate::{
-  args::UploadOutput, client::CommandLineClient, util::crypto::encrypt_file_with_progress_bar,
+  args::{ListOutput, UploadOutput},
+  client::CommandLineClient,
+  util::{
+    checksum::{checked_part, hashed_part, verify_upload},
+    crypto::encrypt_file_with_progress_bar,
+  },
 };
 
 #[derive(Debug)]
 pub struct UploadArguments {
   pub server_addr: String,
   pub auth: Option<(String, String)>,
+  pub api_key: Option<String>,
   pub code_length: Option<usize>,
   pub progress_bar: bool,
   pub expire: Option<u64>,
   pub allow_manual_deletion: Option<bool>,
   pub max_download: Option<u32>,
   pub output: UploadOutput,
-  pub source_file: PathBuf,
+  pub source_file: Vec<PathBuf>,
+  pub compress: bool,
+  pub key_nonce: Option<KeyNonce>,
+  pub resumable: bool,
+  pub resume_url: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct DownloadArguments {
+  pub server_addr: String,
+  pub auth: Option<(String, String)>,
+  pub api_key: Option<String>,
+  pub progress_bar: bool,
+  pub url_path: FileUrlPath,
+  pub destination: PathBuf,
   pub key_nonce: Option<KeyNonce>,
+  pub resume: bool,
+  pub extract: bool,
 }
 
 #[derive(Debug)]
 pub struct CopyArguments {
   pub server_addr: String,
   pub auth: Option<(String, String)>,
+  pub api_key: Option<String>,
   pub file_name: String,
   pub content_type: String,
   pub code_length: Option<usize>,
@@ -46,7 +73,7 @@ pub struct CopyArguments {
 }
 
 pub async fn ping(server_addr: String) {
-  let client = CommandLineClient::new(server_addr);
+  let client = CommandLineClient::new(server_addr, None);
   let (_, resp) = client.health_check().await.unwrap();
   match resp {
     ApiResponseResult::Ok(resp) => {
@@ -57,7 +84,13 @@ pub async fn ping(server_addr: String) {
 }
 
 pub async fn upload(args: UploadArguments) {
-  let mut source_file = args.source_file;
+  if args.source_file.len() > 1 {
+    return upload_bundle(args).await;
+  }
+  if args.source_file[0].is_dir() {
+    return upload_dir(args).await;
+  }
+  let mut source_file = args.source_file.into_iter().next().unwrap();
   if let Some(key_nonce) = args.key_nonce.as_ref() {
     if args.progress_bar {
       let encrypted_file = add_extension(&source_file, "bin");
@@ -77,9 +110,20 @@ pub async fn upload(args: UploadArguments) {
     expire_secs: args.expire,
     allow_manual_deletion: args.allow_manual_deletion,
     qr_code_format: None,
+    sha256: Some(hex::encode(file_sha256(&source_file).await.unwrap())),
   };
-  let client = CommandLineClient::new(args.server_addr);
-  let (_, resp) = if args.progress_bar {
+  let client = CommandLineClient::new(args.server_addr, args.api_key);
+  let (_, resp) = if args.resumable || args.resume_url.is_some() {
+    client
+      .upload_resumable(
+        &source_file,
+        &param,
+        args.auth,
+        args.resume_url,
+        args.progress_bar,
+      )
+      .await
+  } else if args.progress_bar {
     client
       .upload_with_progress_bar(&source_file, &param, args.auth)
       .await
@@ -93,41 +137,136 @@ pub async fn upload(args: UploadArguments) {
   };
 }
 
+async fn upload_bundle(args: UploadArguments) {
+  if args.resumable || args.resume_url.is_some() {
+    eprintln!("Resumable upload supports a single source file.");
+    std::process::exit(1);
+  }
+  let param = UploadQueryParam {
+    max_download: args.max_download,
+    code_length: args.code_length,
+    expire_secs: args.expire,
+    allow_manual_deletion: args.allow_manual_deletion,
+    qr_code_format: None,
+    sha256: None,
+  };
+  // A file sent as it is carries its checksum in its part, since the sha256 parameter covers
+  // a single file. Archived or encrypted parts are hashed while they are sent.
+  let mut file_parts = Vec::with_capacity(args.source_file.len());
+  for source_file in &args.source_file {
+    let (file_name, content_type) = match args.key_nonce.as_ref() {
+      Some(_) if source_file.is_dir() => (
+        format!("{}.bin", archive_name(source_file, args.compress).unwrap()),
+        "application/octet-stream".to_string(),
+      ),
+      Some(_) => (
+        format!("{}.bin", get_file_name(source_file).unwrap()),
+        "application/octet-stream".to_string(),
+      ),
+      None if source_file.is_dir() => (
+        archive_name(source_file, args.compress).unwrap(),
+        pf_sdk::util::tar::content_type(args.compress).to_string(),
+      ),
+      None => (
+        get_file_name(source_file).unwrap(),
+        get_content_type(source_file).unwrap(),
+      ),
+    };
+    let file_part = if source_file.is_dir() {
+      let reader = archive_dir(source_file, args.compress).unwrap();
+      let (part, sha256) =
+        hashed_part(args.key_nonce.as_ref(), file_name, &content_type, reader).unwrap();
+      (part, Some(sha256))
+    } else if args.key_nonce.is_some() {
+      let reader = tokio::fs::File::open(source_file).await.unwrap();
+      let (part, sha256) =
+        hashed_part(args.key_nonce.as_ref(), file_name, &content_type, reader).unwrap();
+      (part, Some(sha256))
+    } else {
+      let sha256 = file_sha256(source_file).await.unwrap();
+      let part = checked_part(source_file, file_name, &content_type, &sha256)
+        .await
+        .unwrap();
+      (part, None)
+    };
+    file_parts.push(file_part);
+  }
+  let client = CommandLineClient::new(args.server_addr, args.api_key);
+  let resp = upload_checked(&client, file_parts, param, args.auth).await;
+  show_upload_response(resp, args.output);
+}
+
+async fn upload_dir(args: UploadArguments) {
+  if args.resumable || args.resume_url.is_some() {
+    eprintln!("Resumable upload does not support directories.");
+    std::process::exit(1);
+  }
+  let param = UploadQueryParam {
+    max_download: args.max_download,
+    code_length: args.code_length,
+    expire_secs: args.expire,
+    allow_manual_deletion: args.allow_manual_deletion,
+    qr_code_format: None,
+    sha256: None,
+  };
+  let source_dir = &args.source_file[0];
+  let file_name = archive_name(source_dir, args.compress).unwrap();
+  let reader = archive_dir(source_dir, args.compress).unwrap();
+  let (file_name, content_type) = if args.key_nonce.is_some() {
+    (format!("{file_name}.bin"), "application/octet-stream")
+  } else {
+    (file_name, pf_sdk::util::tar::content_type(args.compress))
+  };
+  let (part, sha256) =
+    hashed_part(args.key_nonce.as_ref(), file_name, content_type, reader).unwrap();
+  let client = CommandLineClient::new(args.server_addr, args.api_key);
+  let resp = upload_checked(&client, vec![(part, Some(sha256))], param, args.auth).await;
+  show_upload_response(resp, args.output);
+}
+
+/// Uploads the parts, then checks the stored files against the SHA-256 of the parts that
+/// were hashed while they were sent.
+async fn upload_checked(
+  client: &CommandLineClient,
+  file_parts: Vec<(reqwest::multipart::Part, Option<SharedSha256>)>,
+  param: UploadQueryParam,
+  auth: Option<(String, String)>,
+) -> ApiResponseResult<UploadResponse> {
+  let (file_parts, sent): (Vec<_>, Vec<_>) = file_parts.into_iter().unzip();
+  let (_, resp) = client
+    .upload_file_parts(file_parts, &param, auth.clone())
+    .await
+    .unwrap();
+  if let ApiResponseResult::Ok(resp) = &resp {
+    if let Err(err) = verify_upload(client, resp, &sent, auth).await {
+      eprintln!("{err}");
+      std::process::exit(1);
+    }
+  }
+  resp
+}
+
 pub async fn copy<R>(reader: R, args: CopyArguments)
 where
   R: AsyncRead + Send + Sync + Unpin + 'static,
 {
-  let client = CommandLineClient::new(args.server_addr);
+  let client = CommandLineClient::new(args.server_addr, args.api_key);
   let param = UploadQueryParam {
     max_download: args.max_download,
     code_length: args.code_length,
     expire_secs: args.expire,
     allow_manual_deletion: args.allow_manual_deletion,
     qr_code_format: None,
+    sha256: None,
   };
-  let (_, resp) = if let Some(key_nonce) = args.key_nonce.as_ref() {
-    client
-      .upload_encrypt(
-        key_nonce,
-        args.file_name,
-        &args.content_type,
-        reader,
-        &param,
-        args.auth,
-      )
-      .await
-  } else {
-    client
-      .upload_from_reader(
-        args.file_name,
-        &args.content_type,
-        reader,
-        &param,
-        args.auth,
-      )
-      .await
-  }
+  let (part, sha256) = hashed_part(
+    args.key_nonce.as_ref(),
+    args.file_name,
+    &args.content_type,
+    reader,
+  )
   .unwrap();
+  let resp = upload_checked(&client, vec![(part, Some(sha256))], param, args.auth).await;
   show_upload_response(resp, args.output);
 }
 
@@ -142,29 +281,53 @@ fn show_upload_response(resp: ApiResponseResult<UploadResponse>, output: UploadO
         println!("{qr_code}");
       }
       UploadOutput::Url => {
-        println!("{}", resp.url);
+        for url in file_urls(&resp) {
+          println!("{url}");
+        }
       }
       UploadOutput::UrlPath => {
-        println!("{}", &Url::parse(&resp.url).unwrap().path()[1..]);
+        for url in file_urls(&resp) {
+          println!("{}", &Url::parse(url).unwrap().path()[1..]);
+        }
       }
     },
     ApiResponseResult::Err(err) => print_response_err(&err),
   }
 }
 
-pub async fn download(
-  server_addr: String,
-  auth: Option<(String, String)>,
-  progress_bar: bool,
-  url_path: FileUrlPath,
-  mut destination: PathBuf,
-  key_nonce: Option<KeyNonce>,
-) {
-  let client = CommandLineClient::new(server_addr);
+fn file_urls(resp: &UploadResponse) -> Vec<&String> {
+  if resp.urls.is_empty() {
+    vec![&resp.url]
+  } else {
+    resp.urls.iter().collect()
+  }
+}
+
+pub async fn download(args: DownloadArguments) {
+  let DownloadArguments {
+    server_addr,
+    auth,
+    api_key,
+    progress_bar,
+    url_path,
+    mut destination,
+    key_nonce,
+    resume,
+    extract,
+  } = args;
+  let client = CommandLineClient::new(server_addr, api_key);
   if key_nonce.is_some() && destination.extension().is_some() {
     destination = add_extension(destination, "bin");
   }
-  let (_, resp) = if progress_bar {
+  let (_, resp) = if resume && progress_bar {
+    client
+      .resume_download_with_progress_bar(&url_path, auth, destination.clone())
+      .await
+  } else if resume {
+    client
+      .resume_download_file(&url_path, auth, destination.clone())
+      .await
+  } else if progress_bar {
     client
       .download_with_progress_bar(&url_path, auth, destination.clone())
       .await
@@ -190,22 +353,47 @@ pub async fn download(
             .unwrap();
         }
       }
+      if extract {
+        let archive = if key_nonce.is_some() {
+          destination.clone()
+        } else {
+          encrypt_source_file
+        };
+        destination = extract_archive(&archive).await;
+      }
       println!("{}", serde_json::json!({"output":destination}));
     }
     ApiResponseResult::Err(err) => print_response_err(&err),
   }
 }
 
+async fn extract_archive(archive: &Path) -> PathBuf {
+  if !pf_sdk::util::tar::is_archive(archive) {
+    eprintln!("The downloaded file {archive:?} is not a tar archive.");
+    std::process::exit(1);
+  }
+  let destination = archive.parent().unwrap_or(Path::new("."));
+  let mut roots = pf_sdk::util::tar::extract(archive, destination)
+    .await
+    .unwrap();
+  tokio::fs::remove_file(archive).await.unwrap();
+  match roots.len() {
+    1 => roots.remove(0),
+    _ => destination.to_path_buf(),
+  }
+}
+
 pub async fn paste<W>(
   server_addr: String,
   auth: Option<(String, String)>,
+  api_key: Option<String>,
   url_path: FileUrlPath,
   key_nonce: Option<KeyNonce>,
   writer: W,
 ) where
   W: AsyncWrite + Unpin,
 {
-  let client = CommandLineClient::new(server_addr);
+  let client = CommandLineClient::new(server_addr, api_key);
   let (_, resp) = if let Some(key_nonce) = key_nonce.as_ref() {
     client
       .download_and_decrypt(key_nonce, &url_path, auth, writer)
@@ -220,8 +408,13 @@ pub async fn paste<W>(
   }
 }
 
-pub async fn info(server_addr: String, url_path: FileUrlPath, auth: Option<(String, String)>) {
-  let client = CommandLineClient::new(server_addr);
+pub async fn info(
+  server_addr: String,
+  url_path: FileUrlPath,
+  auth: Option<(String, String)>,
+  api_key: Option<String>,
+) {
+  let client = CommandLineClient::new(server_addr, api_key);
   let (_, resp) = client.info(&url_path, auth).await.unwrap();
   match resp {
     ApiResponseResult::Ok(resp) => {
@@ -231,8 +424,13 @@ pub async fn info(server_addr: String, url_path: FileUrlPath, auth: Option<(Stri
   }
 }
 
-pub async fn delete(server_addr: String, url_path: FileUrlPath, auth: Option<(String, String)>) {
-  let client = CommandLineClient::new(server_addr);
+pub async fn delete(
+  server_addr: String,
+  url_path: FileUrlPath,
+  auth: Option<(String, String)>,
+  api_key: Option<String>,
+) {
+  let client = CommandLineClient::new(server_addr, api_key);
   let (_, resp) = client.delete(&url_path, auth).await.unwrap();
   match resp {
     ApiResponseResult::Ok(resp) => {
@@ -242,6 +440,150 @@ pub async fn delete(server_addr: String, url_path: FileUrlPath, auth: Option<(St
   }
 }
 
+pub async fn list(
+  server_addr: String,
+  api_key: Option<String>,
+  param: ListFilesQueryParam,
+  output: ListOutput,
+) {
+  let client = CommandLineClient::new(server_addr, api_key);
+  let (_, resp) = client.list_files(&param).await.unwrap();
+  match resp {
+    ApiResponseResult::Ok(resp) => match output {
+      ListOutput::Json => println!("{}", serde_json::to_string(&resp).unwrap()),
+      ListOutput::Table => print_file_table(&resp),
+    },
+    ApiResponseResult::Err(err) => print_response_err(&err),
+  }
+}
+
+fn print_file_table(resp: &FileListResponse) {
+  let rows = resp
+    .files
+    .iter()
+    .map(|file| {
+      let downloads = match file.meta.max_download {
+        Some(max) => format!("{}/{max}", file.meta.count_downloads),
+        None => file.meta.count_downloads.to_string(),
+      };
+      [
+        format!("{}/{}", file.code, file.file_name),
+        indicatif::HumanBytes(file.meta.size).to_string(),
+        downloads,
+        file
+          .meta
+          .expire_date_time
+          .format("%Y-%m-%d %H:%M:%S")
+          .to_string(),
+      ]
+    })
+    .collect::<Vec<_>>();
+  print_table(["URL PATH", "SIZE", "DOWNLOADS", "EXPIRES (UTC)"], &rows);
+  let pages = resp.total.div_ceil(resp.page_size);
+  println!(
+    "Page {} of {}, {} files in total.",
+    resp.page,
+    pages.max(1),
+    resp.total
+  );
+}
+
+pub async fn audit(
+  server_addr: String,
+  api_key: Option<String>,
+  admin_token: Option<String>,
+  param: AuditQueryParam,
+  output: ListOutput,
+) {
+  let client = CommandLineClient::new(server_addr, api_key);
+  let (_, resp) = client
+    .admin_audit_log(admin_token.as_deref(), &param)
+    .await
+    .unwrap();
+  match resp {
+    ApiResponseResult::Ok(resp) => match output {
+      ListOutput::Json => println!("{}", serde_json::to_string(&resp).unwrap()),
+      ListOutput::Table => print_audit_table(&resp),
+    },
+    ApiResponseResult::Err(err) => print_response_err(&err),
+  }
+}
+
+fn print_audit_table(resp: &AuditLogResponse) {
+  let rows = resp
+    .events
+    .iter()
+    .map(|event| {
+      let url_path = match (&event.code, &event.file_name) {
+        (Some(code), Some(file_name)) => format!("{code}/{file_name}"),
+        (Some(code), None) => code.clone(),
+        _ => "-".to_string(),
+      };
+      let ip = event.ip.map_or("-".to_string(), |ip| ip.to_string());
+      [
+        event.timestamp.format("%Y-%m-%d %H:%M:%S").to_string(),
+        event.action.to_string(),
+        url_path,
+        event.status.to_string(),
+        event.username.clone().unwrap_or_else(|| "-".to_string()),
+        ip,
+      ]
+    })
+    .collect::<Vec<_>>();
+  print_table(
+    ["TIME (UTC)", "ACTION", "URL PATH", "STATUS", "USER", "IP"],
+    &rows,
+  );
+}
+
+fn print_table<const N: usize>(header: [&str; N], rows: &[[String; N]]) {
+  let header = header.map(String::from);
+  let mut widths = header.clone().map(|h| h.len());
+  for row in rows {
+    for (width, cell) in widths.iter_mut().zip(row) {
+      *width = (*width).max(cell.len());
+    }
+  }
+  for row in std::iter::once(&header).chain(rows) {
+    let line = row
+      .iter()
+      .zip(widths)
+      .map(|(cell, width)| format!("{cell:<width$}"))
+      .collect::<Vec<_>>()
+      .join("  ");
+    println!("{}", line.trim_end());
+  }
+}
+
+pub async fn register(server_addr: String, auth: Option<(String, String)>) {
+  let (username, password) = auth.expect("The auth should be set to `username:password`.");
+  let client = CommandLineClient::new(server_addr, None);
+  let (_, resp) = client.create_account(username, password).await.unwrap();
+  match resp {
+    ApiResponseResult::Ok(resp) => {
+      println!("{}", serde_json::to_string(&resp).unwrap());
+    }
+    ApiResponseResult::Err(err) => print_response_err(&err),
+  }
+}
+
+pub async fn create_api_key(
+  server_addr: String,
+  auth: Option<(String, String)>,
+  name: String,
+  scopes: Vec<ApiKeyScope>,
+) {
+  let auth = auth.expect("The auth should be set to `username:password`.");
+  let client = CommandLineClient::new(server_addr, None);
+  let (_, resp) = client.create_api_key(auth, name, scopes).await.unwrap();
+  match resp {
+    ApiResponseResult::Ok(resp) => {
+      println!("{}", serde_json::to_string(&resp).unwrap());
+    }
+    ApiResponseResult::Err(err) => print_response_err(&err),
+  }
+}
+
 pub async fn encrypt_file(
   progress_bar: bool,
   key_nonce: &KeyNonce,
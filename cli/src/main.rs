@@ -1,6 +1,7 @@
 use args::{Args, SubCommand};
 use clap::Parser;
-use command::{CopyArguments, UploadArguments};
+use command::{CopyArguments, DownloadArguments, UploadArguments};
+use pf_sdk::dto::request::{AuditQueryParam, ListFilesQueryParam};
 use pf_sdk::util::{
   file::{add_extension, get_content_type},
   random::generate_random_string,
@@ -30,12 +31,16 @@ async fn main() {
       max_download,
       output,
       source_file,
+      compress,
       key_nonce,
+      resumable,
+      resume_url,
     } => {
       let server_addr = args.server_addr.expect("Server address should be set.");
       let args = UploadArguments {
         server_addr,
         auth: args.auth,
+        api_key: args.api_key,
         code_length,
         progress_bar,
         expire,
@@ -43,7 +48,10 @@ async fn main() {
         max_download,
         output,
         source_file,
+        compress,
         key_nonce,
+        resumable,
+        resume_url,
       };
       command::upload(args).await;
     }
@@ -77,6 +85,7 @@ async fn main() {
       let args = CopyArguments {
         server_addr,
         auth: args.auth,
+        api_key: args.api_key,
         file_name,
         content_type,
         code_length,
@@ -93,17 +102,22 @@ async fn main() {
       url_path,
       destination,
       key_nonce,
+      resume,
+      extract,
     } => {
       let server_addr = args.server_addr.expect("Server address should be set.");
-      command::download(
+      let args = DownloadArguments {
         server_addr,
-        args.auth,
+        auth: args.auth,
+        api_key: args.api_key,
         progress_bar,
         url_path,
         destination,
         key_nonce,
-      )
-      .await;
+        resume,
+        extract,
+      };
+      command::download(args).await;
     }
     SubCommand::Paste {
       url_path,
@@ -111,15 +125,57 @@ async fn main() {
     } => {
       let server_addr = args.server_addr.expect("Server address should be set.");
       let stdout: tokio::io::Stdout = tokio::io::stdout();
-      command::paste(server_addr, args.auth, url_path, key_nonce, stdout).await;
+      command::paste(
+        server_addr,
+        args.auth,
+        args.api_key,
+        url_path,
+        key_nonce,
+        stdout,
+      )
+      .await;
     }
     SubCommand::Info { url_path } => {
       let server_addr = args.server_addr.expect("Server address should be set.");
-      command::info(server_addr, url_path, args.auth).await
+      command::info(server_addr, url_path, args.auth, args.api_key).await
     }
     SubCommand::Delete { url_path } => {
       let server_addr = args.server_addr.expect("Server address should be set.");
-      command::delete(server_addr, url_path, args.auth).await
+      command::delete(server_addr, url_path, args.auth, args.api_key).await
+    }
+    SubCommand::List {
+      page,
+      page_size,
+      output,
+    } => {
+      let server_addr = args.server_addr.expect("Server address should be set.");
+      let param = ListFilesQueryParam { page, page_size };
+      command::list(server_addr, args.api_key, param, output).await
+    }
+    SubCommand::Audit {
+      code,
+      from,
+      to,
+      limit,
+      admin_token,
+      output,
+    } => {
+      let server_addr = args.server_addr.expect("Server address should be set.");
+      let param = AuditQueryParam {
+        code,
+        from,
+        to,
+        limit,
+      };
+      command::audit(server_addr, args.api_key, admin_token, param, output).await
+    }
+    SubCommand::Register => {
+      let server_addr = args.server_addr.expect("Server address should be set.");
+      command::register(server_addr, args.auth).await
+    }
+    SubCommand::CreateKey { name, scopes } => {
+      let server_addr = args.server_addr.expect("Server address should be set.");
+      command::create_api_key(server_addr, args.auth, name, scopes).await
     }
     SubCommand::Encrypt {
       progress_bar,
@@ -1,7 +1,7 @@
 use std::{path::PathBuf, str::FromStr};
 
 use anyhow::anyhow;
-use pf_sdk::dto::FileUrlPath;
+use pf_sdk::dto::{request::ApiKeyScope, FileUrlPath};
 
 use pf_sdk::util::crypto::{KeyNonce, KeyType, NonceType};
 
@@ -27,6 +27,11 @@ pub fn parse_auth(input: &str) -> anyhow::Result<(String, String)> {
   Ok((input[..pos].parse()?, input[pos + 1..].parse()?))
 }
 
+pub fn parse_api_key_scope(input: &str) -> anyhow::Result<ApiKeyScope> {
+  serde_json::from_value(serde_json::Value::String(input.to_lowercase()))
+    .map_err(|_| anyhow!("Invalid scope {input}, expected upload, download, delete or admin"))
+}
+
 pub fn parse_expire_time(input: &str) -> anyhow::Result<u64> {
   let words: Vec<&str> = input.split_whitespace().collect();
   if words.len() != 2 {
@@ -57,6 +62,10 @@ pub fn parse_source_file(source_file: &str) -> anyhow::Result<PathBuf> {
   }
 }
 
+pub fn parse_upload_source(source: &str) -> anyhow::Result<PathBuf> {
+  Ok(PathBuf::from(source).canonicalize()?)
+}
+
 pub fn parse_file_name(file_name: &str) -> anyhow::Result<PathBuf> {
   let source_file = PathBuf::from(file_name);
   if source_file.extension().is_none() {
@@ -0,0 +1,111 @@
+use anyhow::anyhow;
+use pf_sdk::{
+  client::{encrypt_stream, PasteFileClient},
+  dto::{
+    response::{ApiResponseResult, UploadResponse},
+    FileUrlPath,
+  },
+  util::{
+    crypto::KeyNonce,
+    digest::{repr_digest, SharedSha256, REPR_DIGEST_HEADER},
+  },
+};
+use reqwest::header::{HeaderMap, HeaderValue};
+use std::path::Path;
+use tokio::{fs::File, io::AsyncRead};
+use tokio_util::io::ReaderStream;
+
+/// A multipart file part whose `Repr-Digest` header carries the SHA-256 of its content.
+pub async fn checked_part(
+  source: &Path,
+  file_name: String,
+  content_type: &str,
+  sha256: &[u8],
+) -> anyhow::Result<reqwest::multipart::Part> {
+  let file = File::open(source).await?;
+  let mut headers = HeaderMap::new();
+  headers.insert(
+    REPR_DIGEST_HEADER,
+    HeaderValue::from_str(&repr_digest(sha256))?,
+  );
+  Ok(
+    reqwest::multipart::Part::stream(reqwest::Body::wrap_stream(ReaderStream::new(file)))
+      .file_name(file_name)
+      .mime_str(content_type)?
+      .headers(headers),
+  )
+}
+
+/// A multipart file part streamed from `reader`, encrypted when a key is given. Its SHA-256
+/// is computed while it is sent, since the content is not known ahead.
+pub fn hashed_part<R>(
+  key_nonce: Option<&KeyNonce>,
+  file_name: String,
+  content_type: &str,
+  reader: R,
+) -> anyhow::Result<(reqwest::multipart::Part, SharedSha256)>
+where
+  R: AsyncRead + Send + Sync + Unpin + 'static,
+{
+  let sha256 = SharedSha256::default();
+  let body = match key_nonce {
+    Some(key_nonce) => {
+      reqwest::Body::wrap_stream(sha256.inspect(encrypt_stream(key_nonce, reader)))
+    }
+    None => reqwest::Body::wrap_stream(sha256.inspect(ReaderStream::new(reader))),
+  };
+  let part = reqwest::multipart::Part::stream(body)
+    .file_name(file_name)
+    .mime_str(content_type)?;
+  Ok((part, sha256))
+}
+
+/// Compares the stored files with the SHA-256 of the hashed parts that were sent, and deletes
+/// every file of the upload when one does not match.
+pub async fn verify_upload(
+  client: &PasteFileClient,
+  resp: &UploadResponse,
+  sent: &[Option<SharedSha256>],
+  auth: Option<(String, String)>,
+) -> anyhow::Result<()> {
+  let mut mismatch = None;
+  for (url, sha256) in resp.urls.iter().zip(sent) {
+    let Some(sha256) = sha256 else {
+      continue;
+    };
+    let stored = match client
+      .info(&FileUrlPath::from_url(url)?, auth.clone())
+      .await?
+    {
+      (_, ApiResponseResult::Ok(meta)) => meta.sha256,
+      (_, ApiResponseResult::Err(err)) => {
+        return Err(anyhow!(
+          "Checking the SHA-256 of {url} failed, Error: {err:?}"
+        ))
+      }
+    };
+    if stored.is_some_and(|stored| !stored.eq_ignore_ascii_case(&hex::encode(sha256.finalize()))) {
+      mismatch = Some(url);
+      break;
+    }
+  }
+  let Some(url) = mismatch else {
+    return Ok(());
+  };
+  let mut deleted = true;
+  for url in &resp.urls {
+    let (status, _) = client
+      .delete(&FileUrlPath::from_url(url)?, auth.clone())
+      .await?;
+    deleted &= status.is_success();
+  }
+  if deleted {
+    Err(anyhow!(
+      "The SHA-256 checksum of {url} does not match the sent content, the upload was deleted."
+    ))
+  } else {
+    Err(anyhow!(
+      "The SHA-256 checksum of {url} does not match the sent content, and the upload could not be deleted."
+    ))
+  }
+}
@@ -0,0 +1,75 @@
+use assert_cmd::Command;
+use pf_sdk::dto::response::{AccountResponse, ApiKeyResponse, MetaDataFileResponse};
+
+use crate::helper::CliTestContext;
+
+#[test_context::test_context(CliTestContext)]
+#[tokio::test]
+async fn test_register_create_key_and_upload_command(ctx: &mut CliTestContext) {
+  let output = Command::cargo_bin("pf-cli")
+    .unwrap()
+    .args([
+      "--server-addr",
+      &ctx.server_addr,
+      "--auth",
+      "alice:password123",
+      "register",
+    ])
+    .output()
+    .unwrap()
+    .stdout;
+  let account: AccountResponse = serde_json::from_slice(&output).unwrap();
+  assert_eq!(account.username, "alice");
+  let output = Command::cargo_bin("pf-cli")
+    .unwrap()
+    .args([
+      "--server-addr",
+      &ctx.server_addr,
+      "--auth",
+      "alice:password123",
+      "create-key",
+      "--name",
+      "laptop",
+      "--scopes",
+      "upload,download",
+    ])
+    .output()
+    .unwrap()
+    .stdout;
+  let api_key: ApiKeyResponse = serde_json::from_slice(&output).unwrap();
+  let key = api_key.key.unwrap();
+  let (file, _) = ctx.create_dummy_file().await.unwrap();
+  let url_path = Command::cargo_bin("pf-cli")
+    .unwrap()
+    .args([
+      "--server-addr",
+      &ctx.server_addr,
+      "--api-key",
+      &key,
+      "upload",
+      "--source-file",
+      file.to_str().unwrap(),
+      "--output",
+      "url-path",
+    ])
+    .output()
+    .unwrap()
+    .stdout;
+  let url_path = std::str::from_utf8(&url_path).unwrap().trim();
+  let output = Command::cargo_bin("pf-cli")
+    .unwrap()
+    .args([
+      "--server-addr",
+      &ctx.server_addr,
+      "--api-key",
+      &key,
+      "info",
+      "--url-path",
+      url_path,
+    ])
+    .output()
+    .unwrap()
+    .stdout;
+  let info: MetaDataFileResponse = serde_json::from_slice(&output).unwrap();
+  assert_eq!(info.owner.as_deref(), Some("alice"));
+}
@@ -0,0 +1,49 @@
+use assert_cmd::Command;
+use pf_sdk::dto::{
+  request::ApiKeyScope,
+  response::{AuditAction, AuditLogResponse},
+};
+
+use crate::helper::CliTestContext;
+
+#[test_context::test_context(CliTestContext)]
+#[tokio::test]
+async fn test_audit_command(ctx: &mut CliTestContext) {
+  let key = ctx.create_api_key("admin", vec![ApiKeyScope::Admin]).await;
+  let (url_path, _) = ctx.upload_dummy_file().await.unwrap();
+  let output = Command::cargo_bin("pf-cli")
+    .unwrap()
+    .args([
+      "--server-addr",
+      &ctx.server_addr,
+      "--api-key",
+      &key,
+      "audit",
+      "--code",
+      &url_path.code,
+      "--output",
+      "json",
+    ])
+    .output()
+    .unwrap()
+    .stdout;
+  let resp: AuditLogResponse = serde_json::from_slice(&output).unwrap();
+  assert_eq!(resp.events.len(), 1);
+  assert_eq!(resp.events[0].action, AuditAction::Upload);
+  assert_eq!(resp.events[0].file_name.as_ref(), Some(&url_path.file_name));
+  let output = Command::cargo_bin("pf-cli")
+    .unwrap()
+    .args([
+      "--server-addr",
+      &ctx.server_addr,
+      "--api-key",
+      &key,
+      "audit",
+    ])
+    .output()
+    .unwrap()
+    .stdout;
+  let table = std::str::from_utf8(&output).unwrap();
+  assert!(table.starts_with("TIME (UTC)"), "{table}");
+  assert!(table.contains(&url_path.to_string()), "{table}");
+}
@@ -52,3 +52,31 @@ async fn test_download_command_to_the_destination_dir(ctx: &mut CliTestContext)
     .unwrap();
   assert_eq!(actual_content, expected_content);
 }
+
+#[test_context::test_context(CliTestContext)]
+#[tokio::test]
+async fn test_resume_download_command(ctx: &mut CliTestContext) {
+  let (url_path, expected_content) = ctx.upload_dummy_file().await.unwrap();
+  let destination_file = ctx.workspace.join("partial_file.txt");
+  let half = expected_content.len() / 2;
+  tokio::fs::write(&destination_file, &expected_content.as_bytes()[..half])
+    .await
+    .unwrap();
+  Command::cargo_bin("pf-cli")
+    .unwrap()
+    .args([
+      "--server-addr",
+      &ctx.server_addr,
+      "download",
+      "--url-path",
+      &url_path.to_string(),
+      "--destination",
+      destination_file.to_str().unwrap(),
+      "--resume",
+      "--progress-bar",
+    ])
+    .assert()
+    .success();
+  let actual_content = tokio::fs::read_to_string(&destination_file).await.unwrap();
+  assert_eq!(actual_content, expected_content);
+}
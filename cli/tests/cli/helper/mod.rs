@@ -1,7 +1,8 @@
 use fake::{Fake, Faker};
 use once_cell::sync::Lazy;
 use pf_sdk::{
-  dto::FileUrlPath,
+  client::PasteFileClient,
+  dto::{request::ApiKeyScope, response::ApiResponseResult, FileUrlPath},
   retry,
   util::{dir::get_cargo_project_root, random::generate_random_string},
 };
@@ -13,13 +14,14 @@ use std::{
   str::FromStr,
 };
 use test_context::AsyncTestContext;
+use tokio::io::AsyncWriteExt;
 use tracing::info;
 
 static SETUP: Lazy<()> = Lazy::new(|| {
   std::process::Command::new("cargo")
     .arg("build")
     .arg("-q")
-    .current_dir(&get_cargo_project_root().unwrap().unwrap())
+    .current_dir(get_cargo_project_root().unwrap().unwrap())
     .stdout(Stdio::piped())
     .spawn()
     .unwrap()
@@ -46,11 +48,32 @@ impl CliTestContext {
     let port = find_free_port().await.unwrap();
     let server_addr = format!("http://127.0.0.1:{port}");
 
+    // The admin account is created before the server opens the database.
+    let mut create_admin = tokio::process::Command::new("target/debug/pf-api")
+      .args(["--settings", "api/settings/base.toml", "create-admin"])
+      .args(["--username", "admin"])
+      .env("PF__DB__PATH_DIR", &db_path)
+      .env("PF__FS__BASE_DIR", workspace.clone())
+      .current_dir(&root_dir)
+      .stdin(Stdio::piped())
+      .stdout(Stdio::null())
+      .spawn()
+      .expect("Failed to spawn the create-admin command");
+    create_admin
+      .stdin
+      .take()
+      .unwrap()
+      .write_all(b"password123\n")
+      .await
+      .unwrap();
+    assert!(create_admin.wait().await.unwrap().success());
+
     let child = tokio::process::Command::new("target/debug/pf-api")
       .args(["--settings", "api/settings/base.toml"])
       .env("PF__SERVER__PORT", port.to_string())
       .env("PF__DB__PATH_DIR", db_path)
       .env("PF__FS__BASE_DIR", workspace.clone())
+      .env("PF__ALLOW_REGISTRATION", "true")
       .current_dir(&root_dir)
       .stdout(Stdio::piped())
       .spawn()
@@ -72,6 +95,24 @@ impl CliTestContext {
     }
   }
 
+  /// Registers the account, the admin created on startup exists already.
+  pub async fn create_api_key(&self, username: &str, scopes: Vec<ApiKeyScope>) -> String {
+    let client = PasteFileClient::new(self.server_addr.clone());
+    let auth = (username.to_string(), "password123".to_string());
+    client
+      .create_account(auth.0.clone(), auth.1.clone())
+      .await
+      .unwrap();
+    let (_, resp) = client
+      .create_api_key(auth, "test".to_string(), scopes)
+      .await
+      .unwrap();
+    match resp {
+      ApiResponseResult::Ok(resp) => resp.key.unwrap(),
+      ApiResponseResult::Err(err) => panic!("Create api key failed: {err:?}"),
+    }
+  }
+
   pub async fn create_dummy_file(&self) -> anyhow::Result<(PathBuf, String)> {
     let content = Faker.fake::<String>();
     let file_name = self
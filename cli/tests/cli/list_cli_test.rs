@@ -0,0 +1,59 @@
+use assert_cmd::Command;
+use pf_sdk::dto::{request::ApiKeyScope, response::FileListResponse};
+
+use crate::helper::CliTestContext;
+
+#[test_context::test_context(CliTestContext)]
+#[tokio::test]
+async fn test_list_command(ctx: &mut CliTestContext) {
+  let key = ctx
+    .create_api_key("alice", vec![ApiKeyScope::Upload, ApiKeyScope::Download])
+    .await;
+  let (file, _) = ctx.create_dummy_file().await.unwrap();
+  let url_path = Command::cargo_bin("pf-cli")
+    .unwrap()
+    .args([
+      "--server-addr",
+      &ctx.server_addr,
+      "--api-key",
+      &key,
+      "upload",
+      "--source-file",
+      file.to_str().unwrap(),
+      "--output",
+      "url-path",
+    ])
+    .output()
+    .unwrap()
+    .stdout;
+  let url_path = std::str::from_utf8(&url_path).unwrap().trim().to_string();
+  let output = Command::cargo_bin("pf-cli")
+    .unwrap()
+    .args([
+      "--server-addr",
+      &ctx.server_addr,
+      "--api-key",
+      &key,
+      "list",
+      "--output",
+      "json",
+    ])
+    .output()
+    .unwrap()
+    .stdout;
+  let resp: FileListResponse = serde_json::from_slice(&output).unwrap();
+  assert_eq!(resp.total, 1);
+  assert_eq!(
+    format!("{}/{}", resp.files[0].code, resp.files[0].file_name),
+    url_path
+  );
+  let output = Command::cargo_bin("pf-cli")
+    .unwrap()
+    .args(["--server-addr", &ctx.server_addr, "--api-key", &key, "list"])
+    .output()
+    .unwrap()
+    .stdout;
+  let table = std::str::from_utf8(&output).unwrap();
+  assert!(table.starts_with("URL PATH"), "{table}");
+  assert!(table.contains(&url_path), "{table}");
+}
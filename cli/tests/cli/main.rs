@@ -1,10 +1,13 @@
 extern crate core;
 
+pub(crate) mod account_cli_test;
+pub(crate) mod audit_cli_test;
 pub(crate) mod copy_and_paste_cli_test;
 pub(crate) mod delete_cli_test;
 pub(crate) mod download_cli_test;
 pub(crate) mod encrypt_and_decrypt_cli_test;
 pub(crate) mod helper;
 pub(crate) mod info_cli_test;
+pub(crate) mod list_cli_test;
 pub(crate) mod ping_cli_test;
 pub(crate) mod upload_and_download_cli_test;
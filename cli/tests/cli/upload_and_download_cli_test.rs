@@ -219,3 +219,121 @@ async fn test_upload_encrypt_and_download_decrypt_command_to_the_destination_fil
     .unwrap();
   assert_eq!(actual_content, expected_content);
 }
+
+#[test_context::test_context(CliTestContext)]
+#[tokio::test]
+async fn test_resumable_upload_and_download_command(ctx: &mut CliTestContext) {
+  let (file, expected_content) = ctx.create_dummy_file().await.unwrap();
+  let url_path = Command::cargo_bin("pf-cli")
+    .unwrap()
+    .args([
+      "--server-addr",
+      &ctx.server_addr,
+      "upload",
+      "--source-file",
+      file.to_str().unwrap(),
+      "--resumable",
+      "--output",
+      "url-path",
+    ])
+    .output()
+    .unwrap()
+    .stdout;
+  let url_path = std::str::from_utf8(&url_path).unwrap().trim();
+  let destination_file = ctx.workspace.join("destination_file.txt");
+  Command::cargo_bin("pf-cli")
+    .unwrap()
+    .args([
+      "--server-addr",
+      &ctx.server_addr,
+      "download",
+      "--url-path",
+      url_path,
+      "--destination",
+      destination_file.to_str().unwrap(),
+    ])
+    .assert()
+    .success();
+  let actual_content = tokio::fs::read_to_string(&destination_file).await.unwrap();
+  assert_eq!(actual_content, expected_content);
+}
+
+#[test_context::test_context(CliTestContext)]
+#[tokio::test]
+async fn test_upload_directory_and_download_with_extract_command(ctx: &mut CliTestContext) {
+  let source_dir = ctx.workspace.join("source").join("project");
+  tokio::fs::create_dir_all(source_dir.join("nested"))
+    .await
+    .unwrap();
+  let (file, content) = ctx.create_dummy_file().await.unwrap();
+  tokio::fs::rename(&file, source_dir.join("nested").join("file.txt"))
+    .await
+    .unwrap();
+  let script = source_dir.join("run.sh");
+  tokio::fs::write(&script, "#!/bin/sh\n").await.unwrap();
+  #[cfg(unix)]
+  {
+    use std::os::unix::fs::PermissionsExt;
+    let permissions = std::fs::Permissions::from_mode(0o755);
+    tokio::fs::set_permissions(&script, permissions)
+      .await
+      .unwrap();
+  }
+  for (compress, key_nonce) in [(true, None), (false, Some(generate_random_key_nonce()))] {
+    let mut args = vec![
+      "--server-addr".to_string(),
+      ctx.server_addr.clone(),
+      "upload".to_string(),
+      "--source-file".to_string(),
+      source_dir.to_str().unwrap().to_string(),
+      "--output".to_string(),
+      "url-path".to_string(),
+    ];
+    if compress {
+      args.push("--compress".to_string());
+    }
+    if let Some(key_nonce) = &key_nonce {
+      args.extend(["--key-nonce".to_string(), key_nonce.clone()]);
+    }
+    let output = Command::cargo_bin("pf-cli")
+      .unwrap()
+      .args(&args)
+      .output()
+      .unwrap();
+    assert!(output.status.success());
+    let url_path = String::from_utf8(output.stdout).unwrap().trim().to_string();
+    let destination = ctx.workspace.join(format!("destination-{compress}"));
+    tokio::fs::create_dir_all(&destination).await.unwrap();
+    let mut args = vec![
+      "--server-addr".to_string(),
+      ctx.server_addr.clone(),
+      "download".to_string(),
+      "--url-path".to_string(),
+      url_path,
+      "--destination".to_string(),
+      destination.to_str().unwrap().to_string(),
+      "--extract".to_string(),
+    ];
+    if let Some(key_nonce) = &key_nonce {
+      args.extend(["--key-nonce".to_string(), key_nonce.clone()]);
+    }
+    Command::cargo_bin("pf-cli")
+      .unwrap()
+      .args(&args)
+      .assert()
+      .success();
+    let actual_content =
+      tokio::fs::read_to_string(destination.join("project").join("nested").join("file.txt"))
+        .await
+        .unwrap();
+    assert_eq!(actual_content, content);
+    #[cfg(unix)]
+    {
+      use std::os::unix::fs::PermissionsExt;
+      let metadata = tokio::fs::metadata(destination.join("project").join("run.sh"))
+        .await
+        .unwrap();
+      assert_eq!(metadata.permissions().mode() & 0o777, 0o755);
+    }
+  }
+}
@@ -21,3 +21,46 @@ async fn test_upload_command(ctx: &mut CliTestContext) {
     .assert()
     .success();
 }
+
+#[test_context::test_context(CliTestContext)]
+#[tokio::test]
+async fn test_upload_multiple_files_command(ctx: &mut CliTestContext) {
+  let (first_file, first_content) = ctx.create_dummy_file().await.unwrap();
+  let (second_file, second_content) = ctx.create_dummy_file().await.unwrap();
+  let output = Command::cargo_bin("pf-cli")
+    .unwrap()
+    .args([
+      "--server-addr",
+      &ctx.server_addr,
+      "upload",
+      "--source-file",
+      first_file.to_str().unwrap(),
+      "--source-file",
+      second_file.to_str().unwrap(),
+      "--output",
+      "url-path",
+    ])
+    .output()
+    .unwrap();
+  assert!(output.status.success());
+  let url_paths = String::from_utf8(output.stdout).unwrap();
+  let url_paths = url_paths.lines().collect::<Vec<_>>();
+  assert_eq!(url_paths.len(), 2);
+  for (url_path, content) in url_paths.into_iter().zip([first_content, second_content]) {
+    let destination = ctx.workspace.join("download.txt");
+    Command::cargo_bin("pf-cli")
+      .unwrap()
+      .args([
+        "--server-addr",
+        &ctx.server_addr,
+        "download",
+        "--url-path",
+        url_path,
+        "--destination",
+        destination.to_str().unwrap(),
+      ])
+      .assert()
+      .success();
+    assert_eq!(tokio::fs::read_to_string(&destination).await.unwrap(), content);
+  }
+}
@@ -1,34 +1,46 @@
 use std::{
+  collections::BTreeMap,
+  io::SeekFrom,
   ops::Deref,
   path::{Path, PathBuf},
 };
 
 use crate::{
   dto::{
-    request::UploadQueryParam,
-    response::{ApiResponseResult, BodyResponseError, MetaDataFileResponse, UploadResponse},
-    FileUrlPath,
+    request::{
+      AdminListFilesQueryParam, ApiKeyScope, AuditQueryParam, CreateAccountRequest,
+      CreateApiKeyRequest, ListFilesQueryParam, UpdateFileRequest, UploadQueryParam,
+    },
+    response::{
+      AccountResponse, ApiKeyResponse, ApiResponseResult, AuditLogResponse, BodyResponseError,
+      BundleResponse, FileListResponse, MetaDataFileResponse, QuotaResponse, UploadResponse,
+      UploadStatusResponse,
+    },
+    tus::{
+      encode_metadata, OFFSET_OCTET_STREAM, TUS_RESUMABLE, TUS_VERSION, UPLOAD_FILE_EXPIRES,
+      UPLOAD_FILE_URL, UPLOAD_LENGTH, UPLOAD_METADATA, UPLOAD_OFFSET,
+    },
+    FileUrlPath, API_KEY_HEADER,
+  },
+  util::{
+    crypto::{read_full, KeyNonce, ENCRYPT_BUFFER_LEN},
+    digest::{parse_sha256, verify_file_sha256},
   },
-  util::crypto::{KeyNonce, DECRYPT_BUFFER_LEN, ENCRYPT_BUFFER_LEN},
 };
 use anyhow::anyhow;
-use chacha20poly1305::{
-  aead::stream::{DecryptorBE32, EncryptorBE32},
-  KeyInit, XChaCha20Poly1305,
-};
+use chacha20poly1305::{aead::stream::EncryptorBE32, KeyInit, XChaCha20Poly1305};
 
-use futures_util::{StreamExt, TryStreamExt};
+use futures_util::StreamExt;
 use once_cell::sync::Lazy;
 use reqwest::StatusCode;
-use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
-use tokio_util::io::{ReaderStream, StreamReader};
+use tokio::io::{AsyncRead, AsyncSeekExt, AsyncWrite, AsyncWriteExt};
+use tokio_util::io::ReaderStream;
 
-pub static CLIENT: Lazy<reqwest::Client> = Lazy::new(|| {
-  reqwest::Client::builder()
-    .redirect(reqwest::redirect::Policy::custom(|attempt| attempt.stop()))
-    .build()
-    .unwrap()
-});
+pub static CLIENT: Lazy<reqwest::Client> = Lazy::new(|| client_builder().build().unwrap());
+
+fn client_builder() -> reqwest::ClientBuilder {
+  reqwest::Client::builder().redirect(reqwest::redirect::Policy::custom(|attempt| attempt.stop()))
+}
 
 pub struct PasteFileClient {
   pub inner: reqwest::Client,
@@ -43,11 +55,26 @@ impl PasteFileClient {
     }
   }
 
+  /// Sends the API key with every request of this client.
+  pub fn with_api_key(mut self, api_key: &str) -> anyhow::Result<Self> {
+    let mut value = reqwest::header::HeaderValue::from_str(api_key)?;
+    value.set_sensitive(true);
+    let mut headers = reqwest::header::HeaderMap::new();
+    headers.insert(API_KEY_HEADER, value);
+    self.inner = client_builder().default_headers(headers).build()?;
+    Ok(self)
+  }
+
   pub async fn health_check(&self) -> anyhow::Result<(StatusCode, ApiResponseResult)> {
     let resp = self.get(format!("{}/healthz", self.addr)).send().await?;
     Ok((resp.status(), resp.json().await?))
   }
 
+  pub async fn metrics(&self) -> anyhow::Result<(StatusCode, String)> {
+    let resp = self.get(format!("{}/metrics", self.addr)).send().await?;
+    Ok((resp.status(), resp.text().await?))
+  }
+
   pub async fn index_page(&self) -> anyhow::Result<(StatusCode, String)> {
     let resp = self.get(&self.addr).send().await?;
     Ok((resp.status(), resp.text().await?))
@@ -59,7 +86,20 @@ impl PasteFileClient {
     param: &UploadQueryParam,
     auth: Option<(String, String)>,
   ) -> anyhow::Result<(StatusCode, ApiResponseResult<UploadResponse>)> {
-    let form = reqwest::multipart::Form::new().part("file", file_part);
+    self.upload_file_parts(vec![file_part], param, auth).await
+  }
+
+  pub async fn upload_file_parts(
+    &self,
+    file_parts: Vec<reqwest::multipart::Part>,
+    param: &UploadQueryParam,
+    auth: Option<(String, String)>,
+  ) -> anyhow::Result<(StatusCode, ApiResponseResult<UploadResponse>)> {
+    let form = file_parts
+      .into_iter()
+      .fold(reqwest::multipart::Form::new(), |form, part| {
+        form.part("file", part)
+      });
     let mut builder = self
       .post(format!("{}/upload", self.addr))
       .multipart(form)
@@ -87,41 +127,17 @@ impl PasteFileClient {
 
   pub async fn upload_encrypt<R>(
     &self,
-    KeyNonce { key, nonce }: &KeyNonce,
+    key_nonce: &KeyNonce,
     file_name: String,
     content_type: &str,
-    mut reader: R,
+    reader: R,
     param: &UploadQueryParam,
     auth: Option<(String, String)>,
   ) -> anyhow::Result<(StatusCode, ApiResponseResult<UploadResponse>)>
   where
     R: AsyncRead + Send + Sync + Unpin + 'static,
   {
-    let mut buffer = [0u8; ENCRYPT_BUFFER_LEN];
-    let mut stream_encryptor =
-      EncryptorBE32::from_aead(XChaCha20Poly1305::new(key), (*nonce).as_ref().into());
-    let async_stream = async_stream::stream! {
-      loop {
-        let read_count = reader.read(&mut buffer).await?;
-        if read_count == ENCRYPT_BUFFER_LEN {
-          let ciphertext = stream_encryptor
-            .encrypt_next(buffer.as_slice())
-            .map_err(|err| anyhow!("Encrypting file failed, Error: {err}"));
-          yield ciphertext;
-        } else if read_count == 0 {
-          break;
-        } else {
-          let ciphertext = stream_encryptor
-            .encrypt_last(&buffer[..read_count])
-            .map_err(|err| anyhow!("Encrypting file failed, Error: {err}"));
-          yield ciphertext;
-          break;
-        }
-      }
-    };
-    let file_part = reqwest::multipart::Part::stream(reqwest::Body::wrap_stream(async_stream))
-      .file_name(file_name.clone())
-      .mime_str(content_type)?;
+    let file_part = encrypt_file_part(key_nonce, file_name, content_type, reader)?;
     self.upload_file_part(file_part, param, auth).await
   }
 
@@ -157,6 +173,148 @@ impl PasteFileClient {
       .await
   }
 
+  pub async fn upload_files(
+    &self,
+    sources: &[PathBuf],
+    param: &UploadQueryParam,
+    auth: Option<(String, String)>,
+  ) -> anyhow::Result<(StatusCode, ApiResponseResult<UploadResponse>)> {
+    let mut file_parts = Vec::with_capacity(sources.len());
+    for source in sources {
+      file_parts.push(file_part(source).await?);
+    }
+    self.upload_file_parts(file_parts, param, auth).await
+  }
+
+  pub async fn create_upload(
+    &self,
+    file_name: String,
+    length: u64,
+    param: &UploadQueryParam,
+    auth: Option<(String, String)>,
+  ) -> anyhow::Result<(StatusCode, ApiResponseResult<String>)> {
+    let metadata = BTreeMap::from([("filename".to_string(), file_name)]);
+    let mut builder = self
+      .post(format!("{}/tus", self.addr))
+      .header(TUS_RESUMABLE, TUS_VERSION)
+      .header(UPLOAD_LENGTH, length)
+      .header(UPLOAD_METADATA, encode_metadata(&metadata))
+      .query(param);
+    if let Some((user, pass)) = auth {
+      builder = builder.basic_auth(user, Some(pass));
+    }
+    let resp = builder.send().await?;
+    let status = resp.status();
+    if !status.is_success() {
+      return Ok((status, ApiResponseResult::Err(resp.json().await?)));
+    }
+    let location = get_header(&resp, reqwest::header::LOCATION.as_str())?;
+    let upload_path = url::Url::parse(&location)?.path().to_string();
+    Ok((
+      status,
+      ApiResponseResult::Ok(format!("{}{upload_path}", self.addr)),
+    ))
+  }
+
+  pub async fn upload_status(
+    &self,
+    upload_url: &str,
+    auth: Option<(String, String)>,
+  ) -> anyhow::Result<(StatusCode, ApiResponseResult<UploadStatusResponse>)> {
+    let mut builder = self.head(upload_url).header(TUS_RESUMABLE, TUS_VERSION);
+    if let Some((user, pass)) = auth {
+      builder = builder.basic_auth(user, Some(pass));
+    }
+    let resp = builder.send().await?;
+    let status = resp.status();
+    if !status.is_success() {
+      // Responses to HEAD requests carry no body.
+      let error = BodyResponseError::new(status.as_str(), status.to_string());
+      return Ok((status, ApiResponseResult::Err(error)));
+    }
+    Ok((status, ApiResponseResult::Ok(upload_status(&resp)?)))
+  }
+
+  pub async fn upload_chunk_from_reader<R>(
+    &self,
+    upload_url: &str,
+    offset: u64,
+    reader: R,
+    auth: Option<(String, String)>,
+  ) -> anyhow::Result<(StatusCode, ApiResponseResult<UploadStatusResponse>)>
+  where
+    R: AsyncRead + Send + Unpin + 'static + Sync,
+  {
+    let mut builder = self
+      .patch(upload_url)
+      .header(TUS_RESUMABLE, TUS_VERSION)
+      .header(UPLOAD_OFFSET, offset)
+      .header(reqwest::header::CONTENT_TYPE, OFFSET_OCTET_STREAM)
+      .body(reqwest::Body::wrap_stream(ReaderStream::new(reader)));
+    if let Some((user, pass)) = auth {
+      builder = builder.basic_auth(user, Some(pass));
+    }
+    let resp = builder.send().await?;
+    let status = resp.status();
+    if !status.is_success() {
+      return Ok((status, ApiResponseResult::Err(resp.json().await?)));
+    }
+    Ok((status, ApiResponseResult::Ok(upload_status(&resp)?)))
+  }
+
+  pub async fn resume_upload_file(
+    &self,
+    source: &Path,
+    upload_url: &str,
+    auth: Option<(String, String)>,
+  ) -> anyhow::Result<(StatusCode, ApiResponseResult<UploadStatusResponse>)> {
+    let (status, resp) = self.upload_status(upload_url, auth.clone()).await?;
+    let upload_status = match resp {
+      ApiResponseResult::Ok(upload_status) => upload_status,
+      ApiResponseResult::Err(err) => return Ok((status, ApiResponseResult::Err(err))),
+    };
+    if upload_status.upload.is_some() {
+      return Ok((status, ApiResponseResult::Ok(upload_status)));
+    }
+    let mut file = tokio::fs::File::open(source).await?;
+    file.seek(SeekFrom::Start(upload_status.offset)).await?;
+    self
+      .upload_chunk_from_reader(upload_url, upload_status.offset, file, auth)
+      .await
+  }
+
+  pub async fn upload_file_resumable(
+    &self,
+    source: &Path,
+    param: &UploadQueryParam,
+    auth: Option<(String, String)>,
+  ) -> anyhow::Result<(StatusCode, ApiResponseResult<UploadStatusResponse>)> {
+    let file_name = crate::util::file::get_file_name(source)?;
+    let length = tokio::fs::metadata(source).await?.len();
+    let (status, resp) = self
+      .create_upload(file_name, length, param, auth.clone())
+      .await?;
+    match resp {
+      ApiResponseResult::Ok(upload_url) => self.resume_upload_file(source, &upload_url, auth).await,
+      ApiResponseResult::Err(err) => Ok((status, ApiResponseResult::Err(err))),
+    }
+  }
+
+  pub async fn terminate_upload(
+    &self,
+    upload_url: &str,
+    auth: Option<(String, String)>,
+  ) -> anyhow::Result<StatusCode> {
+    let mut builder = self
+      .inner
+      .delete(upload_url)
+      .header(TUS_RESUMABLE, TUS_VERSION);
+    if let Some((user, pass)) = auth {
+      builder = builder.basic_auth(user, Some(pass));
+    }
+    Ok(builder.send().await?.status())
+  }
+
   pub async fn download_to_writer<W>(
     &self,
     url_path: &FileUrlPath,
@@ -172,12 +330,16 @@ impl PasteFileClient {
       let error = resp.json::<BodyResponseError>().await?;
       return Ok((status, ApiResponseResult::Err(error)));
     }
-    let mut stream = resp.bytes_stream();
-    while let Some(chunk) = stream.next().await {
-      let chunk = chunk?;
-      writer.write_all(&chunk).await?;
+    let spool_file = spool_verified(resp).await?;
+    let result = async {
+      let mut file = tokio::fs::File::open(&spool_file).await?;
+      tokio::io::copy(&mut file, &mut writer).await?;
+      writer.flush().await?;
+      anyhow::Ok(())
     }
-    writer.flush().await?;
+    .await;
+    tokio::fs::remove_file(&spool_file).await?;
+    result?;
     Ok((status, ApiResponseResult::Ok(())))
   }
 
@@ -208,12 +370,27 @@ impl PasteFileClient {
     Ok(resp)
   }
 
+  /// Downloads every file of `code` as one archive, `extension` is `zip` or `tar.gz`.
+  pub async fn download_archive(
+    &self,
+    code: &str,
+    extension: &str,
+    auth: Option<(String, String)>,
+  ) -> anyhow::Result<reqwest::Response> {
+    let mut builder = self.get(format!("{}/{code}.{extension}", self.addr));
+    if let Some((user, pass)) = auth {
+      builder = builder.basic_auth(user, Some(pass));
+    }
+    let resp = builder.send().await?;
+    Ok(resp)
+  }
+
   pub async fn download_and_decrypt<W>(
     &self,
-    KeyNonce { key, nonce }: &KeyNonce,
+    key_nonce: &KeyNonce,
     url_path: &FileUrlPath,
     auth: Option<(String, String)>,
-    mut writer: W,
+    writer: W,
   ) -> anyhow::Result<(StatusCode, ApiResponseResult<()>)>
   where
     W: AsyncWrite + Unpin,
@@ -224,31 +401,14 @@ impl PasteFileClient {
       let error = resp.json::<BodyResponseError>().await?;
       return Ok((status, ApiResponseResult::Err(error)));
     }
-    let stream = resp
-      .bytes_stream()
-      .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e));
-    let mut reader = StreamReader::new(stream);
-    let mut buffer = [0u8; DECRYPT_BUFFER_LEN];
-    let mut stream_decryptor =
-      DecryptorBE32::from_aead(XChaCha20Poly1305::new(key), nonce.as_ref().into());
-    loop {
-      let read_count = reader.read(&mut buffer).await?;
-      if read_count == DECRYPT_BUFFER_LEN {
-        let plaintext = stream_decryptor
-          .decrypt_next(buffer.as_slice())
-          .map_err(|err| anyhow!("Decrypting file failed, Error: {err}"))?;
-        writer.write_all(&plaintext).await?;
-      } else if read_count == 0 {
-        break;
-      } else {
-        let plaintext = stream_decryptor
-          .decrypt_last(&buffer[..read_count])
-          .map_err(|err| anyhow!("Decrypting file failed, Error: {err}"))?;
-        writer.write_all(&plaintext).await?;
-        break;
-      }
+    let spool_file = spool_verified(resp).await?;
+    let result = async {
+      let file = tokio::fs::File::open(&spool_file).await?;
+      crate::util::crypto::decrypt(key_nonce, file, writer).await
     }
-    writer.flush().await?;
+    .await;
+    tokio::fs::remove_file(&spool_file).await?;
+    result?;
     Ok((status, ApiResponseResult::Ok(())))
   }
 
@@ -270,12 +430,149 @@ impl PasteFileClient {
     if let Some(parent) = destination.parent() {
       tokio::fs::create_dir_all(parent).await?;
     }
+    let sha256 = parse_sha256(resp.headers());
     let mut file = tokio::fs::File::create(&destination).await?;
     let mut stream = resp.bytes_stream();
     while let Some(chunk) = stream.next().await {
       let chunk = chunk?;
       file.write_all(&chunk).await?;
     }
+    file.flush().await?;
+    verify_file_sha256(&destination, sha256.as_deref()).await?;
+    Ok((status, ApiResponseResult::Ok(destination)))
+  }
+
+  /// Requests the file from `offset`, the server sends all of it when it no longer has
+  /// the `if_range` ETag.
+  pub async fn download_from(
+    &self,
+    url_path: &FileUrlPath,
+    auth: Option<(String, String)>,
+    offset: u64,
+    if_range: Option<&str>,
+  ) -> anyhow::Result<reqwest::Response> {
+    let mut builder = self.get(url_path.to_url(&self.addr)?);
+    if offset > 0 {
+      builder = builder.header(reqwest::header::RANGE, format!("bytes={offset}-"));
+      if let Some(etag) = if_range {
+        builder = builder.header(reqwest::header::IF_RANGE, etag);
+      }
+    }
+    if let Some((user, pass)) = auth {
+      builder = builder.basic_auth(user, Some(pass));
+    }
+    Ok(builder.send().await?)
+  }
+
+  /// Requests the part of the file missing from `destination`, returns `None`
+  /// when the local file is already complete.
+  pub async fn resume_download(
+    &self,
+    url_path: &FileUrlPath,
+    auth: Option<(String, String)>,
+    destination: &Path,
+  ) -> anyhow::Result<(StatusCode, ApiResponseResult<Option<ResumedDownload>>)> {
+    let offset = match tokio::fs::metadata(destination).await {
+      Ok(metadata) => metadata.len(),
+      Err(err) if err.kind() == std::io::ErrorKind::NotFound => 0,
+      Err(err) => return Err(err.into()),
+    };
+    let etag = match offset {
+      0 => None,
+      _ => read_etag(destination).await?,
+    };
+    let resp = self
+      .download_from(url_path, auth.clone(), offset, etag.as_deref())
+      .await?;
+    let status = resp.status();
+    match status {
+      StatusCode::PARTIAL_CONTENT => {
+        let (start, total_size) = parse_content_range(&resp)?;
+        if start != offset {
+          return Err(anyhow!(
+            "The server resumed at byte {start} instead of byte {offset}."
+          ));
+        }
+        Ok((
+          status,
+          ApiResponseResult::Ok(Some(ResumedDownload {
+            etag: response_etag(&resp),
+            resp,
+            offset,
+            total_size,
+          })),
+        ))
+      }
+      StatusCode::RANGE_NOT_SATISFIABLE => {
+        let mut builder = self.head(url_path.to_url(&self.addr)?);
+        if let Some((user, pass)) = auth.clone() {
+          builder = builder.basic_auth(user, Some(pass));
+        }
+        let head = builder.send().await?;
+        if head.status().is_success()
+          && head.content_length() == Some(offset)
+          && etag.is_none_or(|etag| response_etag(&head).as_ref() == Some(&etag))
+        {
+          remove_etag(destination).await?;
+          return Ok((head.status(), ApiResponseResult::Ok(None)));
+        }
+        let resp = self.download_from(url_path, auth, 0, None).await?;
+        self.full_download(resp).await
+      }
+      _ => self.full_download(resp).await,
+    }
+  }
+
+  async fn full_download(
+    &self,
+    resp: reqwest::Response,
+  ) -> anyhow::Result<(StatusCode, ApiResponseResult<Option<ResumedDownload>>)> {
+    let status = resp.status();
+    if !status.is_success() {
+      let error = resp.json::<BodyResponseError>().await?;
+      return Ok((status, ApiResponseResult::Err(error)));
+    }
+    let total_size = resp
+      .content_length()
+      .ok_or_else(|| anyhow!("content length not found"))?;
+    Ok((
+      status,
+      ApiResponseResult::Ok(Some(ResumedDownload {
+        etag: response_etag(&resp),
+        resp,
+        offset: 0,
+        total_size,
+      })),
+    ))
+  }
+
+  pub async fn resume_download_file(
+    &self,
+    url_path: &FileUrlPath,
+    auth: Option<(String, String)>,
+    mut destination: PathBuf,
+  ) -> anyhow::Result<(StatusCode, ApiResponseResult<PathBuf>)> {
+    if destination.is_dir() {
+      destination.push(&url_path.file_name);
+    }
+    let (status, resp) = self.resume_download(url_path, auth, &destination).await?;
+    let download = match resp {
+      ApiResponseResult::Ok(Some(download)) => download,
+      ApiResponseResult::Ok(None) => return Ok((status, ApiResponseResult::Ok(destination))),
+      ApiResponseResult::Err(err) => return Ok((status, ApiResponseResult::Err(err))),
+    };
+    let total_size = download.total_size;
+    let sha256 = parse_sha256(download.resp.headers());
+    let mut file = download.open_destination(&destination).await?;
+    let mut stream = download.resp.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+      let chunk = chunk?;
+      file.write_all(&chunk).await?;
+    }
+    file.flush().await?;
+    verify_file_size(&destination, total_size).await?;
+    verify_file_sha256(&destination, sha256.as_deref()).await?;
+    remove_etag(&destination).await?;
     Ok((status, ApiResponseResult::Ok(destination)))
   }
 
@@ -292,6 +589,19 @@ impl PasteFileClient {
     Ok((resp.status(), resp.json().await?))
   }
 
+  pub async fn list_bundle(
+    &self,
+    code: &str,
+    auth: Option<(String, String)>,
+  ) -> anyhow::Result<(StatusCode, ApiResponseResult<BundleResponse>)> {
+    let mut builder = self.get(format!("{}/{code}", self.addr));
+    if let Some((user, pass)) = auth {
+      builder = builder.basic_auth(user, Some(pass));
+    }
+    let resp = builder.send().await?;
+    Ok((resp.status(), resp.json().await?))
+  }
+
   pub async fn delete(
     &self,
     url_path: &FileUrlPath,
@@ -304,6 +614,361 @@ impl PasteFileClient {
     let resp = builder.send().await?;
     Ok((resp.status(), resp.json().await?))
   }
+
+  pub async fn list_files(
+    &self,
+    param: &ListFilesQueryParam,
+  ) -> anyhow::Result<(StatusCode, ApiResponseResult<FileListResponse>)> {
+    let resp = self
+      .get(format!("{}/files", self.addr))
+      .query(param)
+      .send()
+      .await?;
+    Ok((resp.status(), resp.json().await?))
+  }
+
+  pub async fn admin_list_files(
+    &self,
+    admin_token: Option<&str>,
+    param: &AdminListFilesQueryParam,
+  ) -> anyhow::Result<(StatusCode, ApiResponseResult<FileListResponse>)> {
+    let mut builder = self.get(format!("{}/admin/files", self.addr)).query(param);
+    if let Some(token) = admin_token {
+      builder = builder.bearer_auth(token);
+    }
+    let resp = builder.send().await?;
+    Ok((resp.status(), resp.json().await?))
+  }
+
+  pub async fn admin_audit_log(
+    &self,
+    admin_token: Option<&str>,
+    param: &AuditQueryParam,
+  ) -> anyhow::Result<(StatusCode, ApiResponseResult<AuditLogResponse>)> {
+    let mut builder = self.get(format!("{}/admin/audit", self.addr)).query(param);
+    if let Some(token) = admin_token {
+      builder = builder.bearer_auth(token);
+    }
+    let resp = builder.send().await?;
+    Ok((resp.status(), resp.json().await?))
+  }
+
+  pub async fn admin_reload_config(
+    &self,
+    admin_token: Option<&str>,
+  ) -> anyhow::Result<(StatusCode, ApiResponseResult)> {
+    let mut builder = self.post(format!("{}/admin/reload", self.addr));
+    if let Some(token) = admin_token {
+      builder = builder.bearer_auth(token);
+    }
+    let resp = builder.send().await?;
+    Ok((resp.status(), resp.json().await?))
+  }
+
+  pub async fn admin_delete_file(
+    &self,
+    admin_token: Option<&str>,
+    url_path: &FileUrlPath,
+  ) -> anyhow::Result<(StatusCode, ApiResponseResult)> {
+    let mut builder = self
+      .inner
+      .delete(format!("{}/admin/files/{url_path}", self.addr));
+    if let Some(token) = admin_token {
+      builder = builder.bearer_auth(token);
+    }
+    let resp = builder.send().await?;
+    Ok((resp.status(), resp.json().await?))
+  }
+
+  pub async fn admin_update_file(
+    &self,
+    admin_token: Option<&str>,
+    url_path: &FileUrlPath,
+    req: &UpdateFileRequest,
+  ) -> anyhow::Result<(StatusCode, ApiResponseResult<MetaDataFileResponse>)> {
+    let mut builder = self
+      .patch(format!("{}/admin/files/{url_path}", self.addr))
+      .json(req);
+    if let Some(token) = admin_token {
+      builder = builder.bearer_auth(token);
+    }
+    let resp = builder.send().await?;
+    Ok((resp.status(), resp.json().await?))
+  }
+
+  pub async fn create_account(
+    &self,
+    username: String,
+    password: String,
+  ) -> anyhow::Result<(StatusCode, ApiResponseResult<AccountResponse>)> {
+    let resp = self
+      .post(format!("{}/accounts", self.addr))
+      .json(&CreateAccountRequest { username, password })
+      .send()
+      .await?;
+    Ok((resp.status(), resp.json().await?))
+  }
+
+  pub async fn create_api_key(
+    &self,
+    (user, pass): (String, String),
+    name: String,
+    scopes: Vec<ApiKeyScope>,
+  ) -> anyhow::Result<(StatusCode, ApiResponseResult<ApiKeyResponse>)> {
+    let resp = self
+      .post(format!("{}/accounts/keys", self.addr))
+      .basic_auth(user, Some(pass))
+      .json(&CreateApiKeyRequest { name, scopes })
+      .send()
+      .await?;
+    Ok((resp.status(), resp.json().await?))
+  }
+
+  pub async fn quota(&self) -> anyhow::Result<(StatusCode, ApiResponseResult<QuotaResponse>)> {
+    let resp = self.get(format!("{}/quota", self.addr)).send().await?;
+    Ok((resp.status(), resp.json().await?))
+  }
+
+  pub async fn list_api_keys(
+    &self,
+    (user, pass): (String, String),
+  ) -> anyhow::Result<(StatusCode, ApiResponseResult<Vec<ApiKeyResponse>>)> {
+    let resp = self
+      .get(format!("{}/accounts/keys", self.addr))
+      .basic_auth(user, Some(pass))
+      .send()
+      .await?;
+    Ok((resp.status(), resp.json().await?))
+  }
+
+  pub async fn revoke_api_key(
+    &self,
+    (user, pass): (String, String),
+    id: &str,
+  ) -> anyhow::Result<(StatusCode, ApiResponseResult)> {
+    let resp = self
+      .inner
+      .delete(format!("{}/accounts/keys/{id}", self.addr))
+      .basic_auth(user, Some(pass))
+      .send()
+      .await?;
+    Ok((resp.status(), resp.json().await?))
+  }
+}
+
+/// Keeps the body of `resp` in a temporary file and checks it against the `Repr-Digest` of the
+/// server, so no byte of a corrupted download reaches the writer. The caller removes the file.
+/// The writer has no directory of its own, the file is created in the temporary directory
+/// as a new file only the current user can read.
+async fn spool_verified(resp: reqwest::Response) -> anyhow::Result<PathBuf> {
+  let sha256 = parse_sha256(resp.headers());
+  let spool_file = std::env::temp_dir().join(
+    crate::util::random::generate_random_string_with_prefix("pf-download"),
+  );
+  let mut options = tokio::fs::OpenOptions::new();
+  options.write(true).create_new(true);
+  #[cfg(unix)]
+  options.mode(0o600);
+  // A file that exists already is never reused, it could be read by someone else.
+  let mut file = options.open(&spool_file).await?;
+  let result = async {
+    let mut stream = resp.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+      file.write_all(&chunk?).await?;
+    }
+    file.flush().await?;
+    verify_file_sha256(&spool_file, sha256.as_deref()).await
+  }
+  .await;
+  if result.is_err() {
+    let _ = tokio::fs::remove_file(&spool_file).await;
+  }
+  result.map(|()| spool_file)
+}
+
+pub async fn file_part(source: &Path) -> anyhow::Result<reqwest::multipart::Part> {
+  let file_name = crate::util::file::get_file_name(source)?;
+  let content_type = crate::util::file::get_content_type(source)?;
+  let file = tokio::fs::File::open(source).await?;
+  Ok(
+    reqwest::multipart::Part::stream(reqwest::Body::wrap_stream(ReaderStream::new(file)))
+      .file_name(file_name)
+      .mime_str(&content_type)?,
+  )
+}
+
+pub fn dir_part(dir: &Path, compress: bool) -> anyhow::Result<reqwest::multipart::Part> {
+  let reader = crate::util::tar::archive_dir(dir, compress)?;
+  Ok(
+    reqwest::multipart::Part::stream(reqwest::Body::wrap_stream(ReaderStream::new(reader)))
+      .file_name(crate::util::tar::archive_name(dir, compress)?)
+      .mime_str(crate::util::tar::content_type(compress))?,
+  )
+}
+
+pub fn encrypt_file_part<R>(
+  key_nonce: &KeyNonce,
+  file_name: String,
+  content_type: &str,
+  reader: R,
+) -> anyhow::Result<reqwest::multipart::Part>
+where
+  R: AsyncRead + Send + Sync + Unpin + 'static,
+{
+  Ok(
+    reqwest::multipart::Part::stream(reqwest::Body::wrap_stream(encrypt_stream(
+      key_nonce, reader,
+    )))
+    .file_name(file_name)
+    .mime_str(content_type)?,
+  )
+}
+
+/// The ciphertext of `reader` as a stream of chunks.
+pub fn encrypt_stream<R>(
+  KeyNonce { key, nonce }: &KeyNonce,
+  mut reader: R,
+) -> impl futures_util::Stream<Item = anyhow::Result<Vec<u8>>> + Send + Sync + 'static
+where
+  R: AsyncRead + Send + Sync + Unpin + 'static,
+{
+  let mut buffer = [0u8; ENCRYPT_BUFFER_LEN];
+  let mut stream_encryptor =
+    EncryptorBE32::from_aead(XChaCha20Poly1305::new(key), (*nonce).as_ref().into());
+  async_stream::stream! {
+    loop {
+      let read_count = read_full(&mut reader, &mut buffer).await?;
+      if read_count == ENCRYPT_BUFFER_LEN {
+        let ciphertext = stream_encryptor
+          .encrypt_next(buffer.as_slice())
+          .map_err(|err| anyhow!("Encrypting file failed, Error: {err}"));
+        yield ciphertext;
+      } else if read_count == 0 {
+        break;
+      } else {
+        let ciphertext = stream_encryptor
+          .encrypt_last(&buffer[..read_count])
+          .map_err(|err| anyhow!("Encrypting file failed, Error: {err}"));
+        yield ciphertext;
+        break;
+      }
+    }
+  }
+}
+
+pub struct ResumedDownload {
+  pub resp: reqwest::Response,
+  pub offset: u64,
+  pub total_size: u64,
+  /// The ETag of the content, a later resume only continues the same content.
+  pub etag: Option<String>,
+}
+
+impl ResumedDownload {
+  /// Opens `destination` positioned at the offset the response starts from and keeps the
+  /// ETag of the content next to it until `remove_etag` is called.
+  pub async fn open_destination(&self, destination: &Path) -> anyhow::Result<tokio::fs::File> {
+    if let Some(parent) = destination.parent() {
+      tokio::fs::create_dir_all(parent).await?;
+    }
+    match &self.etag {
+      Some(etag) => tokio::fs::write(etag_path(destination), etag).await?,
+      None => remove_etag(destination).await?,
+    }
+    let mut file = tokio::fs::OpenOptions::new()
+      .create(true)
+      .truncate(false)
+      .write(true)
+      .open(destination)
+      .await?;
+    file.set_len(self.offset).await?;
+    file.seek(SeekFrom::Start(self.offset)).await?;
+    Ok(file)
+  }
+}
+
+/// Where the ETag of a partly downloaded file is kept, a hidden file next to it.
+pub fn etag_path(destination: &Path) -> PathBuf {
+  let name = destination
+    .file_name()
+    .map(|name| name.to_string_lossy())
+    .unwrap_or_default();
+  destination.with_file_name(format!(".{name}.etag"))
+}
+
+async fn read_etag(destination: &Path) -> anyhow::Result<Option<String>> {
+  match tokio::fs::read_to_string(etag_path(destination)).await {
+    Ok(etag) => Ok(Some(etag)),
+    Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+    Err(err) => Err(err.into()),
+  }
+}
+
+/// Forgets the ETag of `destination` once it is downloaded in full.
+pub async fn remove_etag(destination: &Path) -> anyhow::Result<()> {
+  match tokio::fs::remove_file(etag_path(destination)).await {
+    Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err.into()),
+    _ => Ok(()),
+  }
+}
+
+fn response_etag(resp: &reqwest::Response) -> Option<String> {
+  resp
+    .headers()
+    .get(reqwest::header::ETAG)
+    .and_then(|etag| etag.to_str().ok())
+    .map(str::to_string)
+}
+
+pub async fn verify_file_size(path: &Path, expected_size: u64) -> anyhow::Result<()> {
+  let size = tokio::fs::metadata(path).await?.len();
+  if size != expected_size {
+    return Err(anyhow!(
+      "The downloaded file has {size} bytes but the server sent {expected_size} bytes."
+    ));
+  }
+  Ok(())
+}
+
+fn parse_content_range(resp: &reqwest::Response) -> anyhow::Result<(u64, u64)> {
+  let value = get_header(resp, reqwest::header::CONTENT_RANGE.as_str())?;
+  let (range, total_size) = value
+    .strip_prefix("bytes ")
+    .and_then(|v| v.split_once('/'))
+    .ok_or_else(|| anyhow!("Invalid Content-Range header: {value}"))?;
+  let (start, _) = range
+    .split_once('-')
+    .ok_or_else(|| anyhow!("Invalid Content-Range header: {value}"))?;
+  Ok((start.parse()?, total_size.parse()?))
+}
+
+fn get_header(resp: &reqwest::Response, name: &str) -> anyhow::Result<String> {
+  Ok(
+    resp
+      .headers()
+      .get(name)
+      .ok_or_else(|| anyhow!("The {name} header is missing."))?
+      .to_str()?
+      .to_string(),
+  )
+}
+
+fn upload_status(resp: &reqwest::Response) -> anyhow::Result<UploadStatusResponse> {
+  let upload = if resp.headers().contains_key(UPLOAD_FILE_URL) {
+    Some(UploadResponse {
+      url: get_header(resp, UPLOAD_FILE_URL)?,
+      urls: vec![get_header(resp, UPLOAD_FILE_URL)?],
+      expire_date_time: get_header(resp, UPLOAD_FILE_EXPIRES)?.parse()?,
+      qr_code: None,
+    })
+  } else {
+    None
+  };
+  Ok(UploadStatusResponse {
+    offset: get_header(resp, UPLOAD_OFFSET)?.parse()?,
+    length: get_header(resp, UPLOAD_LENGTH)?.parse()?,
+    upload,
+  })
 }
 
 impl Deref for PasteFileClient {
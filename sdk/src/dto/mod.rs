@@ -2,8 +2,11 @@ use crate::util::url::create_url;
 use serde::{Deserialize, Serialize};
 use std::str::FromStr;
 
+pub const API_KEY_HEADER: &str = "x-api-key";
+
 pub mod request;
 pub mod response;
+pub mod tus;
 
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord, fake::Dummy)]
 pub struct FileUrlPath {
@@ -1,3 +1,4 @@
+use chrono::{DateTime, Utc};
 use fake::Dummy;
 use garde::Validate;
 use serde::{Deserialize, Serialize};
@@ -14,6 +15,57 @@ pub struct UploadQueryParam {
   pub allow_manual_deletion: Option<bool>,
   #[garde(skip)]
   pub qr_code_format: Option<QrCodeFormat>,
+  /// Hex SHA-256 the stored file must match.
+  #[garde(pattern("^[0-9a-fA-F]{64}$"))]
+  pub sha256: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate, Default, Dummy)]
+pub struct ListFilesQueryParam {
+  #[garde(range(min = 1))]
+  pub page: Option<usize>,
+  #[garde(range(min = 1, max = 100))]
+  pub page_size: Option<usize>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate, Default, Dummy)]
+pub struct AdminListFilesQueryParam {
+  #[garde(range(min = 1))]
+  pub page: Option<usize>,
+  #[garde(range(min = 1, max = 100))]
+  pub page_size: Option<usize>,
+  #[garde(skip)]
+  pub owner: Option<String>,
+  #[garde(skip)]
+  pub expires_before: Option<DateTime<Utc>>,
+  #[garde(skip)]
+  pub expires_after: Option<DateTime<Utc>>,
+  #[garde(skip)]
+  pub min_size: Option<u64>,
+  #[garde(skip)]
+  pub max_size: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate, Default, Dummy)]
+pub struct AuditQueryParam {
+  #[garde(skip)]
+  pub code: Option<String>,
+  #[garde(skip)]
+  pub from: Option<DateTime<Utc>>,
+  #[garde(skip)]
+  pub to: Option<DateTime<Utc>>,
+  #[garde(range(min = 1, max = 1000))]
+  pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate, Default, Dummy)]
+pub struct UpdateFileRequest {
+  /// Moves the expiration to this many seconds from now.
+  #[garde(range(min = 1, max = 100_000_000))]
+  pub expire_secs: Option<u64>,
+  #[serde(default)]
+  #[garde(skip)]
+  pub reset_downloads: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize, Validate, Dummy)]
@@ -23,3 +75,45 @@ pub enum QrCodeFormat {
   #[serde(rename = "image")]
   Image,
 }
+
+#[derive(Debug, Serialize, Deserialize, Validate, Dummy)]
+pub struct CreateAccountRequest {
+  #[garde(alphanumeric, length(min = 3, max = 32))]
+  pub username: String,
+  #[garde(length(min = 8, max = 128))]
+  pub password: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate, Dummy)]
+pub struct CreateApiKeyRequest {
+  #[garde(length(min = 1, max = 64))]
+  pub name: String,
+  #[garde(length(min = 1))]
+  pub scopes: Vec<ApiKeyScope>,
+}
+
+#[derive(
+  Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Dummy,
+)]
+pub enum ApiKeyScope {
+  #[serde(rename = "upload")]
+  Upload,
+  #[serde(rename = "download")]
+  Download,
+  #[serde(rename = "delete")]
+  Delete,
+  #[serde(rename = "admin")]
+  Admin,
+}
+
+impl std::fmt::Display for ApiKeyScope {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let scope = match self {
+      Self::Upload => "upload",
+      Self::Download => "download",
+      Self::Delete => "delete",
+      Self::Admin => "admin",
+    };
+    f.write_str(scope)
+  }
+}
@@ -1,6 +1,10 @@
+use std::net::IpAddr;
+
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+use super::request::ApiKeyScope;
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct MessageResponse {
   pub message: String,
@@ -18,10 +22,34 @@ impl MessageResponse {
 pub struct UploadResponse {
   pub expire_date_time: DateTime<Utc>,
   pub url: String,
+  #[serde(default)]
+  pub urls: Vec<String>,
   #[serde(skip_serializing_if = "Option::is_none")]
   pub qr_code: Option<String>,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BundleResponse {
+  pub code: String,
+  pub created_at: DateTime<Utc>,
+  pub expire_date_time: DateTime<Utc>,
+  pub files: Vec<BundleFileResponse>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BundleFileResponse {
+  pub file_name: String,
+  pub url: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UploadStatusResponse {
+  pub offset: u64,
+  pub length: u64,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub upload: Option<UploadResponse>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct MetaDataFileResponse {
   pub created_at: DateTime<Utc>,
@@ -29,6 +57,104 @@ pub struct MetaDataFileResponse {
   pub allow_manual_deletion: bool,
   pub max_download: Option<u32>,
   pub count_downloads: u32,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub owner: Option<String>,
+  #[serde(default)]
+  pub size: u64,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub sha256: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FileResponse {
+  pub code: String,
+  pub file_name: String,
+  pub url: String,
+  #[serde(flatten)]
+  pub meta: MetaDataFileResponse,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FileListResponse {
+  pub page: usize,
+  pub page_size: usize,
+  pub total: usize,
+  pub files: Vec<FileResponse>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct QuotaResponse {
+  pub owner: Option<String>,
+  pub used_bytes: u64,
+  pub used_files: usize,
+  pub max_bytes: Option<u64>,
+  pub max_files: Option<usize>,
+  pub remaining_bytes: Option<u64>,
+  pub remaining_files: Option<usize>,
+}
+
+/// One line of the audit log.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct AuditEvent {
+  pub timestamp: DateTime<Utc>,
+  pub action: AuditAction,
+  pub code: Option<String>,
+  pub file_name: Option<String>,
+  pub ip: Option<IpAddr>,
+  pub username: Option<String>,
+  pub key_id: Option<String>,
+  pub status: u16,
+  pub error_type: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, strum::Display)]
+pub enum AuditAction {
+  #[serde(rename = "upload")]
+  #[strum(serialize = "upload")]
+  Upload,
+  #[serde(rename = "download")]
+  #[strum(serialize = "download")]
+  Download,
+  #[serde(rename = "info")]
+  #[strum(serialize = "info")]
+  Info,
+  #[serde(rename = "list")]
+  #[strum(serialize = "list")]
+  List,
+  #[serde(rename = "delete")]
+  #[strum(serialize = "delete")]
+  Delete,
+  /// An admin changed the expiration or the download count of a file.
+  #[serde(rename = "update")]
+  #[strum(serialize = "update")]
+  Update,
+  /// The garbage collector removed an expired file.
+  #[serde(rename = "expire")]
+  #[strum(serialize = "expire")]
+  Expire,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AuditLogResponse {
+  pub events: Vec<AuditEvent>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AccountResponse {
+  pub username: String,
+  pub is_admin: bool,
+  pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ApiKeyResponse {
+  pub id: String,
+  pub name: String,
+  pub scopes: Vec<ApiKeyScope>,
+  pub created_at: DateTime<Utc>,
+  /// Only returned once, when the key is created.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub key: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
@@ -0,0 +1,55 @@
+use std::collections::BTreeMap;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+pub const TUS_VERSION: &str = "1.0.0";
+pub const TUS_EXTENSION: &str = "creation,termination";
+pub const TUS_RESUMABLE: &str = "tus-resumable";
+pub const TUS_VERSION_HEADER: &str = "tus-version";
+pub const TUS_EXTENSION_HEADER: &str = "tus-extension";
+pub const TUS_MAX_SIZE: &str = "tus-max-size";
+pub const UPLOAD_OFFSET: &str = "upload-offset";
+pub const UPLOAD_LENGTH: &str = "upload-length";
+pub const UPLOAD_METADATA: &str = "upload-metadata";
+pub const UPLOAD_FILE_URL: &str = "upload-file-url";
+pub const UPLOAD_FILE_EXPIRES: &str = "upload-file-expires";
+pub const OFFSET_OCTET_STREAM: &str = "application/offset+octet-stream";
+
+pub fn encode_metadata(metadata: &BTreeMap<String, String>) -> String {
+  metadata
+    .iter()
+    .map(|(key, value)| format!("{key} {}", STANDARD.encode(value)))
+    .collect::<Vec<_>>()
+    .join(",")
+}
+
+pub fn decode_metadata(input: &str) -> anyhow::Result<BTreeMap<String, String>> {
+  let mut metadata = BTreeMap::new();
+  for pair in input.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+    let (key, value) = pair.split_once(' ').unwrap_or((pair, ""));
+    let value = String::from_utf8(STANDARD.decode(value.trim())?)?;
+    metadata.insert(key.to_string(), value);
+  }
+  Ok(metadata)
+}
+
+#[cfg(test)]
+mod tests {
+
+  use super::*;
+
+  #[test]
+  fn test_encode_and_decode_metadata() {
+    let metadata = BTreeMap::from([
+      ("filename".to_string(), "file.txt".to_string()),
+      ("is_confidential".to_string(), String::new()),
+    ]);
+    let encoded = encode_metadata(&metadata);
+    assert_eq!(encoded, "filename ZmlsZS50eHQ=,is_confidential ");
+    assert_eq!(decode_metadata(&encoded).unwrap(), metadata);
+    assert_eq!(
+      decode_metadata("is_confidential,filename ZmlsZS50eHQ=").unwrap(),
+      metadata
+    );
+  }
+}
@@ -90,6 +90,22 @@ pub async fn decrypt_file(
   Ok(())
 }
 
+/// Reads until `buf` is full or the reader is exhausted, a short read would otherwise be
+/// mistaken for the last chunk of the stream.
+pub async fn read_full<R>(reader: &mut R, buf: &mut [u8]) -> std::io::Result<usize>
+where
+  R: AsyncRead + Unpin,
+{
+  let mut filled = 0;
+  while filled < buf.len() {
+    match reader.read(&mut buf[filled..]).await? {
+      0 => break,
+      n => filled += n,
+    }
+  }
+  Ok(filled)
+}
+
 pub async fn encrypt<R, W>(
   KeyNonce { key, nonce }: &KeyNonce,
   mut reader: R,
@@ -103,7 +119,7 @@ where
   let mut stream_encryptor =
     EncryptorBE32::from_aead(XChaCha20Poly1305::new(key), (*nonce).as_ref().into());
   loop {
-    let read_count = reader.read(&mut buffer).await?;
+    let read_count = read_full(&mut reader, &mut buffer).await?;
     if read_count == ENCRYPT_BUFFER_LEN {
       let ciphertext = stream_encryptor
         .encrypt_next(buffer.as_slice())
@@ -138,7 +154,7 @@ where
     DecryptorBE32::from_aead(XChaCha20Poly1305::new(key), nonce.as_ref().into());
 
   loop {
-    let read_count = reader.read(&mut buffer).await?;
+    let read_count = read_full(&mut reader, &mut buffer).await?;
     if read_count == DECRYPT_BUFFER_LEN {
       let plaintext = stream_decryptor
         .decrypt_next(buffer.as_slice())
@@ -0,0 +1,121 @@
+use std::{
+  path::Path,
+  sync::{Arc, Mutex},
+};
+
+use anyhow::anyhow;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use futures_util::{Stream, TryStreamExt};
+use reqwest::header::HeaderMap;
+use sha2::{Digest, Sha256};
+use tokio::io::AsyncReadExt;
+
+pub const REPR_DIGEST_HEADER: &str = "repr-digest";
+pub const DIGEST_HEADER: &str = "digest";
+
+/// `Repr-Digest` header value of RFC 9530.
+pub fn repr_digest(sha256: &[u8]) -> String {
+  format!("sha-256=:{}:", STANDARD.encode(sha256))
+}
+
+/// `Digest` header value of RFC 3230, kept for older clients.
+pub fn legacy_digest(sha256: &[u8]) -> String {
+  format!("SHA-256={}", STANDARD.encode(sha256))
+}
+
+/// Reads the SHA-256 of the whole file from `Repr-Digest`, or `Digest` as a fallback.
+pub fn parse_sha256(headers: &HeaderMap) -> Option<Vec<u8>> {
+  let find = |name: &str, prefix: &str| {
+    headers
+      .get(name)?
+      .to_str()
+      .ok()?
+      .split(',')
+      .find_map(|item| {
+        let (algorithm, value) = item.trim().split_once('=')?;
+        algorithm.eq_ignore_ascii_case(prefix).then_some(value)
+      })
+      .and_then(|value| STANDARD.decode(value.trim_matches(':')).ok())
+  };
+  find(REPR_DIGEST_HEADER, "sha-256").or_else(|| find(DIGEST_HEADER, "sha-256"))
+}
+
+pub async fn file_sha256(path: &Path) -> anyhow::Result<Vec<u8>> {
+  let mut file = tokio::fs::File::open(path).await?;
+  let mut hasher = Sha256::new();
+  let mut buf = vec![0u8; 64 * 1024];
+  loop {
+    let n = file.read(&mut buf).await?;
+    if n == 0 {
+      break;
+    }
+    hasher.update(&buf[..n]);
+  }
+  Ok(hasher.finalize().to_vec())
+}
+
+/// Removes the file when its SHA-256 differs from the one sent by the server.
+pub async fn verify_file_sha256(path: &Path, expected: Option<&[u8]>) -> anyhow::Result<()> {
+  let Some(expected) = expected else {
+    return Ok(());
+  };
+  if file_sha256(path).await? != expected {
+    tokio::fs::remove_file(path).await?;
+    return Err(anyhow!(
+      "The SHA-256 checksum of {} does not match the server, the corrupted download was removed.",
+      path.display()
+    ));
+  }
+  Ok(())
+}
+
+/// A SHA-256 of content that is streamed into a request, read once the request is sent.
+#[derive(Clone, Default)]
+pub struct SharedSha256(Arc<Mutex<Sha256>>);
+
+impl SharedSha256 {
+  /// Hashes every chunk of `stream` as it passes through.
+  pub fn inspect<S, T, E>(&self, stream: S) -> impl Stream<Item = Result<T, E>>
+  where
+    S: Stream<Item = Result<T, E>>,
+    T: AsRef<[u8]>,
+  {
+    let sha256 = self.clone();
+    stream.inspect_ok(move |chunk| sha256.0.lock().unwrap().update(chunk.as_ref()))
+  }
+
+  pub fn finalize(&self) -> Vec<u8> {
+    self.0.lock().unwrap().clone().finalize().to_vec()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_parse_sha256_headers() {
+    let sha256 = Sha256::digest(b"hello").to_vec();
+    let mut headers = HeaderMap::new();
+    headers.insert(
+      REPR_DIGEST_HEADER,
+      format!("sha-512=:AAAA:, {}", repr_digest(&sha256))
+        .parse()
+        .unwrap(),
+    );
+    assert_eq!(parse_sha256(&headers), Some(sha256.clone()));
+    let mut headers = HeaderMap::new();
+    headers.insert(DIGEST_HEADER, legacy_digest(&sha256).parse().unwrap());
+    assert_eq!(parse_sha256(&headers), Some(sha256));
+    assert_eq!(parse_sha256(&HeaderMap::new()), None);
+  }
+
+  #[tokio::test]
+  async fn test_shared_sha256_of_stream() {
+    let sha256 = SharedSha256::default();
+    let chunks = futures_util::stream::iter([b"hel".to_vec(), b"lo".to_vec()].map(anyhow::Ok));
+    let sent: Vec<Vec<u8>> = sha256.inspect(chunks).try_collect().await.unwrap();
+    assert_eq!(sent.concat(), b"hello");
+    assert_eq!(sha256.finalize(), Sha256::digest(b"hello").to_vec());
+  }
+}
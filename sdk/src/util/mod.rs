@@ -1,9 +1,11 @@
 pub mod assert;
 pub mod crypto;
+pub mod digest;
 pub mod dir;
 pub mod file;
 pub mod qr_code;
 pub mod random;
 pub mod retry;
+pub mod tar;
 pub mod test;
 pub mod url;
@@ -0,0 +1,403 @@
+use std::{
+  io::{BufRead, BufReader, Cursor, Read, Write},
+  path::{Component, Path, PathBuf},
+  pin::Pin,
+};
+
+use anyhow::anyhow;
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use futures_util::Stream;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio_util::io::StreamReader;
+
+pub const BLOCK_SIZE: usize = 512;
+pub const REGULAR_FILE: u8 = b'0';
+pub const DIRECTORY: u8 = b'5';
+const GNU_LONG_NAME: u8 = b'L';
+const NAME_LEN: usize = 100;
+const BUFFER_LEN: usize = 64 * 1024;
+
+type ArchiveStream = Pin<Box<dyn Stream<Item = std::io::Result<Cursor<Vec<u8>>>> + Send + Sync>>;
+
+struct Entry {
+  source: PathBuf,
+  name: String,
+  kind: u8,
+  mode: u32,
+  size: u64,
+  mtime: i64,
+}
+
+/// Builds the header blocks of an entry, names longer than 100 bytes get a GNU long name entry.
+pub fn entry_header(name: &str, size: u64, mode: u32, mtime: i64, kind: u8) -> Vec<u8> {
+  let mut buf = Vec::with_capacity(BLOCK_SIZE);
+  if name.len() > NAME_LEN {
+    let long_name = [name.as_bytes(), &[0]].concat();
+    buf.extend_from_slice(&header(
+      "././@LongLink",
+      long_name.len() as u64,
+      0o644,
+      mtime,
+      GNU_LONG_NAME,
+    ));
+    buf.extend_from_slice(&long_name);
+    buf.resize(buf.len() + padding(long_name.len() as u64), 0);
+  }
+  buf.extend_from_slice(&header(name, size, mode, mtime, kind));
+  buf
+}
+
+pub fn header(name: &str, size: u64, mode: u32, mtime: i64, kind: u8) -> [u8; BLOCK_SIZE] {
+  let mut header = [0u8; BLOCK_SIZE];
+  let name = &name.as_bytes()[..name.len().min(NAME_LEN)];
+  header[..name.len()].copy_from_slice(name);
+  header[100..107].copy_from_slice(format!("{:07o}", mode & 0o777).as_bytes());
+  header[108..115].copy_from_slice(b"0000000");
+  header[116..123].copy_from_slice(b"0000000");
+  header[124..135].copy_from_slice(format!("{size:011o}").as_bytes());
+  header[136..147].copy_from_slice(format!("{:011o}", mtime.max(0)).as_bytes());
+  header[156] = kind;
+  header[257..263].copy_from_slice(b"ustar\0");
+  header[263..265].copy_from_slice(b"00");
+  // The checksum is computed with its own field filled with spaces.
+  header[148..156].fill(b' ');
+  let checksum: u32 = header.iter().map(|b| *b as u32).sum();
+  header[148..155].copy_from_slice(format!("{checksum:06o}\0").as_bytes());
+  header
+}
+
+pub fn padding(size: u64) -> usize {
+  (BLOCK_SIZE - (size % BLOCK_SIZE as u64) as usize) % BLOCK_SIZE
+}
+
+/// Two empty blocks mark the end of an archive.
+pub fn end_of_archive() -> [u8; BLOCK_SIZE * 2] {
+  [0; BLOCK_SIZE * 2]
+}
+
+pub fn is_archive(path: &Path) -> bool {
+  let name = path.to_string_lossy();
+  name.ends_with(".tar") || name.ends_with(".tar.gz") || name.ends_with(".tgz")
+}
+
+/// Returns the name a directory is uploaded with.
+pub fn archive_name(dir: &Path, compress: bool) -> anyhow::Result<String> {
+  let name = crate::util::file::get_file_name(dir)?;
+  Ok(if compress {
+    format!("{name}.tar.gz")
+  } else {
+    format!("{name}.tar")
+  })
+}
+
+pub fn content_type(compress: bool) -> &'static str {
+  if compress {
+    "application/gzip"
+  } else {
+    "application/x-tar"
+  }
+}
+
+/// Streams `dir` as a tar archive, the entries are named relative to the parent of `dir`.
+/// Only regular files and directories are archived, links and special files are skipped.
+pub fn archive_dir(
+  dir: &Path,
+  compress: bool,
+) -> anyhow::Result<impl AsyncRead + Send + Sync + Unpin + 'static> {
+  let root = dir
+    .parent()
+    .ok_or_else(|| anyhow!("The directory {dir:?} has no parent."))?;
+  let mut entries = Vec::new();
+  collect_entries(root, dir, &mut entries)?;
+  let mut encoder = compress.then(|| GzEncoder::new(Vec::new(), Compression::default()));
+  let stream = async_stream::try_stream! {
+    let mut buf = vec![0u8; BUFFER_LEN];
+    for entry in entries {
+      let header = entry_header(&entry.name, entry.size, entry.mode, entry.mtime, entry.kind);
+      yield encode(&mut encoder, &header)?;
+      if entry.kind == REGULAR_FILE {
+        let mut file = tokio::fs::File::open(&entry.source).await?;
+        let mut remaining = entry.size;
+        while remaining > 0 {
+          let len = (remaining as usize).min(BUFFER_LEN);
+          file.read_exact(&mut buf[..len]).await?;
+          remaining -= len as u64;
+          yield encode(&mut encoder, &buf[..len])?;
+        }
+        yield encode(&mut encoder, &vec![0; padding(entry.size)])?;
+      }
+    }
+    yield encode(&mut encoder, &end_of_archive())?;
+    if let Some(encoder) = encoder.take() {
+      yield Cursor::new(encoder.finish()?);
+    }
+  };
+  let stream: ArchiveStream = Box::pin(stream);
+  Ok(StreamReader::new(stream))
+}
+
+fn encode(
+  encoder: &mut Option<GzEncoder<Vec<u8>>>,
+  data: &[u8],
+) -> std::io::Result<Cursor<Vec<u8>>> {
+  match encoder {
+    Some(encoder) => {
+      encoder.write_all(data)?;
+      Ok(Cursor::new(std::mem::take(encoder.get_mut())))
+    }
+    None => Ok(Cursor::new(data.to_vec())),
+  }
+}
+
+fn collect_entries(root: &Path, path: &Path, entries: &mut Vec<Entry>) -> anyhow::Result<()> {
+  let metadata = std::fs::symlink_metadata(path)?;
+  if !metadata.is_dir() && !metadata.is_file() {
+    return Ok(());
+  }
+  let relative = path.strip_prefix(root)?;
+  let mut name = relative
+    .components()
+    .map(|c| c.as_os_str().to_string_lossy())
+    .collect::<Vec<_>>()
+    .join("/");
+  let mtime = metadata
+    .modified()?
+    .duration_since(std::time::UNIX_EPOCH)
+    .map_or(0, |d| d.as_secs() as i64);
+  if metadata.is_dir() {
+    name.push('/');
+    entries.push(Entry {
+      source: path.to_path_buf(),
+      name,
+      kind: DIRECTORY,
+      mode: file_mode(&metadata, 0o755),
+      size: 0,
+      mtime,
+    });
+    let mut children = std::fs::read_dir(path)?
+      .map(|entry| entry.map(|e| e.path()))
+      .collect::<Result<Vec<_>, _>>()?;
+    children.sort();
+    for child in children {
+      collect_entries(root, &child, entries)?;
+    }
+  } else {
+    entries.push(Entry {
+      source: path.to_path_buf(),
+      name,
+      kind: REGULAR_FILE,
+      mode: file_mode(&metadata, 0o644),
+      size: metadata.len(),
+      mtime,
+    });
+  }
+  Ok(())
+}
+
+#[cfg(unix)]
+fn file_mode(metadata: &std::fs::Metadata, _default: u32) -> u32 {
+  use std::os::unix::fs::PermissionsExt;
+  metadata.permissions().mode()
+}
+
+#[cfg(not(unix))]
+fn file_mode(_metadata: &std::fs::Metadata, default: u32) -> u32 {
+  default
+}
+
+/// Unpacks a tar or tar.gz archive into `destination` and returns the paths of its top level entries.
+pub async fn extract(archive: &Path, destination: &Path) -> anyhow::Result<Vec<PathBuf>> {
+  let archive = archive.to_path_buf();
+  let destination = destination.to_path_buf();
+  tokio::task::spawn_blocking(move || extract_blocking(&archive, &destination)).await?
+}
+
+fn extract_blocking(archive: &Path, destination: &Path) -> anyhow::Result<Vec<PathBuf>> {
+  let mut reader = BufReader::new(std::fs::File::open(archive)?);
+  let mut reader: Box<dyn Read> = if reader.fill_buf()?.starts_with(&[0x1f, 0x8b]) {
+    Box::new(GzDecoder::new(reader))
+  } else {
+    Box::new(reader)
+  };
+  let mut roots = Vec::new();
+  // Set once the entries are written, a read only directory could not be filled otherwise.
+  let mut dir_modes = Vec::new();
+  let mut long_name = None;
+  let mut block = [0u8; BLOCK_SIZE];
+  loop {
+    reader.read_exact(&mut block)?;
+    if block.iter().all(|b| *b == 0) {
+      break;
+    }
+    if &block[257..262] != b"ustar" {
+      return Err(anyhow!("The archive is not a valid tar file."));
+    }
+    let size = parse_octal(&block[124..136])?;
+    // The setuid, setgid and sticky bits are not restored.
+    let mode = parse_octal(&block[100..108])? as u32 & 0o777;
+    let kind = block[156];
+    if kind == GNU_LONG_NAME {
+      let mut name = vec![0; size as usize];
+      reader.read_exact(&mut name)?;
+      skip(&mut reader, padding(size) as u64)?;
+      long_name = Some(String::from_utf8(trim_nul(&name).to_vec())?);
+      continue;
+    }
+    let name = match long_name.take() {
+      Some(name) => name,
+      None => String::from_utf8(trim_nul(&block[..NAME_LEN]).to_vec())?,
+    };
+    let relative = Path::new(&name);
+    if !relative
+      .components()
+      .all(|c| matches!(c, Component::Normal(_)))
+    {
+      return Err(anyhow!("The archive entry {name} escapes the destination."));
+    }
+    let path = destination.join(relative);
+    if let Some(Component::Normal(root)) = relative.components().next() {
+      let root = destination.join(root);
+      if !roots.contains(&root) {
+        roots.push(root);
+      }
+    }
+    match kind {
+      DIRECTORY => {
+        std::fs::create_dir_all(&path)?;
+        dir_modes.push((path, mode));
+      }
+      REGULAR_FILE | 0 => {
+        if let Some(parent) = path.parent() {
+          std::fs::create_dir_all(parent)?;
+        }
+        let mut file = std::fs::File::create(&path)?;
+        let copied = std::io::copy(&mut (&mut reader).take(size), &mut file)?;
+        if copied != size {
+          return Err(anyhow!("The archive entry {name} is truncated."));
+        }
+        set_mode(&path, mode)?;
+        skip(&mut reader, padding(size) as u64)?;
+      }
+      // Links and special files are not supported and their content is skipped.
+      _ => skip(&mut reader, size + padding(size) as u64)?,
+    }
+  }
+  // Children come after their parent in an archive, so the deepest directories go first.
+  for (path, mode) in dir_modes.iter().rev() {
+    set_mode(path, *mode)?;
+  }
+  Ok(roots)
+}
+
+fn parse_octal(field: &[u8]) -> anyhow::Result<u64> {
+  let value = std::str::from_utf8(trim_nul(field))?.trim();
+  if value.is_empty() {
+    return Ok(0);
+  }
+  Ok(u64::from_str_radix(value, 8)?)
+}
+
+fn trim_nul(bytes: &[u8]) -> &[u8] {
+  let end = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+  &bytes[..end]
+}
+
+fn skip(reader: &mut impl Read, len: u64) -> std::io::Result<()> {
+  std::io::copy(&mut reader.take(len), &mut std::io::sink())?;
+  Ok(())
+}
+
+#[cfg(unix)]
+fn set_mode(path: &Path, mode: u32) -> std::io::Result<()> {
+  use std::os::unix::fs::PermissionsExt;
+  std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))
+}
+
+#[cfg(not(unix))]
+fn set_mode(_path: &Path, _mode: u32) -> std::io::Result<()> {
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[tokio::test]
+  async fn test_archive_dir_and_extract() {
+    let workspace = std::env::temp_dir().join(format!("pf-tar-{}", rand::random::<u64>()));
+    let source = workspace.join("source").join("project");
+    let long_name = format!("{}.txt", "a".repeat(120));
+    tokio::fs::create_dir_all(source.join("nested"))
+      .await
+      .unwrap();
+    tokio::fs::write(source.join("hello.txt"), "hello")
+      .await
+      .unwrap();
+    tokio::fs::write(source.join("nested").join(&long_name), "world")
+      .await
+      .unwrap();
+    for compress in [false, true] {
+      let archive = workspace.join(archive_name(&source, compress).unwrap());
+      let mut reader = archive_dir(&source, compress).unwrap();
+      let mut file = tokio::fs::File::create(&archive).await.unwrap();
+      tokio::io::copy(&mut reader, &mut file).await.unwrap();
+      let destination = workspace.join(format!("destination-{compress}"));
+      let roots = extract(&archive, &destination).await.unwrap();
+      assert_eq!(roots, vec![destination.join("project")]);
+      let content = tokio::fs::read_to_string(destination.join("project/hello.txt"))
+        .await
+        .unwrap();
+      assert_eq!(content, "hello");
+      let content = tokio::fs::read_to_string(destination.join("project/nested").join(&long_name))
+        .await
+        .unwrap();
+      assert_eq!(content, "world");
+    }
+    tokio::fs::remove_dir_all(workspace).await.unwrap();
+  }
+
+  #[cfg(unix)]
+  #[tokio::test]
+  async fn test_extract_read_only_dir_and_skip_special_files() {
+    use std::os::unix::fs::PermissionsExt;
+    let workspace = std::env::temp_dir().join(format!("pf-tar-{}", rand::random::<u64>()));
+    let source = workspace.join("source").join("project");
+    let read_only = source.join("read-only");
+    tokio::fs::create_dir_all(&read_only).await.unwrap();
+    tokio::fs::write(read_only.join("file.txt"), "hello")
+      .await
+      .unwrap();
+    let status = std::process::Command::new("mkfifo")
+      .arg(source.join("fifo"))
+      .status()
+      .unwrap();
+    assert!(status.success());
+    let permissions = |mode| std::fs::Permissions::from_mode(mode);
+    std::fs::set_permissions(&read_only, permissions(0o1555)).unwrap();
+    let archive = workspace.join(archive_name(&source, false).unwrap());
+    let mut reader = archive_dir(&source, false).unwrap();
+    let mut file = tokio::fs::File::create(&archive).await.unwrap();
+    tokio::io::copy(&mut reader, &mut file).await.unwrap();
+    let destination = workspace.join("destination");
+    extract(&archive, &destination).await.unwrap();
+    let extracted = destination.join("project/read-only");
+    let content = tokio::fs::read_to_string(extracted.join("file.txt"))
+      .await
+      .unwrap();
+    assert_eq!(content, "hello");
+    let mode = std::fs::metadata(&extracted).unwrap().permissions().mode();
+    assert_eq!(mode & 0o7777, 0o555);
+    assert!(!destination.join("project/fifo").exists());
+    for dir in [&read_only, &extracted] {
+      std::fs::set_permissions(dir, permissions(0o755)).unwrap();
+    }
+    tokio::fs::remove_dir_all(workspace).await.unwrap();
+  }
+
+  #[test]
+  fn test_is_archive() {
+    assert!(is_archive(Path::new("dir.tar")));
+    assert!(is_archive(Path::new("dir.tar.gz")));
+    assert!(!is_archive(Path::new("file.txt")));
+  }
+}
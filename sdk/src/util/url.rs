@@ -5,3 +5,7 @@ pub fn create_url(
 ) -> Result<url::Url, url::ParseError> {
   url::Url::parse(&format!("{base_url}/{}/{}", code, file_name))
 }
+
+pub fn create_bundle_url(base_url: &str, code: &str) -> Result<url::Url, url::ParseError> {
+  url::Url::parse(&format!("{base_url}/{code}"))
+}